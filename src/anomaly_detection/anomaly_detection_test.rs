@@ -0,0 +1,221 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, trait_definition::{OutlierDetector, MLError, Params, ParamValue},
+    persistence::persistence::{Persist, PersistError},
+    supervised::support_vector_machines::support_vector_machines::Kernel};
+use super::{isolation_forest::IsolationForest, local_outlier_factor::LocalOutlierFactor,
+    elliptic_envelope::EllipticEnvelope, one_class_svm::OneClassSvm};
+
+
+fn normal(rng : &fastrand::Rng) -> f32 {
+    let (u, v) = (rng.f32().max(f32::MIN_POSITIVE), rng.f32());
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f32::consts::PI * v).cos()
+}
+
+///`inliers` correlated gaussian points around (1, -1) followed by `outliers` points on a circle of radius 8 around it.
+fn blob_with_outliers(seed : u64, inliers : usize, outliers : usize) -> Matrix {
+    let rng = fastrand::Rng::with_seed(seed);
+    let mut rows = vec![];
+    for _ in 0..inliers {
+        let (a, b) = (normal(&rng), normal(&rng));
+        rows.push(vec![1.0 + a, -1.0 + 0.8 * a + 0.6 * b]);
+    }
+    for outlier in 0..outliers {
+        let angle = 2.0 * std::f32::consts::PI * outlier as f32 / outliers as f32;
+        rows.push(vec![1.0 + 8.0 * angle.cos(), -1.0 + 8.0 * angle.sin()]);
+    }
+    Matrix::from(rows)
+}
+
+///the share of the last `outliers` rows flagged, and the share of the other rows flagged.
+fn flagged(labels : &[bool], outliers : usize) -> (f32, f32) {
+    let inliers = labels.len() - outliers;
+    (labels[inliers..].iter().filter(|label| **label).count() as f32 / outliers as f32,
+        labels[..inliers].iter().filter(|label| **label).count() as f32 / inliers as f32)
+}
+
+fn check_detector<D : OutlierDetector + Persist>(mut detector : D) {
+    let X = blob_with_outliers(0, 300, 12);
+    assert!(!detector.is_fitted() && detector.threshold().is_none() && detector.n_features_in().is_none());
+    let labels = detector.fit_predict(&X).unwrap();
+    assert!(detector.is_fitted());
+    assert_eq!(detector.n_features_in(), Some(2));
+    let (caught, false_alarms) = flagged(&labels, 12);
+    assert_eq!(caught, 1.0);
+    //5% contamination, the outliers make up 4% of the rows.
+    assert!(false_alarms < 0.03, "{}", false_alarms);
+    let scores = detector.scores(&X);
+    let threshold = detector.threshold().unwrap();
+    assert!(scores.iter().zip(&labels).all(|(score, label)| (*score > threshold) == *label));
+    assert!(detector.decision_function(&[1.0, -1.0]) < 0.0);
+    assert!(detector.is_outlier(&[20.0, 20.0]));
+
+    let restored = D::from_json(&detector.to_json().unwrap()).unwrap();
+    assert_eq!(restored.scores(&X), scores);
+    assert_eq!(restored.threshold(), detector.threshold());
+}
+
+fn contaminated<D : OutlierDetector>(mut detector : D) -> D {
+    let mut params = Params::new();
+    params.insert("contamination".to_owned(), ParamValue::Float(0.05));
+    detector.set_params(&params).unwrap();
+    detector
+}
+
+#[test]
+fn detectors_flag_the_outliers() {
+    let mut forest = IsolationForest::new(100);
+    forest.set_seed(3);
+    check_detector(contaminated(forest));
+    check_detector(contaminated(LocalOutlierFactor::new(20)));
+    check_detector(contaminated(EllipticEnvelope::new()));
+    check_detector(contaminated(OneClassSvm::new(Kernel::Rbf { gamma: Some(0.5) }, 0.1)));
+}
+
+#[test]
+fn contamination_sets_the_share_of_training_outliers() {
+    let X = blob_with_outliers(1, 400, 0);
+    for contamination in [0.02, 0.1, 0.25] {
+        let mut forest = IsolationForest::new(50);
+        forest.set_contamination(contamination);
+        let labels = forest.fit_predict(&X).unwrap();
+        let share = labels.iter().filter(|label| **label).count() as f32 / labels.len() as f32;
+        assert!((share - contamination).abs() < 0.01, "{} {}", contamination, share);
+    }
+    for contamination in [0.0, 0.6, f32::NAN] {
+        let mut lof = LocalOutlierFactor::new(10);
+        lof.set_contamination(contamination);
+        assert!(matches!(lof.fit(&X), Err(MLError::InvalidParameter(_))));
+    }
+}
+
+#[test]
+fn isolation_forest_depths() {
+    let X = blob_with_outliers(2, 256, 4);
+    let mut forest = IsolationForest::new(200);
+    forest.fit(&X).unwrap();
+    //outliers are isolated in fewer splits and score above the inliers.
+    assert!(forest.mean_path_length(&[9.0, -1.0]) < forest.mean_path_length(&[1.0, -1.0]));
+    assert!(forest.score(&[9.0, -1.0]) > 0.6 && forest.score(&[1.0, -1.0]) < 0.5);
+    assert!(forest.scores(&X).iter().all(|score| *score > 0.0 && *score <= 1.0));
+    //the trees are seeded on their own, refitting gives the same forest.
+    let mut again = IsolationForest::new(200);
+    again.fit(&X).unwrap();
+    assert_eq!(again.scores(&X), forest.scores(&X));
+
+    //a split pointing back at itself would never reach a leaf.
+    let mut value: serde_json::Value = serde_json::from_str(&forest.to_json().unwrap()).unwrap();
+    value["model"]["trees"][0]["nodes"][0]["Split"]["left"] = serde_json::json!(0);
+    let error = IsolationForest::from_json(&value.to_string()).err().unwrap();
+    assert!(matches!(error.downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_))));
+
+    assert!(IsolationForest::new(0).fit(&X).is_err());
+    let mut forest = IsolationForest::new(10);
+    forest.set_max_samples(1);
+    assert!(forest.fit(&X).is_err());
+    assert_eq!(forest.get_params().get("max_samples"), Some(&ParamValue::Int(1)));
+}
+
+#[test]
+fn local_outlier_factor_adapts_to_the_density() {
+    //a tight and a wide cluster, a point at distance 1 from the tight one is an outlier there,
+    //while the points of the wide cluster are further apart than that and still inliers.
+    let rng = fastrand::Rng::with_seed(4);
+    let mut rows: Vec<Vec<f32>> = (0..100).map(|_| vec![0.1 * normal(&rng), 0.1 * normal(&rng)]).collect();
+    rows.extend((0..100).map(|_| vec![10.0 + 2.0 * normal(&rng), 2.0 * normal(&rng)]));
+    let X = Matrix::from(rows);
+    let mut lof = LocalOutlierFactor::new(10);
+    lof.fit(&X).unwrap();
+    assert!(lof.score(&[1.0, 0.0]) > 3.0, "{}", lof.score(&[1.0, 0.0]));
+    assert!(lof.score(&[10.0, 1.0]) < 1.5);
+    assert!(lof.local_reachability_density(&[0.0, 0.0]) > lof.local_reachability_density(&[10.0, 0.0]));
+    assert_eq!(lof.training_scores().len(), 200);
+    //most points are as dense as their neighbours.
+    let mut training_scores = lof.training_scores().to_vec();
+    training_scores.sort_by(|a, b| a.total_cmp(b));
+    assert!((training_scores[100] - 1.0).abs() < 0.1, "{}", training_scores[100]);
+
+    //the saved index and the cached densities are checked against each other on load.
+    let json = lof.to_json().unwrap();
+    assert_eq!(LocalOutlierFactor::from_json(&json).unwrap().training_scores(), lof.training_scores());
+    let rejected = |change : &dyn Fn(&mut serde_json::Value)| {
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        change(&mut value["model"]);
+        matches!(LocalOutlierFactor::from_json(&value.to_string()).err().unwrap().downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_)))
+    };
+    assert!(rejected(&|model| model["index"]["order"] = serde_json::json!([0, 1])));
+    assert!(rejected(&|model| model["k_distances"] = serde_json::json!([1.0])));
+
+    //duplicates do not give infinite densities.
+    let mut lof = LocalOutlierFactor::new(2);
+    lof.fit(&Matrix::from(vec![vec![1.0, 1.0]; 5])).unwrap();
+    assert!(lof.score(&[1.0, 1.0]).is_finite());
+    assert!(LocalOutlierFactor::new(5).fit(&Matrix::from(vec![vec![1.0, 1.0]; 5])).is_err());
+}
+
+#[test]
+fn elliptic_envelope_is_robust() {
+    //a quarter of the rows are a far away cluster, the plain mean is dragged towards it but the MCD is not.
+    let rng = fastrand::Rng::with_seed(5);
+    let mut rows: Vec<Vec<f32>> = (0..300).map(|_| vec![normal(&rng), 3.0 * normal(&rng)]).collect();
+    rows.extend((0..100).map(|_| vec![20.0 + normal(&rng), 20.0 + normal(&rng)]));
+    let X = Matrix::from(rows);
+    let mut envelope = EllipticEnvelope::new();
+    envelope.set_contamination(0.25);
+    envelope.fit(&X).unwrap();
+    let location = envelope.location().unwrap();
+    assert!(location[0].abs() < 0.3 && location[1].abs() < 0.6, "{:?}", location);
+    let covariance = envelope.covariance().unwrap();
+    assert!((covariance.get(0, 0) - 1.0).abs() < 0.3 && (covariance.get(1, 1) - 9.0).abs() < 2.5, "{:?}", covariance);
+    assert!(envelope.support()[300..].iter().all(|support| !support));
+    assert!(envelope.predict(&X)[300..].iter().all(|label| *label));
+    assert!((envelope.mahalanobis(&[0.0, 6.0]) - 2.0).abs() < 0.4);
+
+    let mut envelope = EllipticEnvelope::new();
+    envelope.set_support_fraction(Some(1.5));
+    assert!(envelope.fit(&X).is_err());
+    //a constant feature has no covariance to invert.
+    let constant = Matrix::from((0..50).map(|sample| vec![sample as f32, 1.0]).collect::<Vec<_>>());
+    assert!(matches!(EllipticEnvelope::new().fit(&constant), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn one_class_svm_nu_bounds_the_support() {
+    let X = blob_with_outliers(6, 200, 0);
+    for nu in [0.05, 0.3] {
+        let mut svm = OneClassSvm::new(Kernel::Rbf { gamma: None }, nu);
+        svm.fit(&X).unwrap();
+        //nu is a lower bound on the share of support vectors and an upper bound on the share outside the region.
+        assert!(svm.n_support_vectors() as f32 >= nu * 200.0 - 1.0, "{} {}", nu, svm.n_support_vectors());
+        let outside = X.iter_rows().filter(|row| svm.region_decision(row) < 0.0).count() as f32 / 200.0;
+        assert!(outside <= nu + 0.02, "{} {}", nu, outside);
+        let coefficients = &svm.machine().unwrap().coefficients;
+        assert!(((coefficients.iter().sum::<f32>() - nu * 200.0) / (nu * 200.0)).abs() < 1e-3);
+        assert!(coefficients.iter().all(|coefficient| *coefficient > 0.0 && *coefficient <= 1.0));
+    }
+    assert!(OneClassSvm::new(Kernel::Linear, 0.0).fit(&X).is_err());
+    assert!(OneClassSvm::new(Kernel::Rbf { gamma: Some(-1.0) }, 0.5).fit(&X).is_err());
+}
+
+#[test]
+#[should_panic]
+fn score_before_fit_panics() {
+    LocalOutlierFactor::new(5).score(&[1.0, 2.0]);
+}
+
+#[test]
+#[should_panic]
+fn score_with_the_wrong_features_panics() {
+    let mut forest = IsolationForest::new(10);
+    forest.fit(&blob_with_outliers(7, 50, 0)).unwrap();
+    forest.score(&[1.0]);
+}
+
+#[test]
+fn detector_input_is_checked() {
+    assert_eq!(IsolationForest::new(10).fit(&Matrix::new(0, 2)), Err(MLError::EmptyInput));
+    assert!(OneClassSvm::new(Kernel::Linear, 0.5).fit(&Matrix::from(vec![vec![1.0, f32::NAN], vec![0.0, 0.0]])).is_err());
+    let mut params = Params::new();
+    params.insert("depth".to_owned(), ParamValue::Int(3));
+    assert!(EllipticEnvelope::new().set_params(&params).is_err());
+}
@@ -0,0 +1,33 @@
+//! The parts the outlier detectors share : checking the input and turning the contamination into a threshold.
+
+use crate::{trait_definition::MLError, n_dimen::matrix::Matrix};
+
+
+///there needs to be something to fit on, and every value needs to be finite.
+pub(crate) fn check_detector_input(X : &Matrix) -> Result<(), MLError> {
+    if X.rows() == 0 || X.cols() == 0 {
+        return Err(MLError::EmptyInput);
+    }
+    if X.as_slice().iter().any(|value| !value.is_finite()) {
+        return Err(MLError::InvalidParameter("the outlier detectors need finite values".to_owned()));
+    }
+    Ok(())
+}
+
+///the share of outliers expected in the training set, in (0, 0.5].
+pub(crate) fn check_contamination(contamination : f32) -> Result<(), MLError> {
+    if contamination > 0.0 && contamination <= 0.5 {
+        Ok(())
+    } else {
+        Err(MLError::InvalidParameter(format!("contamination needs to be in (0, 0.5], got {}", contamination)))
+    }
+}
+
+///the score that `contamination` of the training scores are above, linearly interpolated between the two closest scores.
+pub(crate) fn contamination_threshold(scores : &[f32], contamination : f32) -> f32 {
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let position = (1.0 - contamination) * (sorted.len() - 1) as f32;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f32)
+}
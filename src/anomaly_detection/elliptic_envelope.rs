@@ -0,0 +1,298 @@
+//! #Elliptic envelope#
+//!
+//! Assumes the inliers are roughly gaussian and scores every point by its squared Mahalanobis distance
+//! to a robust estimate of their location and covariance.
+//! The plain mean and covariance get dragged towards the outliers, so the estimate is the minimum covariance determinant(MCD) :
+//! the mean and covariance of the `h` points whose covariance has the smallest determinant, found with FastMCD.
+//!
+//! * Every start takes `n_features + 1` random points(more if their covariance is singular) and runs C-steps :
+//!   keep the `h` points closest to the current estimate and re-estimate from them, until the points stop changing.
+//!   The determinant never grows along the way.
+//! * The start with the smallest determinant wins, its covariance is rescaled to be consistent at the normal distribution,
+//!   and the final estimate comes from every point within the 97.5% chi-square quantile of it(the reweighting step).
+//!
+//! The starts run in parallel, each with a seed of its own.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{trait_definition::{OutlierDetector, MLError, Params, ParamValue, assert_point},
+    n_dimen::{matrix::Matrix, linalg::{cholesky, cholesky_solve, inverse}, distance::{covariance, mahalanobis}},
+    persistence::persistence::{Persist, PersistError, ensure},
+    supervised::ensemble::common::member_seed};
+use super::common::{check_detector_input, check_contamination, contamination_threshold};
+
+
+///the quantile of the chi-square distribution with `degrees` degrees of freedom matching the quantile `z` of the standard normal,
+///from the Wilson-Hilferty approximation.
+fn chi_square_quantile(degrees : usize, z : f32) -> f32 {
+    let degrees = degrees as f32;
+    let spread = 2.0 / (9.0 * degrees);
+    degrees * (1.0 - spread + z * spread.sqrt()).powi(3)
+}
+
+///the 97.5% quantile of the standard normal.
+const Z_975 : f32 = 1.959_964;
+
+///a location, covariance and its Cholesky factor, estimated from some of the rows.
+struct Estimate {
+    location : Vec<f32>,
+    covariance : Matrix,
+    factor : Matrix,
+    subset : Vec<usize>,
+}
+
+impl Estimate {
+
+    ///`None` when the covariance of the rows is singular.
+    fn from_rows(X : &Matrix, mut subset : Vec<usize>) -> Option<Estimate> {
+        if subset.len() < 2 {
+            return None;
+        }
+        subset.sort_unstable();
+        let rows = X.select_rows(&subset);
+        let mut location = vec![0.0_f32; X.cols()];
+        for row in rows.iter_rows() {
+            for (mean, value) in location.iter_mut().zip(row) {
+                *mean += value / subset.len() as f32;
+            }
+        }
+        let covariance = covariance(&rows);
+        let factor = cholesky(&covariance).ok()?;
+        Some(Estimate { location, covariance, factor, subset })
+    }
+
+    fn squared_distance(&self, point : &[f32]) -> f32 {
+        let difference: Vec<f32> = point.iter().zip(&self.location).map(|(x, mean)| x - mean).collect();
+        difference.iter().zip(cholesky_solve(&self.factor, &difference)).map(|(x, y)| x * y).sum::<f32>().max(0.0)
+    }
+
+    fn log_determinant(&self) -> f32 {
+        (0..self.factor.rows()).map(|i| 2.0 * self.factor.get(i, i).ln()).sum()
+    }
+
+    ///the rows sorted by their distance to the estimate, closest first.
+    fn closest(&self, X : &Matrix) -> Vec<(usize, f32)> {
+        let mut distances: Vec<(usize, f32)> = X.iter_rows().map(|row| self.squared_distance(row)).enumerate().collect();
+        distances.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        distances
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EllipticEnvelope {
+    ///the share of the rows the MCD is estimated from, in (0, 1]. `None` is `(n_samples + n_features + 1) / 2` rows, the most robust choice.
+    pub support_fraction : Option<f32>,
+    ///the share of outliers expected in the training set, in (0, 0.5].
+    pub contamination : f32,
+    ///the number of random starts of FastMCD.
+    pub n_starts : usize,
+    ///the most C-steps per start.
+    pub max_iter : usize,
+    pub seed : u64,
+    location : Vec<f32>,
+    covariance : Matrix,
+    precision : Matrix,
+    ///true for the training rows in the final estimate.
+    support : Vec<bool>,
+    threshold : Option<f32>,
+}
+
+impl EllipticEnvelope {
+
+    ///30 starts of atmost 30 C-steps, the default support fraction and 10% contamination.
+    pub fn new() -> EllipticEnvelope {
+        EllipticEnvelope {
+            support_fraction: None,
+            contamination: 0.1,
+            n_starts: 30,
+            max_iter: 30,
+            seed: 0,
+            location: vec![],
+            covariance: Matrix::new(0, 0),
+            precision: Matrix::new(0, 0),
+            support: vec![],
+            threshold: None,
+        }
+    }
+
+    pub fn set_support_fraction(&mut self, support_fraction : Option<f32>) {
+        self.support_fraction = support_fraction;
+    }
+
+    pub fn set_contamination(&mut self, contamination : f32) {
+        self.contamination = contamination;
+    }
+
+    pub fn set_starts(&mut self, n_starts : usize, max_iter : usize) {
+        self.n_starts = n_starts;
+        self.max_iter = max_iter;
+    }
+
+    pub fn set_seed(&mut self, seed : u64) {
+        self.seed = seed;
+    }
+
+    ///the robust location, `None` before fitting.
+    pub fn location(&self) -> Option<&[f32]> {
+        self.is_fitted().then_some(&self.location[..])
+    }
+
+    ///the robust covariance, `None` before fitting.
+    pub fn covariance(&self) -> Option<&Matrix> {
+        self.is_fitted().then_some(&self.covariance)
+    }
+
+    ///true for the training rows the final estimate was computed from.
+    pub fn support(&self) -> &[bool] {
+        &self.support
+    }
+
+    ///the Mahalanobis distance of the point to the robust location.
+    pub fn mahalanobis(&self, point : &[f32]) -> f32 {
+        assert_point(self.n_features_in(), point);
+        mahalanobis(point, &self.location, &self.precision)
+    }
+
+    fn support_size(&self, n_samples : usize, n_features : usize) -> Result<usize, MLError> {
+        let size = match self.support_fraction {
+            Some(fraction) if fraction > 0.0 && fraction <= 1.0 => (fraction * n_samples as f32).ceil() as usize,
+            Some(fraction) => return Err(MLError::InvalidParameter(format!("support_fraction needs to be in (0, 1], got {}", fraction))),
+            None => (n_samples + n_features + 1) / 2,
+        };
+        if size <= n_features {
+            return Err(MLError::InvalidParameter(format!("the MCD needs more than {} supporting rows for {} features, got {} of {} samples", n_features, n_features, size, n_samples)));
+        }
+        Ok(size.min(n_samples))
+    }
+
+    fn score_unchecked(&self, point : &[f32]) -> f32 {
+        let distance = mahalanobis(point, &self.location, &self.precision);
+        distance * distance
+    }
+
+    ///one start of FastMCD, `None` if every subset it tried is singular.
+    fn concentrate(&self, X : &Matrix, support_size : usize, seed : u64) -> Option<Estimate> {
+        let rng = fastrand::Rng::with_seed(seed);
+        let mut order: Vec<usize> = (0..X.rows()).collect();
+        rng.shuffle(&mut order);
+        //grows the random start until its covariance can be inverted.
+        let mut size = X.cols() + 1;
+        let mut estimate = loop {
+            if let Some(estimate) = Estimate::from_rows(X, order[..size].to_vec()) {
+                break estimate;
+            }
+            if size >= X.rows() {
+                return None;
+            }
+            size += 1;
+        };
+        for _ in 0..self.max_iter {
+            let subset: Vec<usize> = estimate.closest(X).into_iter().take(support_size).map(|(row, _)| row).collect();
+            let next = Estimate::from_rows(X, subset)?;
+            if next.subset == estimate.subset {
+                break;
+            }
+            estimate = next;
+        }
+        Some(estimate)
+    }
+}
+
+impl Default for EllipticEnvelope {
+    fn default() -> Self {
+        EllipticEnvelope::new()
+    }
+}
+
+impl OutlierDetector for EllipticEnvelope {
+    fn fit(&mut self, X : &Matrix) -> Result<(), MLError> {
+        check_detector_input(X)?;
+        check_contamination(self.contamination)?;
+        if self.n_starts == 0 || self.max_iter == 0 {
+            return Err(MLError::InvalidParameter("n_starts and max_iter need to be > 0".to_owned()));
+        }
+        let support_size = self.support_size(X.rows(), X.cols())?;
+        self.threshold = None;
+        let singular = || MLError::InvalidParameter("the covariance of the data is singular, some features are constant or linear combinations of the others".to_owned());
+
+        let best = (0..self.n_starts).into_par_iter()
+            .filter_map(|start| self.concentrate(X, support_size, member_seed(self.seed, start)))
+            .min_by(|a, b| a.log_determinant().total_cmp(&b.log_determinant()).then(a.subset.cmp(&b.subset)))
+            .ok_or_else(singular)?;
+
+        //the covariance of the closest half underestimates the spread, scale it so the median distance matches the chi-square median.
+        let distances = best.closest(X);
+        let median = distances[distances.len() / 2].1;
+        let correction = median / chi_square_quantile(X.cols(), 0.0);
+        let cut_off = chi_square_quantile(X.cols(), Z_975) * correction;
+        let support: Vec<usize> = distances.iter().filter(|(_, distance)| *distance <= cut_off).map(|(row, _)| *row).collect();
+        let estimate = Estimate::from_rows(X, support).unwrap_or(best);
+
+        self.precision = inverse(&estimate.covariance).map_err(|_| singular())?;
+        self.support = (0..X.rows()).map(|row| estimate.subset.binary_search(&row).is_ok()).collect();
+        self.location = estimate.location;
+        self.covariance = estimate.covariance;
+        let scores: Vec<f32> = X.par_iter_rows().map(|point| self.score_unchecked(point)).collect();
+        self.threshold = Some(contamination_threshold(&scores, self.contamination));
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.threshold.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.location.len())
+    }
+
+    ///the squared Mahalanobis distance to the robust location.
+    fn score(&self, point : &[f32]) -> f32 {
+        assert_point(self.n_features_in(), point);
+        self.score_unchecked(point)
+    }
+
+    fn threshold(&self) -> Option<f32> {
+        self.threshold
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        if let Some(support_fraction) = self.support_fraction {
+            params.insert("support_fraction".to_owned(), ParamValue::Float(support_fraction));
+        }
+        params.insert("contamination".to_owned(), ParamValue::Float(self.contamination));
+        params.insert("n_starts".to_owned(), ParamValue::Int(self.n_starts as i64));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "support_fraction" => self.support_fraction = Some(value.as_f32(name)?),
+                "contamination" => self.contamination = value.as_f32(name)?,
+                "n_starts" => self.n_starts = value.as_usize(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Persist for EllipticEnvelope {
+    const MODEL_TYPE: &'static str = "EllipticEnvelope";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        if self.threshold.is_none() {
+            return Ok(());
+        }
+        let n_features = self.location.len();
+        ensure(n_features > 0, "a fitted envelope needs a location")?;
+        ensure(self.covariance.shape() == (n_features, n_features) && self.precision.shape() == (n_features, n_features)
+            && self.covariance.is_consistent() && self.precision.is_consistent(), "the covariance does not match the location")
+    }
+}
@@ -0,0 +1,236 @@
+//! #Isolation forest#
+//!
+//! Outliers are few and different, so random splits separate them from the rest sooner than the normal points.
+//! Every tree is grown on a random subsample of `max_samples` rows, splitting on a random feature at a random value
+//! between its minimum and maximum, until every point is alone or the tree is `log2(max_samples)` deep.
+//! The anomaly score of a point is `2^(-E(h) / c(n))`, where `E(h)` is its mean depth over the trees and `c(n)` the mean
+//! depth of an unsuccessful search in a binary tree of `n` points. It is close to 1 for outliers and below 0.5 for normal points.
+//!
+//! The trees are grown in parallel, each with a seed of its own, so the forest does not depend on the number of threads.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{trait_definition::{OutlierDetector, MLError, Params, ParamValue, assert_point},
+    n_dimen::matrix::Matrix,
+    persistence::persistence::{Persist, PersistError, ensure},
+    supervised::ensemble::common::member_seed};
+use super::common::{check_detector_input, check_contamination, contamination_threshold};
+
+
+///the mean depth of an unsuccessful search in a binary search tree of `n` points, what a leaf of `n` points adds to the depth.
+fn average_path_length(n : usize) -> f32 {
+    match n {
+        0 | 1 => 0.0,
+        2 => 1.0,
+        _ => {
+            let n = n as f32;
+            2.0 * ((n - 1.0).ln() + 0.577_215_66) - 2.0 * (n - 1.0) / n
+        },
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum IsolationNode {
+    ///the points with `feature < value` go left.
+    Split { feature : usize, value : f32, left : usize, right : usize },
+    ///the number of training points that ended up here.
+    Leaf { size : usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IsolationTree {
+    ///the root is the first node.
+    nodes : Vec<IsolationNode>,
+}
+
+impl IsolationTree {
+
+    fn grow(X : &Matrix, samples : Vec<usize>, max_depth : usize, rng : &fastrand::Rng) -> IsolationTree {
+        let mut tree = IsolationTree { nodes: vec![] };
+        tree.grow_node(X, samples, 0, max_depth, rng);
+        tree
+    }
+
+    ///adds the node for the given samples and its children, returns its index.
+    fn grow_node(&mut self, X : &Matrix, samples : Vec<usize>, depth : usize, max_depth : usize, rng : &fastrand::Rng) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(IsolationNode::Leaf { size: samples.len() });
+        if samples.len() <= 1 || depth >= max_depth {
+            return index;
+        }
+        //only the features that still have more than one value can split the samples.
+        let ranges: Vec<(usize, f32, f32)> = (0..X.cols()).filter_map(|feature| {
+            let (min, max) = samples.iter().fold((f32::MAX, f32::MIN), |(min, max), sample| (min.min(X.get(*sample, feature)), max.max(X.get(*sample, feature))));
+            (max > min).then_some((feature, min, max))
+        }).collect();
+        if ranges.is_empty() {
+            return index;
+        }
+        let (feature, min, max) = ranges[rng.usize(..ranges.len())];
+        let value = min + rng.f32() * (max - min);
+        let (left_samples, right_samples): (Vec<usize>, Vec<usize>) = samples.into_iter().partition(|sample| X.get(*sample, feature) < value);
+        let left = self.grow_node(X, left_samples, depth + 1, max_depth, rng);
+        let right = self.grow_node(X, right_samples, depth + 1, max_depth, rng);
+        self.nodes[index] = IsolationNode::Split { feature, value, left, right };
+        index
+    }
+
+    ///the depth at which the point is isolated, plus the expected depth left in the leaf it ends in.
+    fn path_length(&self, point : &[f32]) -> f32 {
+        let (mut node, mut depth) = (0, 0.0);
+        loop {
+            match &self.nodes[node] {
+                IsolationNode::Split { feature, value, left, right } => {
+                    node = if point[*feature] < *value { *left } else { *right };
+                    depth += 1.0;
+                },
+                IsolationNode::Leaf { size } => return depth + average_path_length(*size),
+            }
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IsolationForest {
+    pub n_estimators : usize,
+    ///the rows every tree is grown on, at most the number of training rows.
+    pub max_samples : usize,
+    ///the share of outliers expected in the training set, in (0, 0.5].
+    pub contamination : f32,
+    pub seed : u64,
+    trees : Vec<IsolationTree>,
+    ///the number of rows the trees were grown on, for the normalisation of the depths.
+    sample_size : usize,
+    threshold : Option<f32>,
+    n_features : usize,
+}
+
+impl IsolationForest {
+
+    ///256 samples per tree and 10% contamination.
+    pub fn new(n_estimators : usize) -> IsolationForest {
+        IsolationForest {
+            n_estimators,
+            max_samples: 256,
+            contamination: 0.1,
+            seed: 0,
+            trees: vec![],
+            sample_size: 0,
+            threshold: None,
+            n_features: 0,
+        }
+    }
+
+    pub fn set_max_samples(&mut self, max_samples : usize) {
+        self.max_samples = max_samples;
+    }
+
+    pub fn set_contamination(&mut self, contamination : f32) {
+        self.contamination = contamination;
+    }
+
+    pub fn set_seed(&mut self, seed : u64) {
+        self.seed = seed;
+    }
+
+    ///the mean depth at which the trees isolate the point.
+    pub fn mean_path_length(&self, point : &[f32]) -> f32 {
+        assert_point(self.n_features_in(), point);
+        self.mean_depth(point)
+    }
+
+    fn mean_depth(&self, point : &[f32]) -> f32 {
+        self.trees.iter().map(|tree| tree.path_length(point)).sum::<f32>() / self.trees.len() as f32
+    }
+
+    fn score_unchecked(&self, point : &[f32]) -> f32 {
+        //a subsample of one point has no depth to compare to.
+        let normaliser = average_path_length(self.sample_size).max(f32::EPSILON);
+        2.0_f32.powf(-self.mean_depth(point) / normaliser)
+    }
+}
+
+impl OutlierDetector for IsolationForest {
+    fn fit(&mut self, X : &Matrix) -> Result<(), MLError> {
+        check_detector_input(X)?;
+        check_contamination(self.contamination)?;
+        if self.n_estimators == 0 || self.max_samples < 2 {
+            return Err(MLError::InvalidParameter(format!("needs atleast one tree and two samples per tree, got {} and {}", self.n_estimators, self.max_samples)));
+        }
+        self.threshold = None;
+        let sample_size = self.max_samples.min(X.rows());
+        let max_depth = (sample_size as f32).log2().ceil() as usize;
+        self.trees = (0..self.n_estimators).into_par_iter().map(|tree| {
+            let rng = fastrand::Rng::with_seed(member_seed(self.seed, tree));
+            let mut samples: Vec<usize> = (0..X.rows()).collect();
+            rng.shuffle(&mut samples);
+            samples.truncate(sample_size);
+            IsolationTree::grow(X, samples, max_depth, &rng)
+        }).collect();
+        self.sample_size = sample_size;
+        self.n_features = X.cols();
+        let scores: Vec<f32> = X.par_iter_rows().map(|point| self.score_unchecked(point)).collect();
+        self.threshold = Some(contamination_threshold(&scores, self.contamination));
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.threshold.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    ///`2^(-E(h) / c(n))`, in (0, 1].
+    fn score(&self, point : &[f32]) -> f32 {
+        assert_point(self.n_features_in(), point);
+        self.score_unchecked(point)
+    }
+
+    fn threshold(&self) -> Option<f32> {
+        self.threshold
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("n_estimators".to_owned(), ParamValue::Int(self.n_estimators as i64));
+        params.insert("max_samples".to_owned(), ParamValue::Int(self.max_samples as i64));
+        params.insert("contamination".to_owned(), ParamValue::Float(self.contamination));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_estimators" => self.n_estimators = value.as_usize(name)?,
+                "max_samples" => self.max_samples = value.as_usize(name)?,
+                "contamination" => self.contamination = value.as_f32(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Persist for IsolationForest {
+    const MODEL_TYPE: &'static str = "IsolationForest";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        if self.threshold.is_none() {
+            return Ok(());
+        }
+        ensure(!self.trees.is_empty(), "a fitted forest needs atleast one tree")?;
+        //children always come after their parent, anything else could send `path_length` round in a loop.
+        ensure(self.trees.iter().all(|tree| {
+            !tree.nodes.is_empty() && tree.nodes.iter().enumerate().all(|(index, node)| match node {
+                IsolationNode::Split { feature, left, right, .. } => *feature < self.n_features
+                    && *left > index && *right > index && *left < tree.nodes.len() && *right < tree.nodes.len(),
+                IsolationNode::Leaf { .. } => true,
+            })
+        }), "a split points to a feature or node that does not exist, or back up the tree")
+    }
+}
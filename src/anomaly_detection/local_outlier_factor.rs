@@ -0,0 +1,177 @@
+//! #Local outlier factor#
+//!
+//! Compares the density around a point with the density around its neighbours, a point in a sparser region than its
+//! neighbours is an outlier even when the clusters of the data have very different densities(where one distance cut off can not work).
+//!
+//! With `k_distance(o)` the distance from `o` to its k-th nearest neighbour :
+//! * `reach_dist(p, o) = max(k_distance(o), d(p, o))`
+//! * `lrd(p) = 1 / mean(reach_dist(p, o))` over the k nearest neighbours `o` of `p`, the local reachability density.
+//! * `LOF(p) = mean(lrd(o)) / lrd(p)`, around 1 for the points as dense as their neighbours, well above 1 for the outliers.
+//!
+//! The training points are left out of their own neighbours, new points are compared to the training set(the novelty setting).
+//! The neighbours come from a `NeighbourIndex`, so any `DistanceMetric` works.
+
+use serde::{Deserialize, Serialize};
+use crate::{trait_definition::{OutlierDetector, MLError, Params, ParamValue, assert_point},
+    n_dimen::{matrix::Matrix, distance::DistanceMetric, neighbours::{NeighbourIndex, IndexKind, Neighbour}},
+    persistence::persistence::{Persist, PersistError, ensure}};
+use super::common::{check_detector_input, check_contamination, contamination_threshold};
+
+///keeps the densities finite when k points are on top of each other.
+const MIN_REACH_DISTANCE : f32 = 1e-10;
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalOutlierFactor {
+    pub n_neighbors : usize,
+    pub metric : DistanceMetric,
+    pub index_kind : IndexKind,
+    ///the share of outliers expected in the training set, in (0, 0.5].
+    pub contamination : f32,
+    index : Option<NeighbourIndex>,
+    ///the distance of every training point to its k-th neighbour.
+    k_distances : Vec<f32>,
+    ///the local reachability density of every training point.
+    densities : Vec<f32>,
+    ///the LOF of every training point, against the other training points.
+    training_scores : Vec<f32>,
+    threshold : Option<f32>,
+}
+
+impl LocalOutlierFactor {
+
+    ///euclidean distance, the index picked by `IndexKind::Auto` and 10% contamination.
+    pub fn new(n_neighbors : usize) -> LocalOutlierFactor {
+        LocalOutlierFactor {
+            n_neighbors,
+            metric: DistanceMetric::Euclidean,
+            index_kind: IndexKind::Auto,
+            contamination: 0.1,
+            index: None,
+            k_distances: vec![],
+            densities: vec![],
+            training_scores: vec![],
+            threshold: None,
+        }
+    }
+
+    pub fn set_metric(&mut self, metric : DistanceMetric) {
+        self.metric = metric;
+    }
+
+    pub fn set_index_kind(&mut self, index_kind : IndexKind) {
+        self.index_kind = index_kind;
+    }
+
+    pub fn set_contamination(&mut self, contamination : f32) {
+        self.contamination = contamination;
+    }
+
+    ///the LOF of the training points, each left out of its own neighbours. Empty before fitting.
+    pub fn training_scores(&self) -> &[f32] {
+        &self.training_scores
+    }
+
+    ///the local reachability density of the point among the training points.
+    pub fn local_reachability_density(&self, point : &[f32]) -> f32 {
+        assert_point(self.n_features_in(), point);
+        self.density(&self.index.as_ref().unwrap().k_nearest(point, self.n_neighbors))
+    }
+
+    fn density(&self, neighbours : &[Neighbour]) -> f32 {
+        density(neighbours, &self.k_distances)
+    }
+
+    fn factor(&self, neighbours : &[Neighbour]) -> f32 {
+        let neighbour_density = neighbours.iter().map(|neighbour| self.densities[neighbour.index]).sum::<f32>() / neighbours.len() as f32;
+        neighbour_density / self.density(neighbours)
+    }
+}
+
+fn density(neighbours : &[Neighbour], k_distances : &[f32]) -> f32 {
+    let reach = neighbours.iter().map(|neighbour| k_distances[neighbour.index].max(neighbour.distance)).sum::<f32>() / neighbours.len() as f32;
+    1.0 / reach.max(MIN_REACH_DISTANCE)
+}
+
+impl OutlierDetector for LocalOutlierFactor {
+    fn fit(&mut self, X : &Matrix) -> Result<(), MLError> {
+        check_detector_input(X)?;
+        check_contamination(self.contamination)?;
+        if self.n_neighbors == 0 || self.n_neighbors >= X.rows() {
+            return Err(MLError::InvalidParameter(format!("n_neighbors needs to be in [1, {}) for {} samples, got {}", X.rows(), X.rows(), self.n_neighbors)));
+        }
+        self.threshold = None;
+        let index = NeighbourIndex::from_matrix(X, self.metric.clone(), self.index_kind).map_err(MLError::InvalidParameter)?;
+        //one neighbour more than needed, the point itself is dropped(or the farthest one, when duplicates push it out of the order).
+        let neighbours: Vec<Vec<Neighbour>> = index.k_nearest_batch(index.points(), self.n_neighbors + 1).into_iter().enumerate()
+            .map(|(sample, mut neighbours)| {
+                match neighbours.iter().position(|neighbour| neighbour.index == sample) {
+                    Some(position) => { neighbours.remove(position); },
+                    None => { neighbours.pop(); },
+                }
+                neighbours
+            }).collect();
+
+        self.k_distances = neighbours.iter().map(|neighbours| neighbours.last().unwrap().distance).collect();
+        self.densities = neighbours.iter().map(|neighbours| density(neighbours, &self.k_distances)).collect();
+        self.training_scores = neighbours.iter().map(|neighbours| self.factor(neighbours)).collect();
+        self.threshold = Some(contamination_threshold(&self.training_scores, self.contamination));
+        self.index = Some(index);
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.threshold.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then(|| self.index.as_ref().unwrap().dimensions())
+    }
+
+    ///the LOF of the point against the training points.
+    fn score(&self, point : &[f32]) -> f32 {
+        assert_point(self.n_features_in(), point);
+        self.factor(&self.index.as_ref().unwrap().k_nearest(point, self.n_neighbors))
+    }
+
+    fn threshold(&self) -> Option<f32> {
+        self.threshold
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("n_neighbors".to_owned(), ParamValue::Int(self.n_neighbors as i64));
+        params.insert("contamination".to_owned(), ParamValue::Float(self.contamination));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_neighbors" => self.n_neighbors = value.as_usize(name)?,
+                "contamination" => self.contamination = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Persist for LocalOutlierFactor {
+    const MODEL_TYPE: &'static str = "LocalOutlierFactor";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        if self.threshold.is_none() {
+            return Ok(());
+        }
+        let index = match &self.index {
+            Some(index) => index,
+            None => return Err(PersistError::InvalidShape("a fitted model needs its neighbour index".to_owned())),
+        };
+        index.validate().map_err(PersistError::InvalidShape)?;
+        let n_points = index.len();
+        ensure(n_points > self.n_neighbors && self.n_neighbors > 0, "the index needs more points than n_neighbors")?;
+        ensure(self.k_distances.len() == n_points && self.densities.len() == n_points && self.training_scores.len() == n_points,
+            "the densities do not match the points of the index")
+    }
+}
@@ -0,0 +1,163 @@
+//! #One-class SVM#
+//!
+//! Separates the training points from the origin in the kernel feature space with the largest margin(Schölkopf et al.),
+//! the dual problem
+//!     min 1/2 a^T K a     with 0 <= a_i <= 1 and sum(a_i) = nu * n_samples
+//! is solved by the same SMO solver and kernel cache as `Svc`.
+//! `decision(x) = sum(a_i K(x_i, x)) - rho` is positive inside the learnt region, the score is its negative.
+//!
+//! `nu` bounds the share of training points left outside the region(and is a lower bound on the share of support vectors),
+//! the threshold on the score still comes from `contamination` like for the other detectors.
+
+use serde::{Deserialize, Serialize};
+use crate::{trait_definition::{OutlierDetector, MLError, Params, ParamValue, assert_point},
+    n_dimen::matrix::Matrix,
+    persistence::persistence::{Persist, PersistError, ensure},
+    supervised::support_vector_machines::support_vector_machines::{Kernel, KernelMachine, KernelCache, SmoProblem, smo_solve_from}};
+use super::common::{check_detector_input, check_contamination, contamination_threshold};
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OneClassSvm {
+    pub kernel : Kernel,
+    ///in (0, 1], the most training points outside the region.
+    pub nu : f32,
+    ///the share of outliers expected in the training set, in (0, 0.5].
+    pub contamination : f32,
+    ///stops when the biggest violation of the optimality conditions is below this.
+    pub tol : f32,
+    pub max_iter : usize,
+    pub cache_size_mb : usize,
+    ///the kernel with gamma resolved from the training data.
+    fitted_kernel : Kernel,
+    machine : Option<KernelMachine>,
+    threshold : Option<f32>,
+    n_features : usize,
+}
+
+impl OneClassSvm {
+
+    ///10% contamination, the same stopping rule and cache as `Svc`.
+    pub fn new(kernel : Kernel, nu : f32) -> OneClassSvm {
+        OneClassSvm {
+            kernel,
+            nu,
+            contamination: 0.1,
+            tol: 1e-3,
+            max_iter: 1_000_000,
+            cache_size_mb: 200,
+            fitted_kernel: kernel,
+            machine: None,
+            threshold: None,
+            n_features: 0,
+        }
+    }
+
+    pub fn set_contamination(&mut self, contamination : f32) {
+        self.contamination = contamination;
+    }
+
+    pub fn set_stopping(&mut self, tol : f32, max_iter : usize) {
+        self.tol = tol;
+        self.max_iter = max_iter;
+    }
+
+    pub fn set_cache_size(&mut self, cache_size_mb : usize) {
+        self.cache_size_mb = cache_size_mb;
+    }
+
+    pub fn machine(&self) -> Option<&KernelMachine> {
+        self.machine.as_ref()
+    }
+
+    pub fn n_support_vectors(&self) -> usize {
+        self.machine.as_ref().map_or(0, |machine| machine.support_vectors.len())
+    }
+
+    ///`sum(a_i K(x_i, x)) - rho`, positive inside the learnt region.
+    pub fn region_decision(&self, point : &[f32]) -> f32 {
+        assert_point(self.n_features_in(), point);
+        self.machine.as_ref().unwrap().decision(&self.fitted_kernel, point)
+    }
+}
+
+impl OutlierDetector for OneClassSvm {
+    fn fit(&mut self, X : &Matrix) -> Result<(), MLError> {
+        check_detector_input(X)?;
+        check_contamination(self.contamination)?;
+        if !(self.nu > 0.0 && self.nu <= 1.0) {
+            return Err(MLError::InvalidParameter(format!("nu needs to be in (0, 1], got {}", self.nu)));
+        }
+        if !(self.tol > 0.0) || self.max_iter == 0 {
+            return Err(MLError::InvalidParameter("tol and max_iter need to be > 0".to_owned()));
+        }
+        self.kernel.validate()?;
+        self.threshold = None;
+        self.fitted_kernel = self.kernel.resolve(X);
+        let rows: Vec<&[f32]> = X.iter_rows().collect();
+        let n = rows.len();
+        let problem = SmoProblem { linear: vec![0.0; n], signs: vec![1.0; n], upper: vec![1.0; n] };
+        //the first nu * n variables at their bound and the rest of the sum on the next one, as LIBSVM starts.
+        let total = self.nu as f64 * n as f64;
+        let start: Vec<f64> = (0..n).map(|t| (total - t as f64).clamp(0.0, 1.0)).collect();
+        let mut cache = KernelCache::new(self.fitted_kernel, &rows, self.cache_size_mb);
+        let (alpha, rho, _) = smo_solve_from(&problem, &mut cache, start, self.tol as f64, self.max_iter);
+        let machine = KernelMachine::from_solution(&rows, alpha.into_iter(), rho);
+        let scores: Vec<f32> = rows.iter().map(|point| -machine.decision(&self.fitted_kernel, point)).collect();
+        self.machine = Some(machine);
+        self.n_features = X.cols();
+        self.threshold = Some(contamination_threshold(&scores, self.contamination));
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.threshold.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    ///`rho - sum(a_i K(x_i, x))`, the negative of `region_decision`.
+    fn score(&self, point : &[f32]) -> f32 {
+        -self.region_decision(point)
+    }
+
+    fn threshold(&self) -> Option<f32> {
+        self.threshold
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("nu".to_owned(), ParamValue::Float(self.nu));
+        params.insert("contamination".to_owned(), ParamValue::Float(self.contamination));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("cache_size_mb".to_owned(), ParamValue::Int(self.cache_size_mb as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "nu" => self.nu = value.as_f32(name)?,
+                "contamination" => self.contamination = value.as_f32(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "cache_size_mb" => self.cache_size_mb = value.as_usize(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Persist for OneClassSvm {
+    const MODEL_TYPE: &'static str = "OneClassSvm";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(self.machine.as_ref().map_or(true, |machine| machine.support_vectors.len() == machine.coefficients.len()
+            && machine.support_vectors.iter().all(|vector| vector.len() == self.n_features)), "support vectors of the wrong shape")?;
+        ensure(self.machine.is_some() || self.threshold.is_none(), "a fitted one-class SVM needs its machine")
+    }
+}
@@ -23,6 +23,7 @@ pub struct k_means_spec<'a> {
     varience : Option<Vec<Vec<f32>>>,
     pub cluster_populations : Option<Vec<usize>>,
     normalised : bool,
+    metric : DistanceMetric,
 }
 
 use core::{f32, num};
@@ -71,6 +72,7 @@ pub fn k_means_df(csv_file_path : &str, which_features: Vec<usize>) -> k_means_s
         varience: None,
         cluster_populations: None,
         normalised: false,
+        metric: DistanceMetric::Euclidean,
     }
 }
 
@@ -124,7 +126,7 @@ impl k_means_spec<'_> {
         //somehow manage to get the max and min values for each of the features from the csv to df cause we are alredy iterating over all the points we need not again iterate and find the max and the min for each feature.
         for sample_index in 0..self.number_of_samples {
             for feature_index in 0..self.number_of_features {
-                self.data[sample_index].data[feature_index] = (self.data[sample_index].data[feature_index] - self.min_vector[feature_index]) / (self.max_vector[feature_index] - self.min_vector[feature_index]);
+                self.data[sample_index].data[feature_index] = min_max_scale(self.data[sample_index].data[feature_index], self.min_vector[feature_index], self.max_vector[feature_index]);
             }
         }
         self.normalised = true;    
@@ -229,6 +231,16 @@ impl k_means_spec<'_> {
     pub fn set_random_centroids(&mut self, lower_limit : f32, upper_limit : f32) {
         self.random_centroids = Some((lower_limit , upper_limit));
     }
+    ///'''
+    /// data_frame.set_distance_metric(DistanceMetric::Manhattan)?;
+    /// '''
+    /// changes the metric used to find the nearest centroid, euclidean by default, an invalid metric is an error and the old one is kept.
+    /// the centroids are still updated to the mean of their cluster, so metrics far from euclidean(like cosine) only approximate their own k-means objective.
+    pub fn set_distance_metric(&mut self, metric : DistanceMetric) -> Result<(), MLError> {
+        metric.validate().map_err(MLError::InvalidParameter)?;
+        self.metric = metric;
+        Ok(())
+    }
     ///function only works from inside so no need for docs.
     //takes the whole data frame struct and changes the centroid coordinates by finding the AVERAGE of coords in that respective cluster.
     fn update_centroids(&mut self) {
@@ -336,7 +348,7 @@ impl k_means_spec<'_> {
             //now we have one sample in our hand, time to find out the nearest centroid to this.
             for cluster in 0..self.k {
                 //Now we have a centroid and a sample point ;), time to to find out the distance.
-                let dist_now = distance_between(&sample_point.data, &self.centroids[cluster]);
+                let dist_now = self.metric.distance(&sample_point.data, &self.centroids[cluster]);
                 
                 if dist_now < present_nearest.1 {
                     present_nearest = (cluster , dist_now);
@@ -421,9 +433,8 @@ impl k_means_spec<'_> {
             let max = temp_vec[temp_vec.len() - 1];
             //One feature done.
             //dbg!(max , min);
-            let diff = max - min;
             for i in 0..self.k {
-                mod_vec[i][feature_index] = (varience[i][feature_index] - min) / diff;
+                mod_vec[i][feature_index] = min_max_scale(varience[i][feature_index], min, max);
             }
 
         }
@@ -480,7 +491,7 @@ impl k_means_spec<'_> {
         let mut closest_centroid_index = 0;
         
         for (i, centroid) in self.centroids.iter().enumerate() {
-            let dist = distance_between(x, centroid);
+            let dist = self.metric.distance(x, centroid);
             if dist < present_min_dist_with {
                 present_min_dist_with = dist;
                 closest_centroid_index = i;
@@ -715,6 +726,87 @@ impl k_means_spec<'_> {
 
 }
 
+///The part of a fitted `k_means_spec` that is needed to assign new points to clusters,
+/// it does not borrow the csv path or hold the training data, so it can be saved and loaded with `Persist`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KMeansModel {
+    pub header_names : Vec<String>,
+    pub centroids : Vec<Vec<f32>>,
+    pub encodings : Option<Vec<String>>,
+    pub cluster_populations : Option<Vec<usize>>,
+    ///minimum and maximum of each feature in the training data, needed to normalise new points.
+    max_vector : Vec<f32>,
+    min_vector : Vec<f32>,
+    normalised : bool,
+    ///metric used to find the nearest centroid.
+    #[serde(default)]
+    metric : DistanceMetric,
+}
+
+impl k_means_spec<'_> {
+    ///'''
+    /// data_frame.fit(100, 0.001, 3);
+    /// let model = data_frame.to_model();
+    /// model.save_json("k_means.json").unwrap();
+    /// '''
+    /// takes a snapshot of the centroids and the normalisation metadata.
+    pub fn to_model(&self) -> KMeansModel {
+        KMeansModel {
+            header_names : self.header_names.clone(),
+            centroids : self.centroids.clone(),
+            encodings : self.encodings.clone(),
+            cluster_populations : self.cluster_populations.clone(),
+            max_vector : self.max_vector.clone(),
+            min_vector : self.min_vector.clone(),
+            normalised : self.normalised,
+            metric : self.metric.clone(),
+        }
+    }
+}
+
+impl KMeansModel {
+    ///returns the index of the nearest centroid to the point, the point is normalised first if the training data was.
+    pub fn predict(&self, x : &Vec<f32>) -> u32 {
+        let this: Vec<f32> = match self.normalised {
+            true => x.iter().enumerate().map(|(i, value)| min_max_scale(*value, self.min_vector[i], self.max_vector[i])).collect(),
+            false => x.clone(),
+        };
+
+        let mut present_min_dist_with = f32::INFINITY;
+        let mut closest_centroid_index = 0;
+        for (i, centroid) in self.centroids.iter().enumerate() {
+            let dist = self.metric.distance(&this, centroid);
+            if dist < present_min_dist_with {
+                present_min_dist_with = dist;
+                closest_centroid_index = i;
+            }
+        }
+        closest_centroid_index as u32
+    }
+
+    ///returns the encoding name of the cluster the point belongs to, if names were given.
+    pub fn predict_name(&self, x : &Vec<f32>) -> Option<String> {
+        let index = self.predict(x) as usize;
+        self.encodings.as_ref().map(|names| names[index].to_owned())
+    }
+}
+
+impl Persist for KMeansModel {
+    const MODEL_TYPE: &'static str = "KMeans";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let number_of_features = self.max_vector.len();
+        ensure(!self.centroids.is_empty(), "there are no centroids")?;
+        ensure(self.min_vector.len() == number_of_features, "minimum and maximum vectors have different lengths")?;
+        ensure(self.centroids.iter().all(|centroid| centroid.len() == number_of_features), "every centroid needs one value per feature")?;
+        self.metric.validate().map_err(PersistError::InvalidShape)?;
+        if let Some(names) = &self.encodings {
+            ensure(names.len() == self.centroids.len(), "there should be one encoding name per centroid")?;
+        }
+        Ok(())
+    }
+}
+
 fn random_color() -> RGBColor {
     let red = rand::random::<u8>();
     let green = rand::random::<u8>();
@@ -776,10 +868,17 @@ fn get_random_samples_from_df(data : &Vec<sample_point>, k: usize , number_of_fe
 
 
 
+///`(value - min) / (max - min)`, a constant feature(max == min) is only shifted instead of turning into NaN.
+fn min_max_scale(value : f32, min : f32, max : f32) -> f32 {
+    let range = max - min;
+    (value - min) / if range != 0.0 { range } else { 1.0 }
+}
+
 use std::{error::Error, fs::File, io::{prelude::*, BufReader}};
 use csv::ReaderBuilder;
 
-use crate::{n_dimen::n_dimen::{distance_between, max_distance_between_sets}, data_frame::data_frame::get_headers}; 
+use crate::{n_dimen::{n_dimen::{distance_between, max_distance_between_sets}, distance::{Distance, DistanceMetric}}, data_frame::data_frame::get_headers, trait_definition::MLError, persistence::persistence::{Persist, PersistError, ensure}};
+use serde::{Deserialize, Serialize};
 
 //The csv can have multiple columns of string types which cannot be parsed into f32s,
 //so we still need to have a which features to consider array, even though it is annoying, sorry :(
@@ -41,3 +41,32 @@ fn k_means_test_iris() {
     
 }
 
+
+///writes the rows under a header into a csv in the temp directory and gives back its path.
+#[cfg(test)]
+fn small_csv(name : &str, rows : &[[f32; 2]]) -> String {
+    let path = std::env::temp_dir().join(name);
+    let body: String = rows.iter().map(|row| format!("{},{}\n", row[0], row[1])).collect();
+    std::fs::write(&path, format!("a,b\n{}", body)).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn invalid_distance_metric_is_an_error() {
+    use crate::{n_dimen::distance::DistanceMetric, trait_definition::MLError};
+    let path = small_csv("ferrum_k_means_metric.csv", &[[0.0, 0.0], [0.1, 0.2], [5.0, 5.0], [5.1, 4.9]]);
+    let mut data_frame = k_means_df(&path, vec![]);
+    assert!(matches!(data_frame.set_distance_metric(DistanceMetric::Minkowski(0.5)), Err(MLError::InvalidParameter(_))));
+    data_frame.set_distance_metric(DistanceMetric::Manhattan).unwrap();
+}
+
+#[test]
+fn constant_feature_does_not_give_nan() {
+    //the second feature never changes, so its variance is the same(0) in every cluster.
+    let path = small_csv("ferrum_k_means_constant.csv", &[[0.0, 1.0], [0.1, 1.0], [0.2, 1.0], [5.0, 1.0], [5.1, 1.0], [5.2, 1.0]]);
+    let mut data_frame = k_means_df(&path, vec![]);
+    data_frame.fit(100, 0.001, 2);
+    let normal_varience = data_frame.get_normal_varience();
+    assert!(normal_varience.iter().flatten().all(|value| value.is_finite()), "{:?}", normal_varience);
+    assert!(normal_varience.iter().all(|cluster| cluster[1] == 0.0));
+}
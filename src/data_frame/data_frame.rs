@@ -6,6 +6,9 @@ use rayon::{prelude::{IntoParallelRefMutIterator, ParallelIterator, ParallelBrid
 use std::collections::HashMap;
 use rand::seq::SliceRandom;
 use super::data_type::{DataType, length};
+use crate::n_dimen::matrix::Matrix;
+use serde::{Deserialize, Serialize};
+use crate::persistence::persistence::{Persist, PersistError, ensure, nan_vec};
 
 //TODO -- we still need to find a way to normalize a external point -- in progress transform , 
 //we basically store the history of what happned to the each column before and then we are going to do the same on then present.
@@ -22,7 +25,7 @@ pub struct DataFrame {
 
 //data frame can be spitted and trained on.
 pub trait train_test_split {
-    fn train_test_split(&self , test_size : f32 , target_index : usize , shuffle : bool ) -> (Vec<Vec<f32>> , DataType , Vec<Vec<f32>> , DataType);
+    fn train_test_split(&self , test_size : f32 , target_index : usize , shuffle : bool ) -> (Matrix , DataType , Matrix , DataType);
 }
 
 pub fn get_headers(path : &str , which_features: &Vec<usize> , number_of_features : usize) -> Vec<String> {
@@ -690,8 +693,8 @@ impl DataFrame {
     ///this method creates a completely new vector which all the ml algos will use so using this function will be always required even for unsupervised or neural network learning.
     //target index is the index you want as the target variable.
     //shuffle -> shuffle randomly shuffles the data points, still no random seed option.
-    //after this function , we definetely know that the training is going to be on a vec<vec<f32>> and the target is going to be a data_type.
-    pub fn train_test_split(&self , test_size : f32 , target_index : usize , shuffle : bool ) -> (Vec<Vec<f32>> , DataType , Vec<Vec<f32>> , DataType) {
+    //after this function , we definetely know that the training is going to be on a `Matrix` and the target is going to be a data_type.
+    pub fn train_test_split(&self , test_size : f32 , target_index : usize , shuffle : bool ) -> (Matrix , DataType , Matrix , DataType) {
 
         let test_length = (test_size * self.number_of_samples as f32) as usize;
         let train_length = self.number_of_samples as usize - test_length;
@@ -701,8 +704,8 @@ impl DataFrame {
         let sample_number = self.number_of_samples as usize;
         let feature_number = self.number_of_features as u32 as usize;//we are going to remove the extra target column afterwards.
         let number_of_features_here = feature_number - 1;
-        let mut X_train = vec![vec![0.0_f32 ; number_of_features_here] ; train_length];
-        let mut X_test = vec![vec![0.0_f32 ; number_of_features_here] ; test_length];
+        let mut X_train = Matrix::new(train_length, number_of_features_here);
+        let mut X_test = Matrix::new(test_length, number_of_features_here);
         //creating and shuffling the rows.
         let mut all_rows = vec![0_usize ; sample_number];
         for i in 0..sample_number {
@@ -800,3 +803,38 @@ impl DataFrame {
     //replace a value witch meets certain conditions with an other value like a formula.
     //creating new data columns by adding values of other two columns.--will be helpful once we implemented the heatmaps for the relation between two heatmaps.
 }
+
+
+///The preprocessing information of a `DataFrame`, saved next to a model so new points can be prepared the same way the training data was.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataFrameMetadata {
+    pub headers: Vec<String>,
+    #[serde(with = "nan_vec")]
+    pub max_vector: Vec<f32>,
+    #[serde(with = "nan_vec")]
+    pub min_vector: Vec<f32>,
+    pub normalized: bool,
+}
+
+impl DataFrame {
+    ///returns the headers, the min and max of every column and the normalisation state of the data frame.
+    ///`normalize()` resets the min and max vectors to 0 and 1, so take the metadata before normalizing if you need the original ranges.
+    pub fn metadata(&self) -> DataFrameMetadata {
+        DataFrameMetadata {
+            headers: self.headers.clone(),
+            max_vector: self.max_vector.clone(),
+            min_vector: self.min_vector.clone(),
+            normalized: self.normalized,
+        }
+    }
+}
+
+impl Persist for DataFrameMetadata {
+    const MODEL_TYPE: &'static str = "DataFrameMetadata";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(self.max_vector.len() == self.headers.len(), "there should be one maximum per column")?;
+        ensure(self.min_vector.len() == self.headers.len(), "there should be one minimum per column")?;
+        Ok(())
+    }
+}
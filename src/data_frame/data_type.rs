@@ -8,13 +8,20 @@
 
 //a custom iterator which returns an iterator to the mutable reference on the objects .
 
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+use super::return_type::ReturnType;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DataType {
     Strings(Vec<String>),
     Floats(Vec<f32>),
     Category(Vec<u8>),//even if we have only bools or 0 and 1 values a vector of bools will take same amount of space in the memory as of a vector with u8 numbers.
 }
 
+///marks a sample without a label in the `Category` targets of the semi-supervised models(see `supervised::semi_supervised`),
+///so it can not be used as a class there.
+pub const UNLABELLED : u8 = u8::MAX;
+
 
 
 //will return the length of the vector wrapped inside of data_type
@@ -53,4 +60,38 @@ impl print_at_index for DataType {
             },
         }
     }
-}
\ No newline at end of file
+}
+
+impl DataType {
+    ///the sorted unique values of a Category or Strings column and, for every sample, the index of its value in them.
+    ///Returns `None` for float columns, those are not classes.
+    pub fn unique_classes(&self) -> Option<(DataType, Vec<usize>)> {
+        fn encode<T : Ord + Clone>(values : &[T]) -> (Vec<T>, Vec<usize>) {
+            let mut classes = values.to_vec();
+            classes.sort();
+            classes.dedup();
+            let indices = values.iter().map(|value| classes.binary_search(value).unwrap()).collect();
+            (classes, indices)
+        }
+        match self {
+            DataType::Category(temp) => {
+                let (classes, indices) = encode(temp);
+                Some((DataType::Category(classes), indices))
+            },
+            DataType::Strings(temp) => {
+                let (classes, indices) = encode(temp);
+                Some((DataType::Strings(classes), indices))
+            },
+            DataType::Floats(_) => None,
+        }
+    }
+
+    ///the value at `index` in the form `predict` returns it.
+    pub fn value_at(&self, index : usize) -> ReturnType {
+        match self {
+            DataType::Strings(temp) => ReturnType::Strings(temp[index].clone()),
+            DataType::Floats(temp) => ReturnType::Floats(temp[index]),
+            DataType::Category(temp) => ReturnType::Category(temp[index]),
+        }
+    }
+}
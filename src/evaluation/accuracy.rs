@@ -1,18 +1,18 @@
 //TODO - confusion matrix.
 
-use crate::{trait_definition::Predict, data_frame::{data_type::{DataType, length}, return_type::ReturnType}};
+use crate::{trait_definition::{Predict, ClassWeight, class_sample_weights}, n_dimen::matrix::Matrix, data_frame::{data_type::{DataType, length}, return_type::ReturnType}};
 
 
 //model needs to contain the trait predict for this.
-pub fn accuracy_score<T : Predict>(model : &T, X_test: &Vec<Vec<f32>> , y_test: &DataType) -> f32 {
+pub fn accuracy_score<T : Predict + ?Sized>(model : &T, X_test: &Matrix , y_test: &DataType) -> f32 {
     
     let mut correct = 0;
 
-    assert!(X_test.len() == y_test.len() , "The size of the X_test and y_test is not the same");
+    assert!(X_test.rows() == y_test.len() , "The size of the X_test and y_test is not the same");
     
     match y_test {
         DataType::Category(temp) => {
-            for (i , point) in X_test.iter().enumerate() {
+            for (i , point) in X_test.iter_rows().enumerate() {
                 if model.predict(point) == ReturnType::Category(temp[i]) {
                     correct += 1;
                 }
@@ -20,14 +20,14 @@ pub fn accuracy_score<T : Predict>(model : &T, X_test: &Vec<Vec<f32>> , y_test:
         },
         //this type is generally not validated through this method but,. just in case.
         DataType::Floats(temp) => {
-            for (i , point) in X_test.iter().enumerate() {
+            for (i , point) in X_test.iter_rows().enumerate() {
                 if model.predict(point) == ReturnType::Floats(temp[i]) {
                     correct += 1;
                 }
             }
         },
         DataType::Strings(temp) => {
-            for (i , point) in X_test.iter().enumerate() {
+            for (i , point) in X_test.iter_rows().enumerate() {
                 if model.predict(point) == ReturnType::Strings(temp[i].clone()) {
                     correct += 1;
                 }
@@ -36,8 +36,107 @@ pub fn accuracy_score<T : Predict>(model : &T, X_test: &Vec<Vec<f32>> , y_test:
         }
     }
 
-    println!("Total test size : {} , guessed correctly : {}" , X_test.len() , correct);
+    println!("Total test size : {} , guessed correctly : {}" , X_test.rows() , correct);
 
-    correct as f32 / X_test.len() as f32
+    correct as f32 / X_test.rows() as f32
 
-}
\ No newline at end of file
+}
+
+///coefficient of determination `1 - sum((y - y_pred)^2) / sum((y - mean(y))^2)` of the predictions, needs a float target.
+///1 is a perfect fit, 0 is as good as always predicting the mean.
+pub fn r2_score<T : Predict + ?Sized>(model : &T, X_test: &Matrix , y_test: &DataType) -> f32 {
+    let targets = match y_test {
+        DataType::Floats(temp) => temp,
+        _ => panic!("r2_score needs a float target, use accuracy_score for classes"),
+    };
+    assert!(X_test.rows() == targets.len() , "The size of the X_test and y_test is not the same");
+    let predictions: Vec<f32> = X_test.iter_rows().map(|point| match model.predict(point) {
+        ReturnType::Floats(value) => value,
+        other => panic!("r2_score needs a regression model, got the prediction {:?}", other),
+    }).collect();
+    r2(targets, &predictions)
+}
+
+///`r2_score` on already computed predictions.
+pub fn r2(y_true : &[f32], y_pred : &[f32]) -> f32 {
+    weighted_r2(y_true, y_pred, &vec![1.0; y_true.len()])
+}
+
+///`r2` where every squared error(and the weighted mean it is compared to) counts as much as its weight.
+pub fn weighted_r2(y_true : &[f32], y_pred : &[f32], sample_weights : &[f32]) -> f32 {
+    assert!(y_true.len() == sample_weights.len(), "there needs to be one weight per sample");
+    let mean = y_true.iter().zip(sample_weights).map(|(y, w)| y * w).sum::<f32>() / sample_weights.iter().sum::<f32>();
+    let residual: f32 = y_true.iter().zip(y_pred).zip(sample_weights).map(|((y, p), w)| w * (y - p) * (y - p)).sum();
+    let total: f32 = y_true.iter().zip(sample_weights).map(|(y, w)| w * (y - mean) * (y - mean)).sum();
+    if total == 0.0 {
+        return if residual == 0.0 { 1.0 } else { 0.0 };
+    }
+    1.0 - residual / total
+}
+
+///the default score of a model, accuracy for category/string targets and R² for float targets.
+///Unlike `accuracy_score` this prints nothing, so it can be called in loops.
+pub fn score<T : Predict + ?Sized>(model : &T, X_test: &Matrix , y_test: &DataType) -> f32 {
+    if let DataType::Floats(_) = y_test {
+        return r2_score(model, X_test, y_test);
+    }
+    assert!(X_test.rows() == y_test.len() , "The size of the X_test and y_test is not the same");
+    let correct = X_test.iter_rows().enumerate().filter(|(i, point)| model.predict(point) == y_test.value_at(*i)).count();
+    correct as f32 / X_test.rows() as f32
+}
+
+///`score` with a weight per test sample, the weighted share of correct predictions or the weighted R².
+pub fn weighted_score<T : Predict + ?Sized>(model : &T, X_test: &Matrix , y_test: &DataType, sample_weights : &[f32]) -> f32 {
+    assert!(X_test.rows() == y_test.len() , "The size of the X_test and y_test is not the same");
+    assert!(X_test.rows() == sample_weights.len(), "there needs to be one weight per sample");
+    if let DataType::Floats(targets) = y_test {
+        let predictions: Vec<f32> = X_test.iter_rows().map(|point| match model.predict(point) {
+            ReturnType::Floats(value) => value,
+            other => panic!("a float target needs a regression model, got the prediction {:?}", other),
+        }).collect();
+        return weighted_r2(targets, &predictions, sample_weights);
+    }
+    let correct: f32 = X_test.iter_rows().zip(sample_weights).enumerate()
+        .filter(|(i, (point, _))| model.predict(point) == y_test.value_at(*i))
+        .map(|(_, (_, weight))| weight)
+        .sum();
+    correct / sample_weights.iter().sum::<f32>()
+}
+
+///the mean of the per class recalls, the accuracy with `ClassWeight::Balanced` weights.
+///Always predicting the majority class scores `1 / n_classes` instead of its share of the samples.
+pub fn balanced_accuracy_score<T : Predict + ?Sized>(model : &T, X_test: &Matrix , y_test: &DataType) -> f32 {
+    let sample_weights = class_sample_weights(y_test, &ClassWeight::Balanced).unwrap_or_else(|error| panic!("{}", error));
+    weighted_score(model, X_test, y_test, &sample_weights)
+}
+
+///deviance of a single prediction `mu`(> 0) under a Tweedie distribution with the given `power`,
+///0 is the squared error, 1 Poisson, 2 Gamma and anything in between a compound Poisson-Gamma.
+pub fn tweedie_deviance(y : f64, mu : f64, power : f64) -> f64 {
+    if power == 0.0 {
+        (y - mu).powi(2)
+    } else if power == 1.0 {
+        2.0 * (if y > 0.0 { y * (y / mu).ln() } else { 0.0 } - y + mu)
+    } else if power == 2.0 {
+        2.0 * ((mu / y).ln() + y / mu - 1.0)
+    } else {
+        2.0 * (y.max(0.0).powf(2.0 - power) / ((1.0 - power) * (2.0 - power)) - y * mu.powf(1.0 - power) / (1.0 - power) + mu.powf(2.0 - power) / (2.0 - power))
+    }
+}
+
+///mean `tweedie_deviance` of the predictions, the natural error of count(power 1) and cost(power 2) models.
+pub fn mean_tweedie_deviance(y_true : &[f32], y_pred : &[f32], power : f32) -> f32 {
+    let total: f64 = y_true.iter().zip(y_pred).map(|(y, mu)| tweedie_deviance(*y as f64, *mu as f64, power as f64)).sum();
+    (total / y_true.len() as f64) as f32
+}
+
+///fraction of the deviance explained, the `r2` of generalised linear models : `1 - deviance(y, y_pred) / deviance(y, mean(y))`.
+pub fn d2_tweedie(y_true : &[f32], y_pred : &[f32], power : f32) -> f32 {
+    let mean = y_true.iter().sum::<f32>() / y_true.len() as f32;
+    let residual = mean_tweedie_deviance(y_true, y_pred, power);
+    let total = mean_tweedie_deviance(y_true, &vec![mean; y_true.len()], power);
+    if total == 0.0 {
+        return if residual == 0.0 { 1.0 } else { 0.0 };
+    }
+    1.0 - residual / total
+}
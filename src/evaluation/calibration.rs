@@ -0,0 +1,96 @@
+//! How far predicted probabilities can be trusted : the Brier score and the data of a reliability curve.
+//!
+//! Everything works on already computed probabilities. For a classifier, `y_true` is 1 for the samples of the class
+//! the probabilities are for and 0 for the rest.
+
+
+///how `reliability_curve` cuts [0, 1] into bins.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binning {
+    ///bins of the same width.
+    Uniform,
+    ///bins with (about) the same number of samples, cut at the quantiles of the probabilities.
+    Quantile,
+}
+
+///one point per non empty bin, in increasing order of the predicted probability.
+///A calibrated model has `fraction_positive` close to `mean_predicted` in every bin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReliabilityCurve {
+    ///the mean probability the model predicted for the samples of the bin.
+    pub mean_predicted : Vec<f32>,
+    ///the share of the samples of the bin that were actually positive.
+    pub fraction_positive : Vec<f32>,
+    ///the number of samples in the bin.
+    pub counts : Vec<usize>,
+}
+
+impl ReliabilityCurve {
+    ///the expected calibration error, the mean gap between `fraction_positive` and `mean_predicted` weighted by the bin sizes.
+    pub fn expected_calibration_error(&self) -> f32 {
+        let total = self.counts.iter().sum::<usize>() as f32;
+        self.mean_predicted.iter().zip(&self.fraction_positive).zip(&self.counts)
+            .map(|((predicted, positive), count)| (predicted - positive).abs() * *count as f32)
+            .sum::<f32>() / total
+    }
+}
+
+fn check_binary(y_true : &[f32], y_prob : &[f32]) {
+    assert!(y_true.len() == y_prob.len(), "there needs to be one probability per sample, got {} for {} samples", y_prob.len(), y_true.len());
+    assert!(!y_true.is_empty(), "there are no samples");
+    assert!(y_true.iter().all(|y| *y == 0.0 || *y == 1.0), "y_true needs to be 0 or 1");
+    assert!(y_prob.iter().all(|p| (0.0..=1.0).contains(p)), "the probabilities need to be in [0, 1]");
+}
+
+///mean squared difference between the predicted probability of the positive class and the outcome(0 or 1).
+///0 is perfect, always predicting 0.5 scores 0.25.
+pub fn brier_score(y_true : &[f32], y_prob : &[f32]) -> f32 {
+    check_binary(y_true, y_prob);
+    y_true.iter().zip(y_prob).map(|(y, p)| (p - y) * (p - y)).sum::<f32>() / y_true.len() as f32
+}
+
+///the Brier score of all the classes at once : the mean over the samples of the squared distance between the probabilities
+///and the one-hot encoding of the true class, given as the index of the class in the probabilities.
+///For two classes this is twice `brier_score`.
+pub fn multiclass_brier_score(labels : &[usize], probabilities : &[Vec<f32>]) -> f32 {
+    assert!(labels.len() == probabilities.len(), "there needs to be one probability row per sample, got {} for {} samples", probabilities.len(), labels.len());
+    assert!(!labels.is_empty(), "there are no samples");
+    let total: f32 = labels.iter().zip(probabilities).map(|(label, row)| {
+        assert!(*label < row.len(), "the class {} has no probability, there are only {}", label, row.len());
+        row.iter().enumerate().map(|(class, p)| {
+            let y = (class == *label) as u8 as f32;
+            (p - y) * (p - y)
+        }).sum::<f32>()
+    }).sum();
+    total / labels.len() as f32
+}
+
+///the data of a reliability diagram : the samples are put into `n_bins` bins by their predicted probability,
+///and every bin gives the mean predicted probability against the share of positives. Empty bins are left out.
+pub fn reliability_curve(y_true : &[f32], y_prob : &[f32], n_bins : usize, binning : Binning) -> ReliabilityCurve {
+    check_binary(y_true, y_prob);
+    assert!(n_bins > 0, "n_bins needs to be atleast 1");
+    //the upper edges of the bins, the last one always takes 1.
+    let edges: Vec<f32> = match binning {
+        Binning::Uniform => (1..=n_bins).map(|bin| bin as f32 / n_bins as f32).collect(),
+        Binning::Quantile => {
+            let mut sorted = y_prob.to_vec();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            //with fewer samples than bins several edges fall on the same sample and their bins stay empty.
+            (1..=n_bins).map(|bin| sorted[(bin * sorted.len()).div_ceil(n_bins).max(1) - 1]).collect()
+        },
+    };
+    let mut sums = vec![(0.0_f64, 0.0_f64, 0_usize); n_bins];
+    for (y, p) in y_true.iter().zip(y_prob) {
+        let bin = edges.iter().position(|edge| p <= edge).unwrap_or(n_bins - 1);
+        sums[bin].0 += *p as f64;
+        sums[bin].1 += *y as f64;
+        sums[bin].2 += 1;
+    }
+    let filled: Vec<&(f64, f64, usize)> = sums.iter().filter(|(_, _, count)| *count > 0).collect();
+    ReliabilityCurve {
+        mean_predicted: filled.iter().map(|(p, _, count)| (p / *count as f64) as f32).collect(),
+        fraction_positive: filled.iter().map(|(_, y, count)| (y / *count as f64) as f32).collect(),
+        counts: filled.iter().map(|(_, _, count)| *count).collect(),
+    }
+}
@@ -0,0 +1,39 @@
+//! #Permutation feature importance#
+//!
+//! How much the score of a fitted model drops when the values of one feature are shuffled,
+//! which breaks the link between that feature and the target. Works with any model that can predict.
+
+use crate::{trait_definition::Predict, n_dimen::matrix::Matrix, data_frame::data_type::DataType};
+use super::accuracy::score;
+
+
+///mean and standard deviation of the score decrease over `n_repeats` shuffles of every column.
+///The score is accuracy for class targets and R² for float targets(see `accuracy::score`).
+pub fn permutation_importance<T : Predict + ?Sized>(model : &T, X : &Matrix, y : &DataType, n_repeats : usize, seed : u64) -> (Vec<f32>, Vec<f32>) {
+    assert!(n_repeats > 0, "permutation_importance needs atleast one repeat");
+    let baseline = score(model, X, y);
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut means = vec![0.0_f32; X.cols()];
+    let mut std_devs = vec![0.0_f32; X.cols()];
+    let mut shuffled = X.clone();
+
+    for feature in 0..X.cols() {
+        let mut column = X.column(feature);
+        let mut decreases = Vec::with_capacity(n_repeats);
+        for _ in 0..n_repeats {
+            rng.shuffle(&mut column);
+            for (row, value) in column.iter().enumerate() {
+                shuffled[(row, feature)] = *value;
+            }
+            decreases.push(baseline - score(model, &shuffled, y));
+        }
+        //putting the original column back before moving on to the next one.
+        for (row, value) in X.column(feature).iter().enumerate() {
+            shuffled[(row, feature)] = *value;
+        }
+        let mean = decreases.iter().sum::<f32>() / n_repeats as f32;
+        means[feature] = mean;
+        std_devs[feature] = (decreases.iter().map(|decrease| (decrease - mean).powi(2)).sum::<f32>() / n_repeats as f32).sqrt();
+    }
+    (means, std_devs)
+}
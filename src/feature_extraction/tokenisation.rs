@@ -359,6 +359,27 @@ impl Tokens {
         println!("Total number of tokens : {}", self.token_map_index.len());
     }
 
+    ///bag of words, one sparse vector of token counts per string, indexed by the token index.
+    ///Tokens that were removed are left out, the dimension(returned as well) is one past the biggest token index.
+    pub fn sparse_counts(&self) -> (Vec<CsVec<f32>>, usize) {
+        let dimension = self.index_map_token.keys().max().map_or(0, |index| index + 1);
+        let counts = self.data_in_sequence.iter().map(|sequence| {
+            let mut indices: Vec<usize> = sequence.iter().filter(|index| self.index_map_token.contains_key(index)).cloned().collect();
+            indices.sort();
+            let (mut unique, mut counts): (Vec<usize>, Vec<f32>) = (vec![], vec![]);
+            for index in indices {
+                if unique.last() == Some(&index) {
+                    *counts.last_mut().unwrap() += 1.0;
+                } else {
+                    unique.push(index);
+                    counts.push(1.0);
+                }
+            }
+            CsVec::new(dimension, unique, counts)
+        }).collect();
+        (counts, dimension)
+    }
+
     ///Returns the number of times an individual token appears in all the input strings.
     pub fn get_count(&self , token_name : &str) -> u32 { 
         let temp = self.token_map_index.get(token_name);
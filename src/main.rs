@@ -7,6 +7,8 @@
 pub mod preprocessing {
     pub mod pca;
     mod pca_test;
+    pub mod scaling;
+    mod scaling_test;
     
 }
 
@@ -14,6 +16,16 @@ pub mod trait_definition;
 
 pub mod n_dimen {
     pub mod n_dimen;
+    pub mod matrix;
+    mod matrix_test;
+    pub mod linalg;
+    mod linalg_test;
+    pub mod distance;
+    mod distance_test;
+    pub mod neighbours;
+    mod neighbours_test;
+    pub mod optimise;
+    mod optimise_test;
 }
 
 pub mod feature_extraction {
@@ -23,6 +35,8 @@ pub mod feature_extraction {
 
 pub mod evaluation {
     pub mod accuracy;
+    pub mod importance;
+    pub mod calibration;
 }
 
 pub mod data_frame {
@@ -36,6 +50,11 @@ pub mod file_handling {
     pub mod read_from;
 }
 
+pub mod persistence {
+    pub mod persistence;
+    mod persistence_test;
+}
+
 
 pub mod clustering {
     pub mod k_means_clustering {
@@ -65,6 +84,10 @@ pub mod supervised {
     pub mod naive_bayes {
         pub mod gaussian_NB;
         pub mod multinomial_NB;
+        pub mod bernoulli_NB;
+        pub mod complement_NB;
+        pub mod categorical_NB;
+        mod common;
         mod naive_bayes_test;
     }
     pub mod decision_trees {
@@ -74,6 +97,10 @@ pub mod supervised {
     pub mod linear_regression {
         pub mod linear_regression;
         mod linear_regression_test;
+        pub mod glm;
+        mod glm_test;
+        pub mod robust;
+        mod robust_test;
     }
     pub mod logistic_regression{
         pub mod logistic_regression;
@@ -95,6 +122,35 @@ pub mod supervised {
         pub mod k_nearest_neighbours;
         mod k_nearest_neighbours_test;
     }
+    pub mod sgd {
+        pub mod sgd;
+        mod sgd_test;
+    }
+    pub mod ensemble {
+        pub mod voting;
+        pub mod stacking;
+        pub mod bagging;
+        pub mod adaboost;
+        pub(crate) mod common;
+        mod ensemble_test;
+    }
+    pub mod calibration {
+        pub mod calibration;
+        mod calibration_test;
+    }
+    pub mod multiclass {
+        pub mod one_vs_rest;
+        pub mod one_vs_one;
+        pub mod multi_output;
+        mod multiclass_test;
+    }
+    pub mod semi_supervised {
+        pub mod label_propagation;
+        pub mod self_training;
+        mod common;
+        mod semi_supervised_test;
+    }
+    mod test_fixtures;
 
 }
 
@@ -0,0 +1,169 @@
+//! #Distance metrics#
+//!
+//! The `Distance` trait is what k-means and the neighbour based models use to compare two points.
+//! `DistanceMetric` implements it for the common metrics, you can plug in your own metric by implementing the trait for your own type.
+//!
+//! The loops over the coordinates run on 8 independent accumulators, so the compiler can turn them into SIMD instructions.
+
+use serde::{Deserialize, Serialize};
+use super::{matrix::Matrix, linalg::{inverse, LinalgError}};
+
+/// Anything that can measure how far apart two points of the same dimension are.
+pub trait Distance {
+    fn distance(&self, point_1 : &[f32], point_2 : &[f32]) -> f32;
+}
+
+/// The built-in distance metrics.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum DistanceMetric {
+    ///     sqrt(sum((x - y)^2))
+    #[default]
+    Euclidean,
+    ///     sum((x - y)^2), same ordering as euclidean without the square root.
+    SquaredEuclidean,
+    ///     sum(|x - y|)
+    Manhattan,
+    ///     max(|x - y|)
+    Chebyshev,
+    ///     sum(|x - y|^p)^(1/p), p = 1 is manhattan and p = 2 is euclidean.
+    Minkowski(f32),
+    ///     1 - (x.y)/(|x||y|)
+    Cosine,
+    ///     fraction of the coordinates that are different.
+    Hamming,
+    ///     sqrt((x - y)^T S^-1 (x - y)), holds the inverse of the covariance matrix `S`, see `DistanceMetric::mahalanobis`.
+    Mahalanobis(Matrix),
+}
+
+const LANES : usize = 8;
+
+///reduces `f(x_i, y_i)` over all the coordinates with `LANES` independent accumulators.
+#[inline(always)]
+fn lane_sum<F : Fn(f32, f32) -> f32>(point_1 : &[f32], point_2 : &[f32], f : F) -> f32 {
+    assert!(point_1.len() == point_2.len(), "The points have different dimensions {} and {}", point_1.len(), point_2.len());
+    let mut lanes = [0.0_f32; LANES];
+    let mut chunks_1 = point_1.chunks_exact(LANES);
+    let mut chunks_2 = point_2.chunks_exact(LANES);
+    for (chunk_1, chunk_2) in (&mut chunks_1).zip(&mut chunks_2) {
+        for lane in 0..LANES {
+            lanes[lane] += f(chunk_1[lane], chunk_2[lane]);
+        }
+    }
+    let mut total: f32 = lanes.iter().sum();
+    for (x, y) in chunks_1.remainder().iter().zip(chunks_2.remainder()) {
+        total += f(*x, *y);
+    }
+    total
+}
+
+pub fn squared_euclidean(point_1 : &[f32], point_2 : &[f32]) -> f32 {
+    lane_sum(point_1, point_2, |x, y| (x - y) * (x - y))
+}
+
+pub fn euclidean(point_1 : &[f32], point_2 : &[f32]) -> f32 {
+    squared_euclidean(point_1, point_2).sqrt()
+}
+
+pub fn manhattan(point_1 : &[f32], point_2 : &[f32]) -> f32 {
+    lane_sum(point_1, point_2, |x, y| (x - y).abs())
+}
+
+pub fn chebyshev(point_1 : &[f32], point_2 : &[f32]) -> f32 {
+    assert!(point_1.len() == point_2.len(), "The points have different dimensions {} and {}", point_1.len(), point_2.len());
+    point_1.iter().zip(point_2).fold(0.0_f32, |max, (x, y)| max.max((x - y).abs()))
+}
+
+pub fn minkowski(point_1 : &[f32], point_2 : &[f32], p : f32) -> f32 {
+    lane_sum(point_1, point_2, |x, y| (x - y).abs().powf(p)).powf(1.0 / p)
+}
+
+///cosine distance, two zero vectors are treated as the same direction and a zero vector against anything else as orthogonal.
+pub fn cosine(point_1 : &[f32], point_2 : &[f32]) -> f32 {
+    let product = lane_sum(point_1, point_2, |x, y| x * y);
+    let norm_1 = lane_sum(point_1, point_1, |x, y| x * y).sqrt();
+    let norm_2 = lane_sum(point_2, point_2, |x, y| x * y).sqrt();
+    if norm_1 == 0.0 && norm_2 == 0.0 {
+        return 0.0;
+    }
+    if norm_1 == 0.0 || norm_2 == 0.0 {
+        return 1.0;
+    }
+    1.0 - product / (norm_1 * norm_2)
+}
+
+pub fn hamming(point_1 : &[f32], point_2 : &[f32]) -> f32 {
+    if point_1.is_empty() {
+        return 0.0;
+    }
+    lane_sum(point_1, point_2, |x, y| if x != y { 1.0 } else { 0.0 }) / point_1.len() as f32
+}
+
+pub fn mahalanobis(point_1 : &[f32], point_2 : &[f32], inverse_covariance : &Matrix) -> f32 {
+    assert!(inverse_covariance.shape() == (point_1.len(), point_1.len()), "The inverse covariance matrix is {:?} but the points have {} dimensions", inverse_covariance.shape(), point_1.len());
+    let difference: Vec<f32> = point_1.iter().zip(point_2).map(|(x, y)| x - y).collect();
+    let mut total = 0.0_f32;
+    for (i, row) in inverse_covariance.iter_rows().enumerate() {
+        total += difference[i] * lane_sum(row, &difference, |x, y| x * y);
+    }
+    total.max(0.0).sqrt()
+}
+
+impl Distance for DistanceMetric {
+    fn distance(&self, point_1 : &[f32], point_2 : &[f32]) -> f32 {
+        match self {
+            DistanceMetric::Euclidean => euclidean(point_1, point_2),
+            DistanceMetric::SquaredEuclidean => squared_euclidean(point_1, point_2),
+            DistanceMetric::Manhattan => manhattan(point_1, point_2),
+            DistanceMetric::Chebyshev => chebyshev(point_1, point_2),
+            DistanceMetric::Minkowski(p) => minkowski(point_1, point_2, *p),
+            DistanceMetric::Cosine => cosine(point_1, point_2),
+            DistanceMetric::Hamming => hamming(point_1, point_2),
+            DistanceMetric::Mahalanobis(inverse_covariance) => mahalanobis(point_1, point_2, inverse_covariance),
+        }
+    }
+}
+
+impl DistanceMetric {
+    ///creates a `Mahalanobis` metric from the covariance of the given samples(one sample per row).
+    pub fn mahalanobis(samples : &Matrix) -> Result<DistanceMetric, LinalgError> {
+        Ok(DistanceMetric::Mahalanobis(inverse(&covariance(samples))?))
+    }
+
+    ///checks the parameters of the metric, `Minkowski` needs p >= 1 to be a metric.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            DistanceMetric::Minkowski(p) if p.is_nan() || *p < 1.0 => Err(format!("Minkowski distance needs p >= 1, got {}", p)),
+            DistanceMetric::Mahalanobis(inverse_covariance) if inverse_covariance.rows() != inverse_covariance.cols() || !inverse_covariance.is_consistent() =>
+                Err("Mahalanobis distance needs a square inverse covariance matrix".to_owned()),
+            _ => Ok(()),
+        }
+    }
+}
+
+///sample covariance matrix of the columns(features), one sample per row.
+pub fn covariance(samples : &Matrix) -> Matrix {
+    let (rows, cols) = samples.shape();
+    assert!(rows > 1, "Need atleast two samples to calculate a covariance");
+    let mut means = vec![0.0_f32; cols];
+    for row in samples.iter_rows() {
+        for (mean, value) in means.iter_mut().zip(row) {
+            *mean += value / rows as f32;
+        }
+    }
+    let mut out = Matrix::new(cols, cols);
+    for row in samples.iter_rows() {
+        for i in 0..cols {
+            let centered_i = row[i] - means[i];
+            for j in i..cols {
+                out[(i, j)] += centered_i * (row[j] - means[j]);
+            }
+        }
+    }
+    for i in 0..cols {
+        for j in i..cols {
+            out[(i, j)] /= (rows - 1) as f32;
+            out[(j, i)] = out[(i, j)];
+        }
+    }
+    out
+}
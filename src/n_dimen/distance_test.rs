@@ -0,0 +1,69 @@
+#![cfg(test)]
+
+use crate::n_dimen::{matrix::Matrix, distance::*};
+
+fn close(x : f32, y : f32) -> bool {
+    (x - y).abs() < 1e-5
+}
+
+#[test]
+fn known_distances() {
+    let a = [0.0, 3.0, 1.0];
+    let b = [4.0, 0.0, 1.0];
+    assert!(close(DistanceMetric::Euclidean.distance(&a, &b), 5.0));
+    assert!(close(DistanceMetric::SquaredEuclidean.distance(&a, &b), 25.0));
+    assert!(close(DistanceMetric::Manhattan.distance(&a, &b), 7.0));
+    assert!(close(DistanceMetric::Chebyshev.distance(&a, &b), 4.0));
+    assert!(close(DistanceMetric::Minkowski(2.0).distance(&a, &b), 5.0));
+    assert!(close(DistanceMetric::Minkowski(1.0).distance(&a, &b), 7.0));
+    assert!(close(DistanceMetric::Hamming.distance(&a, &b), 2.0 / 3.0));
+    assert!(close(DistanceMetric::Cosine.distance(&[1.0, 0.0], &[0.0, 2.0]), 1.0));
+    assert!(close(DistanceMetric::Cosine.distance(&[1.0, 1.0], &[3.0, 3.0]), 0.0));
+}
+
+#[test]
+fn long_vectors_use_the_remainder_too() {
+    //19 = two full chunks of 8 and a remainder of 3.
+    let a: Vec<f32> = (0..19).map(|x| x as f32).collect();
+    let b = vec![0.0_f32; 19];
+    let expected: f32 = a.iter().map(|x| x * x).sum();
+    assert!(close(squared_euclidean(&a, &b), expected));
+    assert!(close(manhattan(&a, &b), 171.0));
+}
+
+#[test]
+fn mahalanobis_with_identity_is_euclidean() {
+    let metric = DistanceMetric::Mahalanobis(Matrix::identity(2));
+    assert!(close(metric.distance(&[0.0, 0.0], &[3.0, 4.0]), 5.0));
+
+    //the spread along the first feature is much bigger, so moving along it counts for less.
+    let samples = Matrix::from(vec![vec![-10.0, -1.0], vec![10.0, 1.0], vec![-10.0, 1.0], vec![10.0, -1.0]]);
+    let metric = DistanceMetric::mahalanobis(&samples).unwrap();
+    assert!(metric.distance(&[0.0, 0.0], &[5.0, 0.0]) < metric.distance(&[0.0, 0.0], &[0.0, 1.0]));
+}
+
+#[test]
+fn covariance_known_result() {
+    let samples = Matrix::from(vec![vec![1.0, 2.0], vec![3.0, 6.0], vec![5.0, 10.0]]);
+    assert_eq!(covariance(&samples), Matrix::from(vec![vec![4.0, 8.0], vec![8.0, 16.0]]));
+}
+
+#[test]
+fn invalid_parameters() {
+    assert!(DistanceMetric::Minkowski(0.5).validate().is_err());
+    assert!(DistanceMetric::Minkowski(3.0).validate().is_ok());
+}
+
+struct Discrete;
+
+impl Distance for Discrete {
+    fn distance(&self, point_1 : &[f32], point_2 : &[f32]) -> f32 {
+        if point_1 == point_2 { 0.0 } else { 1.0 }
+    }
+}
+
+#[test]
+fn custom_metrics_implement_the_trait() {
+    let metrics: Vec<Box<dyn Distance>> = vec![Box::new(Discrete), Box::new(DistanceMetric::Euclidean)];
+    assert_eq!(metrics[0].distance(&[1.0], &[2.0]), 1.0);
+}
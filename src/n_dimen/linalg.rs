@@ -0,0 +1,546 @@
+//! #Linear algebra#
+//!
+//! Dense linear algebra kernels on top of `Matrix`, all in pure rust.
+//! * BLAS like kernels : `dot`, `axpy`, `matvec`, `transpose` and `matmul`(cache-blocked and parallel over row blocks with rayon).
+//! * Decompositions : Cholesky, Householder QR, symmetric eigendecomposition(cyclic Jacobi) and SVD(one-sided Jacobi).
+//! * Solvers : `solve`, `lstsq`, `inverse` and `pinv`.
+//!
+//! The matrices hold f32's but the decompositions work in f64 internally, f32 alone loses too much precision on badly scaled data.
+
+use std::fmt;
+use rayon::prelude::*;
+use super::matrix::Matrix;
+
+/// Size of the square tiles used by the blocked kernels.
+const BLOCK : usize = 64;
+/// Maximum number of sweeps for the Jacobi based methods.
+const MAX_SWEEPS : usize = 100;
+
+/// Numerical failures, shape mismatches are programming errors and panic instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinalgError {
+    ///Cholesky needs a symmetric positive definite matrix.
+    NotPositiveDefinite,
+    ///the matrix is singular(or rank deficient for least squares) to working precision.
+    Singular,
+    ///the iterative method did not converge in the allowed number of sweeps.
+    NoConvergence,
+}
+
+impl fmt::Display for LinalgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinalgError::NotPositiveDefinite => write!(f, "the matrix is not positive definite"),
+            LinalgError::Singular => write!(f, "the matrix is singular"),
+            LinalgError::NoConvergence => write!(f, "the decomposition did not converge"),
+        }
+    }
+}
+
+impl std::error::Error for LinalgError {}
+
+
+//***************************************
+//BASIC KERNELS
+//***************************************
+
+///sum of the products of the respective elements.
+pub fn dot(x : &[f32], y : &[f32]) -> f32 {
+    assert!(x.len() == y.len(), "dot product of vectors with different lengths {} and {}", x.len(), y.len());
+    //four independent accumulators so the compiler can vectorise the loop.
+    let mut sums = [0.0_f32; 4];
+    let chunks = x.len() / 4;
+    for i in 0..chunks {
+        for lane in 0..4 {
+            sums[lane] += x[4*i + lane] * y[4*i + lane];
+        }
+    }
+    let mut total = sums[0] + sums[1] + sums[2] + sums[3];
+    for i in 4*chunks..x.len() {
+        total += x[i] * y[i];
+    }
+    total
+}
+
+///y = alpha * x + y
+pub fn axpy(alpha : f32, x : &[f32], y : &mut [f32]) {
+    assert!(x.len() == y.len(), "axpy on vectors with different lengths {} and {}", x.len(), y.len());
+    for (y_value, x_value) in y.iter_mut().zip(x.iter()) {
+        *y_value += alpha * x_value;
+    }
+}
+
+///matrix times a vector.
+pub fn matvec(a : &Matrix, x : &[f32]) -> Vec<f32> {
+    assert!(a.cols() == x.len(), "cannot multiply a {:?} matrix with a vector of length {}", a.shape(), x.len());
+    a.iter_rows().take(a.rows()).map(|row| dot(row, x)).collect()
+}
+
+///returns the transpose, copied tile by tile so both the reads and the writes stay in cache.
+pub fn transpose(a : &Matrix) -> Matrix {
+    let (rows, cols) = a.shape();
+    let mut out = Matrix::new(cols, rows);
+    for row_start in (0..rows).step_by(BLOCK) {
+        for col_start in (0..cols).step_by(BLOCK) {
+            for i in row_start..(row_start + BLOCK).min(rows) {
+                for j in col_start..(col_start + BLOCK).min(cols) {
+                    out[(j, i)] = a[(i, j)];
+                }
+            }
+        }
+    }
+    out
+}
+
+///matrix product `a * b`.
+///The output is split into blocks of rows which are computed in parallel, inside a block the
+///inner dimension and the output columns are tiled so the rows of `b` that are being used stay in cache.
+pub fn matmul(a : &Matrix, b : &Matrix) -> Matrix {
+    assert!(a.cols() == b.rows(), "cannot multiply a {:?} matrix with a {:?} matrix", a.shape(), b.shape());
+    let (inner, width) = b.shape();
+    let mut out = Matrix::new(a.rows(), width);
+    if out.is_empty() || inner == 0 {
+        return out;
+    }
+
+    out.as_mut_slice().par_chunks_mut(BLOCK * width).enumerate().for_each(|(block_index, out_block)| {
+        let row_start = block_index * BLOCK;
+        for k_start in (0..inner).step_by(BLOCK) {
+            let k_end = (k_start + BLOCK).min(inner);
+            for j_start in (0..width).step_by(BLOCK) {
+                let j_end = (j_start + BLOCK).min(width);
+                for (local_row, out_row) in out_block.chunks_exact_mut(width).enumerate() {
+                    let a_row = a.row(row_start + local_row);
+                    for k in k_start..k_end {
+                        let a_value = a_row[k];
+                        if a_value == 0.0 {
+                            continue;
+                        }
+                        axpy(a_value, &b.row(k)[j_start..j_end], &mut out_row[j_start..j_end]);
+                    }
+                }
+            }
+        }
+    });
+
+    out
+}
+
+///`a^T * a`, used by the normal equations and covariance computations.
+pub fn gram(a : &Matrix) -> Matrix {
+    matmul(&transpose(a), a)
+}
+
+impl Matrix {
+    ///see `linalg::transpose`.
+    pub fn transpose(&self) -> Matrix {
+        transpose(self)
+    }
+
+    ///see `linalg::matmul`.
+    pub fn matmul(&self, other : &Matrix) -> Matrix {
+        matmul(self, other)
+    }
+}
+
+
+//***************************************
+//f64 WORKING COPIES
+//***************************************
+
+fn to_f64(a : &Matrix) -> Vec<Vec<f64>> {
+    a.iter_rows().take(a.rows()).map(|row| row.iter().map(|x| *x as f64).collect()).collect()
+}
+
+fn from_f64(rows : &Vec<Vec<f64>>, cols : usize) -> Matrix {
+    let mut out = Matrix::new(rows.len(), cols);
+    for (i, row) in rows.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            out[(i, j)] = *value as f32;
+        }
+    }
+    out
+}
+
+fn assert_square(a : &Matrix, name : &str) {
+    assert!(a.rows() == a.cols(), "{} needs a square matrix, got {:?}", name, a.shape());
+}
+
+
+//***************************************
+//DECOMPOSITIONS
+//***************************************
+
+///Cholesky decomposition of a symmetric positive definite matrix, returns the lower triangular `L` with `a = L * L^T`.
+pub fn cholesky(a : &Matrix) -> Result<Matrix, LinalgError> {
+    assert_square(a, "cholesky");
+    let n = a.rows();
+    let a = to_f64(a);
+    let mut l = vec![vec![0.0_f64; n]; n];
+
+    for j in 0..n {
+        let mut diagonal = a[j][j];
+        for k in 0..j {
+            diagonal -= l[j][k] * l[j][k];
+        }
+        if diagonal <= 0.0 || !diagonal.is_finite() {
+            return Err(LinalgError::NotPositiveDefinite);
+        }
+        l[j][j] = diagonal.sqrt();
+        for i in (j+1)..n {
+            let mut sum = a[i][j];
+            for k in 0..j {
+                sum -= l[i][k] * l[j][k];
+            }
+            l[i][j] = sum / l[j][j];
+        }
+    }
+
+    Ok(from_f64(&l, n))
+}
+
+///solves `a x = b` given the Cholesky factor `l` of `a`.
+pub fn cholesky_solve(l : &Matrix, b : &[f32]) -> Vec<f32> {
+    assert_square(l, "cholesky_solve");
+    let n = l.rows();
+    assert!(b.len() == n, "right hand side has length {}, expected {}", b.len(), n);
+    //forward substitution L y = b, then back substitution L^T x = y.
+    let mut y = vec![0.0_f64; n];
+    for i in 0..n {
+        let mut sum = b[i] as f64;
+        for k in 0..i {
+            sum -= l[(i, k)] as f64 * y[k];
+        }
+        y[i] = sum / l[(i, i)] as f64;
+    }
+    let mut x = vec![0.0_f64; n];
+    for i in (0..n).rev() {
+        let mut sum = y[i];
+        for k in (i+1)..n {
+            sum -= l[(k, i)] as f64 * x[k];
+        }
+        x[i] = sum / l[(i, i)] as f64;
+    }
+    x.iter().map(|value| *value as f32).collect()
+}
+
+///Householder reflections of a `m x n` (m >= n) matrix in f64, the reflected matrix is left in `r`.
+fn householder(a : &Matrix) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let (m, n) = a.shape();
+    let mut r = to_f64(a);
+    let mut reflectors = Vec::with_capacity(n);
+
+    for k in 0..n {
+        let norm: f64 = (k..m).map(|i| r[i][k] * r[i][k]).sum::<f64>().sqrt();
+        let mut v = vec![0.0_f64; m - k];
+        if norm == 0.0 {
+            reflectors.push(v);
+            continue;
+        }
+        let alpha = if r[k][k] > 0.0 { -norm } else { norm };
+        for i in k..m {
+            v[i - k] = r[i][k];
+        }
+        v[0] -= alpha;
+        let v_norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+        v.iter_mut().for_each(|x| *x /= v_norm);
+
+        for j in k..n {
+            let projection: f64 = (k..m).map(|i| v[i - k] * r[i][j]).sum();
+            for i in k..m {
+                r[i][j] -= 2.0 * v[i - k] * projection;
+            }
+        }
+        reflectors.push(v);
+    }
+
+    (r, reflectors)
+}
+
+///Thin QR decomposition of a `m x n` matrix with `m >= n` using Householder reflections.
+///Returns `Q`(m x n, orthonormal columns) and `R`(n x n, upper triangular) with `a = Q * R`.
+pub fn qr(a : &Matrix) -> (Matrix, Matrix) {
+    let (m, n) = a.shape();
+    assert!(m >= n, "qr needs atleast as many rows as columns, got {:?}", a.shape());
+    let (r, reflectors) = householder(a);
+
+    //Q is built by applying the reflections in reverse to the first n columns of the identity.
+    let mut q = vec![vec![0.0_f64; n]; m];
+    for i in 0..n {
+        q[i][i] = 1.0;
+    }
+    for k in (0..n).rev() {
+        let v = &reflectors[k];
+        for j in 0..n {
+            let projection: f64 = (k..m).map(|i| v[i - k] * q[i][j]).sum();
+            for i in k..m {
+                q[i][j] -= 2.0 * v[i - k] * projection;
+            }
+        }
+    }
+
+    let mut r_square = vec![vec![0.0_f64; n]; n];
+    for i in 0..n {
+        for j in i..n {
+            r_square[i][j] = r[i][j];
+        }
+    }
+
+    (from_f64(&q, n), from_f64(&r_square, n))
+}
+
+///Eigendecomposition of a symmetric matrix using cyclic Jacobi rotations.
+///Returns the eigenvalues in decreasing order and a matrix whose columns are the respective unit eigenvectors.
+pub fn symmetric_eigen(a : &Matrix) -> Result<(Vec<f32>, Matrix), LinalgError> {
+    assert_square(a, "symmetric_eigen");
+    let n = a.rows();
+    let mut a = to_f64(a);
+    let mut v = vec![vec![0.0_f64; n]; n];
+    for i in 0..n {
+        v[i][i] = 1.0;
+    }
+
+    let scale: f64 = a.iter().flatten().map(|x| x * x).sum::<f64>().sqrt().max(f64::MIN_POSITIVE);
+    let mut converged = false;
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal: f64 = (0..n).flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j))).map(|(i, j)| a[i][j] * a[i][j]).sum::<f64>().sqrt();
+        if off_diagonal <= 1e-12 * scale {
+            converged = true;
+            break;
+        }
+        for p in 0..n {
+            for q in (p+1)..n {
+                if a[p][q].abs() <= f64::MIN_POSITIVE {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[k][p], a[k][q]);
+                    a[k][p] = c * akp - s * akq;
+                    a[k][q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p][k], a[q][k]);
+                    a[p][k] = c * apk - s * aqk;
+                    a[q][k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k][p], v[k][q]);
+                    v[k][p] = c * vkp - s * vkq;
+                    v[k][q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    if !converged {
+        return Err(LinalgError::NoConvergence);
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|x, y| a[*y][*y].partial_cmp(&a[*x][*x]).unwrap_or(std::cmp::Ordering::Equal));
+    let eigenvalues = order.iter().map(|i| a[*i][*i] as f32).collect();
+    let mut eigenvectors = Matrix::new(n, n);
+    for (column, &i) in order.iter().enumerate() {
+        for k in 0..n {
+            eigenvectors[(k, column)] = v[k][i] as f32;
+        }
+    }
+
+    Ok((eigenvalues, eigenvectors))
+}
+
+///Singular value decomposition using one-sided Jacobi rotations.
+///For a `m x n` matrix with `k = min(m, n)` returns `U`(m x k), the singular values in decreasing order(length k) and `V^T`(k x n),
+///so that `a = U * diag(s) * V^T`.
+pub fn svd(a : &Matrix) -> Result<(Matrix, Vec<f32>, Matrix), LinalgError> {
+    let (m, n) = a.shape();
+    if m < n {
+        //a^T = V S U^T, so the factors of the transpose just swap places.
+        let (u, s, vt) = svd(&transpose(a))?;
+        return Ok((transpose(&vt), s, transpose(&u)));
+    }
+
+    //working on columns, so they are stored as rows here.
+    let mut u: Vec<Vec<f64>> = (0..n).map(|j| (0..m).map(|i| a[(i, j)] as f64).collect()).collect();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|j| (0..n).map(|i| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+
+    let mut converged = false;
+    for _ in 0..MAX_SWEEPS {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in (p+1)..n {
+                let alpha: f64 = u[p].iter().map(|x| x * x).sum();
+                let beta: f64 = u[q].iter().map(|x| x * x).sum();
+                let gamma: f64 = u[p].iter().zip(u[q].iter()).map(|(x, y)| x * y).sum();
+                if gamma.abs() <= 1e-15 * (alpha * beta).sqrt() || gamma == 0.0 {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                for k in 0..m {
+                    let (up, uq) = (u[p][k], u[q][k]);
+                    u[p][k] = c * up - s * uq;
+                    u[q][k] = s * up + c * uq;
+                }
+                for k in 0..n {
+                    let (vp, vq) = (v[p][k], v[q][k]);
+                    v[p][k] = c * vp - s * vq;
+                    v[q][k] = s * vp + c * vq;
+                }
+            }
+        }
+        if !rotated {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        return Err(LinalgError::NoConvergence);
+    }
+
+    let norms: Vec<f64> = u.iter().map(|column| column.iter().map(|x| x * x).sum::<f64>().sqrt()).collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|x, y| norms[*y].partial_cmp(&norms[*x]).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut u_out = Matrix::new(m, n);
+    let mut vt_out = Matrix::new(n, n);
+    let mut singular_values = Vec::with_capacity(n);
+    for (column, &j) in order.iter().enumerate() {
+        singular_values.push(norms[j] as f32);
+        for k in 0..m {
+            //columns for zero singular values are left as zeros.
+            u_out[(k, column)] = if norms[j] > f64::MIN_POSITIVE { (u[j][k] / norms[j]) as f32 } else { 0.0 };
+        }
+        for k in 0..n {
+            vt_out[(column, k)] = v[j][k] as f32;
+        }
+    }
+
+    Ok((u_out, singular_values, vt_out))
+}
+
+
+//***************************************
+//SOLVERS
+//***************************************
+
+///LU factorisation with partial pivoting, in place, returns the row permutation.
+fn lu_in_place(a : &mut Vec<Vec<f64>>) -> Result<Vec<usize>, LinalgError> {
+    let n = a.len();
+    let scale = a.iter().flatten().fold(0.0_f64, |max, x| max.max(x.abs()));
+    let mut permutation: Vec<usize> = (0..n).collect();
+    for k in 0..n {
+        let pivot = (k..n).max_by(|x, y| a[*x][k].abs().partial_cmp(&a[*y][k].abs()).unwrap()).unwrap();
+        if a[pivot][k].abs() <= 1e-12 * scale || scale == 0.0 {
+            return Err(LinalgError::Singular);
+        }
+        a.swap(k, pivot);
+        permutation.swap(k, pivot);
+        for i in (k+1)..n {
+            let factor = a[i][k] / a[k][k];
+            a[i][k] = factor;
+            for j in (k+1)..n {
+                a[i][j] -= factor * a[k][j];
+            }
+        }
+    }
+    Ok(permutation)
+}
+
+fn lu_solve(lu : &Vec<Vec<f64>>, permutation : &Vec<usize>, b : &[f64]) -> Vec<f64> {
+    let n = lu.len();
+    let mut x: Vec<f64> = permutation.iter().map(|i| b[*i]).collect();
+    for i in 0..n {
+        for k in 0..i {
+            x[i] -= lu[i][k] * x[k];
+        }
+    }
+    for i in (0..n).rev() {
+        for k in (i+1)..n {
+            x[i] -= lu[i][k] * x[k];
+        }
+        x[i] /= lu[i][i];
+    }
+    x
+}
+
+///solves the square system `a x = b`.
+pub fn solve(a : &Matrix, b : &[f32]) -> Result<Vec<f32>, LinalgError> {
+    assert_square(a, "solve");
+    assert!(b.len() == a.rows(), "right hand side has length {}, expected {}", b.len(), a.rows());
+    let mut lu = to_f64(a);
+    let permutation = lu_in_place(&mut lu)?;
+    let b: Vec<f64> = b.iter().map(|x| *x as f64).collect();
+    Ok(lu_solve(&lu, &permutation, &b).iter().map(|x| *x as f32).collect())
+}
+
+///inverse of a square matrix.
+pub fn inverse(a : &Matrix) -> Result<Matrix, LinalgError> {
+    assert_square(a, "inverse");
+    let n = a.rows();
+    let mut lu = to_f64(a);
+    let permutation = lu_in_place(&mut lu)?;
+    let mut out = Matrix::new(n, n);
+    let mut unit = vec![0.0_f64; n];
+    for j in 0..n {
+        unit.iter_mut().for_each(|x| *x = 0.0);
+        unit[j] = 1.0;
+        let column = lu_solve(&lu, &permutation, &unit);
+        for i in 0..n {
+            out[(i, j)] = column[i] as f32;
+        }
+    }
+    Ok(out)
+}
+
+///least squares solution of `a x ≈ b` for a `m x n`(m >= n) matrix with full column rank, through Householder QR.
+///For rank deficient problems use `pinv`.
+pub fn lstsq(a : &Matrix, b : &[f32]) -> Result<Vec<f32>, LinalgError> {
+    let (m, n) = a.shape();
+    assert!(m >= n, "lstsq needs atleast as many rows as columns, got {:?}", a.shape());
+    assert!(b.len() == m, "right hand side has length {}, expected {}", b.len(), m);
+    let (r, reflectors) = householder(a);
+
+    //applying the same reflections to b gives Q^T b.
+    let mut qtb: Vec<f64> = b.iter().map(|x| *x as f64).collect();
+    for (k, v) in reflectors.iter().enumerate() {
+        let projection: f64 = (k..m).map(|i| v[i - k] * qtb[i]).sum();
+        for i in k..m {
+            qtb[i] -= 2.0 * v[i - k] * projection;
+        }
+    }
+
+    let largest = (0..n).fold(0.0_f64, |max, i| max.max(r[i][i].abs()));
+    let mut x = vec![0.0_f64; n];
+    for i in (0..n).rev() {
+        if r[i][i].abs() <= 1e-10 * largest || largest == 0.0 {
+            return Err(LinalgError::Singular);
+        }
+        let mut sum = qtb[i];
+        for k in (i+1)..n {
+            sum -= r[i][k] * x[k];
+        }
+        x[i] = sum / r[i][i];
+    }
+    Ok(x.iter().map(|value| *value as f32).collect())
+}
+
+///Moore-Penrose pseudo inverse through the SVD, singular values below `rcond * largest` are treated as zero.
+pub fn pinv(a : &Matrix, rcond : f32) -> Result<Matrix, LinalgError> {
+    let (u, s, vt) = svd(a)?;
+    let cutoff = rcond * s.first().cloned().unwrap_or(0.0);
+    //pinv = V * diag(1/s) * U^T
+    let mut v_scaled = transpose(&vt);
+    for row in v_scaled.iter_rows_mut() {
+        for (value, singular) in row.iter_mut().zip(s.iter()) {
+            *value = if *singular > cutoff { *value / singular } else { 0.0 };
+        }
+    }
+    Ok(matmul(&v_scaled, &transpose(&u)))
+}
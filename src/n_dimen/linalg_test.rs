@@ -0,0 +1,130 @@
+#![cfg(test)]
+
+use crate::n_dimen::{matrix::Matrix, linalg::*};
+
+fn assert_close(a : &Matrix, b : &Matrix, tolerance : f32) {
+    assert_eq!(a.shape(), b.shape());
+    for (x, y) in a.as_slice().iter().zip(b.as_slice()) {
+        assert!((x - y).abs() <= tolerance, "{:?}\n!=\n{:?}", a, b);
+    }
+}
+
+fn assert_close_vec(a : &[f32], b : &[f32], tolerance : f32) {
+    assert_eq!(a.len(), b.len());
+    for (x, y) in a.iter().zip(b) {
+        assert!((x - y).abs() <= tolerance, "{:?} != {:?}", a, b);
+    }
+}
+
+fn diagonal(values : &[f32]) -> Matrix {
+    let mut out = Matrix::new(values.len(), values.len());
+    for (i, value) in values.iter().enumerate() {
+        out[(i, i)] = *value;
+    }
+    out
+}
+
+#[test]
+fn dot_and_axpy() {
+    assert_eq!(dot(&[1.0, 2.0, 3.0, 4.0, 5.0], &[5.0, 4.0, 3.0, 2.0, 1.0]), 35.0);
+    let mut y = vec![1.0, 1.0, 1.0];
+    axpy(2.0, &[1.0, 2.0, 3.0], &mut y);
+    assert_eq!(y, vec![3.0, 5.0, 7.0]);
+    assert_eq!(matvec(&Matrix::from(vec![vec![1.0, 2.0], vec![3.0, 4.0]]), &[1.0, 1.0]), vec![3.0, 7.0]);
+}
+
+#[test]
+fn matmul_known_result() {
+    let a = Matrix::from(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+    let b = Matrix::from(vec![vec![7.0, 8.0], vec![9.0, 10.0], vec![11.0, 12.0]]);
+    assert_eq!(matmul(&a, &b), Matrix::from(vec![vec![58.0, 64.0], vec![139.0, 154.0]]));
+    assert_eq!(a.transpose(), Matrix::from(vec![vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]));
+}
+
+#[test]
+fn blocked_matmul_matches_naive() {
+    //bigger than a block in every dimension, and not a multiple of it.
+    let (m, k, n) = (130, 70, 90);
+    let a = Matrix::from_vec(m, k, (0..m*k).map(|x| ((x * 7) % 13) as f32 - 6.0).collect());
+    let b = Matrix::from_vec(k, n, (0..k*n).map(|x| ((x * 5) % 11) as f32 - 5.0).collect());
+    let mut naive = Matrix::new(m, n);
+    for i in 0..m {
+        for j in 0..n {
+            naive[(i, j)] = (0..k).map(|t| a[(i, t)] * b[(t, j)]).sum();
+        }
+    }
+    assert_eq!(matmul(&a, &b), naive);
+    assert_eq!(transpose(&transpose(&a)), a);
+}
+
+#[test]
+fn cholesky_known_result() {
+    let a = Matrix::from(vec![vec![4.0, 12.0, -16.0], vec![12.0, 37.0, -43.0], vec![-16.0, -43.0, 98.0]]);
+    let l = cholesky(&a).unwrap();
+    assert_close(&l, &Matrix::from(vec![vec![2.0, 0.0, 0.0], vec![6.0, 1.0, 0.0], vec![-8.0, 5.0, 3.0]]), 1e-5);
+    let x = cholesky_solve(&l, &[1.0, 2.0, 3.0]);
+    assert_close_vec(&matvec(&a, &x), &[1.0, 2.0, 3.0], 1e-3);
+
+    assert_eq!(cholesky(&Matrix::from(vec![vec![1.0, 2.0], vec![2.0, 1.0]])), Err(LinalgError::NotPositiveDefinite));
+}
+
+#[test]
+fn qr_reconstructs() {
+    let a = Matrix::from(vec![vec![12.0, -51.0, 4.0], vec![6.0, 167.0, -68.0], vec![-4.0, 24.0, -41.0], vec![1.0, 1.0, 1.0]]);
+    let (q, r) = qr(&a);
+    assert_eq!(q.shape(), (4, 3));
+    assert_close(&matmul(&q, &r), &a, 1e-3);
+    assert_close(&matmul(&q.transpose(), &q), &Matrix::identity(3), 1e-5);
+    for i in 0..3 {
+        for j in 0..i {
+            assert_eq!(r[(i, j)], 0.0);
+        }
+    }
+}
+
+#[test]
+fn symmetric_eigen_known_result() {
+    let a = Matrix::from(vec![vec![2.0, 1.0], vec![1.0, 2.0]]);
+    let (values, vectors) = symmetric_eigen(&a).unwrap();
+    assert_close_vec(&values, &[3.0, 1.0], 1e-5);
+    //A v = lambda v for every column.
+    for (column, value) in values.iter().enumerate() {
+        let v = vectors.column(column);
+        let av = matvec(&a, &v);
+        assert_close_vec(&av, &v.iter().map(|x| x * value).collect::<Vec<f32>>(), 1e-5);
+    }
+
+    let b = Matrix::from(vec![vec![4.0, 1.0, 2.0], vec![1.0, 3.0, 0.5], vec![2.0, 0.5, 5.0]]);
+    let (values, vectors) = symmetric_eigen(&b).unwrap();
+    let rebuilt = matmul(&matmul(&vectors, &diagonal(&values)), &vectors.transpose());
+    assert_close(&rebuilt, &b, 1e-4);
+}
+
+#[test]
+fn svd_reconstructs_tall_and_wide() {
+    let tall = Matrix::from(vec![vec![3.0, 2.0], vec![2.0, 3.0], vec![2.0, -2.0]]);
+    for a in [tall.clone(), tall.transpose()] {
+        let (u, s, vt) = svd(&a).unwrap();
+        assert_close_vec(&s, &[5.0, 3.0], 1e-4);
+        assert_close(&matmul(&matmul(&u, &diagonal(&s)), &vt), &a, 1e-4);
+    }
+}
+
+#[test]
+fn solvers() {
+    let a = Matrix::from(vec![vec![2.0, 1.0, 1.0], vec![1.0, 3.0, 2.0], vec![1.0, 0.0, 0.0]]);
+    let x = solve(&a, &[4.0, 5.0, 6.0]).unwrap();
+    assert_close_vec(&x, &[6.0, 15.0, -23.0], 1e-3);
+
+    let inv = inverse(&a).unwrap();
+    assert_close(&matmul(&a, &inv), &Matrix::identity(3), 1e-5);
+    assert_eq!(inverse(&Matrix::from(vec![vec![1.0, 2.0], vec![2.0, 4.0]])), Err(LinalgError::Singular));
+
+    //fitting y = 1 + 2x through points that lie exactly on the line.
+    let design = Matrix::from(vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![1.0, 2.0], vec![1.0, 3.0]]);
+    assert_close_vec(&lstsq(&design, &[1.0, 3.0, 5.0, 7.0]).unwrap(), &[1.0, 2.0], 1e-4);
+    assert_close(&pinv(&design, 1e-6).unwrap().matmul(&design), &Matrix::identity(2), 1e-4);
+
+    let rank_deficient = Matrix::from(vec![vec![1.0, 2.0], vec![2.0, 4.0], vec![3.0, 6.0]]);
+    assert_eq!(lstsq(&rank_deficient, &[1.0, 2.0, 3.0]), Err(LinalgError::Singular));
+}
@@ -0,0 +1,342 @@
+//! #Matrix#
+//!
+//! A dense, contiguous, row-major matrix of f32's.
+//! All the rows live in one allocation, so iterating over the samples of a training set is cache friendly and
+//! a big data set is one allocation instead of one per row like in a `Vec<Vec<f32>>`.
+//!
+//! * `Matrix` owns its data.
+//! * `MatrixView` borrows a rectangular block of a `Matrix` without copying it.
+//!
+//! Indexing with a single `usize` gives the row as a slice, so `matrix[i][j]` works the same way it did on a `Vec<Vec<f32>>`.
+
+use std::ops::{Index, IndexMut, Range};
+use std::slice::{ChunksExact, ChunksExactMut};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::data_frame::{data_frame::DataFrame, data_type::DataType};
+
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Matrix {
+    data: Vec<f32>,
+    rows: usize,
+    cols: usize,
+}
+
+impl Matrix {
+
+    ///creates a `rows x cols` matrix filled with zeros.
+    pub fn new(rows : usize, cols : usize) -> Matrix {
+        Matrix::filled(rows, cols, 0.0)
+    }
+
+    ///creates a `rows x cols` matrix with every element set to `value`.
+    pub fn filled(rows : usize, cols : usize, value : f32) -> Matrix {
+        Matrix { data: vec![value; rows*cols], rows, cols }
+    }
+
+    ///creates the identity matrix of the given size.
+    pub fn identity(size : usize) -> Matrix {
+        let mut out = Matrix::new(size, size);
+        for i in 0..size {
+            out.data[i*size + i] = 1.0;
+        }
+        out
+    }
+
+    ///wraps a flat row-major vector, the length has to be `rows * cols`.
+    pub fn from_vec(rows : usize, cols : usize, data : Vec<f32>) -> Matrix {
+        assert!(data.len() == rows*cols, "A {}x{} matrix needs {} values, but {} were given", rows, cols, rows*cols, data.len());
+        Matrix { data, rows, cols }
+    }
+
+    ///copies the rows into one contiguous matrix, all the rows need to have the same length.
+    pub fn from_rows(rows : &[Vec<f32>]) -> Matrix {
+        let cols = rows.first().map_or(0, |row| row.len());
+        let mut data = Vec::with_capacity(rows.len()*cols);
+        for (index, row) in rows.iter().enumerate() {
+            assert!(row.len() == cols, "Row {} has {} values, but the first row has {}", index, row.len(), cols);
+            data.extend_from_slice(row);
+        }
+        Matrix { data, rows: rows.len(), cols }
+    }
+
+    ///a matrix with a single column.
+    pub fn column_vector(values : &[f32]) -> Matrix {
+        Matrix { data: values.to_vec(), rows: values.len(), cols: 1 }
+    }
+
+    ///copies the matrix back into a vector of rows.
+    pub fn to_rows(&self) -> Vec<Vec<f32>> {
+        self.iter_rows().map(|row| row.to_vec()).collect()
+    }
+
+    ///(rows, columns)
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    ///number of rows(samples).
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    ///number of columns(features).
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    ///how many elements to step over to move one row down and one column right.
+    pub fn strides(&self) -> (usize, usize) {
+        (self.cols, 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0 || self.cols == 0
+    }
+
+    ///checks that the flat buffer really holds `rows * cols` values(a deserialized matrix may not).
+    pub fn is_consistent(&self) -> bool {
+        self.data.len() == self.rows*self.cols
+    }
+
+    pub fn get(&self, row : usize, col : usize) -> f32 {
+        self.data[row*self.cols + col]
+    }
+
+    pub fn set(&mut self, row : usize, col : usize, value : f32) {
+        self.data[row*self.cols + col] = value;
+    }
+
+    pub fn row(&self, index : usize) -> &[f32] {
+        &self.data[index*self.cols..(index+1)*self.cols]
+    }
+
+    pub fn row_mut(&mut self, index : usize) -> &mut [f32] {
+        &mut self.data[index*self.cols..(index+1)*self.cols]
+    }
+
+    ///copies out a column.
+    pub fn column(&self, index : usize) -> Vec<f32> {
+        assert!(index < self.cols, "Column index {} is out of bounds for a matrix with {} columns", index, self.cols);
+        self.data.iter().skip(index).step_by(self.cols).cloned().collect()
+    }
+
+    ///iterator over the rows as slices.
+    pub fn iter_rows(&self) -> ChunksExact<'_, f32> {
+        self.data.chunks_exact(self.cols.max(1))
+    }
+
+    ///iterator over the rows as mutable slices.
+    pub fn iter_rows_mut(&mut self) -> ChunksExactMut<'_, f32> {
+        self.data.chunks_exact_mut(self.cols.max(1))
+    }
+
+    ///parallel(rayon) iterator over the rows.
+    pub fn par_iter_rows(&self) -> rayon::slice::ChunksExact<'_, f32> {
+        self.data.par_chunks_exact(self.cols.max(1))
+    }
+
+    ///parallel(rayon) iterator over the mutable rows.
+    pub fn par_iter_rows_mut(&mut self) -> rayon::slice::ChunksExactMut<'_, f32> {
+        self.data.par_chunks_exact_mut(self.cols.max(1))
+    }
+
+    ///the underlying row-major buffer.
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [f32] {
+        &mut self.data
+    }
+
+    pub fn into_vec(self) -> Vec<f32> {
+        self.data
+    }
+
+    ///a view over the whole matrix.
+    pub fn view(&self) -> MatrixView<'_> {
+        MatrixView { data: &self.data, rows: self.rows, cols: self.cols, row_stride: self.cols }
+    }
+
+    ///a view over a block of the matrix, no data is copied.
+    pub fn slice(&self, rows : Range<usize>, cols : Range<usize>) -> MatrixView<'_> {
+        self.view().slice(rows, cols)
+    }
+
+    ///copies the given rows(in the given order, repeats are allowed) into a new matrix, used for shuffling and bootstrapping.
+    pub fn select_rows(&self, indices : &[usize]) -> Matrix {
+        let mut data = Vec::with_capacity(indices.len()*self.cols);
+        for &index in indices {
+            data.extend_from_slice(self.row(index));
+        }
+        Matrix { data, rows: indices.len(), cols: self.cols }
+    }
+
+    ///copies the given columns(in the given order) into a new matrix.
+    pub fn select_columns(&self, indices : &[usize]) -> Matrix {
+        let mut data = Vec::with_capacity(self.rows*indices.len());
+        for row in self.iter_rows() {
+            for &index in indices {
+                data.push(row[index]);
+            }
+        }
+        Matrix { data, rows: self.rows, cols: indices.len() }
+    }
+
+    ///appends a row at the bottom.
+    pub fn push_row(&mut self, row : &[f32]) {
+        if self.rows == 0 && self.cols == 0 {
+            self.cols = row.len();
+        }
+        assert!(row.len() == self.cols, "The new row has {} values, but the matrix has {} columns", row.len(), self.cols);
+        self.data.extend_from_slice(row);
+        self.rows += 1;
+    }
+}
+
+
+impl Index<usize> for Matrix {
+    type Output = [f32];
+    ///`matrix[i]` gives the i'th row.
+    fn index(&self, index : usize) -> &[f32] {
+        self.row(index)
+    }
+}
+
+impl IndexMut<usize> for Matrix {
+    fn index_mut(&mut self, index : usize) -> &mut [f32] {
+        self.row_mut(index)
+    }
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = f32;
+    fn index(&self, (row, col) : (usize, usize)) -> &f32 {
+        &self.data[row*self.cols + col]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (row, col) : (usize, usize)) -> &mut f32 {
+        &mut self.data[row*self.cols + col]
+    }
+}
+
+impl From<&Vec<Vec<f32>>> for Matrix {
+    fn from(rows : &Vec<Vec<f32>>) -> Matrix {
+        Matrix::from_rows(rows)
+    }
+}
+
+impl From<Vec<Vec<f32>>> for Matrix {
+    fn from(rows : Vec<Vec<f32>>) -> Matrix {
+        Matrix::from_rows(&rows)
+    }
+}
+
+impl From<&DataFrame> for Matrix {
+    ///all the columns of the data frame as features, see `DataFrame::to_matrix` to choose the columns.
+    fn from(data_frame : &DataFrame) -> Matrix {
+        data_frame.to_matrix(&vec![])
+    }
+}
+
+
+///A borrowed rectangular block of a `Matrix`.
+/// The rows of the block are still contiguous, only the distance between two rows(`row_stride`) can be bigger than the width.
+#[derive(Debug, Clone, Copy)]
+pub struct MatrixView<'a> {
+    data: &'a [f32],
+    rows: usize,
+    cols: usize,
+    row_stride: usize,
+}
+
+impl<'a> MatrixView<'a> {
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn strides(&self) -> (usize, usize) {
+        (self.row_stride, 1)
+    }
+
+    pub fn get(&self, row : usize, col : usize) -> f32 {
+        assert!(row < self.rows && col < self.cols, "Index ({}, {}) is out of bounds for a {}x{} view", row, col, self.rows, self.cols);
+        self.data[row*self.row_stride + col]
+    }
+
+    pub fn row(&self, index : usize) -> &'a [f32] {
+        assert!(index < self.rows, "Row {} is out of bounds for a view with {} rows", index, self.rows);
+        &self.data[index*self.row_stride..index*self.row_stride + self.cols]
+    }
+
+    pub fn iter_rows(&self) -> impl Iterator<Item = &'a [f32]> + '_ {
+        (0..self.rows).map(move |index| self.row(index))
+    }
+
+    ///a smaller block of this view, the ranges are relative to the view.
+    pub fn slice(&self, rows : Range<usize>, cols : Range<usize>) -> MatrixView<'a> {
+        assert!(rows.end <= self.rows && cols.end <= self.cols && rows.start <= rows.end && cols.start <= cols.end,
+            "The slice [{:?}, {:?}] does not fit in a {}x{} matrix", rows, cols, self.rows, self.cols);
+        let number_of_rows = rows.end - rows.start;
+        let number_of_cols = cols.end - cols.start;
+        let start = rows.start*self.row_stride + cols.start;
+        //the last row only needs to reach the end of the block, not the full stride.
+        let end = if number_of_rows == 0 { start } else { start + (number_of_rows-1)*self.row_stride + number_of_cols };
+        MatrixView { data: &self.data[start..end], rows: number_of_rows, cols: number_of_cols, row_stride: self.row_stride }
+    }
+
+    ///copies the block into a new owned matrix.
+    pub fn to_matrix(&self) -> Matrix {
+        let mut data = Vec::with_capacity(self.rows*self.cols);
+        for row in self.iter_rows() {
+            data.extend_from_slice(row);
+        }
+        Matrix { data, rows: self.rows, cols: self.cols }
+    }
+}
+
+
+impl DataFrame {
+    ///copies the chosen columns(all of them if `which_columns` is empty) into a `Matrix`, one row per sample.
+    ///Category columns are cast to f32, string columns need to be encoded first.
+    pub fn to_matrix(&self, which_columns : &Vec<usize>) -> Matrix {
+        let columns: Vec<usize> = if which_columns.is_empty() {
+            (0..self.data.len()).collect()
+        } else {
+            which_columns.clone()
+        };
+        let number_of_samples = self.number_of_samples as usize;
+        let mut out = Matrix::new(number_of_samples, columns.len());
+
+        for (enumerated, &column) in columns.iter().enumerate() {
+            match &self.data[column] {
+                DataType::Floats(temp) => {
+                    for (i, value) in temp.iter().enumerate() {
+                        out.data[i*out.cols + enumerated] = *value;
+                    }
+                },
+                DataType::Category(temp) => {
+                    for (i, value) in temp.iter().enumerate() {
+                        out.data[i*out.cols + enumerated] = *value as f32;
+                    }
+                },
+                DataType::Strings(_) => panic!("The column at {} holds strings, encode it before turning the data frame into a matrix", column),
+            }
+        }
+
+        out
+    }
+}
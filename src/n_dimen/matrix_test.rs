@@ -0,0 +1,74 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_frame::DataFrame, data_type::DataType}};
+
+#[test]
+fn from_rows_is_row_major() {
+    let matrix = Matrix::from(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+    assert_eq!(matrix.shape(), (2, 3));
+    assert_eq!(matrix.strides(), (3, 1));
+    assert_eq!(matrix.as_slice(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    assert_eq!(matrix[1][2], 6.0);
+    assert_eq!(matrix[(0, 1)], 2.0);
+    assert_eq!(matrix.column(1), vec![2.0, 5.0]);
+    assert_eq!(matrix.to_rows(), vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+}
+
+#[test]
+#[should_panic]
+fn ragged_rows_are_rejected() {
+    Matrix::from(vec![vec![1.0, 2.0], vec![3.0]]);
+}
+
+#[test]
+fn row_iterators() {
+    let mut matrix = Matrix::from_vec(3, 2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    let sums: Vec<f32> = matrix.iter_rows().map(|row| row.iter().sum()).collect();
+    assert_eq!(sums, vec![3.0, 7.0, 11.0]);
+
+    matrix.iter_rows_mut().for_each(|row| row[0] = 0.0);
+    assert_eq!(matrix.column(0), vec![0.0, 0.0, 0.0]);
+
+    use rayon::prelude::*;
+    let parallel_sum: f32 = matrix.par_iter_rows().map(|row| row[1]).sum();
+    assert_eq!(parallel_sum, 12.0);
+}
+
+#[test]
+fn views_do_not_copy_and_can_be_nested() {
+    let matrix = Matrix::from_vec(3, 4, (0..12).map(|x| x as f32).collect());
+    let view = matrix.slice(1..3, 1..4);
+    assert_eq!(view.shape(), (2, 3));
+    assert_eq!(view.strides(), (4, 1));
+    assert_eq!(view.row(0), &[5.0, 6.0, 7.0]);
+    assert_eq!(view.get(1, 2), 11.0);
+
+    let inner = view.slice(1..2, 0..2);
+    assert_eq!(inner.to_matrix(), Matrix::from_vec(1, 2, vec![9.0, 10.0]));
+    assert_eq!(matrix.slice(0..0, 0..4).rows(), 0);
+}
+
+#[test]
+fn selecting_rows_and_columns() {
+    let matrix = Matrix::from(vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]]);
+    assert_eq!(matrix.select_rows(&[2, 2, 0]).to_rows(), vec![vec![5.0, 6.0], vec![5.0, 6.0], vec![1.0, 2.0]]);
+    assert_eq!(matrix.select_columns(&[1]).as_slice(), &[2.0, 4.0, 6.0]);
+
+    let mut grown = Matrix::default();
+    grown.push_row(&[1.0, 2.0]);
+    grown.push_row(&[3.0, 4.0]);
+    assert_eq!(grown, matrix.select_rows(&[0, 1]));
+}
+
+#[test]
+fn from_data_frame() {
+    let mut data_frame = DataFrame::new();
+    data_frame.new_column(DataType::Floats(vec![0.5, 1.5]), 0);
+    data_frame.new_column(DataType::Category(vec![3, 7]), 1);
+    let matrix = Matrix::from(&data_frame);
+    assert_eq!(matrix.to_rows(), vec![vec![0.5, 3.0], vec![1.5, 7.0]]);
+
+    let (X_train, y_train, _, _) = data_frame.train_test_split(0.0, 1, false);
+    assert_eq!(X_train.shape(), (2, 1));
+    assert!(matches!(y_train, DataType::Category(_)));
+}
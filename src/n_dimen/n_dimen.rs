@@ -18,14 +18,7 @@ pub fn max_distance_between_sets ( previous_centroid : &Vec<Vec<f32>> , current_
 }
 
 ///takes two arrays of length n(n-dimensional points), returns the euclidean distance between them.
+///see `n_dimen::distance` for the other metrics.
 pub fn distance_between( point_1 : &Vec<f32> , point_2 : &Vec<f32> ) -> f32 {
-
-    let dimensions = point_1.len();
-    let mut sum_of_squares_of_difference = 0.0;
-
-    for i in 0..dimensions {
-        sum_of_squares_of_difference += (point_1[i] - point_2[i]).powf(2.0);
-    }
-    sum_of_squares_of_difference.sqrt()
-
-}
\ No newline at end of file
+    super::distance::euclidean(point_1, point_2)
+}
@@ -0,0 +1,393 @@
+//! #Neighbour index#
+//!
+//! Finds the nearest points(or all the points within a radius) of a query among a fixed set of points.
+//! Build a `NeighbourIndex` once and share it between everything that needs neighbours(k-nearest-neighbours, DBSCAN, imputation...).
+//!
+//! Three structures back the index :
+//! * Brute force -> compares the query against every point, works for any metric.
+//! * KD-tree -> splits the space into boxes along the coordinates, fast in low dimensions.
+//!   Only for the metrics where the closest point of a box is the query clamped into the box(euclidean, squared euclidean, manhattan, chebyshev, minkowski).
+//! * Ball tree -> groups the points into balls around their mean, holds up better in higher dimensions.
+//!   Needs the triangle inequality(every metric except squared euclidean and cosine).
+//!
+//! `IndexKind::Auto` picks brute force for few points, the KD-tree below `KD_TREE_MAX_DIMENSIONS` dimensions,
+//! the ball tree below `BALL_TREE_MAX_DIMENSIONS` dimensions and brute force above that(where no tree prunes much anymore).
+//! Results are sorted by distance, ties by the index of the point, so all the structures return the same neighbours.
+
+use std::{cmp::Ordering, collections::BinaryHeap};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use super::{matrix::Matrix, distance::{Distance, DistanceMetric}};
+
+///`IndexKind::Auto` uses the KD-tree for fewer dimensions than this.
+pub const KD_TREE_MAX_DIMENSIONS : usize = 16;
+///`IndexKind::Auto` uses the ball tree for fewer dimensions than this.
+pub const BALL_TREE_MAX_DIMENSIONS : usize = 100;
+const DEFAULT_LEAF_SIZE : usize = 30;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum IndexKind {
+    ///chosen from the number of points, the dimensions and the metric.
+    #[default]
+    Auto,
+    BruteForce,
+    ///`leaf_size` is the number of points below which a node is not split any further.
+    KdTree { leaf_size : usize },
+    BallTree { leaf_size : usize },
+}
+
+///a point of the index and its distance to the query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Neighbour {
+    pub index : usize,
+    pub distance : f32,
+}
+
+impl Eq for Neighbour {}
+
+impl Ord for Neighbour {
+    fn cmp(&self, other : &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Neighbour {
+    fn partial_cmp(&self, other : &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Bound {
+    Box { min : Vec<f32>, max : Vec<f32> },
+    Ball { centre : Vec<f32>, radius : f32 },
+}
+
+///a node holds the points `order[start..end]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TreeNode {
+    start : usize,
+    end : usize,
+    children : Option<(usize, usize)>,
+    bound : Bound,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeighbourIndex {
+    ///one point per row.
+    points : Matrix,
+    metric : DistanceMetric,
+    kind : IndexKind,
+    ///the point indices, permuted so that every tree node is a contiguous range.
+    order : Vec<usize>,
+    ///empty for brute force, the root is node 0.
+    nodes : Vec<TreeNode>,
+}
+
+impl NeighbourIndex {
+
+    ///builds the index over the points, all of them need the same dimension.
+    pub fn new(points : Vec<Vec<f32>>, metric : DistanceMetric, kind : IndexKind) -> Result<NeighbourIndex, String> {
+        let dimensions = points.first().map_or(0, |point| point.len());
+        if points.iter().any(|point| point.len() != dimensions) {
+            return Err("all the points of the index need the same dimension".to_owned());
+        }
+        NeighbourIndex::build_index(Matrix::from_rows(&points), metric, kind)
+    }
+
+    ///one point per row of the matrix.
+    pub fn from_matrix(points : &Matrix, metric : DistanceMetric, kind : IndexKind) -> Result<NeighbourIndex, String> {
+        NeighbourIndex::build_index(points.clone(), metric, kind)
+    }
+
+    fn build_index(points : Matrix, metric : DistanceMetric, kind : IndexKind) -> Result<NeighbourIndex, String> {
+        metric.validate()?;
+        let kind = match kind {
+            IndexKind::Auto => NeighbourIndex::auto_kind(points.rows(), points.cols(), &metric),
+            IndexKind::KdTree { leaf_size } | IndexKind::BallTree { leaf_size } if leaf_size == 0 => return Err("leaf_size needs to be > 0".to_owned()),
+            IndexKind::KdTree { .. } if !supports_kd_tree(&metric) => return Err(format!("a KD-tree can not be used with the {:?} metric", metric)),
+            IndexKind::BallTree { .. } if !supports_ball_tree(&metric) => return Err(format!("a ball tree can not be used with the {:?} metric", metric)),
+            kind => kind,
+        };
+        let mut index = NeighbourIndex { order: (0..points.rows()).collect(), points, metric, kind, nodes: vec![] };
+        if let IndexKind::KdTree { leaf_size } | IndexKind::BallTree { leaf_size } = kind {
+            if index.points.rows() > 0 {
+                index.build(0, index.points.rows(), leaf_size);
+            }
+        }
+        Ok(index)
+    }
+
+    fn auto_kind(n_points : usize, dimensions : usize, metric : &DistanceMetric) -> IndexKind {
+        if n_points <= 2 * DEFAULT_LEAF_SIZE {
+            IndexKind::BruteForce
+        } else if dimensions < KD_TREE_MAX_DIMENSIONS && supports_kd_tree(metric) {
+            IndexKind::KdTree { leaf_size: DEFAULT_LEAF_SIZE }
+        } else if dimensions < BALL_TREE_MAX_DIMENSIONS && supports_ball_tree(metric) {
+            IndexKind::BallTree { leaf_size: DEFAULT_LEAF_SIZE }
+        } else {
+            IndexKind::BruteForce
+        }
+    }
+
+    ///the structure in use, never `Auto`.
+    pub fn kind(&self) -> IndexKind {
+        self.kind
+    }
+
+    pub fn metric(&self) -> &DistanceMetric {
+        &self.metric
+    }
+
+    ///the indexed points, one per row.
+    pub fn points(&self) -> &Matrix {
+        &self.points
+    }
+
+    pub fn point(&self, index : usize) -> &[f32] {
+        self.points.row(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.rows()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.rows() == 0
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.points.cols()
+    }
+
+    ///the `k` closest points, closest first. Returns every point if there are fewer than `k`.
+    pub fn k_nearest(&self, query : &[f32], k : usize) -> Vec<Neighbour> {
+        if k == 0 || self.is_empty() {
+            return vec![];
+        }
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if self.nodes.is_empty() {
+            for (index, point) in self.points.iter_rows().enumerate() {
+                push_bounded(&mut heap, Neighbour { index, distance: self.metric.distance(query, point) }, k);
+            }
+        } else {
+            self.search_k(0, query, k, &mut heap);
+        }
+        heap.into_sorted_vec()
+    }
+
+    ///every point within `radius`(inclusive) of the query, closest first.
+    pub fn within_radius(&self, query : &[f32], radius : f32) -> Vec<Neighbour> {
+        let mut found = vec![];
+        if self.nodes.is_empty() {
+            for (index, point) in self.points.iter_rows().enumerate() {
+                let distance = self.metric.distance(query, point);
+                if distance <= radius {
+                    found.push(Neighbour { index, distance });
+                }
+            }
+        } else if !self.is_empty() {
+            self.search_radius(0, query, radius, &mut found);
+        }
+        found.sort();
+        found
+    }
+
+    ///`k_nearest` for every row of the queries, in parallel.
+    pub fn k_nearest_batch(&self, queries : &Matrix, k : usize) -> Vec<Vec<Neighbour>> {
+        queries.par_iter_rows().map(|query| self.k_nearest(query, k)).collect()
+    }
+
+    ///`within_radius` for every row of the queries, in parallel.
+    pub fn within_radius_batch(&self, queries : &Matrix, radius : f32) -> Vec<Vec<Neighbour>> {
+        queries.par_iter_rows().map(|query| self.within_radius(query, radius)).collect()
+    }
+
+    ///checks that the points, the order and the tree nodes agree with each other, for an index that was not built by `new`(like a loaded one).
+    pub fn validate(&self) -> Result<(), String> {
+        self.metric.validate()?;
+        if !self.points.is_consistent() {
+            return Err("the points do not fill their matrix".to_owned());
+        }
+        match self.kind {
+            IndexKind::Auto => return Err("a built index can not be of the Auto kind".to_owned()),
+            IndexKind::KdTree { leaf_size } | IndexKind::BallTree { leaf_size } if leaf_size == 0 => return Err("leaf_size needs to be > 0".to_owned()),
+            IndexKind::KdTree { .. } if !supports_kd_tree(&self.metric) => return Err(format!("a KD-tree can not be used with the {:?} metric", self.metric)),
+            IndexKind::BallTree { .. } if !supports_ball_tree(&self.metric) => return Err(format!("a ball tree can not be used with the {:?} metric", self.metric)),
+            _ => {},
+        }
+        let mut seen = vec![false; self.len()];
+        for index in &self.order {
+            if *index >= self.len() || seen[*index] {
+                return Err("the order is not a permutation of the points".to_owned());
+            }
+            seen[*index] = true;
+        }
+        if self.order.len() != self.len() {
+            return Err("the order is not a permutation of the points".to_owned());
+        }
+        let needs_tree = !self.is_empty() && self.kind != IndexKind::BruteForce;
+        if needs_tree == self.nodes.is_empty() {
+            return Err("the tree nodes do not match the kind of index".to_owned());
+        }
+        if let Some(root) = self.nodes.first() {
+            if root.start != 0 || root.end != self.len() {
+                return Err("the root node does not hold every point".to_owned());
+            }
+        }
+        for (position, node) in self.nodes.iter().enumerate() {
+            if node.start >= node.end || node.end > self.len() {
+                return Err(format!("node {} holds an invalid range of points", position));
+            }
+            let dimensions_match = match (&node.bound, self.kind) {
+                (Bound::Box { min, max }, IndexKind::KdTree { .. }) => min.len() == self.dimensions() && max.len() == self.dimensions(),
+                (Bound::Ball { centre, .. }, IndexKind::BallTree { .. }) => centre.len() == self.dimensions(),
+                _ => false,
+            };
+            if !dimensions_match {
+                return Err(format!("the bound of node {} does not match the index", position));
+            }
+            //the children are built after their parent, so pointing forward also rules out cycles.
+            if let Some((left, right)) = node.children {
+                if left <= position || right <= position || left >= self.nodes.len() || right >= self.nodes.len()
+                    || self.nodes[left].start != node.start || self.nodes[left].end != self.nodes[right].start || self.nodes[right].end != node.end {
+                    return Err(format!("the children of node {} do not split its points", position));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //***************************************
+    //TREE CONSTRUCTION AND SEARCH
+    //***************************************
+
+    fn build(&mut self, start : usize, end : usize, leaf_size : usize) -> usize {
+        let bound = self.bound(start, end);
+        let position = self.nodes.len();
+        self.nodes.push(TreeNode { start, end, children: None, bound });
+        if end - start <= leaf_size {
+            return position;
+        }
+        //split at the median of the coordinate with the biggest spread.
+        let dimensions = self.dimensions();
+        let spread = |dimension : usize| {
+            let values = self.order[start..end].iter().map(|index| self.points.get(*index, dimension));
+            values.clone().fold(f32::MIN, f32::max) - values.fold(f32::MAX, f32::min)
+        };
+        let (dimension, widest) = (0..dimensions).map(|dimension| (dimension, spread(dimension)))
+            .fold((0, 0.0_f32), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+        if widest <= 0.0 {
+            return position;
+        }
+        let middle = (start + end) / 2;
+        let points = &self.points;
+        self.order[start..end].select_nth_unstable_by(middle - start, |a, b| points.get(*a, dimension).total_cmp(&points.get(*b, dimension)));
+        let left = self.build(start, middle, leaf_size);
+        let right = self.build(middle, end, leaf_size);
+        self.nodes[position].children = Some((left, right));
+        position
+    }
+
+    fn bound(&self, start : usize, end : usize) -> Bound {
+        let dimensions = self.dimensions();
+        let members = &self.order[start..end];
+        match self.kind {
+            IndexKind::KdTree { .. } => {
+                let (mut min, mut max) = (vec![f32::MAX; dimensions], vec![f32::MIN; dimensions]);
+                for index in members {
+                    for (dimension, value) in self.points.row(*index).iter().enumerate() {
+                        min[dimension] = min[dimension].min(*value);
+                        max[dimension] = max[dimension].max(*value);
+                    }
+                }
+                Bound::Box { min, max }
+            },
+            _ => {
+                let mut centre = vec![0.0_f32; dimensions];
+                for index in members {
+                    for (sum, value) in centre.iter_mut().zip(self.points.row(*index)) {
+                        *sum += value / members.len() as f32;
+                    }
+                }
+                let radius = members.iter().map(|index| self.metric.distance(&centre, self.points.row(*index))).fold(0.0_f32, f32::max);
+                Bound::Ball { centre, radius }
+            },
+        }
+    }
+
+    ///no point of the node is closer to the query than this.
+    fn lower_bound(&self, node : &TreeNode, query : &[f32]) -> f32 {
+        match &node.bound {
+            Bound::Box { min, max } => {
+                let clamped: Vec<f32> = query.iter().zip(min.iter().zip(max)).map(|(value, (low, high))| value.clamp(*low, *high)).collect();
+                self.metric.distance(query, &clamped)
+            },
+            //the ball radius is not rounded in our favour, leave a little room for the float error.
+            Bound::Ball { centre, radius } => (self.metric.distance(query, centre) - radius * (1.0 + 1e-5)).max(0.0),
+        }
+    }
+
+    fn search_k(&self, node : usize, query : &[f32], k : usize, heap : &mut BinaryHeap<Neighbour>) {
+        let current = &self.nodes[node];
+        match current.children {
+            None => {
+                for index in &self.order[current.start..current.end] {
+                    push_bounded(heap, Neighbour { index: *index, distance: self.metric.distance(query, self.points.row(*index)) }, k);
+                }
+            },
+            Some((left, right)) => {
+                let left_bound = self.lower_bound(&self.nodes[left], query);
+                let right_bound = self.lower_bound(&self.nodes[right], query);
+                let visits = if left_bound <= right_bound { [(left, left_bound), (right, right_bound)] } else { [(right, right_bound), (left, left_bound)] };
+                for (child, bound) in visits {
+                    if heap.len() < k || bound <= heap.peek().unwrap().distance {
+                        self.search_k(child, query, k, heap);
+                    }
+                }
+            },
+        }
+    }
+
+    fn search_radius(&self, node : usize, query : &[f32], radius : f32, found : &mut Vec<Neighbour>) {
+        let current = &self.nodes[node];
+        if self.lower_bound(current, query) > radius {
+            return;
+        }
+        match current.children {
+            None => {
+                for index in &self.order[current.start..current.end] {
+                    let distance = self.metric.distance(query, self.points.row(*index));
+                    if distance <= radius {
+                        found.push(Neighbour { index: *index, distance });
+                    }
+                }
+            },
+            Some((left, right)) => {
+                self.search_radius(left, query, radius, found);
+                self.search_radius(right, query, radius, found);
+            },
+        }
+    }
+}
+
+///keeps the `k` smallest neighbours in the max heap.
+fn push_bounded(heap : &mut BinaryHeap<Neighbour>, neighbour : Neighbour, k : usize) {
+    if heap.len() < k {
+        heap.push(neighbour);
+    } else if neighbour < *heap.peek().unwrap() {
+        heap.pop();
+        heap.push(neighbour);
+    }
+}
+
+fn supports_kd_tree(metric : &DistanceMetric) -> bool {
+    matches!(metric, DistanceMetric::Euclidean | DistanceMetric::SquaredEuclidean | DistanceMetric::Manhattan
+        | DistanceMetric::Chebyshev | DistanceMetric::Minkowski(_))
+}
+
+fn supports_ball_tree(metric : &DistanceMetric) -> bool {
+    !matches!(metric, DistanceMetric::SquaredEuclidean | DistanceMetric::Cosine)
+}
@@ -0,0 +1,77 @@
+#![cfg(test)]
+
+use crate::n_dimen::{matrix::Matrix, distance::DistanceMetric, neighbours::*};
+
+fn random_points(seed : u64, count : usize, dimensions : usize) -> Vec<Vec<f32>> {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    (0..count).map(|_| (0..dimensions).map(|_| rng.f32()).collect()).collect()
+}
+
+#[test]
+fn trees_agree_with_brute_force() {
+    let points = random_points(1, 500, 3);
+    let queries = random_points(2, 30, 3);
+    for metric in [DistanceMetric::Euclidean, DistanceMetric::Manhattan, DistanceMetric::Chebyshev, DistanceMetric::Minkowski(3.0)] {
+        let brute = NeighbourIndex::new(points.clone(), metric.clone(), IndexKind::BruteForce).unwrap();
+        let kd = NeighbourIndex::new(points.clone(), metric.clone(), IndexKind::KdTree { leaf_size: 8 }).unwrap();
+        let ball = NeighbourIndex::new(points.clone(), metric.clone(), IndexKind::BallTree { leaf_size: 8 }).unwrap();
+        for query in &queries {
+            let expected = brute.k_nearest(query, 7);
+            assert_eq!(kd.k_nearest(query, 7), expected, "{:?}", metric);
+            assert_eq!(ball.k_nearest(query, 7), expected, "{:?}", metric);
+            assert_eq!(kd.within_radius(query, 0.2), brute.within_radius(query, 0.2));
+            assert_eq!(ball.within_radius(query, 0.2), brute.within_radius(query, 0.2));
+        }
+    }
+}
+
+#[test]
+fn results_are_sorted_and_batches_match() {
+    let points = random_points(3, 200, 2);
+    let index = NeighbourIndex::new(points.clone(), DistanceMetric::Euclidean, IndexKind::Auto).unwrap();
+    let queries = Matrix::from(random_points(4, 10, 2));
+    let batch = index.k_nearest_batch(&queries, 5);
+    for (query, neighbours) in queries.iter_rows().zip(&batch) {
+        assert_eq!(neighbours, &index.k_nearest(query, 5));
+        assert!(neighbours.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+    }
+    let radius = index.within_radius_batch(&queries, 0.1);
+    assert!(radius.iter().flatten().all(|neighbour| neighbour.distance <= 0.1));
+    //asking for more neighbours than points gives all of them.
+    assert_eq!(index.k_nearest(&[0.5, 0.5], 1000).len(), 200);
+    assert_eq!(index.k_nearest(&points[17], 1)[0].index, 17);
+    assert_eq!(index.point(17), points[17].as_slice());
+    assert_eq!(index.points(), &Matrix::from(points));
+}
+
+#[test]
+fn auto_picks_by_dimension_and_metric() {
+    let kind = |count, dimensions, metric| NeighbourIndex::new(random_points(5, count, dimensions), metric, IndexKind::Auto).unwrap().kind();
+    assert_eq!(kind(20, 3, DistanceMetric::Euclidean), IndexKind::BruteForce);
+    assert!(matches!(kind(300, 3, DistanceMetric::Euclidean), IndexKind::KdTree { .. }));
+    assert!(matches!(kind(300, 40, DistanceMetric::Euclidean), IndexKind::BallTree { .. }));
+    assert!(matches!(kind(300, 3, DistanceMetric::Hamming), IndexKind::BallTree { .. }));
+    assert_eq!(kind(300, 3, DistanceMetric::Cosine), IndexKind::BruteForce);
+    assert_eq!(kind(300, 150, DistanceMetric::Euclidean), IndexKind::BruteForce);
+
+    assert!(NeighbourIndex::new(random_points(6, 10, 2), DistanceMetric::Cosine, IndexKind::KdTree { leaf_size: 4 }).is_err());
+    assert!(NeighbourIndex::new(vec![vec![0.0, 1.0], vec![1.0]], DistanceMetric::Euclidean, IndexKind::BruteForce).is_err());
+}
+
+#[test]
+fn validate_catches_broken_indexes() {
+    let index = NeighbourIndex::new(random_points(7, 100, 2), DistanceMetric::Euclidean, IndexKind::KdTree { leaf_size: 4 }).unwrap();
+    assert_eq!(index.validate(), Ok(()));
+    let broken = |change : &dyn Fn(&mut serde_json::Value)| {
+        let mut value = serde_json::to_value(&index).unwrap();
+        change(&mut value);
+        serde_json::from_value::<NeighbourIndex>(value).unwrap().validate().is_err()
+    };
+    assert!(broken(&|value| value["order"][0] = serde_json::json!(1)));
+    assert!(broken(&|value| value["order"][0] = serde_json::json!(100)));
+    assert!(broken(&|value| value["nodes"][0]["end"] = serde_json::json!(101)));
+    assert!(broken(&|value| value["nodes"][0]["children"] = serde_json::json!([0, 2])));
+    assert!(broken(&|value| value["nodes"] = serde_json::json!([])));
+    assert!(broken(&|value| value["metric"] = serde_json::json!("Cosine")));
+    assert!(broken(&|value| value["kind"] = serde_json::json!("Auto")));
+}
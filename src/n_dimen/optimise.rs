@@ -0,0 +1,85 @@
+//! #Smooth optimisation#
+//!
+//! Minimisers for smooth objectives given as a closure returning the value and the gradient at a point.
+//! Everything is in f64, the models convert their parameters back to f32 once they are done.
+
+use std::collections::VecDeque;
+
+
+///largest absolute entry.
+pub fn inf_norm(values : &[f64]) -> f64 {
+    values.iter().fold(0.0, |max, value| max.max(value.abs()))
+}
+
+pub fn dot64(x : &[f64], y : &[f64]) -> f64 {
+    x.iter().zip(y).map(|(a, b)| a * b).sum()
+}
+
+///backtracking(Armijo) line search along `direction`, returns the new parameters, value and gradient,
+///`None` when no step decreases the objective.
+pub fn line_search<F : Fn(&[f64]) -> (f64, Vec<f64>)>(objective : &F, theta : &[f64], loss : f64, gradient : &[f64], direction : &[f64]) -> Option<(Vec<f64>, f64, Vec<f64>)> {
+    let slope = dot64(gradient, direction);
+    let mut step = 1.0_f64;
+    for _ in 0..40 {
+        let candidate: Vec<f64> = theta.iter().zip(direction).map(|(value, d)| value + step * d).collect();
+        let (candidate_loss, candidate_gradient) = objective(&candidate);
+        if candidate_loss.is_finite() && candidate_loss <= loss + 1e-4 * step * slope {
+            return Some((candidate, candidate_loss, candidate_gradient));
+        }
+        step *= 0.5;
+    }
+    None
+}
+
+///limited memory BFGS keeping the last `memory` updates, starting from and updating `theta`.
+///Stops once the largest gradient entry is below `tol` or no step decreases the objective anymore,
+///returns the number of iterations.
+pub fn lbfgs<F : Fn(&[f64]) -> (f64, Vec<f64>)>(objective : F, theta : &mut Vec<f64>, memory : usize, max_iter : usize, tol : f64) -> usize {
+    let (mut loss, mut gradient) = objective(theta);
+    let mut history: VecDeque<(Vec<f64>, Vec<f64>, f64)> = VecDeque::with_capacity(memory);
+
+    for iteration in 0..max_iter {
+        if inf_norm(&gradient) < tol {
+            return iteration;
+        }
+        //two loop recursion for the approximate inverse Hessian times the gradient.
+        let mut direction = gradient.clone();
+        let mut alphas = vec![0.0_f64; history.len()];
+        for (index, (s, y, rho)) in history.iter().enumerate().rev() {
+            alphas[index] = rho * dot64(s, &direction);
+            direction.iter_mut().zip(y).for_each(|(d, y)| *d -= alphas[index] * y);
+        }
+        let gamma = match history.back() {
+            Some((s, y, _)) => dot64(s, y) / dot64(y, y),
+            None => 1.0 / inf_norm(&gradient).max(1.0),
+        };
+        direction.iter_mut().for_each(|d| *d *= gamma);
+        for (index, (s, y, rho)) in history.iter().enumerate() {
+            let beta = rho * dot64(y, &direction);
+            direction.iter_mut().zip(s).for_each(|(d, s)| *d += (alphas[index] - beta) * s);
+        }
+        direction.iter_mut().for_each(|d| *d = -*d);
+        if dot64(&direction, &gradient) >= 0.0 {
+            history.clear();
+            direction = gradient.iter().map(|g| -g).collect();
+        }
+
+        let (new_theta, new_loss, new_gradient) = match line_search(&objective, theta, loss, &gradient, &direction) {
+            Some(found) => found,
+            None => return iteration + 1,
+        };
+        let s: Vec<f64> = new_theta.iter().zip(theta.iter()).map(|(a, b)| a - b).collect();
+        let y: Vec<f64> = new_gradient.iter().zip(&gradient).map(|(a, b)| a - b).collect();
+        let sy = dot64(&s, &y);
+        if sy > 1e-12 {
+            if history.len() == memory {
+                history.pop_front();
+            }
+            history.push_back((s, y, 1.0 / sy));
+        }
+        *theta = new_theta;
+        loss = new_loss;
+        gradient = new_gradient;
+    }
+    max_iter
+}
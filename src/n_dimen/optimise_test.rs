@@ -0,0 +1,30 @@
+#![cfg(test)]
+
+use super::optimise::{lbfgs, line_search};
+
+
+///the Rosenbrock function, a long curved valley with the minimum at (1, 1).
+fn rosenbrock(theta : &[f64]) -> (f64, Vec<f64>) {
+    let (x, y) = (theta[0], theta[1]);
+    let value = (1.0 - x).powi(2) + 100.0 * (y - x * x).powi(2);
+    let gradient = vec![-2.0 * (1.0 - x) - 400.0 * x * (y - x * x), 200.0 * (y - x * x)];
+    (value, gradient)
+}
+
+#[test]
+fn lbfgs_finds_the_bottom_of_the_valley() {
+    let mut theta = vec![-1.2, 1.0];
+    let n_iter = lbfgs(rosenbrock, &mut theta, 10, 500, 1e-8);
+    assert!(n_iter < 500);
+    assert!((theta[0] - 1.0).abs() < 1e-5 && (theta[1] - 1.0).abs() < 1e-5, "{:?}", theta);
+}
+
+#[test]
+fn line_search_only_accepts_a_decrease() {
+    let theta = vec![0.0, 0.0];
+    let (value, gradient) = rosenbrock(&theta);
+    let downhill: Vec<f64> = gradient.iter().map(|g| -g).collect();
+    let (_, new_value, _) = line_search(&rosenbrock, &theta, value, &gradient, &downhill).unwrap();
+    assert!(new_value < value);
+    assert!(line_search(&rosenbrock, &theta, value, &gradient, &gradient).is_none());
+}
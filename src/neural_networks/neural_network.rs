@@ -10,8 +10,9 @@
 use std::{collections::{HashMap, HashSet}, f32::consts::E};
 use fastrand::f32;
 use rand::{random, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use crate::data_frame::{data_type::DataType, return_type::ReturnType};
-use crate::{data_frame::data_frame::DataFrame, trait_definition::MLalgo};
+use crate::{data_frame::data_frame::DataFrame, trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_predict_input, check_sample_weights}, persistence::persistence::{Persist, PersistError, ensure}, n_dimen::matrix::Matrix};
 
 
 //***************************************
@@ -20,7 +21,7 @@ use crate::{data_frame::data_frame::DataFrame, trait_definition::MLalgo};
 ///* Different activation functions,
 ///* The functions and their differentials are defined below.
 ///* You can create your own ActivationFunction by implementing the traits `FunctionValueAt` and `DerivativeValueAt`
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ActivationFunction {
     ///         x                             ,Identity
     Linear,
@@ -109,7 +110,7 @@ pub fn set_leaky_value(value : f32) {
 
 
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 //*************************************************
 //COST FUNCTIONS
 //*************************************************
@@ -129,7 +130,7 @@ pub enum CostFunction {
 
 impl CostFunction {
     
-    fn cost(&self, present_values : &Vec<f32>, ground_truth : &Vec<f32>) -> f32 {
+    fn cost(&self, present_values : &Vec<f32>, ground_truth : &[f32]) -> f32 {
         let count = present_values.len() as f32;
         match self {
             CostFunction::MSE => {
@@ -160,7 +161,7 @@ impl CostFunction {
     }
 
     ///returns a vector of f32's which are the derivatives of the cost w.r.t each respective output node.
-    pub fn cost_derivative(&self, present_values : &Vec<f32>, ground_truth : &Vec<f32>) -> Vec<f32> {
+    pub fn cost_derivative(&self, present_values : &Vec<f32>, ground_truth : &[f32]) -> Vec<f32> {
         match self {
             CostFunction::MSE => {
                 let mut out_vec = vec![0.0_f32; present_values.len()];
@@ -207,6 +208,7 @@ impl CostFunction {
 //OUPUT MAP
 //******************************************
 
+#[derive(Clone, Serialize, Deserialize)]
 pub enum OutputMap {
     ///Returns the soft max of the input with the index whose value is at highest.
     SoftMax,
@@ -246,6 +248,7 @@ impl OutputMap {
 
 ///All the types that are possible for the output in NeuralNets.
 //{-PRESENTLY NOT BEING USED-}
+#[derive(Clone, Serialize, Deserialize)]
 pub enum OutputCanBe {
     ///For single continuous targets.
     Float(f32),
@@ -261,6 +264,7 @@ pub enum OutputCanBe {
 
 //*******************************************************
 //*******************************************************
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NeuralNet<T> {
         ///the number of features and the number of classes.
     in_out_size: (usize, usize),
@@ -270,7 +274,7 @@ pub struct NeuralNet<T> {
     active_values: Vec<Vec<f32>>,
         ///number of nodes in each layer (including the input and the output layer).
     pub layer_width: Vec<usize>,//(length of this - 2) gives the number of hidden layers.
-        ///weights, a vector that stores the matrices of weights(the conventional indexing way).
+        ///weights, a vector that stores the matrices of weights(the conventional indexing way), each one is a contiguous row-major `Matrix`.
         /// 
         ///top vector has a length of (number of hidden layers + 1(output layer))
         /// 
@@ -279,7 +283,7 @@ pub struct NeuralNet<T> {
         /// 
         ///for example if you have a layer 'i' and a layer 'j', and the layer i is the n'th hidden layer, 
         /// then the value of the weight from arbitrary i and j is : weight_matrices[n][j][i];(n=0 gives the weights between input and the first hidden layer).
-    pub weight_matrices: Vec<Vec<Vec<f32>>>,
+    pub weight_matrices: Vec<Matrix>,
         ///biases, a vector that stores the bias value for each node from the first hidden layer to the output layer(inclusively).
         /// 
         ///length: number of hidden layers + 1;
@@ -313,6 +317,8 @@ pub struct NeuralNet<T> {
         /// please keep in mind havinig more number of threads than the number of cpu's is useless unless 
         /// there is some cpu technology like Intel's Hyperthreading.
     thread_number : u8,
+        ///set once `fit` (or `fit_multi_task_float`) has run.
+    fitted : bool,
 }
 
 
@@ -402,9 +408,9 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
 
 
         ///The weights are randomly given value between 0..1
-        let mut weight_matrices: Vec<Vec<Vec<f32>>> = vec![];
+        let mut weight_matrices: Vec<Matrix> = vec![];
         for (present_index, width) in layer_width[1..].iter().enumerate() {
-            weight_matrices.push(vec![vec![0.0_f32; layer_width[present_index]]; *width]);
+            weight_matrices.push(Matrix::new(*width, layer_width[present_index]));
         }
         Self::fill_rand(&mut weight_matrices);
         
@@ -442,6 +448,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
             bias_clipping_value : f32::MAX,
             epoch_value : 50,
             thread_number : 1,
+            fitted : false,
         }
 
     }
@@ -452,7 +459,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
     pub fn xavier_weights(&mut self) {
         //we can directly use this 
         for (input_index, input_node_len) in self.layer_width.iter().skip(1).enumerate() {
-            for weight_node_group in self.weight_matrices[input_index].iter_mut() {
+            for weight_node_group in self.weight_matrices[input_index].iter_rows_mut() {
                 for node_prev_weight in weight_node_group.iter_mut() {
                     *node_prev_weight = rand::thread_rng().gen_range((-1.0/(*input_node_len as f32).sqrt())..((1.0)/(*input_node_len as f32).sqrt()));
                 }
@@ -465,7 +472,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
     /// works better with ReLU and other activation functions.
     pub fn he_weights(&mut self) {
         for (input_index, input_node_len) in self.layer_width.iter().skip(1).enumerate() {
-            for weight_node_group in self.weight_matrices[input_index].iter_mut() {
+            for weight_node_group in self.weight_matrices[input_index].iter_rows_mut() {
                 for node_prev_weight in weight_node_group.iter_mut() {
                     *node_prev_weight = rand::thread_rng().gen_range((-2.0 /(*input_node_len as f32).sqrt())..((2.0) / (*input_node_len as f32).sqrt()));
                 }
@@ -479,12 +486,10 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
         self.least_cost = minimum_cost;        
     }
 
-    fn fill_rand(input : &mut Vec<Vec<Vec<f32>>>) {
+    fn fill_rand(input : &mut Vec<Matrix>) {
         input.iter_mut().for_each(|x| {
-            x.iter_mut().for_each(|y| {
-                y.iter_mut().for_each(|z| {
-                    *z = fastrand::f32();
-                });
+            x.as_mut_slice().iter_mut().for_each(|z| {
+                *z = fastrand::f32();
             });
         });
     }
@@ -497,12 +502,10 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
         });
     }
 
-    fn fill_same(input : &mut Vec<Vec<Vec<f32>>>, same : f32) {
+    fn fill_same(input : &mut Vec<Matrix>, same : f32) {
         input.iter_mut().for_each(|x| {
-            x.iter_mut().for_each(|y| {
-                y.iter_mut().for_each(|z| {
-                    *z = same;
-                });
+            x.as_mut_slice().iter_mut().for_each(|z| {
+                *z = same;
             });
         });
     }
@@ -565,7 +568,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
     ///The last layer of activation gives the current active values, after this function is run once.
     /// 
     ///Returns the last(output layer), used in the prediction function.
-    pub fn feed_forward(&mut self, input_values: &Vec<f32>) -> &Vec<f32> {
+    pub fn feed_forward(&mut self, input_values: &[f32]) -> &Vec<f32> {
 
         assert!(input_values.len() == self.layer_width[0], "The input dimensionality must be same for both the NeuralNet and the present input_values");
 
@@ -603,7 +606,15 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
     //Takes in the value of the output and what `should` they be.
     //Modifies the values of weights and the biases, to make the 'cost' less.
     // This function does NOT use any kind of parallelism.
-    pub fn feed_forward_back_propogate(&mut self, input_values: &Vec<f32>, ground: &Vec<f32>) -> f32 {
+    pub fn feed_forward_back_propogate(&mut self, input_values: &[f32], ground: &[f32]) -> f32 {
+        self.feed_forward_back_propogate_weighted(input_values, ground, 1.0)
+    }
+
+    //Same as above, but the step taken for this sample is scaled by its weight,
+    //so a sample with weight 2 counts like the sample seen twice and a weight of 0 leaves the network untouched.
+    //Returns the weighted cost.
+    pub fn feed_forward_back_propogate_weighted(&mut self, input_values: &[f32], ground: &[f32], sample_weight: f32) -> f32 {
+        let step = self.learning_step*sample_weight;
         //feeding forward
         self.feed_forward(input_values);
         //the `chained derivative` field of the struct stores the chain of derivaives till the 'net' value of the node at the respective index in the other fields such as active values and net values.
@@ -621,7 +632,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
             //updating the weights(for the last layer)
             //for each weight to the present node.
             for (prev_layer_index, weight_value) in self.weight_matrices[last_index][node_index].iter_mut().enumerate() {
-                *weight_value -= self.learning_step*(self.chained_derivate[last_index][node_index]*(self.active_values[last_index-1][prev_layer_index]));
+                *weight_value -= step*(self.chained_derivate[last_index][node_index]*(self.active_values[last_index-1][prev_layer_index]));
                 if f32::abs(*weight_value) > f32::abs(self.weight_clipping_value) {
                     //we gotta preserve the sign though.
                     if *weight_value < 0.0 {
@@ -632,7 +643,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
                 }
             }
             //updating the biases(for the last layer)
-            self.bias_vectors[last_index][node_index] -= self.learning_step*(self.chained_derivate[last_index][node_index]);
+            self.bias_vectors[last_index][node_index] -= step*(self.chained_derivate[last_index][node_index]);
             if f32::abs(self.bias_vectors[last_index][node_index]) > f32::abs(self.bias_clipping_value) {
                 if self.bias_vectors[last_index][node_index] < 0.0 {
                     self.bias_vectors[last_index][node_index] *= -self.bias_clipping_value;
@@ -665,7 +676,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
                 if (layer_index != 0) {
                     //updating the related weights and the related bias.
                     for (weight_index, weight_value) in self.weight_matrices[layer_index][node_index].iter_mut().enumerate() {
-                        *weight_value -= self.learning_step*(self.chained_derivate[layer_index][node_index]*self.active_values[layer_index-1][weight_index]);
+                        *weight_value -= step*(self.chained_derivate[layer_index][node_index]*self.active_values[layer_index-1][weight_index]);
                         if f32::abs(*weight_value) > f32::abs(self.weight_clipping_value) {
                             if *weight_value < 0.0 {
                                 *weight_value *= -self.weight_clipping_value;
@@ -674,7 +685,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
                             }
                         }
                     }
-                    self.bias_vectors[layer_index][node_index] += self.learning_step*(self.chained_derivate[layer_index][node_index]);
+                    self.bias_vectors[layer_index][node_index] += step*(self.chained_derivate[layer_index][node_index]);
                     if f32::abs(self.bias_vectors[layer_index][node_index]) > f32::abs(self.bias_clipping_value) {
                         if self.bias_vectors[layer_index][node_index] < 0.0 {
                             self.bias_vectors[layer_index][node_index] *= -self.bias_clipping_value;
@@ -687,9 +698,9 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
         }
 
         //FOR THE WEIGHTS FROM THE INPUT TO THE FIRST HIDDEN LAYER.
-        for (node_index, weight_values) in self.weight_matrices[0].iter_mut().enumerate() {
+        for (node_index, weight_values) in self.weight_matrices[0].iter_rows_mut().enumerate() {
             for (input_index, weight_value) in weight_values.iter_mut().enumerate() {
-                *weight_value -= self.learning_step*(input_values[input_index]*self.chained_derivate[0][node_index]);
+                *weight_value -= step*(input_values[input_index]*self.chained_derivate[0][node_index]);
                 if f32::abs(*weight_value) > f32::abs(self.weight_clipping_value) {
                     if *weight_value < 0.0 {
                         *weight_value *= -self.weight_clipping_value;
@@ -698,7 +709,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
                     }
                 }
             }
-            self.bias_vectors[0][node_index] -= self.learning_step*(self.chained_derivate[0][node_index]);
+            self.bias_vectors[0][node_index] -= step*(self.chained_derivate[0][node_index]);
             if f32::abs(self.bias_vectors[0][node_index]) > f32::abs(self.bias_clipping_value) {
                 if self.bias_vectors[0][node_index] < 0.0 {
                     self.bias_vectors[0][node_index] *= -self.bias_clipping_value;
@@ -708,13 +719,13 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
             }
         }
 
-        return total_cost;
+        return total_cost*sample_weight;
         
     }
 
     //Basically curve fitting.
     ///* Curve fitting on a single continuous output.
-    fn fit_float(&mut self, X_train : &Vec<Vec<f32>> , y_train : &DataType) {
+    fn fit_float(&mut self, X_train : &Matrix , y_train : &DataType, sample_weights : &[f32]) {
         let ground: &Vec<f32> = match y_train {
             DataType::Floats(temp) => temp,
             _ => panic!("Wrong type!"),
@@ -726,11 +737,11 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
         for epoch_index in 0..self.epoch_value {
             let mut present_cost_max = f32::MIN;
             // for each data point, backpropogate and update the weights.
-            for (index, present_theta) in X_train.iter().enumerate() {
+            for (index, present_theta) in X_train.iter_rows().enumerate() {
                 //setting the ground truth value for this sample.
                 placeholder_vector[0] = ground[index];
                 //this function first feeds forward, then back-propogates.
-                present_cost = self.feed_forward_back_propogate(present_theta, &placeholder_vector);
+                present_cost = self.feed_forward_back_propogate_weighted(present_theta, &placeholder_vector, sample_weights[index]);
                 //updating the present cost if it is the biggest till now in the present epoch.
                 if (present_cost > present_cost_max) {
                     present_cost_max = present_cost;
@@ -757,14 +768,14 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
 
 
 
-    fn fit_string(&mut self, X_train : &Vec<Vec<f32>> , y_train : &DataType) {
+    fn fit_string(&mut self, X_train : &Matrix , y_train : &DataType) {
         todo!();
     }
 
     /// This is called when the target is of the type 'DataType::Category'
     /// use the cost functions : "CostFunction::BCE" and "CostFunction::CCE" for categorical targets.
     /// there will be a warning if tried to train with BCE but there are more than 
-    fn fit_category(&mut self, X_train : &Vec<Vec<f32>> , y_train : &DataType) {
+    fn fit_category(&mut self, X_train : &Matrix , y_train : &DataType, sample_weights : &[f32]) {
         //The basic back-prop when to to stop loop.
 
         let ground: &Vec<u8> = match y_train {
@@ -778,11 +789,11 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
         for epoch_index in 0..self.epoch_value {
             let mut present_cost_max = f32::MIN;
             // for each data point, backpropogate and update the weights.
-            for (index, present_theta) in X_train.iter().enumerate() {
+            for (index, present_theta) in X_train.iter_rows().enumerate() {
                 //setting the ground truth value for this sample.
                 placeholder_vector[0] = ground[index] as f32;
                 //this function first feeds forward, then back-propogates.
-                present_cost = self.feed_forward_back_propogate(present_theta, &placeholder_vector);
+                present_cost = self.feed_forward_back_propogate_weighted(present_theta, &placeholder_vector, sample_weights[index]);
                 //updating the present cost if it is the biggest till now in the present epoch.
                 if (present_cost > present_cost_max) {
                     present_cost_max = present_cost;
@@ -802,8 +813,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
 
 
     //Multiple curve fitting.
-    pub fn fit_multi_task_float(&mut self, X_train : &Vec<Vec<f32>> , y_train : &Vec<Vec<f32>>) {
-
+    pub fn fit_multi_task_float(&mut self, X_train : &Matrix , y_train : &Matrix) {
         //the y_train is going to store the targets in the form of columns, which we will need to dereference to use them.
         let mut present_cost: f32;
         let mut placeholder_vector = vec![0.0_f32; self.target_indices.len()];
@@ -811,10 +821,10 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
         for epoch_index in 0..self.epoch_value {
             let mut present_cost_max = f32::MIN;
             // for each data point, backpropogate and update the weights.
-            for (index, present_theta) in X_train.iter().enumerate() {
+            for (index, present_theta) in X_train.iter_rows().enumerate() {
                 //setting the ground truth value for this sample.
                 //this function first feeds forward, then back-propogates.
-                present_cost = self.feed_forward_back_propogate(present_theta, &y_train[index]);
+                present_cost = self.feed_forward_back_propogate(present_theta, y_train.row(index));
                 //updating the present cost if it is the biggest till now in the present epoch.
                 if (present_cost > present_cost_max) {
                     present_cost_max = present_cost;
@@ -828,6 +838,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
                 break;
             }
         }
+        self.fitted = true;
     }
 
     fn predict_string() {
@@ -837,7 +848,7 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
     ///Returns the values of last layer(output layer),
     ///* Here only one input is being expected and also returned from the feed_forward() method,
     /// so we do not need to worry about
-    pub fn predict_float(&mut self, input_values: &Vec<f32>) -> f32 {
+    pub fn predict_float(&mut self, input_values: &[f32]) -> f32 {
         return self.feed_forward(input_values)[0];
     }
 
@@ -845,12 +856,19 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
         todo!();
     }
 
-    fn predict_multi_task_float(&mut self, input_values: &Vec<f32>) -> &Vec<f32> {
+    fn predict_multi_task_float(&mut self, input_values: &[f32]) -> &Vec<f32> {
         return self.feed_forward(input_values);
     }
 
-    fn predict(&self, point : &Vec<f32>) -> ReturnType {
-        todo!();
+    ///the net values of the output layer, same as `feed_forward` but without touching the buffers of the network.
+    fn forward(&self, input_values : &[f32]) -> Vec<f32> {
+        let mut active = input_values.to_vec();
+        let mut net = vec![];
+        for (layer_index, (weights, biases)) in self.weight_matrices.iter().zip(&self.bias_vectors).enumerate() {
+            net = weights.iter_rows().zip(biases).map(|(row, bias)| row.iter().zip(&active).map(|(weight, value)| weight*value).sum::<f32>() + bias).collect();
+            active = net.iter().map(|value| self.activation_function[layer_index].function_at(*value)).collect();
+        }
+        net
     }
 
 }
@@ -858,19 +876,114 @@ impl<T : functionValueAt + DerivativeValueAt> NeuralNet<T> {
 
 impl<T : functionValueAt + DerivativeValueAt> MLalgo for NeuralNet<T> {
     ///The fit function automatically changes the type of algorithm used based on the target type.
-    fn fit(&mut self, X_train : &Vec<Vec<f32>> , y_train : &DataType) {
+    fn fit(&mut self, X_train : &Matrix , y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///every back-propagation step is scaled by the weight of its sample.
+    fn fit_weighted(&mut self, X_train : &Matrix , y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        if X_train.cols() != self.in_out_size.0 {
+            return Err(MLError::FeatureMismatch { expected: self.in_out_size.0, found: X_train.cols() });
+        }
+        if std::mem::discriminant(&self.target_type) != std::mem::discriminant(y_train) {
+            return Err(MLError::InvalidTarget("the target type is not the one the network was created for".to_owned()));
+        }
         let start_time = std::time::Instant::now();
         match &self.target_type {
-            DataType::Strings(_) => self.fit_string(X_train, y_train),
+            DataType::Strings(_) => return Err(MLError::InvalidTarget("string targets are not supported yet, encode them as categories".to_owned())),
             DataType::Floats(_) => if self.target_indices.len() == 1 {
-                self.fit_float(X_train, y_train);
+                self.fit_float(X_train, y_train, sample_weights);
             } else {
-                panic!("Please use 'ObjectName.fit_multi_task_float(X_train, y_train)' for this purpose");
+                return Err(MLError::InvalidTarget("Please use 'ObjectName.fit_multi_task_float(X_train, y_train)' for this purpose".to_owned()));
             },
-            DataType::Category(_) => self.fit_category(X_train, y_train),
+            DataType::Category(_) => self.fit_category(X_train, y_train, sample_weights),
+        }
+        self.fitted = true;
+        eprintln!("Time required to train : {:?}", start_time.elapsed());
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.then_some(self.in_out_size.0)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("learning_step".to_owned(), ParamValue::Float(self.learning_step));
+        params.insert("epoch_value".to_owned(), ParamValue::Int(self.epoch_value as i64));
+        params.insert("least_cost".to_owned(), ParamValue::Float(self.least_cost));
+        params.insert("weight_clipping_value".to_owned(), ParamValue::Float(self.weight_clipping_value));
+        params.insert("bias_clipping_value".to_owned(), ParamValue::Float(self.bias_clipping_value));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "learning_step" => self.learning_step = value.as_f32(name)?,
+                "epoch_value" => self.epoch_value = value.as_usize(name)?,
+                "least_cost" => self.least_cost = value.as_f32(name)?,
+                "weight_clipping_value" => self.weight_clipping_value = value.as_f32(name)?,
+                "bias_clipping_value" => self.bias_clipping_value = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
         }
-        eprintln!("Time required to train : {:?}", start_time.elapsed())
+        Ok(())
     }
 }
 
 
+
+
+impl<T : functionValueAt + DerivativeValueAt> Predict for NeuralNet<T> {
+    ///the output node of a network with a single float target, like `predict_float`.
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        self.try_predict(point).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    ///the category and multi-task networks are not predicted through `Predict`, they give an `InvalidTarget` error.
+    fn try_predict(&self, point : &[f32]) -> Result<ReturnType, MLError> {
+        check_predict_input(self, point)?;
+        match self.target_type {
+            DataType::Floats(_) if self.target_indices.len() == 1 => Ok(ReturnType::Floats(self.forward(point)[0])),
+            _ => Err(MLError::InvalidTarget("only a network with a single float target can predict a point, use 'feed_forward' for the others".to_owned())),
+        }
+    }
+}
+
+impl<T : functionValueAt + DerivativeValueAt + Clone + Send + Sync + 'static> Estimator for NeuralNet<T> {}
+
+
+
+
+impl<T : functionValueAt + DerivativeValueAt + Serialize + DeserializeOwned> Persist for NeuralNet<T> {
+    const MODEL_TYPE: &'static str = "NeuralNet";
+
+    ///checks that the weights, biases, activations and the working buffers all agree with `layer_width`.
+    fn validate(&self) -> Result<(), PersistError> {
+        let number_of_layers = self.layer_width.len();
+        ensure(number_of_layers >= 3, "a network needs an input, atleast one hidden and an output layer")?;
+        ensure(self.in_out_size == (self.layer_width[0], self.layer_width[number_of_layers-1]), "input/output size does not match the layer widths")?;
+        ensure(self.activation_function.len() == number_of_layers-1, "there should be one activation function for every layer after the input")?;
+        ensure(self.weight_matrices.len() == number_of_layers-1, "there should be one weight matrix for every layer after the input")?;
+        for (index, matrix) in self.weight_matrices.iter().enumerate() {
+            ensure(matrix.is_consistent(), "weight matrix does not hold rows * columns values")?;
+            ensure(matrix.shape() == (self.layer_width[index+1], self.layer_width[index]), "weight matrix shape does not match the layer widths")?;
+        }
+        for buffer in [&self.bias_vectors, &self.net_values, &self.active_values, &self.chained_derivate] {
+            ensure(buffer.len() == number_of_layers-1, "there should be one bias/value vector for every layer after the input")?;
+            ensure(buffer.iter().zip(self.layer_width[1..].iter()).all(|(layer, width)| layer.len() == *width), "bias/value vectors do not match the layer widths")?;
+        }
+        Ok(())
+    }
+}
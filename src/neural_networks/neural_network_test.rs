@@ -1,6 +1,6 @@
 use crate::{data_frame::{data_frame::*, data_type::{DataType, print_at_index}}, neural_networks::neural_network::{functionValueAt, DerivativeValueAt}};
 use rand::random;
-use crate::{file_handling::read_from::read_csv, neural_networks::neural_network::OutputMap, trait_definition::MLalgo};
+use crate::{file_handling::read_from::read_csv, neural_networks::neural_network::OutputMap, trait_definition::{MLalgo, MLError, ParamValue}};
 use super::neural_network::{ActivationFunction, NeuralNet, CostFunction, set_leaky_value};
 use plotters::prelude::*;
 
@@ -36,7 +36,7 @@ fn new_Struct() {
     // println!("{:?}", hava.get_layer_detes());
     // hava.debug_activation_values();
     let (X_train, y_train, X_test, y_test) = temp.train_test_split(0.8, 6, false);
-    neural_net.fit(&X_train, &y_train);
+    neural_net.fit(&X_train, &y_train).unwrap();
 
     let filename = format!("dummy/scatter____.png");
     let root = BitMapBackend::new(&filename, (800, 600)).into_drawing_area();
@@ -47,9 +47,9 @@ fn new_Struct() {
         .y_label_area_size(40)
         .build_cartesian_2d(0.0..1.0, 0.0..1.0).unwrap();
 
-    let mut predict_vec = vec![0.0_f32; X_test.len()];
+    let mut predict_vec = vec![0.0_f32; X_test.rows()];
 
-    for (index, value) in X_test.iter().enumerate() {
+    for (index, value) in X_test.iter_rows().enumerate() {
         let value = neural_net.predict_float(value);
         predict_vec[index] = value;
         print!("predicted value : {:?}, actual value : ", value);
@@ -119,7 +119,7 @@ fn sin_validation() -> Result<(), Box<dyn std::error::Error>> {
     let mut initinit = 0;
 
     for i in 1..250 {
-        for (index, frame) in X_train.iter().enumerate() {
+        for (index, frame) in X_train.iter_rows().enumerate() {
             neural_net.feed_forward_back_propogate(frame, &vec![yyyy[index]]);
 
             if (index%250 == 0) {
@@ -156,3 +156,25 @@ fn useless_test() {
     println!("Hello, World!");
 }
 
+#[test]
+fn hyperparameters_can_be_read_and_changed() {
+    let mut df = DataFrame::new();
+    df.new_column(DataType::Floats(vec![0.0, 1.0, 2.0]), 0);
+    df.new_column(DataType::Floats(vec![0.0, 0.5, 1.0]), 1);
+    let mut neural_net = NeuralNet::new(&df, vec![1], vec![4], vec![ActivationFunction::Tanh, ActivationFunction::Tanh], CostFunction::MSE, -0.01, OutputMap::ArgMax, 1);
+    assert!(!neural_net.is_fitted());
+
+    let mut params = neural_net.get_params();
+    assert_eq!(params.get("learning_step"), Some(&ParamValue::Float(-0.01)));
+    params.insert("epoch_value".to_owned(), ParamValue::Int(3));
+    neural_net.set_params(&params).unwrap();
+    assert_eq!(neural_net.epoch_value, 3);
+
+    params.insert("epoch_value".to_owned(), ParamValue::Float(2.5));
+    assert!(matches!(neural_net.set_params(&params), Err(MLError::InvalidParameter(_))));
+
+    let (X_train, y_train, _, _) = df.train_test_split(0.0, 1, false);
+    assert!(matches!(neural_net.fit(&X_train, &DataType::Category(vec![0, 1, 1])), Err(MLError::InvalidTarget(_))));
+    neural_net.fit(&X_train, &y_train).unwrap();
+    assert_eq!(neural_net.n_features_in(), Some(1));
+}
@@ -0,0 +1,171 @@
+//! #Model persistence#
+//!
+//! Save a trained model once and load it back later, without having to retrain from the csv on every start.
+//! Two formats are supported :
+//! * JSON -> readable, good for inspecting what the model has learned.
+//! * Binary(bincode) -> much smaller on disk and faster to load.
+//!
+//! Every saved file is wrapped in an envelope that stores the format version and the type of the model,
+//! so loading a `GaussianNb` file as a `NeuralNet` (or loading a file written by an incompatible version) fails cleanly.
+//! After deserializing, `Persist::validate` is run on the model so broken shapes are caught at load time and not in the middle of a `predict`.
+
+use std::{error::Error, fmt, fs::File, io::{BufReader, BufWriter}};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Version of the on-disk format, bump this whenever the layout of any persisted model changes.
+/// * 1 -> the first format.
+/// * 2 -> matrices stored as `Matrix`, the fitted state and hyperparameters of every model,
+///   float class counts for the weighted naive Bayes fits, and the priors, smoothing and variance floor of `GaussianNb`/`MultinomialNb`.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// The errors that can happen while loading a saved model (on top of the usual io and parsing errors).
+#[derive(Debug, PartialEq)]
+pub enum PersistError {
+    ///the file was written with a different format version.
+    VersionMismatch { found: u32, expected: u32 },
+    ///the file contains a different kind of model than the one being loaded.
+    WrongModelType { found: String, expected: String },
+    ///the model was deserialized but its fields do not agree with each other.
+    InvalidShape(String),
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistError::VersionMismatch { found, expected } => write!(f, "saved model has format version {}, expected version {}", found, expected),
+            PersistError::WrongModelType { found, expected } => write!(f, "saved model is of the type '{}', but '{}' was being loaded", found, expected),
+            PersistError::InvalidShape(reason) => write!(f, "saved model is corrupted : {}", reason),
+        }
+    }
+}
+
+impl Error for PersistError {}
+
+/// The envelope every model is wrapped in before being written.
+#[derive(Serialize, Deserialize)]
+struct SavedModel<M> {
+    format_version: u32,
+    model_type: String,
+    model: M,
+}
+
+/// Only used to read the header of a saved file before deserializing the whole model.
+#[derive(Deserialize)]
+struct SavedHeader {
+    format_version: u32,
+    model_type: String,
+}
+
+/// Implemented by everything that can be saved to and loaded from the disk.
+/// The serde traits take care of the actual encoding, the implementor only needs to give a unique name and
+/// say what a valid (non-corrupted) model looks like.
+pub trait Persist: Serialize + DeserializeOwned {
+    ///unique name of the model, stored in the saved file.
+    const MODEL_TYPE: &'static str;
+
+    ///checks that all the learned parameters agree with each other, called right after loading.
+    fn validate(&self) -> Result<(), PersistError>;
+
+    ///Serializes the model into a JSON string.
+    fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(&wrap(self))?)
+    }
+
+    ///Deserializes and validates a model from a JSON string.
+    fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let header: SavedHeader = serde_json::from_str(json)?;
+        check_header::<Self>(&header)?;
+        let saved: SavedModel<Self> = serde_json::from_str(json)?;
+        unwrap_checked(saved)
+    }
+
+    ///Serializes the model into bytes.
+    fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(bincode::serialize(&wrap(self))?)
+    }
+
+    ///Deserializes and validates a model from bytes written by `to_bytes`.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        //the header fields are serialized first so they can be read on their own.
+        let header: SavedHeader = bincode::deserialize(bytes)?;
+        check_header::<Self>(&header)?;
+        let saved: SavedModel<Self> = bincode::deserialize(bytes)?;
+        unwrap_checked(saved)
+    }
+
+    ///Saves the model as a JSON file at the given path.
+    fn save_json(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, &wrap(self))?;
+        Ok(())
+    }
+
+    ///Loads a model saved by `save_json`.
+    fn load_json(path: &str) -> Result<Self, Box<dyn Error>> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    ///Saves the model in the binary format at the given path.
+    fn save_binary(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, &wrap(self))?;
+        Ok(())
+    }
+
+    ///Loads a model saved by `save_binary`.
+    fn load_binary(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut bytes = vec![];
+        std::io::Read::read_to_end(&mut reader, &mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn wrap<M: Persist>(model: &M) -> SavedModel<&M> {
+    SavedModel {
+        format_version: FORMAT_VERSION,
+        model_type: M::MODEL_TYPE.to_owned(),
+        model,
+    }
+}
+
+fn check_header<M: Persist>(header: &SavedHeader) -> Result<(), PersistError> {
+    if header.format_version != FORMAT_VERSION {
+        return Err(PersistError::VersionMismatch { found: header.format_version, expected: FORMAT_VERSION });
+    }
+    if header.model_type != M::MODEL_TYPE {
+        return Err(PersistError::WrongModelType { found: header.model_type.clone(), expected: M::MODEL_TYPE.to_owned() });
+    }
+    Ok(())
+}
+
+fn unwrap_checked<M: Persist>(saved: SavedModel<M>) -> Result<M, Box<dyn Error>> {
+    saved.model.validate()?;
+    Ok(saved.model)
+}
+
+/// Helper for the `validate` implementations, fails with `InvalidShape` if the condition is false.
+pub fn ensure(condition: bool, reason: &str) -> Result<(), PersistError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(PersistError::InvalidShape(reason.to_owned()))
+    }
+}
+
+/// JSON has no NaN, serde_json writes it as `null` and then refuses to read it back into a f32.
+/// Use with `#[serde(with = "nan_vec")]` on `Vec<f32>` fields that can hold NaN(like the min and max of string columns).
+pub mod nan_vec {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(values: &Vec<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+        let options: Vec<Option<f32>> = values.iter().map(|value| if value.is_nan() { None } else { Some(*value) }).collect();
+        options.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f32>, D::Error> {
+        let options: Vec<Option<f32>> = Vec::deserialize(deserializer)?;
+        Ok(options.into_iter().map(|value| value.unwrap_or(f32::NAN)).collect())
+    }
+}
@@ -0,0 +1,149 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_frame::{DataFrame, DataFrameMetadata}, data_type::DataType}, persistence::persistence::{Persist, PersistError},
+    supervised::naive_bayes::{gaussian_NB::GaussianNb, multinomial_NB::{multinomial_NB, MultinomialNb},
+        bernoulli_NB::BernoulliNb, complement_NB::ComplementNb, categorical_NB::CategoricalNb}, trait_definition::{MLalgo, Predict},
+    neural_networks::neural_network::{NeuralNet, ActivationFunction, CostFunction, OutputMap}};
+
+fn small_gaussian() -> GaussianNb {
+    let X_train = Matrix::from(vec![vec![1.0, 2.1], vec![1.2, 1.9], vec![0.9, 2.0], vec![5.0, 7.9], vec![5.2, 8.1], vec![4.9, 8.0]]);
+    let y_train = DataType::Strings(vec!["low".to_owned(), "low".to_owned(), "low".to_owned(), "high".to_owned(), "high".to_owned(), "high".to_owned()]);
+    let mut model = GaussianNb::new();
+    model.fit(&X_train, &y_train).unwrap();
+    model
+}
+
+#[test]
+fn gaussian_json_round_trip() {
+    let model = small_gaussian();
+    let json = model.to_json().unwrap();
+    let loaded = GaussianNb::from_json(&json).unwrap();
+    for point in [vec![1.0, 2.0], vec![5.1, 8.0]] {
+        assert_eq!(model.predict(&point), loaded.predict(&point));
+    }
+}
+
+#[test]
+fn gaussian_binary_round_trip() {
+    let model = small_gaussian();
+    let path = std::env::temp_dir().join("ferrum_gaussian_nb.bin");
+    model.save_binary(path.to_str().unwrap()).unwrap();
+    let loaded = GaussianNb::load_binary(path.to_str().unwrap()).unwrap();
+    assert_eq!(loaded.predict(&vec![5.1, 8.0]), model.predict(&vec![5.1, 8.0]));
+    assert!(model.to_bytes().unwrap().len() < model.to_json().unwrap().len());
+}
+
+#[test]
+fn loading_the_wrong_model_type_fails() {
+    let json = small_gaussian().to_json().unwrap();
+    let error = NeuralNet::<ActivationFunction>::from_json(&json).err().unwrap();
+    assert_eq!(error.downcast_ref::<PersistError>(), Some(&PersistError::WrongModelType { found: "GaussianNb".to_owned(), expected: "NeuralNet".to_owned() }));
+}
+
+#[test]
+fn corrupted_shapes_are_rejected() {
+    let json = small_gaussian().to_json().unwrap();
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    //dropping one class worth of means leaves the model inconsistent.
+    value["model"]["means_and_std_devs"].as_array_mut().unwrap().pop();
+    let error = GaussianNb::from_json(&value.to_string()).err().unwrap();
+    assert!(matches!(error.downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_))));
+
+    //files of the first format have integer class counts and no fitted state, they are refused instead of misread.
+    for version in [0, 1] {
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["format_version"] = serde_json::json!(version);
+        let error = GaussianNb::from_json(&value.to_string()).err().unwrap();
+        assert!(matches!(error.downcast_ref::<PersistError>(), Some(PersistError::VersionMismatch { .. })));
+    }
+}
+
+#[test]
+fn unfitted_and_empty_naive_bayes_files() {
+    //an unfitted model loads back unfitted.
+    let loaded = GaussianNb::from_json(&GaussianNb::new().to_json().unwrap()).unwrap();
+    assert!(!loaded.is_fitted());
+    let loaded = MultinomialNb::from_bytes(&multinomial_NB().to_bytes().unwrap()).unwrap();
+    assert!(!loaded.is_fitted());
+
+    //a fitted model without any class is refused, not indexed into.
+    let mut value: serde_json::Value = serde_json::from_str(&small_gaussian().to_json().unwrap()).unwrap();
+    value["model"]["target_classes"] = serde_json::json!({ "Strings": [] });
+    value["model"]["target_class_distributions"] = serde_json::json!([]);
+    value["model"]["means_and_std_devs"] = serde_json::json!([]);
+    let error = GaussianNb::from_json(&value.to_string()).err().unwrap();
+    assert!(matches!(error.downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_))));
+
+    let mut model = multinomial_NB();
+    model.fit(&Matrix::from(vec![vec![1.0, 0.0], vec![0.0, 2.0]]), &DataType::Category(vec![0, 1])).unwrap();
+    let mut value: serde_json::Value = serde_json::from_str(&model.to_json().unwrap()).unwrap();
+    for field in ["target_class_distributions", "feature_counts", "class_log_prior", "feature_log_prob"] {
+        value["model"][field] = serde_json::json!([]);
+    }
+    value["model"]["target_classes"] = serde_json::json!({ "Category": [] });
+    let error = MultinomialNb::from_json(&value.to_string()).err().unwrap();
+    assert!(matches!(error.downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_))));
+}
+
+#[test]
+fn discrete_naive_bayes_files_without_classes_are_refused() {
+    let X_train = Matrix::from(vec![vec![1.0, 0.0], vec![0.0, 2.0]]);
+    let y_train = DataType::Category(vec![0, 1]);
+    let mut bernoulli = BernoulliNb::new();
+    bernoulli.fit(&X_train, &y_train).unwrap();
+    let mut complement = ComplementNb::new();
+    complement.fit(&X_train, &y_train).unwrap();
+    let mut categorical = CategoricalNb::new();
+    categorical.fit(&X_train, &y_train).unwrap();
+
+    let emptied = |json : String, fields : &[&str]| {
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        for field in fields {
+            value["model"][*field] = serde_json::json!([]);
+        }
+        value["model"]["classes"] = serde_json::json!({ "Category": [] });
+        value.to_string()
+    };
+    let rejected = |error : Box<dyn std::error::Error>| matches!(error.downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_)));
+
+    let json = emptied(bernoulli.to_json().unwrap(), &["class_counts", "feature_counts", "class_log_prior", "feature_log_prob"]);
+    assert!(rejected(BernoulliNb::from_json(&json).err().unwrap()));
+    let json = emptied(complement.to_json().unwrap(), &["class_counts", "feature_counts", "class_log_prior", "feature_weights"]);
+    assert!(rejected(ComplementNb::from_json(&json).err().unwrap()));
+    let json = emptied(categorical.to_json().unwrap(), &["class_counts", "category_counts", "class_log_prior", "category_log_prob", "unseen_log_prob"]);
+    assert!(rejected(CategoricalNb::from_json(&json).err().unwrap()));
+
+    //an unseen category probability is needed for every feature, not only for every class.
+    let mut value: serde_json::Value = serde_json::from_str(&categorical.to_json().unwrap()).unwrap();
+    value["model"]["unseen_log_prob"][0] = serde_json::json!([-1.0]);
+    assert!(rejected(CategoricalNb::from_json(&value.to_string()).err().unwrap()));
+}
+
+#[test]
+fn neural_net_round_trip() {
+    let mut data_frame = DataFrame::new();
+    data_frame.new_column(DataType::Floats(vec![0.0, 1.0]), 0);
+    data_frame.new_column(DataType::Floats(vec![1.0, 0.0]), 1);
+    let net = NeuralNet::new(&data_frame, vec![1], vec![3], vec![ActivationFunction::Sigmoid, ActivationFunction::Linear], CostFunction::MSE, 0.1, OutputMap::ArgMax, 1);
+
+    let json = net.to_json().unwrap();
+    let mut loaded = NeuralNet::<ActivationFunction>::from_json(&json).unwrap();
+    assert_eq!(loaded.weight_matrices, net.weight_matrices);
+    assert_eq!(loaded.get_layer_detes(), &vec![1, 3, 1]);
+    loaded.predict_float(&vec![0.5]);
+
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["model"]["weight_matrices"][1]["cols"] = serde_json::json!(4);
+    assert!(NeuralNet::<ActivationFunction>::from_json(&value.to_string()).is_err());
+}
+
+#[test]
+fn metadata_keeps_nan_ranges_of_string_columns() {
+    let mut data_frame = DataFrame::new();
+    data_frame.new_column(DataType::Floats(vec![2.0, 4.0]), 0);
+    data_frame.new_column(DataType::Strings(vec!["a".to_owned(), "b".to_owned()]), 1);
+
+    let loaded = DataFrameMetadata::from_json(&data_frame.metadata().to_json().unwrap()).unwrap();
+    assert_eq!(loaded.max_vector[0], 4.0);
+    assert!(loaded.max_vector[1].is_nan() && loaded.min_vector[1].is_nan());
+}
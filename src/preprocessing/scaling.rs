@@ -0,0 +1,138 @@
+//! #Feature scaling#
+//!
+//! Transformers that learn per-column statistics in `fit` and rescale every column in `transform`,
+//! so the test set is scaled with the numbers learned on the training set.
+
+use serde::{Deserialize, Serialize};
+use crate::{n_dimen::matrix::Matrix, trait_definition::{Transformer, MLError}};
+
+
+///Scales every column to zero mean and unit variance, `(x - mean) / std_dev`.
+///Columns with no variance are only centred.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StandardScaler {
+    means: Vec<f32>,
+    std_devs: Vec<f32>,
+}
+
+impl StandardScaler {
+    pub fn new() -> StandardScaler {
+        StandardScaler::default()
+    }
+
+    pub fn means(&self) -> &[f32] {
+        &self.means
+    }
+
+    pub fn std_devs(&self) -> &[f32] {
+        &self.std_devs
+    }
+}
+
+impl Transformer for StandardScaler {
+    fn fit(&mut self, X : &Matrix) -> Result<(), MLError> {
+        if X.is_empty() {
+            return Err(MLError::EmptyInput);
+        }
+        let number_of_samples = X.rows() as f32;
+        let mut means = vec![0.0_f32; X.cols()];
+        for row in X.iter_rows() {
+            for (mean, value) in means.iter_mut().zip(row) {
+                *mean += value;
+            }
+        }
+        means.iter_mut().for_each(|mean| *mean /= number_of_samples);
+
+        let mut std_devs = vec![0.0_f32; X.cols()];
+        for row in X.iter_rows() {
+            for ((std_dev, mean), value) in std_devs.iter_mut().zip(&means).zip(row) {
+                *std_dev += (value - mean) * (value - mean);
+            }
+        }
+        std_devs.iter_mut().for_each(|std_dev| *std_dev = (*std_dev / number_of_samples).sqrt());
+
+        self.means = means;
+        self.std_devs = std_devs;
+        Ok(())
+    }
+
+    fn transform(&self, X : &Matrix) -> Result<Matrix, MLError> {
+        check_columns(self.means.len(), X)?;
+        let mut out = X.clone();
+        for row in out.iter_rows_mut() {
+            for ((value, mean), std_dev) in row.iter_mut().zip(&self.means).zip(&self.std_devs) {
+                *value -= mean;
+                if *std_dev > 0.0 {
+                    *value /= std_dev;
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+
+///Scales every column into the `[0, 1]` range, `(x - min) / (max - min)`.
+///Constant columns are mapped to 0.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MinMaxScaler {
+    mins: Vec<f32>,
+    maxs: Vec<f32>,
+}
+
+impl MinMaxScaler {
+    pub fn new() -> MinMaxScaler {
+        MinMaxScaler::default()
+    }
+
+    pub fn mins(&self) -> &[f32] {
+        &self.mins
+    }
+
+    pub fn maxs(&self) -> &[f32] {
+        &self.maxs
+    }
+}
+
+impl Transformer for MinMaxScaler {
+    fn fit(&mut self, X : &Matrix) -> Result<(), MLError> {
+        if X.is_empty() {
+            return Err(MLError::EmptyInput);
+        }
+        let mut mins = vec![f32::MAX; X.cols()];
+        let mut maxs = vec![f32::MIN; X.cols()];
+        for row in X.iter_rows() {
+            for ((min, max), value) in mins.iter_mut().zip(maxs.iter_mut()).zip(row) {
+                *min = min.min(*value);
+                *max = max.max(*value);
+            }
+        }
+        self.mins = mins;
+        self.maxs = maxs;
+        Ok(())
+    }
+
+    fn transform(&self, X : &Matrix) -> Result<Matrix, MLError> {
+        check_columns(self.mins.len(), X)?;
+        let mut out = X.clone();
+        for row in out.iter_rows_mut() {
+            for ((value, min), max) in row.iter_mut().zip(&self.mins).zip(&self.maxs) {
+                let range = max - min;
+                *value = if range > 0.0 { (*value - min) / range } else { 0.0 };
+            }
+        }
+        Ok(out)
+    }
+}
+
+
+///`fitted_columns` is 0 before `fit`.
+fn check_columns(fitted_columns : usize, X : &Matrix) -> Result<(), MLError> {
+    if fitted_columns == 0 {
+        return Err(MLError::NotFitted);
+    }
+    if fitted_columns != X.cols() {
+        return Err(MLError::FeatureMismatch { expected: fitted_columns, found: X.cols() });
+    }
+    Ok(())
+}
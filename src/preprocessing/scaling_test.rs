@@ -0,0 +1,40 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, trait_definition::{Transformer, MLError}};
+use super::scaling::{StandardScaler, MinMaxScaler};
+
+
+fn close(a : f32, b : f32) -> bool {
+    (a - b).abs() < 1e-5
+}
+
+#[test]
+fn standard_scaler_centres_and_scales() {
+    let X = Matrix::from(vec![vec![1.0, 5.0], vec![2.0, 5.0], vec![3.0, 5.0]]);
+    let mut scaler = StandardScaler::new();
+    let scaled = scaler.fit_transform(&X).unwrap();
+    assert_eq!(scaler.means(), &[2.0, 5.0]);
+    let column = scaled.column(0);
+    assert!(close(column.iter().sum::<f32>(), 0.0));
+    assert!(close(column[2], (1.5_f32).sqrt()));
+    //a constant column is only centred.
+    assert_eq!(scaled.column(1), vec![0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn min_max_scaler_uses_the_training_range() {
+    let mut scaler = MinMaxScaler::new();
+    scaler.fit(&Matrix::from(vec![vec![0.0, -1.0], vec![10.0, 1.0]])).unwrap();
+    let scaled = scaler.transform(&Matrix::from(vec![vec![5.0, 0.0], vec![20.0, -1.0]])).unwrap();
+    assert_eq!(scaled.to_rows(), vec![vec![0.5, 0.5], vec![2.0, 0.0]]);
+}
+
+#[test]
+fn scalers_check_their_input() {
+    let X = Matrix::from(vec![vec![1.0, 2.0]]);
+    assert_eq!(StandardScaler::new().transform(&X), Err(MLError::NotFitted));
+    let mut scaler = MinMaxScaler::new();
+    assert_eq!(scaler.fit(&Matrix::new(0, 0)), Err(MLError::EmptyInput));
+    scaler.fit(&X).unwrap();
+    assert_eq!(scaler.transform(&Matrix::from(vec![vec![1.0]])), Err(MLError::FeatureMismatch { expected: 2, found: 1 }));
+}
@@ -0,0 +1,376 @@
+//! #Probability calibration#
+//!
+//! `CalibratedClassifier` wraps a classifier with probabilities and maps its probabilities onto ones that match how often
+//! the classes actually occur. The calibration is learnt with cross-validation : for every fold a copy of the classifier is
+//! fitted on the other folds and a `Calibrator` on the held out probabilities, so it never learns from probabilities the
+//! classifier gave for its own training samples.
+//!
+//! * `CalibrationMethod::Sigmoid` -> Platt scaling, `1 / (1 + exp(a * p + b))`. Needs little data, but can only fix
+//!   probabilities that are off in a sigmoid shape(like the overconfident ones of naive Bayes).
+//! * `CalibrationMethod::Isotonic` -> the best non-decreasing step function, fits any monotonic distortion but
+//!   overfits on small data sets.
+//!
+//! Two classes calibrate the probability of the second class and the first one gets the rest. With more classes every class
+//! is calibrated against the others and the probabilities are normalised to sum to 1.
+//! With `ensemble` the(classifier, calibrators) pair of every fold is kept and their probabilities are averaged. Without it
+//! the calibrators are fitted on the out-of-fold probabilities of all the samples and the classifier is refitted on the whole set.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::{matrix::Matrix, optimise::lbfgs},
+    supervised::ensemble::common::{select_targets, encode_classes, argmax, fit_member, k_folds}};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CalibrationMethod {
+    Sigmoid,
+    Isotonic,
+}
+
+impl CalibrationMethod {
+    fn name(&self) -> &'static str {
+        match self {
+            CalibrationMethod::Sigmoid => "sigmoid",
+            CalibrationMethod::Isotonic => "isotonic",
+        }
+    }
+
+    fn from_name(name : &str) -> Result<CalibrationMethod, MLError> {
+        match name {
+            "sigmoid" => Ok(CalibrationMethod::Sigmoid),
+            "isotonic" => Ok(CalibrationMethod::Isotonic),
+            _ => Err(MLError::InvalidParameter(format!("unknown calibration method '{}', use sigmoid or isotonic", name))),
+        }
+    }
+}
+
+
+///maps an uncalibrated probability(or any score) of a class onto a calibrated probability.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Calibrator {
+    ///`1 / (1 + exp(a * score + b))`.
+    Sigmoid { a : f64, b : f64 },
+    ///the fitted values at the increasing `thresholds`, linearly interpolated in between and constant outside of them.
+    Isotonic { thresholds : Vec<f32>, values : Vec<f32> },
+}
+
+impl Calibrator {
+
+    ///fits a calibrator of the given kind, `positives` are 1 for the samples of the class and 0 for the rest.
+    pub fn fit(method : CalibrationMethod, scores : &[f32], positives : &[f32], sample_weights : &[f32]) -> Result<Calibrator, MLError> {
+        if scores.len() != positives.len() {
+            return Err(MLError::SampleMismatch { samples: scores.len(), targets: positives.len() });
+        }
+        if scores.is_empty() {
+            return Err(MLError::EmptyInput);
+        }
+        check_sample_weights(sample_weights, scores.len())?;
+        if sample_weights.iter().sum::<f32>() <= 0.0 {
+            return Err(MLError::InvalidParameter("the calibration samples have no weight".to_owned()));
+        }
+        Ok(match method {
+            CalibrationMethod::Sigmoid => Calibrator::sigmoid(scores, positives, sample_weights),
+            CalibrationMethod::Isotonic => Calibrator::isotonic(scores, positives, sample_weights),
+        })
+    }
+
+    ///Platt's fit of the sigmoid, on the targets `(n+ + 1) / (n+ + 2)` and `1 / (n- + 2)` instead of 1 and 0 so a
+    ///separable set does not push the sigmoid into a step.
+    fn sigmoid(scores : &[f32], positives : &[f32], sample_weights : &[f32]) -> Calibrator {
+        let positive_weight: f64 = positives.iter().zip(sample_weights).map(|(y, w)| (y * w) as f64).sum();
+        let negative_weight: f64 = sample_weights.iter().map(|w| *w as f64).sum::<f64>() - positive_weight;
+        let high = (positive_weight + 1.0) / (positive_weight + 2.0);
+        let low = 1.0 / (negative_weight + 2.0);
+        let targets: Vec<f64> = positives.iter().map(|y| if *y > 0.5 { high } else { low }).collect();
+
+        let objective = |theta : &[f64]| {
+            let (mut loss, mut gradient) = (0.0, vec![0.0; 2]);
+            for ((score, target), weight) in scores.iter().zip(&targets).zip(sample_weights) {
+                let (score, weight) = (*score as f64, *weight as f64);
+                let z = theta[0] * score + theta[1];
+                //p = 1 / (1 + exp(z)), written so neither exp overflows.
+                let log_p = -z.max(0.0) - (-z.abs()).exp().ln_1p();
+                let log_not_p = log_p + z;
+                loss -= weight * (target * log_p + (1.0 - target) * log_not_p);
+                let p = log_p.exp();
+                gradient[0] += weight * (target - p) * score;
+                gradient[1] += weight * (target - p);
+            }
+            (loss, gradient)
+        };
+        let mut theta = vec![0.0, ((negative_weight + 1.0) / (positive_weight + 1.0)).ln()];
+        lbfgs(objective, &mut theta, 5, 200, 1e-8);
+        Calibrator::Sigmoid { a: theta[0], b: theta[1] }
+    }
+
+    ///pool adjacent violators on the samples sorted by score, tied scores are pooled from the start.
+    fn isotonic(scores : &[f32], positives : &[f32], sample_weights : &[f32]) -> Calibrator {
+        let mut order: Vec<usize> = (0..scores.len()).collect();
+        order.sort_by(|a, b| scores[*a].partial_cmp(&scores[*b]).unwrap());
+        //(first threshold, last threshold, weighted mean, weight) of every block.
+        let mut blocks: Vec<(f32, f32, f64, f64)> = vec![];
+        for sample in order {
+            let (score, y, weight) = (scores[sample], positives[sample] as f64, sample_weights[sample] as f64);
+            if weight == 0.0 {
+                continue;
+            }
+            match blocks.last_mut() {
+                Some(last) if last.1 == score => {
+                    last.2 = (last.2 * last.3 + y * weight) / (last.3 + weight);
+                    last.3 += weight;
+                },
+                _ => blocks.push((score, score, y, weight)),
+            }
+            while blocks.len() > 1 && blocks[blocks.len() - 2].2 >= blocks[blocks.len() - 1].2 {
+                let last = blocks.pop().unwrap();
+                let previous = blocks.last_mut().unwrap();
+                previous.2 = (previous.2 * previous.3 + last.2 * last.3) / (previous.3 + last.3);
+                previous.3 += last.3;
+                previous.1 = last.1;
+            }
+        }
+        //a block is flat from its first to its last score, so both ends are kept for the interpolation.
+        let (mut thresholds, mut values) = (vec![], vec![]);
+        for (first, last, value, _) in blocks {
+            thresholds.push(first);
+            values.push(value as f32);
+            if last > first {
+                thresholds.push(last);
+                values.push(value as f32);
+            }
+        }
+        Calibrator::Isotonic { thresholds, values }
+    }
+
+    ///the calibrated probability of a score.
+    pub fn transform(&self, score : f32) -> f32 {
+        match self {
+            Calibrator::Sigmoid { a, b } => (1.0 / (1.0 + (a * score as f64 + b).exp())) as f32,
+            Calibrator::Isotonic { thresholds, values } => {
+                let upper = thresholds.partition_point(|threshold| *threshold < score);
+                if upper == 0 {
+                    values[0]
+                } else if upper == thresholds.len() {
+                    values[values.len() - 1]
+                } else {
+                    let (x0, x1) = (thresholds[upper - 1], thresholds[upper]);
+                    values[upper - 1] + (values[upper] - values[upper - 1]) * (score - x0) / (x1 - x0)
+                }
+            },
+        }
+    }
+}
+
+
+///the calibrated probabilities of a point, from the uncalibrated ones.
+fn calibrate(calibrators : &[Calibrator], uncalibrated : &[f32]) -> Vec<f32> {
+    if uncalibrated.len() == 2 {
+        let positive = calibrators[0].transform(uncalibrated[1]).clamp(0.0, 1.0);
+        return vec![1.0 - positive, positive];
+    }
+    let calibrated: Vec<f32> = calibrators.iter().zip(uncalibrated).map(|(calibrator, p)| calibrator.transform(*p).clamp(0.0, 1.0)).collect();
+    let total: f32 = calibrated.iter().sum();
+    if total > 0.0 {
+        calibrated.iter().map(|p| p / total).collect()
+    } else {
+        vec![1.0 / calibrated.len() as f32; calibrated.len()]
+    }
+}
+
+///the uncalibrated probabilities of the classifier for a point, which need to cover every class.
+fn base_probabilities(estimator : &dyn Estimator, n_classes : usize, point : &[f32]) -> Result<Vec<f32>, MLError> {
+    match estimator.probabilities(point) {
+        Some(probabilities) if probabilities.len() == n_classes => Ok(probabilities),
+        Some(probabilities) => Err(MLError::InvalidParameter(format!("the classifier gave {} probabilities for {} classes, every fold needs every class", probabilities.len(), n_classes))),
+        None => Err(MLError::InvalidParameter("the classifier has no probabilities to calibrate".to_owned())),
+    }
+}
+
+///one calibrator for two classes(the second class), one per class otherwise.
+fn fit_calibrators(method : CalibrationMethod, probabilities : &[Vec<f32>], labels : &[usize], sample_weights : &[f32]) -> Result<Vec<Calibrator>, MLError> {
+    let n_classes = probabilities[0].len();
+    let calibrated_classes = if n_classes == 2 { 1..2 } else { 0..n_classes };
+    calibrated_classes.map(|class| {
+        let scores: Vec<f32> = probabilities.iter().map(|row| row[class]).collect();
+        let positives: Vec<f32> = labels.iter().map(|label| (*label == class) as u8 as f32).collect();
+        Calibrator::fit(method, &scores, &positives, sample_weights)
+    }).collect()
+}
+
+
+#[derive(Clone)]
+pub struct CalibratedClassifier {
+    pub method : CalibrationMethod,
+    ///number of cross-validation folds.
+    pub n_folds : usize,
+    ///keep the classifier and calibrators of every fold and average them, instead of one refitted classifier.
+    pub ensemble : bool,
+    ///seed for dealing the samples into folds.
+    pub seed : u64,
+    base : Box<dyn Estimator>,
+    ///(classifier, calibrators) pairs, one per fold with `ensemble` and a single one without.
+    members : Vec<(Box<dyn Estimator>, Vec<Calibrator>)>,
+    classes : Option<DataType>,
+    n_features : usize,
+}
+
+impl CalibratedClassifier {
+
+    ///calibrates the given classifier, which needs to give probabilities. 5 folds with `ensemble` by default.
+    pub fn new<E : Estimator + 'static>(estimator : E, method : CalibrationMethod) -> CalibratedClassifier {
+        CalibratedClassifier {
+            method,
+            n_folds: 5,
+            ensemble: true,
+            seed: 0,
+            base: Box::new(estimator),
+            members: vec![],
+            classes: None,
+            n_features: 0,
+        }
+    }
+
+    pub fn set_folds(&mut self, n_folds : usize, seed : u64) {
+        self.n_folds = n_folds;
+        self.seed = seed;
+    }
+
+    pub fn set_ensemble(&mut self, ensemble : bool) {
+        self.ensemble = ensemble;
+    }
+
+    ///the classifier as it was given, unfitted.
+    pub fn base_estimator(&self) -> &dyn Estimator {
+        self.base.as_ref()
+    }
+
+    ///the fitted(classifier, calibrators) pairs.
+    pub fn calibrated_members(&self) -> Vec<(&dyn Estimator, &[Calibrator])> {
+        self.members.iter().map(|(estimator, calibrators)| (estimator.as_ref(), calibrators.as_slice())).collect()
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///the calibrated probabilities in the order of `classes()`, averaged over the members.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        let n_classes = self.classes.as_ref().unwrap().len();
+        let mut probabilities = vec![0.0; n_classes];
+        for (estimator, calibrators) in &self.members {
+            let uncalibrated = base_probabilities(estimator.as_ref(), n_classes, point).unwrap_or_else(|error| panic!("{}", error));
+            probabilities.iter_mut().zip(calibrate(calibrators, &uncalibrated)).for_each(|(total, p)| *total += p);
+        }
+        probabilities.iter().map(|p| p / self.members.len() as f32).collect()
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        if let Some(sample_weights) = sample_weights {
+            check_sample_weights(sample_weights, X_train.rows())?;
+        }
+        if self.n_folds < 2 || self.n_folds > X_train.rows() {
+            return Err(MLError::InvalidParameter(format!("n_folds needs to be in [2, {}], got {}", X_train.rows(), self.n_folds)));
+        }
+        let (classes, labels) = encode_classes(y_train, "a calibrated classifier")?;
+        if classes.len() < 2 {
+            return Err(MLError::InvalidTarget("calibration needs atleast two classes".to_owned()));
+        }
+        self.classes = None;
+        let n_classes = classes.len();
+        let weights = sample_weights.map(|weights| weights.to_vec()).unwrap_or_else(|| vec![1.0; X_train.rows()]);
+        let folds = k_folds(y_train, self.n_folds, self.seed, true);
+
+        //a copy of the classifier per fold, fitted on the other folds, with its probabilities for the held out samples.
+        let fitted: Vec<(Box<dyn Estimator>, Vec<Vec<f32>>)> = (0..self.n_folds).into_par_iter().map(|fold| {
+            let train: Vec<usize> = folds.iter().enumerate().filter(|(other, _)| *other != fold).flat_map(|(_, samples)| samples.iter().copied()).collect();
+            let mut copy = self.base.clone_box();
+            let train_weights = sample_weights.map(|weights| train.iter().map(|sample| weights[*sample]).collect::<Vec<f32>>());
+            fit_member("the classifier", copy.as_mut(), &X_train.select_rows(&train), &select_targets(y_train, &train), train_weights.as_deref())?;
+            let held_out = folds[fold].iter().map(|sample| base_probabilities(copy.as_ref(), n_classes, X_train.row(*sample))).collect::<Result<_, MLError>>()?;
+            Ok((copy, held_out))
+        }).collect::<Result<_, MLError>>()?;
+
+        let pick = |samples : &[usize], values : &[usize]| samples.iter().map(|sample| values[*sample]).collect::<Vec<usize>>();
+        let pick_weights = |samples : &[usize]| samples.iter().map(|sample| weights[*sample]).collect::<Vec<f32>>();
+        self.members = if self.ensemble {
+            fitted.into_iter().zip(&folds).map(|((estimator, held_out), fold)| {
+                let calibrators = fit_calibrators(self.method, &held_out, &pick(fold, &labels), &pick_weights(fold))?;
+                Ok((estimator, calibrators))
+            }).collect::<Result<_, MLError>>()?
+        } else {
+            let mut probabilities = vec![vec![]; X_train.rows()];
+            for ((_, held_out), fold) in fitted.into_iter().zip(&folds) {
+                fold.iter().zip(held_out).for_each(|(sample, row)| probabilities[*sample] = row);
+            }
+            let calibrators = fit_calibrators(self.method, &probabilities, &labels, &weights)?;
+            let mut estimator = self.base.clone_box();
+            fit_member("the classifier", estimator.as_mut(), X_train, y_train, sample_weights)?;
+            vec![(estimator, calibrators)]
+        };
+        self.classes = Some(classes);
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+}
+
+impl MLalgo for CalibratedClassifier {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    ///the calibrators always take the weights, the classifier needs to as well.
+    fn supports_sample_weights(&self) -> bool {
+        self.base.supports_sample_weights()
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("method".to_owned(), ParamValue::Text(self.method.name().to_owned()));
+        params.insert("n_folds".to_owned(), ParamValue::Int(self.n_folds as i64));
+        params.insert("ensemble".to_owned(), ParamValue::Bool(self.ensemble));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "method" => self.method = CalibrationMethod::from_name(value.as_str(name)?)?,
+                "n_folds" => self.n_folds = value.as_usize(name)?,
+                "ensemble" => self.ensemble = value.as_bool(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predict for CalibratedClassifier {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let probabilities = self.predict_proba(point);
+        self.classes.as_ref().unwrap().value_at(argmax(&probabilities))
+    }
+}
+
+impl Estimator for CalibratedClassifier {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
@@ -0,0 +1,154 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::data_type::DataType,
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue}, evaluation::{accuracy::accuracy_score,
+        calibration::{brier_score, multiclass_brier_score, reliability_curve, Binning}},
+    supervised::{naive_bayes::gaussian_NB::GaussianNb, support_vector_machines::support_vector_machines::{Svc, Kernel}}};
+use crate::supervised::test_fixtures::blobs;
+use super::calibration::{CalibratedClassifier, CalibrationMethod, Calibrator};
+
+
+///two overlapping classes on one noisy feature, repeated `copies` times so naive Bayes counts the same evidence over and
+///over and gets overconfident.
+fn repeated_feature(seed : u64, samples : usize, copies : usize) -> (Matrix, DataType) {
+    let rng = fastrand::Rng::with_seed(seed);
+    let (mut rows, mut targets) = (vec![], vec![]);
+    for sample in 0..samples {
+        let class = sample % 2;
+        //Box-Muller, a normal spread of 1 around the class.
+        let x = class as f32 + (-2.0 * (1.0 - rng.f32()).ln()).sqrt() * (std::f32::consts::TAU * rng.f32()).cos();
+        rows.push(vec![x; copies]);
+        targets.push(class as u8);
+    }
+    (Matrix::from(rows), DataType::Category(targets))
+}
+
+fn positives(y : &DataType) -> Vec<f32> {
+    match y {
+        DataType::Category(targets) => targets.iter().map(|target| *target as f32).collect(),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn calibrators_fit_monotonic_maps() {
+    //the positives get more likely with the score, but only half as fast as the score claims.
+    let scores: Vec<f32> = (0..200).map(|sample| sample as f32 / 199.0).collect();
+    let labels: Vec<f32> = scores.iter().enumerate().map(|(sample, score)| ((sample * 7 % 10) as f32 / 10.0 < 0.25 + score * 0.5) as u8 as f32).collect();
+    let weights = vec![1.0; scores.len()];
+
+    let isotonic = Calibrator::fit(CalibrationMethod::Isotonic, &scores, &labels, &weights).unwrap();
+    let mapped: Vec<f32> = scores.iter().map(|score| isotonic.transform(*score)).collect();
+    assert!(mapped.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(mapped[0] < 0.5 && mapped[199] > 0.5 && mapped[199] < 1.0 + 1e-6);
+    //constant outside of the fitted range.
+    assert_eq!(isotonic.transform(-1.0), mapped[0]);
+    assert_eq!(isotonic.transform(2.0), mapped[199]);
+
+    let sigmoid = Calibrator::fit(CalibrationMethod::Sigmoid, &scores, &labels, &weights).unwrap();
+    let (low, middle, high) = (sigmoid.transform(0.0), sigmoid.transform(0.5), sigmoid.transform(1.0));
+    assert!(low < middle && middle < high);
+    assert!(low > 0.1 && high < 0.9, "{} {}", low, high);
+    assert!((middle - 0.5).abs() < 0.1);
+
+    //tied scores are pooled and weights of 0 are left out.
+    let pooled = Calibrator::fit(CalibrationMethod::Isotonic, &[0.5, 0.5, 0.9], &[0.0, 1.0, 0.0], &[1.0, 1.0, 0.0]).unwrap();
+    assert_eq!(pooled, Calibrator::Isotonic { thresholds: vec![0.5], values: vec![0.5] });
+
+    assert!(matches!(Calibrator::fit(CalibrationMethod::Sigmoid, &[], &[], &[]), Err(MLError::EmptyInput)));
+    assert!(matches!(Calibrator::fit(CalibrationMethod::Sigmoid, &[0.1], &[1.0, 0.0], &[1.0]), Err(MLError::SampleMismatch { .. })));
+    assert!(matches!(Calibrator::fit(CalibrationMethod::Isotonic, &[0.1], &[1.0], &[0.0]), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn calibration_fixes_overconfident_naive_bayes() {
+    let (X, y) = repeated_feature(0, 600, 8);
+    let (X_test, y_test) = repeated_feature(1, 2000, 8);
+    let outcomes = positives(&y_test);
+
+    let mut bayes = GaussianNb::new();
+    bayes.fit(&X, &y).unwrap();
+    let uncalibrated: Vec<f32> = X_test.iter_rows().map(|row| bayes.predict_proba(row).unwrap()[1]).collect();
+
+    for method in [CalibrationMethod::Sigmoid, CalibrationMethod::Isotonic] {
+        let mut model = CalibratedClassifier::new(GaussianNb::new(), method);
+        assert!(!model.is_fitted());
+        model.fit(&X, &y).unwrap();
+        assert_eq!(model.calibrated_members().len(), 5);
+        assert!(accuracy_score(&model, &X_test, &y_test) > 0.65);
+        let calibrated: Vec<f32> = X_test.iter_rows().map(|row| model.predict_proba(row)[1]).collect();
+        assert!(brier_score(&outcomes, &calibrated) < brier_score(&outcomes, &uncalibrated));
+        let before = reliability_curve(&outcomes, &uncalibrated, 10, Binning::Uniform).expected_calibration_error();
+        let after = reliability_curve(&outcomes, &calibrated, 10, Binning::Quantile).expected_calibration_error();
+        assert!(after < before && after < 0.1, "{:?} {} -> {}", method, before, after);
+    }
+}
+
+#[test]
+fn calibrated_multiclass_without_ensemble() {
+    let (X, y) = blobs(2, 300);
+    let (X_test, y_test) = blobs(3, 300);
+    let mut model = CalibratedClassifier::new(GaussianNb::new(), CalibrationMethod::Sigmoid);
+    model.set_ensemble(false);
+    model.set_folds(3, 7);
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.calibrated_members().len(), 1);
+    assert_eq!(model.calibrated_members()[0].1.len(), 3);
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.8);
+    let labels: Vec<usize> = positives(&y_test).iter().map(|label| *label as usize).collect();
+    let probabilities: Vec<Vec<f32>> = X_test.iter_rows().map(|row| model.probabilities(row).unwrap()).collect();
+    assert!(probabilities.iter().all(|row| (row.iter().sum::<f32>() - 1.0).abs() < 1e-5));
+    assert!(multiclass_brier_score(&labels, &probabilities) < 0.3);
+
+    let mut params = Params::new();
+    params.insert("method".to_owned(), ParamValue::Text("isotonic".to_owned()));
+    params.insert("n_folds".to_owned(), ParamValue::Int(4));
+    model.set_params(&params).unwrap();
+    assert_eq!(model.method, CalibrationMethod::Isotonic);
+    assert_eq!(model.get_params()["n_folds"], ParamValue::Int(4));
+    params.insert("method".to_owned(), ParamValue::Text("beta".to_owned()));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn calibration_rejects_bad_input() {
+    let (X, y) = blobs(4, 60);
+    //a classifier without probabilities has nothing to calibrate.
+    let mut model = CalibratedClassifier::new(Svc::new(Kernel::Linear, 1.0), CalibrationMethod::Sigmoid);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    assert!(!model.is_fitted());
+
+    let mut model = CalibratedClassifier::new(GaussianNb::new(), CalibrationMethod::Isotonic);
+    model.set_folds(1, 0);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    model.set_folds(3, 0);
+    assert!(matches!(model.fit(&X, &DataType::Category(vec![1; 60])), Err(MLError::InvalidTarget(_))));
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.n_features_in(), Some(2));
+}
+
+#[test]
+fn brier_score_and_reliability_curve() {
+    let y_true = [0.0, 0.0, 1.0, 1.0];
+    assert_eq!(brier_score(&y_true, &[0.0, 0.0, 1.0, 1.0]), 0.0);
+    assert_eq!(brier_score(&y_true, &[0.5; 4]), 0.25);
+    assert!((brier_score(&y_true, &[0.1, 0.3, 0.6, 0.8]) - (0.01 + 0.09 + 0.16 + 0.04) / 4.0).abs() < 1e-6);
+    let two_classes = vec![vec![0.9, 0.1], vec![0.7, 0.3], vec![0.4, 0.6], vec![0.2, 0.8]];
+    assert!((multiclass_brier_score(&[0, 0, 1, 1], &two_classes) - 2.0 * brier_score(&y_true, &[0.1, 0.3, 0.6, 0.8])).abs() < 1e-6);
+
+    let y_prob = [0.05, 0.15, 0.15, 0.85, 0.95, 0.95];
+    let outcomes = [0.0, 0.0, 1.0, 1.0, 1.0, 0.0];
+    let curve = reliability_curve(&outcomes, &y_prob, 5, Binning::Uniform);
+    assert_eq!(curve.counts, vec![3, 3]);
+    assert!((curve.mean_predicted[0] - 0.35 / 3.0).abs() < 1e-6 && (curve.fraction_positive[0] - 1.0 / 3.0).abs() < 1e-6);
+    assert!((curve.fraction_positive[1] - 2.0 / 3.0).abs() < 1e-6);
+    let curve = reliability_curve(&outcomes, &y_prob, 2, Binning::Quantile);
+    assert_eq!(curve.counts, vec![3, 3]);
+    //fewer samples than bins, every sample ends up in a bin of its own.
+    let curve = reliability_curve(&[0.0, 1.0, 1.0], &[0.2, 0.6, 0.9], 10, Binning::Quantile);
+    assert_eq!(curve.counts, vec![1, 1, 1]);
+    assert_eq!(curve.mean_predicted, vec![0.2, 0.6, 0.9]);
+    //perfectly calibrated bins have no error.
+    let calibrated = reliability_curve(&[0.0, 1.0, 1.0, 1.0], &[0.5, 0.5, 1.0, 1.0], 2, Binning::Uniform);
+    assert_eq!(calibrated.expected_calibration_error(), 0.0);
+}
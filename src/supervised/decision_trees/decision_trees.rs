@@ -0,0 +1,783 @@
+//! #Decision trees#
+//!
+//! CART(classification and regression trees), binary splits of the form `x[feature] <= threshold`.
+//! * `Category` and `Strings` targets grow a classification tree, split with `Gini` or `Entropy`.
+//! * `Floats` targets grow a regression tree, split with `MSE` or `MAE`.
+//!
+//! The tree is kept as a flat vector of nodes, the root is node 0.
+//! After growing it can be pruned with minimal cost-complexity pruning(`ccp_alpha`).
+//!
+//! `to_text` and `to_dot` dump the learned tree, the second one for Graphviz(`dot -Tpng tree.dot -o tree.png`).
+
+use std::fmt::Write;
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix,
+    persistence::persistence::{Persist, PersistError, ensure}};
+
+
+///How the quality of a split is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Criterion {
+    ///     1 - sum(p_k^2), classification.
+    Gini,
+    ///     -sum(p_k * log2(p_k)), classification.
+    Entropy,
+    ///mean squared error around the mean, regression.
+    MSE,
+    ///mean absolute error around the median, regression. Every candidate split needs a median so it is a lot slower than `MSE`.
+    MAE,
+}
+
+impl Criterion {
+    pub fn is_classification(&self) -> bool {
+        matches!(self, Criterion::Gini | Criterion::Entropy)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Criterion::Gini => "gini",
+            Criterion::Entropy => "entropy",
+            Criterion::MSE => "mse",
+            Criterion::MAE => "mae",
+        }
+    }
+
+    fn from_name(name : &str) -> Result<Criterion, MLError> {
+        match name {
+            "gini" => Ok(Criterion::Gini),
+            "entropy" => Ok(Criterion::Entropy),
+            "mse" => Ok(Criterion::MSE),
+            "mae" => Ok(Criterion::MAE),
+            _ => Err(MLError::InvalidParameter(format!("unknown criterion '{}'", name))),
+        }
+    }
+}
+
+
+///How many features are looked at when searching for the best split of a node, a new random subset is drawn for every node.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum MaxFeatures {
+    ///every feature, the usual CART.
+    #[default]
+    All,
+    ///sqrt(n_features), the usual choice for random forest classifiers.
+    Sqrt,
+    ///log2(n_features).
+    Log2,
+    ///this fraction of the features(atleast one).
+    Fraction(f32),
+    ///exactly this many features(atmost all of them).
+    Count(usize),
+}
+
+impl MaxFeatures {
+    ///the number of features to draw out of `n_features`.
+    pub fn count(&self, n_features : usize) -> usize {
+        let count = match self {
+            MaxFeatures::All => n_features,
+            MaxFeatures::Sqrt => (n_features as f32).sqrt() as usize,
+            MaxFeatures::Log2 => (n_features as f32).log2() as usize,
+            MaxFeatures::Fraction(fraction) => (fraction * n_features as f32) as usize,
+            MaxFeatures::Count(count) => *count,
+        };
+        count.clamp(1, n_features.max(1))
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NodeKind {
+    Leaf,
+    ///samples with `x[feature] <= threshold` go to `left`, the rest to `right`.
+    Split { feature : usize, threshold : f32, left : usize, right : usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub kind : NodeKind,
+    ///impurity of the samples that reached this node.
+    pub impurity : f32,
+    pub n_samples : usize,
+    ///sum of the sample weights that reached this node.
+    pub weight : f32,
+    ///class probabilities(in the order of the classes) for classification, `[mean]`(or `[median]` for MAE) for regression.
+    pub value : Vec<f32>,
+}
+
+impl Node {
+    pub fn is_leaf(&self) -> bool {
+        self.kind == NodeKind::Leaf
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionTree {
+    pub criterion : Criterion,
+    ///`None` grows until the leaves are pure or too small to split.
+    pub max_depth : Option<usize>,
+    ///a node with fewer samples is not split.
+    pub min_samples_split : usize,
+    ///a split that leaves fewer samples on either side is not considered.
+    pub min_samples_leaf : usize,
+    ///a node is split only if `(weight_node / weight_total) * (impurity - weighted child impurity)` is atleast this much.
+    pub min_impurity_decrease : f32,
+    ///complexity parameter of the cost-complexity pruning, 0 does not prune.
+    pub ccp_alpha : f32,
+    ///number of features considered at every split.
+    pub max_features : MaxFeatures,
+    ///seeds the feature subsampling, only used when `max_features` is not `All`.
+    pub seed : u64,
+    nodes : Vec<Node>,
+    ///sorted classes for a classification tree, `None` before fitting and for regression.
+    classes : Option<DataType>,
+    n_features : usize,
+    feature_importances : Vec<f32>,
+}
+
+///what the tree is being fitted to, class indices or values.
+#[derive(Clone, Copy)]
+enum Targets<'a> {
+    Classes(&'a [usize], usize),
+    Values(&'a [f32]),
+}
+
+impl DecisionTree {
+
+    ///a classification tree with the Gini criterion and no limits.
+    pub fn classifier() -> DecisionTree {
+        DecisionTree::new(Criterion::Gini)
+    }
+
+    ///a regression tree with the MSE criterion and no limits.
+    pub fn regressor() -> DecisionTree {
+        DecisionTree::new(Criterion::MSE)
+    }
+
+    pub fn new(criterion : Criterion) -> DecisionTree {
+        DecisionTree {
+            criterion,
+            max_depth: None,
+            min_samples_split: 2,
+            min_samples_leaf: 1,
+            min_impurity_decrease: 0.0,
+            ccp_alpha: 0.0,
+            max_features: MaxFeatures::All,
+            seed: 0,
+            nodes: vec![],
+            classes: None,
+            n_features: 0,
+            feature_importances: vec![],
+        }
+    }
+
+    pub fn set_criterion(&mut self, criterion : Criterion) {
+        self.criterion = criterion;
+    }
+
+    pub fn set_max_depth(&mut self, max_depth : Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn set_min_samples(&mut self, min_samples_split : usize, min_samples_leaf : usize) {
+        self.min_samples_split = min_samples_split;
+        self.min_samples_leaf = min_samples_leaf;
+    }
+
+    pub fn set_min_impurity_decrease(&mut self, min_impurity_decrease : f32) {
+        self.min_impurity_decrease = min_impurity_decrease;
+    }
+
+    pub fn set_ccp_alpha(&mut self, ccp_alpha : f32) {
+        self.ccp_alpha = ccp_alpha;
+    }
+
+    ///look at a random subset of the features at every split, drawn with the given seed.
+    pub fn set_max_features(&mut self, max_features : MaxFeatures, seed : u64) {
+        self.max_features = max_features;
+        self.seed = seed;
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    pub fn n_leaves(&self) -> usize {
+        self.nodes.iter().filter(|node| node.is_leaf()).count()
+    }
+
+    ///length of the longest path from the root to a leaf, a lone leaf has depth 0.
+    pub fn depth(&self) -> usize {
+        fn depth_of(nodes : &[Node], index : usize) -> usize {
+            match nodes[index].kind {
+                NodeKind::Leaf => 0,
+                NodeKind::Split { left, right, .. } => 1 + depth_of(nodes, left).max(depth_of(nodes, right)),
+            }
+        }
+        if self.nodes.is_empty() { 0 } else { depth_of(&self.nodes, 0) }
+    }
+
+    ///the total impurity decrease brought by each feature, normalised to sum to 1.
+    pub fn feature_importances(&self) -> &[f32] {
+        &self.feature_importances
+    }
+
+    ///index of the leaf the point ends up in.
+    pub fn apply(&self, point : &[f32]) -> usize {
+        assert_predict_input(self, point);
+        let mut index = 0;
+        while let NodeKind::Split { feature, threshold, left, right } = self.nodes[index].kind {
+            index = if point[feature] <= threshold { left } else { right };
+        }
+        index
+    }
+
+    ///class probabilities of the leaf the point ends up in, in the order of `classes()`. Panics for a regression tree.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert!(self.criterion.is_classification(), "predict_proba needs a classification tree");
+        self.nodes[self.apply(point)].value.clone()
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if self.min_samples_split < 2 {
+            return Err(MLError::InvalidParameter("min_samples_split needs to be atleast 2".to_owned()));
+        }
+        if self.min_samples_leaf < 1 {
+            return Err(MLError::InvalidParameter("min_samples_leaf needs to be atleast 1".to_owned()));
+        }
+        if !(self.min_impurity_decrease >= 0.0) || !(self.ccp_alpha >= 0.0) {
+            return Err(MLError::InvalidParameter("min_impurity_decrease and ccp_alpha need to be >= 0".to_owned()));
+        }
+        if let MaxFeatures::Fraction(fraction) = self.max_features {
+            if !(fraction > 0.0 && fraction <= 1.0) {
+                return Err(MLError::InvalidParameter(format!("the max_features fraction needs to be in (0, 1], got {}", fraction)));
+            }
+        }
+        Ok(())
+    }
+
+    //***************************************
+    //COST-COMPLEXITY PRUNING
+    //***************************************
+
+    ///`R(t)`, the weighted impurity of a node as a share of the whole training set.
+    fn node_risk(&self, index : usize) -> f32 {
+        self.nodes[index].weight / self.nodes[0].weight * self.nodes[index].impurity
+    }
+
+    ///(sum of the risks of the leaves, number of leaves) of the subtree under `index`.
+    fn subtree_risk(&self, index : usize) -> (f32, usize) {
+        match self.nodes[index].kind {
+            NodeKind::Leaf => (self.node_risk(index), 1),
+            NodeKind::Split { left, right, .. } => {
+                let (left_risk, left_leaves) = self.subtree_risk(left);
+                let (right_risk, right_leaves) = self.subtree_risk(right);
+                (left_risk + right_risk, left_leaves + right_leaves)
+            },
+        }
+    }
+
+    ///the internal node with the smallest effective alpha `(R(t) - R(T_t)) / (leaves(T_t) - 1)`, the weakest link.
+    fn weakest_link(&self) -> Option<(usize, f32)> {
+        let mut weakest: Option<(usize, f32)> = None;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            if let NodeKind::Split { left, right, .. } = self.nodes[index].kind {
+                let (risk, leaves) = self.subtree_risk(index);
+                let alpha = ((self.node_risk(index) - risk) / (leaves - 1) as f32).max(0.0);
+                if weakest.map_or(true, |(_, smallest)| alpha < smallest) {
+                    weakest = Some((index, alpha));
+                }
+                stack.push(right);
+                stack.push(left);
+            }
+        }
+        weakest
+    }
+
+    ///the effective alphas at which the subtrees get pruned and the total leaf impurity after every pruning step,
+    ///starting from the full tree. Fitting with a `ccp_alpha` between two consecutive alphas gives the same tree.
+    pub fn cost_complexity_pruning_path(&self) -> (Vec<f32>, Vec<f32>) {
+        let mut tree = self.clone();
+        let mut alphas = vec![0.0];
+        let mut impurities = vec![tree.subtree_risk(0).0];
+        while let Some((index, alpha)) = tree.weakest_link() {
+            tree.nodes[index].kind = NodeKind::Leaf;
+            alphas.push(alpha);
+            impurities.push(tree.subtree_risk(0).0);
+        }
+        (alphas, impurities)
+    }
+
+    ///prunes the weakest links while their effective alpha is atmost `ccp_alpha`.
+    pub fn prune(&mut self, ccp_alpha : f32) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        while let Some((index, alpha)) = self.weakest_link() {
+            if alpha > ccp_alpha {
+                break;
+            }
+            self.nodes[index].kind = NodeKind::Leaf;
+        }
+        self.compact();
+    }
+
+    ///drops the nodes that are no longer reachable after pruning and recomputes the feature importances.
+    fn compact(&mut self) {
+        fn copy(old : &[Node], index : usize, new : &mut Vec<Node>) -> usize {
+            let position = new.len();
+            new.push(old[index].clone());
+            if let NodeKind::Split { feature, threshold, left, right } = old[index].kind {
+                let left = copy(old, left, new);
+                let right = copy(old, right, new);
+                new[position].kind = NodeKind::Split { feature, threshold, left, right };
+            }
+            position
+        }
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        copy(&self.nodes, 0, &mut nodes);
+        self.nodes = nodes;
+
+        let mut importances = vec![0.0_f32; self.n_features];
+        for node in &self.nodes {
+            if let NodeKind::Split { feature, left, right, .. } = node.kind {
+                let (left, right) = (&self.nodes[left], &self.nodes[right]);
+                importances[feature] += node.weight * node.impurity - left.weight * left.impurity - right.weight * right.impurity;
+            }
+        }
+        self.feature_importances = normalise(importances);
+    }
+
+    //***************************************
+    //EXPORT
+    //***************************************
+
+    fn feature_name(feature_names : Option<&[&str]>, feature : usize) -> String {
+        match feature_names {
+            Some(names) if feature < names.len() => names[feature].to_owned(),
+            _ => format!("x{}", feature),
+        }
+    }
+
+    fn describe_value(&self, node : &Node) -> String {
+        match &self.classes {
+            Some(classes) => {
+                let best = node.value.iter().enumerate().fold(0, |best, (index, p)| if *p > node.value[best] { index } else { best });
+                match classes.value_at(best) {
+                    ReturnType::Strings(name) => format!("class: {}", name),
+                    ReturnType::Category(class) => format!("class: {}", class),
+                    ReturnType::Floats(value) => format!("class: {}", value),
+                }
+            },
+            None => format!("value: {}", node.value[0]),
+        }
+    }
+
+    ///the tree as indented text, one line per decision and leaf.
+    ///Features without a name in `feature_names` are called `x<index>`.
+    pub fn to_text(&self, feature_names : Option<&[&str]>) -> String {
+        fn walk(tree : &DecisionTree, index : usize, depth : usize, feature_names : Option<&[&str]>, out : &mut String) {
+            let indent = "|   ".repeat(depth);
+            match tree.nodes[index].kind {
+                NodeKind::Leaf => { let _ = writeln!(out, "{}|--- {}", indent, tree.describe_value(&tree.nodes[index])); },
+                NodeKind::Split { feature, threshold, left, right } => {
+                    let name = DecisionTree::feature_name(feature_names, feature);
+                    let _ = writeln!(out, "{}|--- {} <= {}", indent, name, threshold);
+                    walk(tree, left, depth + 1, feature_names, out);
+                    let _ = writeln!(out, "{}|--- {} >  {}", indent, name, threshold);
+                    walk(tree, right, depth + 1, feature_names, out);
+                },
+            }
+        }
+        let mut out = String::new();
+        if !self.nodes.is_empty() {
+            walk(self, 0, 0, feature_names, &mut out);
+        }
+        out
+    }
+
+    ///the tree in the Graphviz DOT language.
+    pub fn to_dot(&self, feature_names : Option<&[&str]>) -> String {
+        let mut out = String::from("digraph Tree {\nnode [shape=box, fontname=\"helvetica\"] ;\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            let mut label = String::new();
+            if let NodeKind::Split { feature, threshold, .. } = node.kind {
+                let _ = write!(label, "{} <= {}\\n", DecisionTree::feature_name(feature_names, feature), threshold);
+            }
+            let _ = write!(label, "{} = {:.4}\\nsamples = {}\\n{}", self.criterion.name(), node.impurity, node.n_samples, self.describe_value(node));
+            let _ = writeln!(out, "{} [label=\"{}\"] ;", index, label.replace('"', "\\\""));
+            if let NodeKind::Split { left, right, .. } = node.kind {
+                let _ = writeln!(out, "{} -> {} [labeldistance=2.5, labelangle=45, headlabel=\"True\"] ;", index, left);
+                let _ = writeln!(out, "{} -> {} [labeldistance=2.5, labelangle=-45, headlabel=\"False\"] ;", index, right);
+            }
+        }
+        out.push('}');
+        out.push('\n');
+        out
+    }
+}
+
+fn normalise(mut values : Vec<f32>) -> Vec<f32> {
+    let total: f32 = values.iter().sum();
+    if total > 0.0 {
+        values.iter_mut().for_each(|value| *value /= total);
+    }
+    values
+}
+
+
+//***************************************
+//GROWING THE TREE
+//***************************************
+
+struct Builder<'a> {
+    tree : &'a DecisionTree,
+    X : &'a Matrix,
+    targets : Targets<'a>,
+    weights : &'a [f32],
+    total_weight : f32,
+    nodes : Vec<Node>,
+    importances : Vec<f32>,
+    rng : fastrand::Rng,
+}
+
+///the best split found for a node.
+struct Split {
+    feature : usize,
+    threshold : f32,
+    ///weighted sum of the child impurities.
+    child_impurity : f32,
+    left : Vec<usize>,
+    right : Vec<usize>,
+}
+
+///weighted median and the mean absolute deviation around it.
+fn median_and_deviation(values : &mut [(f32, f32)]) -> (f32, f32) {
+    values.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let total: f32 = values.iter().map(|(_, weight)| weight).sum();
+    let mut running = 0.0;
+    let mut median = values[0].0;
+    for (value, weight) in values.iter() {
+        running += weight;
+        if running >= total / 2.0 {
+            median = *value;
+            break;
+        }
+    }
+    let deviation = values.iter().map(|(value, weight)| weight * (value - median).abs()).sum::<f32>() / total.max(f32::MIN_POSITIVE);
+    (median, deviation)
+}
+
+impl<'a> Builder<'a> {
+
+    fn class_impurity(&self, counts : &[f32], total : f32) -> f32 {
+        if total <= 0.0 {
+            return 0.0;
+        }
+        match self.tree.criterion {
+            Criterion::Entropy => counts.iter().filter(|count| **count > 0.0).map(|count| {
+                let p = count / total;
+                -p * p.log2()
+            }).sum(),
+            _ => 1.0 - counts.iter().map(|count| (count / total) * (count / total)).sum::<f32>(),
+        }
+    }
+
+    ///(value, impurity, weight) of the samples.
+    fn node_stats(&self, indices : &[usize]) -> (Vec<f32>, f32, f32) {
+        let weight: f32 = indices.iter().map(|index| self.weights[*index]).sum();
+        match self.targets {
+            Targets::Classes(classes, n_classes) => {
+                let mut counts = vec![0.0_f32; n_classes];
+                for index in indices {
+                    counts[classes[*index]] += self.weights[*index];
+                }
+                let impurity = self.class_impurity(&counts, weight);
+                (counts.iter().map(|count| count / weight).collect(), impurity, weight)
+            },
+            Targets::Values(values) if self.tree.criterion == Criterion::MAE => {
+                let mut pairs: Vec<(f32, f32)> = indices.iter().map(|index| (values[*index], self.weights[*index])).collect();
+                let (median, deviation) = median_and_deviation(&mut pairs);
+                (vec![median], deviation, weight)
+            },
+            Targets::Values(values) => {
+                let mean = indices.iter().map(|index| self.weights[*index] * values[*index]).sum::<f32>() / weight;
+                let variance = indices.iter().map(|index| self.weights[*index] * (values[*index] - mean).powi(2)).sum::<f32>() / weight;
+                (vec![mean], variance, weight)
+            },
+        }
+    }
+
+    ///the split of the samples with the smallest weighted child impurity, `None` if no split respects `min_samples_leaf`.
+    fn best_split(&self, indices : &[usize], features : &[usize]) -> Option<Split> {
+        let min_leaf = self.tree.min_samples_leaf;
+        let mut best: Option<(usize, f32, f32, usize)> = None;
+        let mut sorted = indices.to_vec();
+
+        for &feature in features {
+            sorted.sort_by(|a, b| self.X[(*a, feature)].total_cmp(&self.X[(*b, feature)]));
+            let mut candidate = |position : usize, child_impurity : f32| {
+                if best.map_or(true, |(_, _, impurity, _)| child_impurity < impurity - 1e-7) {
+                    let (value, next) = (self.X[(sorted[position], feature)], self.X[(sorted[position + 1], feature)]);
+                    //between two adjacent floats the midpoint can round up to `next`, which would send it left too.
+                    let midpoint = (value + next) / 2.0;
+                    let threshold = if midpoint < next { midpoint } else { value };
+                    best = Some((feature, threshold, child_impurity, position));
+                }
+            };
+
+            match self.targets {
+                Targets::Classes(classes, n_classes) => {
+                    let mut left = vec![0.0_f32; n_classes];
+                    let mut right = vec![0.0_f32; n_classes];
+                    for index in &sorted {
+                        right[classes[*index]] += self.weights[*index];
+                    }
+                    let (mut left_weight, mut right_weight) = (0.0_f32, right.iter().sum::<f32>());
+                    for position in 0..sorted.len() - 1 {
+                        let index = sorted[position];
+                        left[classes[index]] += self.weights[index];
+                        right[classes[index]] -= self.weights[index];
+                        left_weight += self.weights[index];
+                        right_weight -= self.weights[index];
+                        if position + 1 < min_leaf || sorted.len() - position - 1 < min_leaf
+                            || self.X[(index, feature)] == self.X[(sorted[position + 1], feature)] {
+                            continue;
+                        }
+                        let impurity = left_weight * self.class_impurity(&left, left_weight) + right_weight * self.class_impurity(&right, right_weight);
+                        candidate(position, impurity);
+                    }
+                },
+                Targets::Values(values) if self.tree.criterion == Criterion::MAE => {
+                    for position in 0..sorted.len() - 1 {
+                        if position + 1 < min_leaf || sorted.len() - position - 1 < min_leaf
+                            || self.X[(sorted[position], feature)] == self.X[(sorted[position + 1], feature)] {
+                            continue;
+                        }
+                        let mut impurity = 0.0;
+                        for side in [&sorted[..=position], &sorted[position + 1..]] {
+                            let mut pairs: Vec<(f32, f32)> = side.iter().map(|index| (values[*index], self.weights[*index])).collect();
+                            let weight: f32 = pairs.iter().map(|(_, weight)| weight).sum();
+                            impurity += weight * median_and_deviation(&mut pairs).1;
+                        }
+                        candidate(position, impurity);
+                    }
+                },
+                Targets::Values(values) => {
+                    //running weighted sums, the variance of a side is sum(w y^2)/W - (sum(w y)/W)^2.
+                    let (mut left_weight, mut left_sum, mut left_squares) = (0.0_f64, 0.0_f64, 0.0_f64);
+                    let (mut right_weight, mut right_sum, mut right_squares) = (0.0_f64, 0.0_f64, 0.0_f64);
+                    for index in &sorted {
+                        let (weight, value) = (self.weights[*index] as f64, values[*index] as f64);
+                        right_weight += weight;
+                        right_sum += weight * value;
+                        right_squares += weight * value * value;
+                    }
+                    for position in 0..sorted.len() - 1 {
+                        let index = sorted[position];
+                        let (weight, value) = (self.weights[index] as f64, values[index] as f64);
+                        left_weight += weight;
+                        left_sum += weight * value;
+                        left_squares += weight * value * value;
+                        right_weight -= weight;
+                        right_sum -= weight * value;
+                        right_squares -= weight * value * value;
+                        if position + 1 < min_leaf || sorted.len() - position - 1 < min_leaf
+                            || self.X[(index, feature)] == self.X[(sorted[position + 1], feature)] {
+                            continue;
+                        }
+                        let left_error = if left_weight > 0.0 { left_squares - left_sum * left_sum / left_weight } else { 0.0 };
+                        let right_error = if right_weight > 0.0 { right_squares - right_sum * right_sum / right_weight } else { 0.0 };
+                        candidate(position, (left_error.max(0.0) + right_error.max(0.0)) as f32);
+                    }
+                },
+            }
+        }
+
+        let (feature, threshold, child_impurity, _) = best?;
+        let (left, right) = indices.iter().partition(|index| self.X[(**index, feature)] <= threshold);
+        Some(Split { feature, threshold, child_impurity, left, right })
+    }
+
+    ///grows the subtree for the samples and returns the index of its root.
+    fn grow(&mut self, indices : Vec<usize>, depth : usize) -> usize {
+        let (value, impurity, weight) = self.node_stats(&indices);
+        let position = self.nodes.len();
+        self.nodes.push(Node { kind: NodeKind::Leaf, impurity, n_samples: indices.len(), weight, value });
+
+        let can_split = self.tree.max_depth.map_or(true, |max_depth| depth < max_depth)
+            && indices.len() >= self.tree.min_samples_split
+            && indices.len() >= 2 * self.tree.min_samples_leaf
+            && impurity > 1e-7;
+        if !can_split {
+            return position;
+        }
+        let mut features: Vec<usize> = (0..self.X.cols()).collect();
+        if self.tree.max_features != MaxFeatures::All {
+            //partial Fisher-Yates, the first `count` entries end up being a uniform random subset.
+            let count = self.tree.max_features.count(features.len());
+            for i in 0..count {
+                let j = self.rng.usize(i..features.len());
+                features.swap(i, j);
+            }
+            features.truncate(count);
+        }
+        let split = match self.best_split(&indices, &features) {
+            Some(split) => split,
+            None => return position,
+        };
+        let decrease = weight * impurity - split.child_impurity;
+        if decrease / self.total_weight < self.tree.min_impurity_decrease {
+            return position;
+        }
+
+        self.importances[split.feature] += decrease.max(0.0);
+        let left = self.grow(split.left, depth + 1);
+        let right = self.grow(split.right, depth + 1);
+        self.nodes[position].kind = NodeKind::Split { feature: split.feature, threshold: split.threshold, left, right };
+        position
+    }
+}
+
+
+impl MLalgo for DecisionTree {
+    ///`Category`/`Strings` targets need a classification criterion and `Floats` a regression one.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        self.validate_params()?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+
+        let encoded;
+        let (targets, classes) = match (y_train, self.criterion.is_classification()) {
+            (DataType::Floats(values), false) => (Targets::Values(values), None),
+            (DataType::Floats(_), true) => return Err(MLError::InvalidTarget(format!("the {} criterion needs category or string targets", self.criterion.name()))),
+            (_, false) => return Err(MLError::InvalidTarget(format!("the {} criterion needs float targets", self.criterion.name()))),
+            (_, true) => {
+                let (classes, indices) = y_train.unique_classes().unwrap();
+                encoded = indices;
+                (Targets::Classes(&encoded, classes.len()), Some(classes))
+            },
+        };
+
+        let mut builder = Builder {
+            tree: self,
+            X: X_train,
+            targets,
+            weights: sample_weights,
+            total_weight: sample_weights.iter().sum::<f32>().max(f32::MIN_POSITIVE),
+            nodes: vec![],
+            importances: vec![0.0; X_train.cols()],
+            rng: fastrand::Rng::with_seed(self.seed),
+        };
+        let indices: Vec<usize> = (0..X_train.rows()).filter(|index| sample_weights[*index] > 0.0).collect();
+        if indices.is_empty() {
+            return Err(MLError::EmptyInput);
+        }
+        builder.grow(indices, 0);
+        let (nodes, importances) = (builder.nodes, builder.importances);
+
+        self.nodes = nodes;
+        self.classes = classes;
+        self.n_features = X_train.cols();
+        if self.ccp_alpha > 0.0 {
+            self.prune(self.ccp_alpha);
+        } else {
+            self.feature_importances = normalise(importances);
+        }
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.nodes.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    ///`max_depth` is -1 when there is no limit.
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("criterion".to_owned(), ParamValue::Text(self.criterion.name().to_owned()));
+        params.insert("max_depth".to_owned(), ParamValue::Int(self.max_depth.map_or(-1, |depth| depth as i64)));
+        params.insert("min_samples_split".to_owned(), ParamValue::Int(self.min_samples_split as i64));
+        params.insert("min_samples_leaf".to_owned(), ParamValue::Int(self.min_samples_leaf as i64));
+        params.insert("min_impurity_decrease".to_owned(), ParamValue::Float(self.min_impurity_decrease));
+        params.insert("ccp_alpha".to_owned(), ParamValue::Float(self.ccp_alpha));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "criterion" => self.criterion = Criterion::from_name(value.as_str(name)?)?,
+                "max_depth" => self.max_depth = match value {
+                    ParamValue::Int(depth) if *depth < 0 => None,
+                    _ => Some(value.as_usize(name)?),
+                },
+                "min_samples_split" => self.min_samples_split = value.as_usize(name)?,
+                "min_samples_leaf" => self.min_samples_leaf = value.as_usize(name)?,
+                "min_impurity_decrease" => self.min_impurity_decrease = value.as_f32(name)?,
+                "ccp_alpha" => self.ccp_alpha = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for DecisionTree {
+    ///the majority class of the leaf for classification, its mean(median for MAE) for regression.
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let leaf = &self.nodes[self.apply(point)];
+        match &self.classes {
+            Some(classes) => {
+                let best = leaf.value.iter().enumerate().fold(0, |best, (index, p)| if *p > leaf.value[best] { index } else { best });
+                classes.value_at(best)
+            },
+            None => ReturnType::Floats(leaf.value[0]),
+        }
+    }
+}
+
+impl Estimator for DecisionTree {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        self.criterion.is_classification().then(|| self.predict_proba(point))
+    }
+}
+
+impl Persist for DecisionTree {
+    const MODEL_TYPE: &'static str = "DecisionTree";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let value_length = match &self.classes {
+            Some(classes) => classes.len(),
+            None => 1,
+        };
+        ensure(self.classes.is_some() == self.criterion.is_classification() || self.nodes.is_empty(), "the criterion does not match the kind of tree")?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            ensure(node.value.len() == value_length, "node values do not match the number of classes")?;
+            if let NodeKind::Split { feature, left, right, .. } = node.kind {
+                //children are always stored after their parent, this also rules out cycles.
+                ensure(feature < self.n_features, "split on a feature that does not exist")?;
+                ensure(left > index && right > index && left < self.nodes.len() && right < self.nodes.len(), "child index out of range")?;
+            }
+        }
+        Ok(())
+    }
+}
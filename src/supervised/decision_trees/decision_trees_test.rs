@@ -0,0 +1,153 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::{MLalgo, Predict, MLError, Params, ParamValue},
+    evaluation::accuracy::accuracy_score, persistence::persistence::Persist};
+use super::decision_trees::{DecisionTree, Criterion, NodeKind};
+
+
+///class is "inside" when the first feature is in (2, 6), the second feature is noise.
+fn interval_data() -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(11);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for i in 0..80 {
+        let x = i as f32 / 10.0;
+        rows.push(vec![x, rng.f32()]);
+        targets.push(if x > 2.0 && x < 6.0 { "inside".to_owned() } else { "outside".to_owned() });
+    }
+    (Matrix::from(rows), DataType::Strings(targets))
+}
+
+///a step function with a small slope.
+fn step_data() -> (Matrix, DataType) {
+    let rows: Vec<Vec<f32>> = (0..40).map(|i| vec![i as f32]).collect();
+    let targets = (0..40).map(|i| if i < 20 { 1.0 } else { 5.0 } + i as f32 * 0.01).collect();
+    (Matrix::from(rows), DataType::Floats(targets))
+}
+
+#[test]
+fn classification_tree_learns_the_interval() {
+    let (X, y) = interval_data();
+    for criterion in [Criterion::Gini, Criterion::Entropy] {
+        let mut tree = DecisionTree::new(criterion);
+        tree.fit(&X, &y).unwrap();
+        assert_eq!(accuracy_score(&tree, &X, &y), 1.0);
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(tree.n_leaves(), 3);
+        assert_eq!(tree.predict(&[4.0, 0.5]), ReturnType::Strings("inside".to_owned()));
+        assert_eq!(tree.feature_importances(), &[1.0, 0.0]);
+        assert_eq!(tree.predict_proba(&[7.0, 0.5]), vec![0.0, 1.0]);
+    }
+}
+
+#[test]
+fn regression_tree_splits_at_the_step() {
+    let (X, y) = step_data();
+    let mut tree = DecisionTree::regressor();
+    tree.set_max_depth(Some(1));
+    tree.fit(&X, &y).unwrap();
+    match tree.nodes()[0].kind {
+        NodeKind::Split { feature, threshold, .. } => assert_eq!((feature, threshold), (0, 19.5)),
+        NodeKind::Leaf => panic!("the root should be split"),
+    }
+    match tree.predict(&[3.0]) {
+        ReturnType::Floats(value) => assert!((value - 1.095).abs() < 1e-4),
+        other => panic!("expected a float, got {:?}", other),
+    }
+
+    let mut median_tree = DecisionTree::new(Criterion::MAE);
+    median_tree.set_max_depth(Some(1));
+    median_tree.fit(&X, &y).unwrap();
+    assert_eq!(median_tree.predict(&[30.0]), ReturnType::Floats(5.29));
+}
+
+#[test]
+fn adjacent_floats_still_split() {
+    //(low + high) / 2 rounds to high for these two neighbouring floats.
+    let low = f32::from_bits(1.0_f32.to_bits() + 1);
+    let high = f32::from_bits(low.to_bits() + 1);
+    assert_eq!((low + high) / 2.0, high);
+    let X = Matrix::from(vec![vec![low], vec![low], vec![high], vec![high]]);
+
+    let mut tree = DecisionTree::classifier();
+    tree.fit(&X, &DataType::Category(vec![0, 0, 1, 1])).unwrap();
+    assert_eq!(tree.n_leaves(), 2);
+    assert_eq!(tree.predict(&[low]), ReturnType::Category(0));
+    assert_eq!(tree.predict(&[high]), ReturnType::Category(1));
+
+    for criterion in [Criterion::MSE, Criterion::MAE] {
+        let mut tree = DecisionTree::new(criterion);
+        tree.fit(&X, &DataType::Floats(vec![1.0, 1.0, 3.0, 3.0])).unwrap();
+        assert_eq!(tree.predict(&[low]), ReturnType::Floats(1.0));
+        assert_eq!(tree.predict(&[high]), ReturnType::Floats(3.0));
+    }
+}
+
+#[test]
+fn stopping_rules_limit_the_tree() {
+    let (X, y) = step_data();
+    let mut full = DecisionTree::regressor();
+    full.fit(&X, &y).unwrap();
+    assert_eq!(full.n_leaves(), 40);
+
+    let mut leafy = DecisionTree::regressor();
+    leafy.set_min_samples(2, 5);
+    leafy.fit(&X, &y).unwrap();
+    assert!(leafy.nodes().iter().filter(|node| node.is_leaf()).all(|node| node.n_samples >= 5));
+
+    let mut shallow = DecisionTree::regressor();
+    shallow.set_min_impurity_decrease(0.01);
+    shallow.fit(&X, &y).unwrap();
+    assert!(shallow.n_leaves() < 10);
+}
+
+#[test]
+fn pruning_follows_the_cost_complexity_path() {
+    let (X, y) = step_data();
+    let mut tree = DecisionTree::regressor();
+    tree.fit(&X, &y).unwrap();
+    let (alphas, impurities) = tree.cost_complexity_pruning_path();
+    assert!(alphas.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(impurities.windows(2).all(|pair| pair[0] <= pair[1] + 1e-6));
+
+    //just below the last alpha only the root split survives.
+    let mut pruned = DecisionTree::regressor();
+    pruned.set_ccp_alpha(alphas[alphas.len() - 1] * 0.99);
+    pruned.fit(&X, &y).unwrap();
+    assert_eq!(pruned.n_leaves(), 2);
+    assert_eq!(pruned.nodes().len(), 3);
+    assert_eq!(pruned.feature_importances(), &[1.0]);
+}
+
+#[test]
+fn text_and_dot_exports() {
+    let (X, y) = interval_data();
+    let mut tree = DecisionTree::classifier();
+    tree.fit(&X, &y).unwrap();
+    let text = tree.to_text(Some(&["position"]));
+    assert!(text.starts_with("|--- position <= "));
+    assert!(text.contains("|   |--- class: inside"));
+    assert_eq!(text.lines().count(), 7);
+
+    let dot = tree.to_dot(None);
+    assert!(dot.starts_with("digraph Tree {"));
+    assert!(dot.contains("0 -> 1"));
+    assert!(dot.contains("x0 <= "));
+    assert!(dot.trim_end().ends_with('}'));
+}
+
+#[test]
+fn criterion_has_to_match_the_target() {
+    let (X, y) = step_data();
+    assert!(matches!(DecisionTree::classifier().fit(&X, &y), Err(MLError::InvalidTarget(_))));
+    let mut params = Params::new();
+    params.insert("criterion".to_owned(), ParamValue::Text("mse".to_owned()));
+    params.insert("max_depth".to_owned(), ParamValue::Int(-1));
+    let mut tree = DecisionTree::classifier();
+    tree.set_params(&params).unwrap();
+    tree.fit(&X, &y).unwrap();
+    assert_eq!(tree.get_params().get("max_depth"), Some(&ParamValue::Int(-1)));
+
+    let loaded = DecisionTree::from_json(&tree.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.predict(&[12.0]), tree.predict(&[12.0]));
+}
@@ -0,0 +1,526 @@
+//! #AdaBoost#
+//!
+//! Fits copies of any `Estimator` one after the other, every one on sample weights that favour the samples the ones before
+//! it got wrong.
+//! * `AdaBoostClassifier` -> SAMME(multiclass AdaBoost on the predicted classes) or SAMME.R(on the class probabilities,
+//!   every member needs `Estimator::probabilities`).
+//! * `AdaBoostRegressor` -> AdaBoost.R2, the prediction is the weighted median of the members.
+//!
+//! Members that take sample weights(see `MLalgo::supports_sample_weights`) are fitted on them directly, the others on a
+//! resample of the training set drawn with the weights. Both boosters take sample weights themselves through `fit_weighted`.
+
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix};
+use super::common::{class_index, argmax, encode_classes, member_seed, fit_with_weights};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AdaBoostAlgorithm {
+    ///discrete boosting on the predicted classes.
+    Samme,
+    ///real boosting on the class probabilities, usually needs fewer members.
+    SammeR,
+}
+
+impl AdaBoostAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdaBoostAlgorithm::Samme => "SAMME",
+            AdaBoostAlgorithm::SammeR => "SAMME.R",
+        }
+    }
+}
+
+///how the absolute errors of a regressor are turned into a loss in [0, 1], relative to the biggest error.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AdaBoostLoss {
+    Linear,
+    Square,
+    Exponential,
+}
+
+impl AdaBoostLoss {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdaBoostLoss::Linear => "linear",
+            AdaBoostLoss::Square => "square",
+            AdaBoostLoss::Exponential => "exponential",
+        }
+    }
+
+    fn loss(&self, relative_error : f64) -> f64 {
+        match self {
+            AdaBoostLoss::Linear => relative_error,
+            AdaBoostLoss::Square => relative_error * relative_error,
+            AdaBoostLoss::Exponential => 1.0 - (-relative_error).exp(),
+        }
+    }
+}
+
+fn check_boosting(n_estimators : usize, learning_rate : f32) -> Result<(), MLError> {
+    if n_estimators == 0 {
+        return Err(MLError::InvalidParameter("n_estimators needs to be > 0".to_owned()));
+    }
+    if !(learning_rate > 0.0) {
+        return Err(MLError::InvalidParameter(format!("the learning rate needs to be > 0, got {}", learning_rate)));
+    }
+    Ok(())
+}
+
+///the weights scaled to sum to the number of samples, which is what the members expect.
+fn normalised(weights : &[f64]) -> Vec<f32> {
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|weight| (weight / total * weights.len() as f64) as f32).collect()
+}
+
+fn softmax(values : &mut [f32]) {
+    let max = values.iter().fold(f32::MIN, |max, value| max.max(*value));
+    values.iter_mut().for_each(|value| *value = (*value - max).exp());
+    let total: f32 = values.iter().sum();
+    values.iter_mut().for_each(|value| *value /= total);
+}
+
+///clipped logs of the class probabilities of a member, SAMME.R can not work with 0's.
+fn log_probabilities(estimator : &dyn Estimator, classes : usize, point : &[f32]) -> Result<Vec<f64>, MLError> {
+    match estimator.probabilities(point) {
+        Some(probabilities) if probabilities.len() == classes => Ok(probabilities.iter().map(|p| (*p as f64).max(1e-7).ln()).collect()),
+        Some(probabilities) => Err(MLError::InvalidParameter(format!("a member gave {} probabilities for {} classes, it probably missed a class", probabilities.len(), classes))),
+        None => Err(MLError::InvalidParameter("SAMME.R needs members with probabilities, use SAMME".to_owned())),
+    }
+}
+
+
+#[derive(Clone)]
+pub struct AdaBoostClassifier {
+    ///the most members, boosting stops early on a perfect member.
+    pub n_estimators : usize,
+    ///shrinks the contribution of every member.
+    pub learning_rate : f32,
+    pub algorithm : AdaBoostAlgorithm,
+    ///seed for the resamples of members that do not take sample weights.
+    pub seed : u64,
+    base : Box<dyn Estimator>,
+    estimators : Vec<Box<dyn Estimator>>,
+    estimator_weights : Vec<f32>,
+    estimator_errors : Vec<f32>,
+    classes : Option<DataType>,
+    n_features : usize,
+}
+
+impl AdaBoostClassifier {
+
+    ///SAMME with atmost `n_estimators` copies of `base`, a depth 1 `DecisionTree`(a stump) is the usual choice.
+    pub fn new<E : Estimator + 'static>(base : E, n_estimators : usize) -> AdaBoostClassifier {
+        AdaBoostClassifier {
+            n_estimators,
+            learning_rate: 1.0,
+            algorithm: AdaBoostAlgorithm::Samme,
+            seed: 0,
+            base: Box::new(base),
+            estimators: vec![],
+            estimator_weights: vec![],
+            estimator_errors: vec![],
+            classes: None,
+            n_features: 0,
+        }
+    }
+
+    pub fn set_learning_rate(&mut self, learning_rate : f32) {
+        self.learning_rate = learning_rate;
+    }
+
+    pub fn set_algorithm(&mut self, algorithm : AdaBoostAlgorithm) {
+        self.algorithm = algorithm;
+    }
+
+    pub fn set_seed(&mut self, seed : u64) {
+        self.seed = seed;
+    }
+
+    pub fn estimators(&self) -> Vec<&dyn Estimator> {
+        self.estimators.iter().map(|estimator| estimator.as_ref()).collect()
+    }
+
+    ///the say of every member in the vote, all 1 for SAMME.R.
+    pub fn estimator_weights(&self) -> &[f32] {
+        &self.estimator_weights
+    }
+
+    ///the weighted training error of every member, on the weights it was fitted with.
+    pub fn estimator_errors(&self) -> &[f32] {
+        &self.estimator_errors
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///a score per class in the order of `classes()`, the highest one is predicted.
+    pub fn decision_function(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        let classes = self.classes.as_ref().unwrap();
+        let n_classes = classes.len();
+        let mut scores = vec![0.0_f32; n_classes];
+        match self.algorithm {
+            AdaBoostAlgorithm::Samme => {
+                for (estimator, weight) in self.estimators.iter().zip(&self.estimator_weights) {
+                    let prediction = estimator.predict(point);
+                    let class = class_index(classes, &prediction).unwrap_or_else(|| panic!("a member predicted {:?}, which is not a class", prediction));
+                    scores[class] += weight;
+                }
+                let total: f32 = self.estimator_weights.iter().sum();
+                scores.iter_mut().for_each(|score| *score /= total);
+            },
+            AdaBoostAlgorithm::SammeR => {
+                for estimator in &self.estimators {
+                    let logs = log_probabilities(estimator.as_ref(), n_classes, point).unwrap_or_else(|error| panic!("{}", error));
+                    let mean = logs.iter().sum::<f64>() / n_classes as f64;
+                    scores.iter_mut().zip(&logs).for_each(|(score, log)| *score += ((n_classes - 1) as f64 * (log - mean)) as f32);
+                }
+                scores.iter_mut().for_each(|score| *score /= self.estimators.len() as f32);
+            },
+        }
+        scores
+    }
+
+    ///the softmax of the decision function divided by(classes - 1), in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        let mut scores = self.decision_function(point);
+        let divisor = (scores.len() - 1).max(1) as f32;
+        scores.iter_mut().for_each(|score| *score /= divisor);
+        softmax(&mut scores);
+        scores
+    }
+}
+
+impl MLalgo for AdaBoostClassifier {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        check_boosting(self.n_estimators, self.learning_rate)?;
+        let (classes, labels) = encode_classes(y_train, "AdaBoost classification")?;
+        let n_classes = classes.len();
+        if n_classes < 2 {
+            return Err(MLError::InvalidTarget("boosting needs atleast two classes".to_owned()));
+        }
+        let mut weights: Vec<f64> = sample_weights.iter().map(|weight| *weight as f64).collect();
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err(MLError::InvalidParameter("the sample weights sum to 0".to_owned()));
+        }
+        let learning_rate = self.learning_rate as f64;
+        let (mut estimators, mut estimator_weights, mut estimator_errors) = (vec![], vec![], vec![]);
+
+        for iteration in 0..self.n_estimators {
+            let mut member = self.base.clone_box();
+            fit_with_weights(member.as_mut(), X_train, y_train, &normalised(&weights), member_seed(self.seed, iteration))?;
+            let total: f64 = weights.iter().sum();
+
+            match self.algorithm {
+                AdaBoostAlgorithm::Samme => {
+                    let wrong = X_train.iter_rows().zip(&labels).map(|(row, label)| {
+                        let prediction = member.predict(row);
+                        class_index(&classes, &prediction).map(|class| class != *label)
+                            .ok_or_else(|| MLError::InvalidTarget(format!("a member predicted {:?}, which is not a class", prediction)))
+                    }).collect::<Result<Vec<bool>, MLError>>()?;
+                    let error = weights.iter().zip(&wrong).filter(|(_, wrong)| **wrong).map(|(weight, _)| weight).sum::<f64>() / total;
+                    if error <= 0.0 {
+                        estimators.push(member);
+                        estimator_weights.push(1.0);
+                        estimator_errors.push(0.0);
+                        break;
+                    }
+                    //no better than guessing, the weights can not be improved on.
+                    if error >= 1.0 - 1.0 / n_classes as f64 {
+                        if estimators.is_empty() {
+                            return Err(MLError::InvalidParameter("the first member is no better than chance, use a stronger base estimator".to_owned()));
+                        }
+                        break;
+                    }
+                    let alpha = learning_rate * (((1.0 - error) / error).ln() + ((n_classes - 1) as f64).ln());
+                    weights.iter_mut().zip(&wrong).filter(|(_, wrong)| **wrong).for_each(|(weight, _)| *weight *= alpha.exp());
+                    estimators.push(member);
+                    estimator_weights.push(alpha as f32);
+                    estimator_errors.push(error as f32);
+                },
+                AdaBoostAlgorithm::SammeR => {
+                    let mut error = 0.0_f64;
+                    let off = 1.0 / (n_classes - 1) as f64;
+                    let factor = learning_rate * (n_classes - 1) as f64 / n_classes as f64;
+                    for ((row, label), weight) in X_train.iter_rows().zip(&labels).zip(weights.iter_mut()) {
+                        let logs = log_probabilities(member.as_ref(), n_classes, row)?;
+                        let logs32: Vec<f32> = logs.iter().map(|log| *log as f32).collect();
+                        if argmax(&logs32) != *label {
+                            error += *weight;
+                        }
+                        //y_k is 1 for the true class and -1/(classes - 1) for the others.
+                        let agreement = logs[*label] - off * logs.iter().enumerate().filter(|(class, _)| class != label).map(|(_, log)| log).sum::<f64>();
+                        *weight *= (-factor * agreement).exp();
+                    }
+                    estimators.push(member);
+                    estimator_weights.push(1.0);
+                    estimator_errors.push((error / total) as f32);
+                    if error <= 0.0 {
+                        break;
+                    }
+                },
+            }
+            let total: f64 = weights.iter().sum();
+            if !(total > 0.0 && total.is_finite()) {
+                break;
+            }
+            weights.iter_mut().for_each(|weight| *weight /= total);
+        }
+
+        self.estimators = estimators;
+        self.estimator_weights = estimator_weights;
+        self.estimator_errors = estimator_errors;
+        self.classes = Some(classes);
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.estimators.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("n_estimators".to_owned(), ParamValue::Int(self.n_estimators as i64));
+        params.insert("learning_rate".to_owned(), ParamValue::Float(self.learning_rate));
+        params.insert("algorithm".to_owned(), ParamValue::Text(self.algorithm.name().to_owned()));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_estimators" => self.n_estimators = value.as_usize(name)?,
+                "learning_rate" => self.learning_rate = value.as_f32(name)?,
+                "algorithm" => self.algorithm = match value.as_str(name)? {
+                    "SAMME" => AdaBoostAlgorithm::Samme,
+                    "SAMME.R" => AdaBoostAlgorithm::SammeR,
+                    other => return Err(MLError::InvalidParameter(format!("the algorithm needs to be 'SAMME' or 'SAMME.R', got '{}'", other))),
+                },
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        check_boosting(self.n_estimators, self.learning_rate)
+    }
+}
+
+impl Predict for AdaBoostClassifier {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let scores = self.decision_function(point);
+        self.classes.as_ref().unwrap().value_at(argmax(&scores))
+    }
+}
+
+impl Estimator for AdaBoostClassifier {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+
+#[derive(Clone)]
+pub struct AdaBoostRegressor {
+    ///the most members, boosting stops early on a perfect member.
+    pub n_estimators : usize,
+    ///shrinks the contribution of every member.
+    pub learning_rate : f32,
+    pub loss : AdaBoostLoss,
+    ///seed for the resamples of members that do not take sample weights.
+    pub seed : u64,
+    base : Box<dyn Estimator>,
+    estimators : Vec<Box<dyn Estimator>>,
+    estimator_weights : Vec<f32>,
+    estimator_errors : Vec<f32>,
+    n_features : usize,
+}
+
+impl AdaBoostRegressor {
+
+    ///AdaBoost.R2 with the linear loss and atmost `n_estimators` copies of `base`.
+    pub fn new<E : Estimator + 'static>(base : E, n_estimators : usize) -> AdaBoostRegressor {
+        AdaBoostRegressor {
+            n_estimators,
+            learning_rate: 1.0,
+            loss: AdaBoostLoss::Linear,
+            seed: 0,
+            base: Box::new(base),
+            estimators: vec![],
+            estimator_weights: vec![],
+            estimator_errors: vec![],
+            n_features: 0,
+        }
+    }
+
+    pub fn set_learning_rate(&mut self, learning_rate : f32) {
+        self.learning_rate = learning_rate;
+    }
+
+    pub fn set_loss(&mut self, loss : AdaBoostLoss) {
+        self.loss = loss;
+    }
+
+    pub fn set_seed(&mut self, seed : u64) {
+        self.seed = seed;
+    }
+
+    pub fn estimators(&self) -> Vec<&dyn Estimator> {
+        self.estimators.iter().map(|estimator| estimator.as_ref()).collect()
+    }
+
+    ///the weight of every member in the weighted median.
+    pub fn estimator_weights(&self) -> &[f32] {
+        &self.estimator_weights
+    }
+
+    ///the weighted mean loss of every member, on the weights it was fitted with.
+    pub fn estimator_errors(&self) -> &[f32] {
+        &self.estimator_errors
+    }
+}
+
+impl MLalgo for AdaBoostRegressor {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        check_boosting(self.n_estimators, self.learning_rate)?;
+        let targets = match y_train {
+            DataType::Floats(temp) => temp,
+            _ => return Err(MLError::InvalidTarget("AdaBoost regression needs a float target, use the classifier for classes".to_owned())),
+        };
+        let mut weights: Vec<f64> = sample_weights.iter().map(|weight| *weight as f64).collect();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err(MLError::InvalidParameter("the sample weights sum to 0".to_owned()));
+        }
+        weights.iter_mut().for_each(|weight| *weight /= total);
+        let learning_rate = self.learning_rate as f64;
+        let (mut estimators, mut estimator_weights, mut estimator_errors) = (vec![], vec![], vec![]);
+
+        for iteration in 0..self.n_estimators {
+            let mut member = self.base.clone_box();
+            fit_with_weights(member.as_mut(), X_train, y_train, &normalised(&weights), member_seed(self.seed, iteration))?;
+            let errors = X_train.iter_rows().zip(targets).map(|(row, target)| match member.predict(row) {
+                ReturnType::Floats(prediction) => Ok((*target as f64 - prediction as f64).abs()),
+                other => Err(MLError::InvalidTarget(format!("a member predicted {:?}, AdaBoost regression needs float predictions", other))),
+            }).collect::<Result<Vec<f64>, MLError>>()?;
+            let largest = errors.iter().fold(0.0_f64, |max, error| max.max(*error));
+            let losses: Vec<f64> = errors.iter().map(|error| if largest > 0.0 { self.loss.loss(error / largest) } else { 0.0 }).collect();
+            let average: f64 = weights.iter().zip(&losses).map(|(weight, loss)| weight * loss).sum();
+
+            if average <= 0.0 {
+                estimators.push(member);
+                estimator_weights.push(1.0);
+                estimator_errors.push(0.0);
+                break;
+            }
+            //worse than the R2 limit, only kept when there is nothing else.
+            if average >= 0.5 {
+                if estimators.is_empty() {
+                    estimators.push(member);
+                    estimator_weights.push(1.0);
+                    estimator_errors.push(average as f32);
+                }
+                break;
+            }
+            let beta = average / (1.0 - average);
+            weights.iter_mut().zip(&losses).for_each(|(weight, loss)| *weight *= beta.powf((1.0 - loss) * learning_rate));
+            let total: f64 = weights.iter().sum();
+            weights.iter_mut().for_each(|weight| *weight /= total);
+            estimators.push(member);
+            estimator_weights.push((learning_rate * (1.0 / beta).ln()) as f32);
+            estimator_errors.push(average as f32);
+        }
+
+        self.estimators = estimators;
+        self.estimator_weights = estimator_weights;
+        self.estimator_errors = estimator_errors;
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.estimators.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("n_estimators".to_owned(), ParamValue::Int(self.n_estimators as i64));
+        params.insert("learning_rate".to_owned(), ParamValue::Float(self.learning_rate));
+        params.insert("loss".to_owned(), ParamValue::Text(self.loss.name().to_owned()));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_estimators" => self.n_estimators = value.as_usize(name)?,
+                "learning_rate" => self.learning_rate = value.as_f32(name)?,
+                "loss" => self.loss = match value.as_str(name)? {
+                    "linear" => AdaBoostLoss::Linear,
+                    "square" => AdaBoostLoss::Square,
+                    "exponential" => AdaBoostLoss::Exponential,
+                    other => return Err(MLError::InvalidParameter(format!("the loss needs to be 'linear', 'square' or 'exponential', got '{}'", other))),
+                },
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        check_boosting(self.n_estimators, self.learning_rate)
+    }
+}
+
+impl Predict for AdaBoostRegressor {
+    ///the weighted median of the member predictions.
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        let mut predictions: Vec<(f32, f32)> = self.estimators.iter().zip(&self.estimator_weights).map(|(estimator, weight)| match estimator.predict(point) {
+            ReturnType::Floats(value) => (value, *weight),
+            other => panic!("a member predicted {:?}, AdaBoost regression needs float predictions", other),
+        }).collect();
+        predictions.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let half = predictions.iter().map(|(_, weight)| weight).sum::<f32>() / 2.0;
+        let mut cumulative = 0.0_f32;
+        for (value, weight) in &predictions {
+            cumulative += weight;
+            if cumulative >= half {
+                return ReturnType::Floats(*value);
+            }
+        }
+        ReturnType::Floats(predictions.last().unwrap().0)
+    }
+}
+
+impl Estimator for AdaBoostRegressor {}
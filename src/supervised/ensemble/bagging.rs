@@ -0,0 +1,363 @@
+//! #Bagging#
+//!
+//! Fits copies of any `Estimator` on random subsets of the training set and averages them, which mostly takes away variance.
+//! Every member draws `max_samples` of the rows(with replacement when `bootstrap`) and `max_features` of the columns
+//! (with replacement when `bootstrap_features`), and only sees those columns at predict time as well.
+//!
+//! * `BaggingClassifier` -> the mean of the member probabilities. Members without probabilities, or that missed a class in
+//!   their sample, vote for the class they predict.
+//! * `BaggingRegressor` -> the mean of the member predictions.
+//!
+//! The members are fitted in parallel, each one with its own seed derived from `seed`.
+//! With sample weights every member gets the weights of its rows, or a resample drawn with them if it does not take weights.
+
+use rayon::prelude::*;
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix};
+use super::common::{select_targets, argmax, encode_classes, member_seed, member_probabilities, fit_with_weights};
+
+
+///the rows and the columns every member is fitted on.
+fn draw(n_estimators : usize, samples : usize, features : usize, max_samples : f32, max_features : f32,
+    bootstrap : bool, bootstrap_features : bool, seed : u64) -> Vec<(Vec<usize>, Vec<usize>)> {
+    let subset = |rng : &fastrand::Rng, total : usize, fraction : f32, replace : bool| -> Vec<usize> {
+        let count = ((fraction * total as f32).round() as usize).clamp(1, total);
+        if replace {
+            (0..count).map(|_| rng.usize(0..total)).collect()
+        } else {
+            let mut indices: Vec<usize> = (0..total).collect();
+            rng.shuffle(&mut indices);
+            indices.truncate(count);
+            indices.sort();
+            indices
+        }
+    };
+    (0..n_estimators).map(|index| {
+        let rng = fastrand::Rng::with_seed(member_seed(seed, index));
+        let rows = subset(&rng, samples, max_samples, bootstrap);
+        let columns = subset(&rng, features, max_features, bootstrap_features);
+        (rows, columns)
+    }).collect()
+}
+
+fn fit_bags(base : &dyn Estimator, draws : &[(Vec<usize>, Vec<usize>)], X_train : &Matrix, y_train : &DataType,
+    sample_weights : Option<&[f32]>, seed : u64) -> Result<Vec<Box<dyn Estimator>>, MLError> {
+    draws.par_iter().enumerate().map(|(index, (rows, columns))| {
+        let mut member = base.clone_box();
+        let (X, y) = (X_train.select_rows(rows).select_columns(columns), select_targets(y_train, rows));
+        match sample_weights {
+            Some(weights) => {
+                let weights: Vec<f32> = rows.iter().map(|row| weights[*row]).collect();
+                //a stream apart from the one that drew the rows.
+                fit_with_weights(member.as_mut(), &X, &y, &weights, !member_seed(seed, index))?;
+            },
+            None => member.fit(&X, &y)?,
+        }
+        Ok(member)
+    }).collect()
+}
+
+fn check_bagging(n_estimators : usize, max_samples : f32, max_features : f32) -> Result<(), MLError> {
+    if n_estimators == 0 {
+        return Err(MLError::InvalidParameter("n_estimators needs to be > 0".to_owned()));
+    }
+    if !(max_samples > 0.0 && max_samples <= 1.0) || !(max_features > 0.0 && max_features <= 1.0) {
+        return Err(MLError::InvalidParameter(format!("max_samples and max_features are fractions in (0, 1], got {} and {}", max_samples, max_features)));
+    }
+    Ok(())
+}
+
+fn select(point : &[f32], columns : &[usize]) -> Vec<f32> {
+    columns.iter().map(|column| point[*column]).collect()
+}
+
+
+#[derive(Clone)]
+pub struct BaggingClassifier {
+    pub n_estimators : usize,
+    ///fraction of the rows every member is fitted on.
+    pub max_samples : f32,
+    ///fraction of the columns every member is fitted on.
+    pub max_features : f32,
+    ///draw the rows with replacement.
+    pub bootstrap : bool,
+    ///draw the columns with replacement.
+    pub bootstrap_features : bool,
+    pub seed : u64,
+    base : Box<dyn Estimator>,
+    estimators : Vec<Box<dyn Estimator>>,
+    features : Vec<Vec<usize>>,
+    classes : Option<DataType>,
+    n_features : usize,
+}
+
+impl BaggingClassifier {
+
+    ///`n_estimators` copies of `base`, each on a bootstrap sample of all the rows and all the features.
+    pub fn new<E : Estimator + 'static>(base : E, n_estimators : usize) -> BaggingClassifier {
+        BaggingClassifier {
+            n_estimators,
+            max_samples: 1.0,
+            max_features: 1.0,
+            bootstrap: true,
+            bootstrap_features: false,
+            seed: 0,
+            base: Box::new(base),
+            estimators: vec![],
+            features: vec![],
+            classes: None,
+            n_features: 0,
+        }
+    }
+
+    pub fn set_samples(&mut self, max_samples : f32, bootstrap : bool) {
+        self.max_samples = max_samples;
+        self.bootstrap = bootstrap;
+    }
+
+    pub fn set_features(&mut self, max_features : f32, bootstrap_features : bool) {
+        self.max_features = max_features;
+        self.bootstrap_features = bootstrap_features;
+    }
+
+    pub fn set_seed(&mut self, seed : u64) {
+        self.seed = seed;
+    }
+
+    pub fn estimators(&self) -> Vec<&dyn Estimator> {
+        self.estimators.iter().map(|estimator| estimator.as_ref()).collect()
+    }
+
+    ///the columns every member was fitted on.
+    pub fn estimator_features(&self) -> &[Vec<usize>] {
+        &self.features
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_bagging(self.n_estimators, self.max_samples, self.max_features)?;
+        let (classes, _) = encode_classes(y_train, "a bagging classifier")?;
+        let draws = draw(self.n_estimators, X_train.rows(), X_train.cols(), self.max_samples, self.max_features, self.bootstrap, self.bootstrap_features, self.seed);
+        self.estimators = fit_bags(self.base.as_ref(), &draws, X_train, y_train, sample_weights, self.seed)?;
+        self.features = draws.into_iter().map(|(_, columns)| columns).collect();
+        self.classes = Some(classes);
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///mean of the member probabilities, in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        let classes = self.classes.as_ref().unwrap();
+        let mut totals = vec![0.0_f32; classes.len()];
+        for (estimator, columns) in self.estimators.iter().zip(&self.features) {
+            let probabilities = member_probabilities(estimator.as_ref(), classes, &select(point, columns));
+            totals.iter_mut().zip(probabilities).for_each(|(total, p)| *total += p);
+        }
+        totals.iter_mut().for_each(|total| *total /= self.estimators.len() as f32);
+        totals
+    }
+}
+
+impl MLalgo for BaggingClassifier {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.estimators.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("n_estimators".to_owned(), ParamValue::Int(self.n_estimators as i64));
+        params.insert("max_samples".to_owned(), ParamValue::Float(self.max_samples));
+        params.insert("max_features".to_owned(), ParamValue::Float(self.max_features));
+        params.insert("bootstrap".to_owned(), ParamValue::Bool(self.bootstrap));
+        params.insert("bootstrap_features".to_owned(), ParamValue::Bool(self.bootstrap_features));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_estimators" => self.n_estimators = value.as_usize(name)?,
+                "max_samples" => self.max_samples = value.as_f32(name)?,
+                "max_features" => self.max_features = value.as_f32(name)?,
+                "bootstrap" => self.bootstrap = value.as_bool(name)?,
+                "bootstrap_features" => self.bootstrap_features = value.as_bool(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        check_bagging(self.n_estimators, self.max_samples, self.max_features)
+    }
+}
+
+impl Predict for BaggingClassifier {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let probabilities = self.predict_proba(point);
+        self.classes.as_ref().unwrap().value_at(argmax(&probabilities))
+    }
+}
+
+impl Estimator for BaggingClassifier {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+
+#[derive(Clone)]
+pub struct BaggingRegressor {
+    pub n_estimators : usize,
+    ///fraction of the rows every member is fitted on.
+    pub max_samples : f32,
+    ///fraction of the columns every member is fitted on.
+    pub max_features : f32,
+    ///draw the rows with replacement.
+    pub bootstrap : bool,
+    ///draw the columns with replacement.
+    pub bootstrap_features : bool,
+    pub seed : u64,
+    base : Box<dyn Estimator>,
+    estimators : Vec<Box<dyn Estimator>>,
+    features : Vec<Vec<usize>>,
+    n_features : usize,
+}
+
+impl BaggingRegressor {
+
+    ///`n_estimators` copies of `base`, each on a bootstrap sample of all the rows and all the features.
+    pub fn new<E : Estimator + 'static>(base : E, n_estimators : usize) -> BaggingRegressor {
+        BaggingRegressor {
+            n_estimators,
+            max_samples: 1.0,
+            max_features: 1.0,
+            bootstrap: true,
+            bootstrap_features: false,
+            seed: 0,
+            base: Box::new(base),
+            estimators: vec![],
+            features: vec![],
+            n_features: 0,
+        }
+    }
+
+    pub fn set_samples(&mut self, max_samples : f32, bootstrap : bool) {
+        self.max_samples = max_samples;
+        self.bootstrap = bootstrap;
+    }
+
+    pub fn set_features(&mut self, max_features : f32, bootstrap_features : bool) {
+        self.max_features = max_features;
+        self.bootstrap_features = bootstrap_features;
+    }
+
+    pub fn set_seed(&mut self, seed : u64) {
+        self.seed = seed;
+    }
+
+    pub fn estimators(&self) -> Vec<&dyn Estimator> {
+        self.estimators.iter().map(|estimator| estimator.as_ref()).collect()
+    }
+
+    ///the columns every member was fitted on.
+    pub fn estimator_features(&self) -> &[Vec<usize>] {
+        &self.features
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_bagging(self.n_estimators, self.max_samples, self.max_features)?;
+        if !matches!(y_train, DataType::Floats(_)) {
+            return Err(MLError::InvalidTarget("a bagging regressor needs a float target, use the bagging classifier for classes".to_owned()));
+        }
+        let draws = draw(self.n_estimators, X_train.rows(), X_train.cols(), self.max_samples, self.max_features, self.bootstrap, self.bootstrap_features, self.seed);
+        self.estimators = fit_bags(self.base.as_ref(), &draws, X_train, y_train, sample_weights, self.seed)?;
+        self.features = draws.into_iter().map(|(_, columns)| columns).collect();
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+}
+
+impl MLalgo for BaggingRegressor {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.estimators.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("n_estimators".to_owned(), ParamValue::Int(self.n_estimators as i64));
+        params.insert("max_samples".to_owned(), ParamValue::Float(self.max_samples));
+        params.insert("max_features".to_owned(), ParamValue::Float(self.max_features));
+        params.insert("bootstrap".to_owned(), ParamValue::Bool(self.bootstrap));
+        params.insert("bootstrap_features".to_owned(), ParamValue::Bool(self.bootstrap_features));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_estimators" => self.n_estimators = value.as_usize(name)?,
+                "max_samples" => self.max_samples = value.as_f32(name)?,
+                "max_features" => self.max_features = value.as_f32(name)?,
+                "bootstrap" => self.bootstrap = value.as_bool(name)?,
+                "bootstrap_features" => self.bootstrap_features = value.as_bool(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        check_bagging(self.n_estimators, self.max_samples, self.max_features)
+    }
+}
+
+impl Predict for BaggingRegressor {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        let total: f32 = self.estimators.iter().zip(&self.features).map(|(estimator, columns)| match estimator.predict(&select(point, columns)) {
+            ReturnType::Floats(value) => value,
+            other => panic!("a member predicted {:?}, a bagging regressor needs float predictions", other),
+        }).sum();
+        ReturnType::Floats(total / self.estimators.len() as f32)
+    }
+}
+
+impl Estimator for BaggingRegressor {}
@@ -0,0 +1,145 @@
+//! The parts the ensembles share : picking targets by index, folds, fitting the members in parallel and turning votes into classes.
+
+use rayon::prelude::*;
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{Estimator, MLError},
+    n_dimen::matrix::Matrix};
+
+
+///the targets of the given samples, in the given order.
+pub(crate) fn select_targets(y : &DataType, indices : &[usize]) -> DataType {
+    match y {
+        DataType::Floats(temp) => DataType::Floats(indices.iter().map(|index| temp[*index]).collect()),
+        DataType::Category(temp) => DataType::Category(indices.iter().map(|index| temp[*index]).collect()),
+        DataType::Strings(temp) => DataType::Strings(indices.iter().map(|index| temp[*index].clone()).collect()),
+    }
+}
+
+///the position of a predicted value in the class list, `None` if the model predicted something that is not a class.
+pub(crate) fn class_index(classes : &DataType, value : &ReturnType) -> Option<usize> {
+    (0..classes.len()).find(|class| classes.value_at(*class) == *value)
+}
+
+///the index of the biggest value, ties go to the class that comes first.
+pub(crate) fn argmax(values : &[f32]) -> usize {
+    values.iter().enumerate().fold(0, |best, (index, value)| if *value > values[best] { index } else { best })
+}
+
+///the sorted classes of a classification target.
+pub(crate) fn encode_classes(y : &DataType, model : &str) -> Result<(DataType, Vec<usize>), MLError> {
+    y.unique_classes().ok_or_else(|| MLError::InvalidTarget(format!("{} needs category or string targets, use the regressor for floats", model)))
+}
+
+///one weight per member, `None` for equal weights.
+pub(crate) fn member_weights(weights : Option<&Vec<f32>>, members : usize) -> Result<Vec<f32>, MLError> {
+    match weights {
+        Some(weights) if weights.len() != members => Err(MLError::InvalidParameter(format!("{} weights were given for {} estimators", weights.len(), members))),
+        Some(weights) if weights.iter().any(|weight| !(*weight >= 0.0)) || weights.iter().sum::<f32>() <= 0.0 =>
+            Err(MLError::InvalidParameter("the weights need to be >= 0 with a positive sum".to_owned())),
+        Some(weights) => Ok(weights.clone()),
+        None => Ok(vec![1.0; members]),
+    }
+}
+
+///an ensemble needs atleast one member and every member a different name.
+pub(crate) fn check_members(estimators : &[(String, Box<dyn Estimator>)]) -> Result<(), MLError> {
+    if estimators.is_empty() {
+        return Err(MLError::InvalidParameter("the ensemble has no estimators, add some first".to_owned()));
+    }
+    for (index, (name, _)) in estimators.iter().enumerate() {
+        if estimators[..index].iter().any(|(other, _)| other == name) {
+            return Err(MLError::InvalidParameter(format!("two estimators are called '{}'", name)));
+        }
+    }
+    Ok(())
+}
+
+///fits a member, with the sample weights if there are any. A member that does not take them is an error.
+pub(crate) fn fit_member(name : &str, estimator : &mut dyn Estimator, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+    match sample_weights {
+        None => estimator.fit(X_train, y_train),
+        Some(sample_weights) if estimator.supports_sample_weights() => estimator.fit_weighted(X_train, y_train, sample_weights),
+        Some(_) => Err(MLError::InvalidParameter(format!("'{}' does not take sample weights", name))),
+    }
+}
+
+///fits every member on the same data, in parallel.
+pub(crate) fn fit_members(estimators : &mut [(String, Box<dyn Estimator>)], X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+    estimators.par_iter_mut().map(|(name, estimator)| fit_member(name, estimator.as_mut(), X_train, y_train, sample_weights)).collect()
+}
+
+///every member takes sample weights.
+pub(crate) fn members_take_weights(estimators : &[(String, Box<dyn Estimator>)]) -> bool {
+    estimators.iter().all(|(_, estimator)| estimator.supports_sample_weights())
+}
+
+///the held out samples of every fold. With `stratify` every class is dealt over the folds on its own,
+///so every fold keeps(about) the class balance of the whole set.
+pub(crate) fn k_folds(y : &DataType, n_folds : usize, seed : u64, stratify : bool) -> Vec<Vec<usize>> {
+    let rng = fastrand::Rng::with_seed(seed);
+    let mut groups: Vec<Vec<usize>> = match (stratify, y.unique_classes()) {
+        (true, Some((classes, labels))) => {
+            let mut groups = vec![vec![]; classes.len()];
+            labels.iter().enumerate().for_each(|(sample, label)| groups[*label].push(sample));
+            groups
+        },
+        _ => vec![(0..y.len()).collect()],
+    };
+    let mut folds = vec![vec![]; n_folds];
+    let mut next = 0;
+    for group in groups.iter_mut() {
+        rng.shuffle(group);
+        for sample in group.iter() {
+            folds[next % n_folds].push(*sample);
+            next += 1;
+        }
+    }
+    folds.iter_mut().for_each(|fold| fold.sort());
+    folds
+}
+
+///a seed of its own for every member, so the members do not depend on the order they are fitted in.
+pub(crate) fn member_seed(seed : u64, index : usize) -> u64 {
+    let mut z = seed.wrapping_add((index as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+///as many samples as there are weights, drawn with replacement with a probability proportional to the weight.
+pub(crate) fn weighted_resample(sample_weights : &[f32], seed : u64) -> Vec<usize> {
+    let mut cumulative = Vec::with_capacity(sample_weights.len());
+    let mut total = 0.0_f64;
+    for weight in sample_weights {
+        total += *weight as f64;
+        cumulative.push(total);
+    }
+    let rng = fastrand::Rng::with_seed(seed);
+    (0..sample_weights.len()).map(|_| {
+        let target = rng.f64() * total;
+        cumulative.partition_point(|value| *value <= target).min(sample_weights.len() - 1)
+    }).collect()
+}
+
+///fits with the sample weights when the model takes them, otherwise on a resample drawn with the weights.
+pub(crate) fn fit_with_weights(estimator : &mut dyn Estimator, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32], seed : u64) -> Result<(), MLError> {
+    if estimator.supports_sample_weights() {
+        estimator.fit_weighted(X_train, y_train, sample_weights)
+    } else {
+        let samples = weighted_resample(sample_weights, seed);
+        estimator.fit(&X_train.select_rows(&samples), &select_targets(y_train, &samples))
+    }
+}
+
+///the class probabilities of a member in the order of the ensemble's classes. A member that did not see every class
+///(or has no probabilities) gives all its weight to the class it predicts.
+pub(crate) fn member_probabilities(estimator : &dyn Estimator, classes : &DataType, point : &[f32]) -> Vec<f32> {
+    match estimator.probabilities(point) {
+        Some(probabilities) if probabilities.len() == classes.len() => probabilities,
+        _ => {
+            let prediction = estimator.predict(point);
+            let class = class_index(classes, &prediction).unwrap_or_else(|| panic!("a member predicted {:?}, which is not a class", prediction));
+            (0..classes.len()).map(|index| (index == class) as u8 as f32).collect()
+        },
+    }
+}
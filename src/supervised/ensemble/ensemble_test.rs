@@ -0,0 +1,248 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_frame::DataFrame, data_type::DataType, return_type::ReturnType},
+    neural_networks::neural_network::{NeuralNet, ActivationFunction, CostFunction, OutputMap},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue}, evaluation::accuracy::{accuracy_score, r2_score},
+    supervised::{naive_bayes::gaussian_NB::GaussianNb, logistic_regression::logistic_regression::LogisticRegression,
+        decision_trees::decision_trees::{DecisionTree, Criterion}, k_nearest_neighbours::k_nearest_neighbours::KNearestNeighbours,
+        support_vector_machines::support_vector_machines::{Svc, Kernel}, linear_regression::linear_regression::LinearRegression}};
+use crate::supervised::test_fixtures::blobs;
+use super::{voting::{VotingClassifier, VotingRegressor, Voting}, stacking::{StackingClassifier, StackingRegressor},
+    bagging::{BaggingClassifier, BaggingRegressor}, adaboost::{AdaBoostClassifier, AdaBoostRegressor, AdaBoostAlgorithm, AdaBoostLoss}};
+
+
+///`y = x0^2 + x1` on [-1, 1], no single linear model gets it.
+fn curve(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let rng = fastrand::Rng::with_seed(seed);
+    let rows: Vec<Vec<f32>> = (0..samples).map(|_| vec![rng.f32() * 2.0 - 1.0, rng.f32() * 2.0 - 1.0]).collect();
+    let targets = rows.iter().map(|row| row[0] * row[0] + row[1]).collect();
+    (Matrix::from(rows), DataType::Floats(targets))
+}
+
+#[test]
+fn voting_classifier_hard_and_soft() {
+    let (X, y) = blobs(0, 300);
+    let (X_test, y_test) = blobs(1, 300);
+    let mut model = VotingClassifier::new(Voting::Soft);
+    model.add("bayes", GaussianNb::new());
+    model.add("logistic", LogisticRegression::new());
+    model.add("tree", { let mut tree = DecisionTree::new(Criterion::Gini); tree.set_max_depth(Some(4)); tree });
+    assert!(!model.is_fitted());
+    model.fit(&X, &y).unwrap();
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.8);
+    let probabilities = model.predict_proba(&[2.0, 0.0]);
+    assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5 && probabilities[1] > 0.5);
+    assert_eq!(model.names(), vec!["bayes", "logistic", "tree"]);
+    assert!(model.estimator("bayes").unwrap().is_fitted());
+
+    //a member without probabilities can only vote hard.
+    model.add("svm", Svc::new(Kernel::Rbf { gamma: None }, 1.0));
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    let mut params = Params::new();
+    params.insert("voting".to_owned(), ParamValue::Text("hard".to_owned()));
+    model.set_params(&params).unwrap();
+    model.fit(&X, &y).unwrap();
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.8);
+    //every vote counts 1/4, all of them agree far from the boundaries.
+    assert_eq!(model.predict_proba(&[1.0, 3.0]), vec![0.0, 0.0, 1.0]);
+
+    //with all the weight on one member the ensemble is that member.
+    model.set_weights(Some(vec![0.0, 0.0, 0.0, 1.0])).unwrap();
+    model.fit(&X, &y).unwrap();
+    let svm = model.estimator("svm").unwrap();
+    assert!(X_test.iter_rows().all(|row| model.predict(row) == svm.predict(row)));
+    //the wrong number of weights is refused up front and the fitted ensemble keeps predicting.
+    assert!(matches!(model.set_weights(Some(vec![1.0])), Err(MLError::InvalidParameter(_))));
+    assert!(matches!(model.set_weights(Some(vec![0.0; 4])), Err(MLError::InvalidParameter(_))));
+    assert!(X_test.iter_rows().all(|row| model.predict(row) == model.estimator("svm").unwrap().predict(row)));
+    assert!(matches!(VotingClassifier::new(Voting::Hard).fit(&X, &y), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn voting_regressor_takes_a_neural_network() {
+    let (X, y) = curve(2, 200);
+    let (X_test, y_test) = curve(3, 200);
+    let mut frame = DataFrame::new();
+    frame.new_column(DataType::Floats(X.iter_rows().map(|row| row[0]).collect()), 0);
+    frame.new_column(DataType::Floats(X.iter_rows().map(|row| row[1]).collect()), 1);
+    frame.new_column(y.clone(), 2);
+    let mut net = NeuralNet::new(&frame, vec![2], vec![8], vec![ActivationFunction::Tanh, ActivationFunction::Linear], CostFunction::MSE, -0.02, OutputMap::ArgMax, 2);
+    net.xavier_weights();
+    net.set_epoch_value(100);
+    assert_eq!(net.try_predict(&[0.5, -0.2]), Err(MLError::NotFitted));
+    net.fit(&X, &y).unwrap();
+    //`predict` gives the output node, like `predict_float` but without the mutable borrow.
+    assert_eq!(net.predict(&[0.5, -0.2]), ReturnType::Floats(net.predict_float(&[0.5, -0.2])));
+    assert!(r2_score(&net, &X_test, &y_test) > 0.8);
+    assert_eq!(net.probabilities(&[0.5, -0.2]), None);
+
+    let mut model = VotingRegressor::new();
+    model.add("linear", LinearRegression::new());
+    model.add("network", net);
+    model.fit(&X, &y).unwrap();
+    assert!(model.estimator("network").unwrap().is_fitted());
+    assert!(r2_score(&model, &X_test, &y_test) > 0.8);
+}
+
+#[test]
+fn voting_regressor_averages() {
+    let (X, y) = curve(2, 200);
+    let mut model = VotingRegressor::new();
+    model.add("linear", LinearRegression::new());
+    model.add("neighbours", KNearestNeighbours::regressor(5));
+    model.set_weights(Some(vec![1.0, 3.0])).unwrap();
+    model.fit(&X, &y).unwrap();
+    let point = [0.5, -0.2];
+    let expected = match (model.estimator("linear").unwrap().predict(&point), model.estimator("neighbours").unwrap().predict(&point)) {
+        (ReturnType::Floats(linear), ReturnType::Floats(neighbours)) => (linear + 3.0 * neighbours) / 4.0,
+        _ => unreachable!(),
+    };
+    match model.predict(&point) {
+        ReturnType::Floats(value) => assert!((value - expected).abs() < 1e-5),
+        _ => unreachable!(),
+    }
+    assert!(matches!(model.fit(&X, &DataType::Category(vec![0; 200])), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn stacking_classifier_uses_out_of_fold_predictions() {
+    let (X, y) = blobs(3, 300);
+    let (X_test, y_test) = blobs(4, 300);
+    let mut model = StackingClassifier::new(LogisticRegression::new());
+    model.add("bayes", GaussianNb::new());
+    model.add("neighbours", KNearestNeighbours::classifier(7));
+    model.add("svm", Svc::new(Kernel::Rbf { gamma: None }, 1.0));
+    model.fit(&X, &y).unwrap();
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.8);
+    //3 probabilities from the bayes and the neighbours, a one-hot vote from the svm.
+    let features = model.transform(&[1.0, 1.0]);
+    assert_eq!(features.len(), 9);
+    assert_eq!(features[6..].iter().sum::<f32>(), 1.0);
+    let probabilities = model.predict_proba(&[1.0, 2.0]).unwrap();
+    assert!(probabilities[2] > 0.5);
+
+    model.set_passthrough(true);
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.transform(&[1.0, 1.0]).len(), 11);
+    //the same seed deals the same folds.
+    let mut again = model.clone();
+    again.fit(&X, &y).unwrap();
+    assert!(X_test.iter_rows().all(|row| model.probabilities(row) == again.probabilities(row)));
+
+    model.set_folds(1, 0);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn stacking_regressor_beats_its_members() {
+    let (X, y) = curve(5, 300);
+    let (X_test, y_test) = curve(6, 300);
+    let mut model = StackingRegressor::new(LinearRegression::new());
+    model.add("linear", LinearRegression::new());
+    model.add("neighbours", KNearestNeighbours::regressor(5));
+    model.set_passthrough(true);
+    model.fit(&X, &y).unwrap();
+    let stacked = r2_score(&model, &X_test, &y_test);
+    for name in ["linear", "neighbours"] {
+        assert!(stacked > r2_score(model.estimator(name).unwrap(), &X_test, &y_test), "{} {}", name, stacked);
+    }
+    assert_eq!(model.transform(&[0.0, 0.0]).len(), 4);
+    assert!(matches!(model.fit(&X, &DataType::Category(vec![0; 300])), Err(MLError::InvalidTarget(_))));
+}
+
+fn stump() -> DecisionTree {
+    let mut tree = DecisionTree::new(Criterion::Gini);
+    tree.set_max_depth(Some(1));
+    tree
+}
+
+#[test]
+fn bagging_wraps_any_estimator() {
+    let (X, y) = blobs(7, 300);
+    let (X_test, y_test) = blobs(8, 300);
+    let mut model = BaggingClassifier::new(DecisionTree::new(Criterion::Gini), 20);
+    model.set_features(0.5, false);
+    model.set_seed(3);
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.estimators().len(), 20);
+    assert!(model.estimator_features().iter().all(|columns| columns.len() == 1));
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.75);
+    let probabilities = model.predict_proba(&[1.0, 2.0]);
+    assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+
+    //the members of a seed are the same whatever the thread they were fitted on.
+    let mut again = model.clone();
+    again.fit(&X, &y).unwrap();
+    assert!(X_test.iter_rows().all(|row| model.predict_proba(row) == again.predict_proba(row)));
+
+    let mut bayes = BaggingClassifier::new(GaussianNb::new(), 10);
+    bayes.set_samples(0.5, false);
+    bayes.fit(&X, &y).unwrap();
+    assert!(accuracy_score(&bayes, &X_test, &y_test) > 0.8);
+    bayes.set_samples(1.5, false);
+    assert!(matches!(bayes.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+
+    //averaging deep trees beats a single deep tree.
+    let (X, y) = curve(9, 300);
+    let (X_test, y_test) = curve(10, 300);
+    let mut tree = DecisionTree::new(Criterion::MSE);
+    tree.fit(&X, &y).unwrap();
+    let mut bagged = BaggingRegressor::new(DecisionTree::new(Criterion::MSE), 30);
+    bagged.fit(&X, &y).unwrap();
+    assert!(r2_score(&bagged, &X_test, &y_test) > r2_score(&tree, &X_test, &y_test));
+    assert!(matches!(bagged.fit(&X, &DataType::Category(vec![0; 300])), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn adaboost_classifier_boosts_stumps() {
+    let (X, y) = blobs(11, 300);
+    let (X_test, y_test) = blobs(12, 300);
+    let mut single = stump();
+    single.fit(&X, &y).unwrap();
+    for algorithm in [AdaBoostAlgorithm::Samme, AdaBoostAlgorithm::SammeR] {
+        let mut model = AdaBoostClassifier::new(stump(), 50);
+        model.set_algorithm(algorithm);
+        model.set_learning_rate(0.5);
+        model.fit(&X, &y).unwrap();
+        //a stump can only tell two of the three classes apart.
+        assert!(accuracy_score(&single, &X_test, &y_test) < 0.7);
+        assert!(accuracy_score(&model, &X_test, &y_test) > 0.8, "{:?}", algorithm);
+        assert_eq!(model.estimators().len(), model.estimator_weights().len());
+        assert!(model.estimator_errors().iter().all(|error| *error < 2.0 / 3.0));
+        let probabilities = model.predict_proba(&[0.0, 0.0]);
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5 && probabilities[0] > probabilities[1]);
+    }
+
+    //a model without sample weights is boosted on weighted resamples.
+    let mut bayes = AdaBoostClassifier::new(GaussianNb::new(), 10);
+    bayes.fit(&X, &y).unwrap();
+    assert!(accuracy_score(&bayes, &X_test, &y_test) > 0.8);
+
+    //samples with no weight are never learned.
+    let labels = match &y { DataType::Category(labels) => labels.clone(), _ => unreachable!() };
+    let weights: Vec<f32> = labels.iter().map(|label| if *label == 2 { 0.0 } else { 1.0 }).collect();
+    let mut model = AdaBoostClassifier::new(stump(), 20);
+    model.fit_weighted(&X, &y, &weights).unwrap();
+    assert!(X_test.iter_rows().all(|row| model.predict(row) != ReturnType::Category(2)));
+    assert!(matches!(model.fit_weighted(&X, &y, &[1.0]), Err(MLError::SampleMismatch { .. })));
+    assert!(matches!(AdaBoostClassifier::new(Svc::new(Kernel::Linear, 1.0), 5).fit(&X, &DataType::Category(vec![0; 300])), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn adaboost_regressor_takes_the_weighted_median() {
+    let (X, y) = curve(13, 300);
+    let (X_test, y_test) = curve(14, 300);
+    let shallow = || { let mut tree = DecisionTree::new(Criterion::MSE); tree.set_max_depth(Some(3)); tree };
+    let mut single = shallow();
+    single.fit(&X, &y).unwrap();
+    for loss in [AdaBoostLoss::Linear, AdaBoostLoss::Square, AdaBoostLoss::Exponential] {
+        let mut model = AdaBoostRegressor::new(shallow(), 30);
+        model.set_loss(loss);
+        model.fit(&X, &y).unwrap();
+        assert!(r2_score(&model, &X_test, &y_test) > r2_score(&single, &X_test, &y_test), "{:?}", loss);
+        assert!(model.estimator_errors().iter().all(|error| *error < 0.5));
+    }
+    let mut params = Params::new();
+    params.insert("loss".to_owned(), ParamValue::Text("cubic".to_owned()));
+    assert!(matches!(AdaBoostRegressor::new(shallow(), 5).set_params(&params), Err(MLError::InvalidParameter(_))));
+}
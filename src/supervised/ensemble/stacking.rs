@@ -0,0 +1,353 @@
+//! #Stacking ensembles#
+//!
+//! A final estimator learns how to combine the predictions of the members. To keep it from trusting members that just
+//! memorised the training set, it is fitted on out-of-fold predictions : the training set is cut into `n_folds` folds and
+//! every fold is predicted by copies of the members fitted on the other folds. The members are then refitted on the whole set for predicting.
+//!
+//! * `StackingClassifier` -> the features of the final estimator are the class probabilities of every member, or a one-hot
+//!   encoding of the predicted class for members without probabilities. The folds are stratified.
+//! * `StackingRegressor` -> the features are the predictions of the members.
+//!
+//! With `passthrough` the final estimator also sees the original features. Every(member, fold) pair is fitted in parallel.
+//! Sample weights go to every fit, the members' and the final estimator's, so all of them need to take weights.
+
+use rayon::prelude::*;
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix};
+use super::common::{select_targets, class_index, encode_classes, check_members, fit_member, fit_members, members_take_weights, k_folds};
+
+
+///what the members say about a point, the input of the final estimator.
+fn meta_features(estimators : &[(String, Box<dyn Estimator>)], classes : Option<&DataType>, point : &[f32], passthrough : bool) -> Result<Vec<f32>, MLError> {
+    let mut features = vec![];
+    for (name, estimator) in estimators {
+        append_member(&mut features, name, estimator.as_ref(), classes, point)?;
+    }
+    if passthrough {
+        features.extend_from_slice(point);
+    }
+    Ok(features)
+}
+
+fn append_member(features : &mut Vec<f32>, name : &str, estimator : &dyn Estimator, classes : Option<&DataType>, point : &[f32]) -> Result<(), MLError> {
+    match classes {
+        Some(classes) => match estimator.probabilities(point) {
+            Some(probabilities) if probabilities.len() == classes.len() => features.extend(probabilities),
+            Some(probabilities) => return Err(MLError::InvalidParameter(format!("'{}' gave {} probabilities for {} classes, every fold needs every class", name, probabilities.len(), classes.len()))),
+            None => {
+                let prediction = estimator.predict(point);
+                let class = class_index(classes, &prediction).ok_or_else(|| MLError::InvalidTarget(format!("'{}' predicted {:?}, which is not a class", name, prediction)))?;
+                features.extend((0..classes.len()).map(|index| (index == class) as u8 as f32));
+            },
+        },
+        None => match estimator.predict(point) {
+            ReturnType::Floats(value) => features.push(value),
+            other => return Err(MLError::InvalidTarget(format!("'{}' predicted {:?}, a stacking regressor needs float predictions", name, other))),
+        },
+    }
+    Ok(())
+}
+
+///the out-of-fold meta features of every training sample.
+fn out_of_fold(estimators : &[(String, Box<dyn Estimator>)], classes : Option<&DataType>, X_train : &Matrix, y_train : &DataType,
+    sample_weights : Option<&[f32]>, n_folds : usize, seed : u64, passthrough : bool) -> Result<Matrix, MLError> {
+    let folds = k_folds(y_train, n_folds, seed, classes.is_some());
+    let jobs: Vec<(usize, usize)> = (0..estimators.len()).flat_map(|member| (0..n_folds).map(move |fold| (member, fold))).collect();
+    //for every job, the meta features of the member for the held out samples of the fold.
+    let columns: Vec<Vec<Vec<f32>>> = jobs.par_iter().map(|(member, fold)| {
+        let (name, estimator) = &estimators[*member];
+        let train: Vec<usize> = folds.iter().enumerate().filter(|(other, _)| other != fold).flat_map(|(_, samples)| samples.iter().copied()).collect();
+        let mut copy = estimator.clone_box();
+        let train_weights = sample_weights.map(|weights| train.iter().map(|sample| weights[*sample]).collect::<Vec<f32>>());
+        fit_member(name, copy.as_mut(), &X_train.select_rows(&train), &select_targets(y_train, &train), train_weights.as_deref())?;
+        folds[*fold].iter().map(|sample| {
+            let mut features = vec![];
+            append_member(&mut features, name, copy.as_ref(), classes, X_train.row(*sample))?;
+            Ok(features)
+        }).collect()
+    }).collect::<Result<_, MLError>>()?;
+
+    let mut rows = vec![vec![]; X_train.rows()];
+    for ((_, fold), features) in jobs.iter().zip(columns) {
+        for (sample, member_features) in folds[*fold].iter().zip(features) {
+            rows[*sample].extend(member_features);
+        }
+    }
+    if passthrough {
+        rows.iter_mut().zip(X_train.iter_rows()).for_each(|(row, point)| row.extend_from_slice(point));
+    }
+    Ok(Matrix::from(rows))
+}
+
+fn check_folds(n_folds : usize, samples : usize) -> Result<(), MLError> {
+    if n_folds < 2 || n_folds > samples {
+        return Err(MLError::InvalidParameter(format!("n_folds needs to be in [2, {}], got {}", samples, n_folds)));
+    }
+    Ok(())
+}
+
+fn stacking_params(n_folds : usize, passthrough : bool, seed : u64) -> Params {
+    let mut params = Params::new();
+    params.insert("n_folds".to_owned(), ParamValue::Int(n_folds as i64));
+    params.insert("passthrough".to_owned(), ParamValue::Bool(passthrough));
+    params.insert("seed".to_owned(), ParamValue::Int(seed as i64));
+    params
+}
+
+
+#[derive(Clone)]
+pub struct StackingClassifier {
+    ///number of folds for the out-of-fold predictions.
+    pub n_folds : usize,
+    ///give the original features to the final estimator as well.
+    pub passthrough : bool,
+    ///seed for dealing the samples into folds.
+    pub seed : u64,
+    estimators : Vec<(String, Box<dyn Estimator>)>,
+    final_estimator : Box<dyn Estimator>,
+    classes : Option<DataType>,
+    n_features : usize,
+}
+
+impl StackingClassifier {
+
+    ///a `LogisticRegression` is the usual final estimator.
+    pub fn new<E : Estimator + 'static>(final_estimator : E) -> StackingClassifier {
+        StackingClassifier {
+            n_folds: 5,
+            passthrough: false,
+            seed: 0,
+            estimators: vec![],
+            final_estimator: Box::new(final_estimator),
+            classes: None,
+            n_features: 0,
+        }
+    }
+
+    ///adds a member, the name is used to get it back with `estimator`.
+    pub fn add<E : Estimator + 'static>(&mut self, name : &str, estimator : E) {
+        self.estimators.push((name.to_owned(), Box::new(estimator)));
+        self.classes = None;
+    }
+
+    pub fn set_folds(&mut self, n_folds : usize, seed : u64) {
+        self.n_folds = n_folds;
+        self.seed = seed;
+    }
+
+    pub fn set_passthrough(&mut self, passthrough : bool) {
+        self.passthrough = passthrough;
+    }
+
+    pub fn estimator(&self, name : &str) -> Option<&dyn Estimator> {
+        self.estimators.iter().find(|(other, _)| other == name).map(|(_, estimator)| estimator.as_ref())
+    }
+
+    pub fn final_estimator(&self) -> &dyn Estimator {
+        self.final_estimator.as_ref()
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///the features the final estimator sees for a point.
+    pub fn transform(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        meta_features(&self.estimators, self.classes.as_ref(), point, self.passthrough).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    ///the probabilities of the final estimator, `None` if it has none.
+    pub fn predict_proba(&self, point : &[f32]) -> Option<Vec<f32>> {
+        self.final_estimator.probabilities(&self.transform(point))
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_members(&self.estimators)?;
+        check_folds(self.n_folds, X_train.rows())?;
+        let (classes, _) = encode_classes(y_train, "a stacking classifier")?;
+        self.classes = None;
+        let meta = out_of_fold(&self.estimators, Some(&classes), X_train, y_train, sample_weights, self.n_folds, self.seed, self.passthrough)?;
+        fit_member("final", self.final_estimator.as_mut(), &meta, y_train, sample_weights)?;
+        fit_members(&mut self.estimators, X_train, y_train, sample_weights)?;
+        self.classes = Some(classes);
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+}
+
+impl MLalgo for StackingClassifier {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        members_take_weights(&self.estimators) && self.final_estimator.supports_sample_weights()
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        stacking_params(self.n_folds, self.passthrough, self.seed)
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_folds" => self.n_folds = value.as_usize(name)?,
+                "passthrough" => self.passthrough = value.as_bool(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predict for StackingClassifier {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        self.final_estimator.predict(&self.transform(point))
+    }
+}
+
+impl Estimator for StackingClassifier {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        self.predict_proba(point)
+    }
+}
+
+
+#[derive(Clone)]
+pub struct StackingRegressor {
+    ///number of folds for the out-of-fold predictions.
+    pub n_folds : usize,
+    ///give the original features to the final estimator as well.
+    pub passthrough : bool,
+    ///seed for dealing the samples into folds.
+    pub seed : u64,
+    estimators : Vec<(String, Box<dyn Estimator>)>,
+    final_estimator : Box<dyn Estimator>,
+    n_features : usize,
+    fitted : bool,
+}
+
+impl StackingRegressor {
+
+    ///a ridge `LinearRegression` is the usual final estimator.
+    pub fn new<E : Estimator + 'static>(final_estimator : E) -> StackingRegressor {
+        StackingRegressor {
+            n_folds: 5,
+            passthrough: false,
+            seed: 0,
+            estimators: vec![],
+            final_estimator: Box::new(final_estimator),
+            n_features: 0,
+            fitted: false,
+        }
+    }
+
+    ///adds a member, the name is used to get it back with `estimator`.
+    pub fn add<E : Estimator + 'static>(&mut self, name : &str, estimator : E) {
+        self.estimators.push((name.to_owned(), Box::new(estimator)));
+        self.fitted = false;
+    }
+
+    pub fn set_folds(&mut self, n_folds : usize, seed : u64) {
+        self.n_folds = n_folds;
+        self.seed = seed;
+    }
+
+    pub fn set_passthrough(&mut self, passthrough : bool) {
+        self.passthrough = passthrough;
+    }
+
+    pub fn estimator(&self, name : &str) -> Option<&dyn Estimator> {
+        self.estimators.iter().find(|(other, _)| other == name).map(|(_, estimator)| estimator.as_ref())
+    }
+
+    pub fn final_estimator(&self) -> &dyn Estimator {
+        self.final_estimator.as_ref()
+    }
+
+    ///the features the final estimator sees for a point.
+    pub fn transform(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        meta_features(&self.estimators, None, point, self.passthrough).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_members(&self.estimators)?;
+        check_folds(self.n_folds, X_train.rows())?;
+        if !matches!(y_train, DataType::Floats(_)) {
+            return Err(MLError::InvalidTarget("a stacking regressor needs a float target, use the stacking classifier for classes".to_owned()));
+        }
+        self.fitted = false;
+        let meta = out_of_fold(&self.estimators, None, X_train, y_train, sample_weights, self.n_folds, self.seed, self.passthrough)?;
+        fit_member("final", self.final_estimator.as_mut(), &meta, y_train, sample_weights)?;
+        fit_members(&mut self.estimators, X_train, y_train, sample_weights)?;
+        self.n_features = X_train.cols();
+        self.fitted = true;
+        Ok(())
+    }
+}
+
+impl MLalgo for StackingRegressor {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        members_take_weights(&self.estimators) && self.final_estimator.supports_sample_weights()
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        stacking_params(self.n_folds, self.passthrough, self.seed)
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_folds" => self.n_folds = value.as_usize(name)?,
+                "passthrough" => self.passthrough = value.as_bool(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predict for StackingRegressor {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        self.final_estimator.predict(&self.transform(point))
+    }
+}
+
+impl Estimator for StackingRegressor {}
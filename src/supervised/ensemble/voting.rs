@@ -0,0 +1,287 @@
+//! #Voting ensembles#
+//!
+//! Fit a few different models on the same data and let them vote.
+//! * `VotingClassifier` -> hard voting counts the(weighted) predicted classes, soft voting averages the(weighted) class probabilities,
+//!   which needs every member to give probabilities(see `Estimator::probabilities`).
+//! * `VotingRegressor` -> the weighted mean of the predictions.
+//!
+//! The members are any `Estimator`, they are trained in parallel with rayon. Sample weights are passed on to every member,
+//! so all of them need to take weights.
+
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix};
+use super::common::{class_index, argmax, encode_classes, member_weights, check_members, fit_members, members_take_weights};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Voting {
+    ///majority of the predicted classes.
+    Hard,
+    ///highest mean probability.
+    Soft,
+}
+
+impl Voting {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Voting::Hard => "hard",
+            Voting::Soft => "soft",
+        }
+    }
+}
+
+
+#[derive(Clone)]
+pub struct VotingClassifier {
+    pub voting : Voting,
+    ///one weight per estimator, in the order they were added, `None` for equal weights.
+    pub weights : Option<Vec<f32>>,
+    estimators : Vec<(String, Box<dyn Estimator>)>,
+    classes : Option<DataType>,
+    n_features : usize,
+}
+
+impl VotingClassifier {
+
+    pub fn new(voting : Voting) -> VotingClassifier {
+        VotingClassifier {
+            voting,
+            weights: None,
+            estimators: vec![],
+            classes: None,
+            n_features: 0,
+        }
+    }
+
+    ///adds a member, the name is used to get it back with `estimator`.
+    pub fn add<E : Estimator + 'static>(&mut self, name : &str, estimator : E) {
+        self.estimators.push((name.to_owned(), Box::new(estimator)));
+        self.classes = None;
+    }
+
+    pub fn set_voting(&mut self, voting : Voting) {
+        self.voting = voting;
+    }
+
+    ///one weight per member added so far, so add all the members first.
+    pub fn set_weights(&mut self, weights : Option<Vec<f32>>) -> Result<(), MLError> {
+        member_weights(weights.as_ref(), self.estimators.len())?;
+        self.weights = weights;
+        Ok(())
+    }
+
+    pub fn estimator(&self, name : &str) -> Option<&dyn Estimator> {
+        self.estimators.iter().find(|(other, _)| other == name).map(|(_, estimator)| estimator.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.estimators.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///the weighted mean of the member probabilities(soft voting) or the weighted share of the votes(hard voting), in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        let classes = self.classes.as_ref().unwrap();
+        let weights = member_weights(self.weights.as_ref(), self.estimators.len()).unwrap();
+        let mut totals = vec![0.0_f32; classes.len()];
+        for ((name, estimator), weight) in self.estimators.iter().zip(&weights) {
+            match self.voting {
+                Voting::Soft => {
+                    let probabilities = estimator.probabilities(point).unwrap_or_else(|| panic!("'{}' can not give probabilities", name));
+                    totals.iter_mut().zip(&probabilities).for_each(|(total, p)| *total += weight * p);
+                },
+                Voting::Hard => {
+                    let prediction = estimator.predict(point);
+                    let class = class_index(classes, &prediction).unwrap_or_else(|| panic!("'{}' predicted {:?}, which is not a class", name, prediction));
+                    totals[class] += weight;
+                },
+            }
+        }
+        let total: f32 = weights.iter().sum();
+        totals.iter_mut().for_each(|value| *value /= total);
+        totals
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_members(&self.estimators)?;
+        member_weights(self.weights.as_ref(), self.estimators.len())?;
+        let (classes, _) = encode_classes(y_train, "a voting classifier")?;
+        self.classes = None;
+        fit_members(&mut self.estimators, X_train, y_train, sample_weights)?;
+        if self.voting == Voting::Soft {
+            for (name, estimator) in &self.estimators {
+                match estimator.probabilities(X_train.row(0)) {
+                    Some(probabilities) if probabilities.len() == classes.len() => {},
+                    Some(probabilities) => return Err(MLError::InvalidParameter(format!("'{}' gave {} probabilities for {} classes", name, probabilities.len(), classes.len()))),
+                    None => return Err(MLError::InvalidParameter(format!("'{}' can not give probabilities, use hard voting", name))),
+                }
+            }
+        }
+        self.classes = Some(classes);
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+}
+
+impl MLalgo for VotingClassifier {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        members_take_weights(&self.estimators)
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("voting".to_owned(), ParamValue::Text(self.voting.name().to_owned()));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "voting" => self.voting = match value.as_str(name)? {
+                    "hard" => Voting::Hard,
+                    "soft" => Voting::Soft,
+                    other => return Err(MLError::InvalidParameter(format!("voting needs to be 'hard' or 'soft', got '{}'", other))),
+                },
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predict for VotingClassifier {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let probabilities = self.predict_proba(point);
+        self.classes.as_ref().unwrap().value_at(argmax(&probabilities))
+    }
+}
+
+impl Estimator for VotingClassifier {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+
+#[derive(Clone)]
+pub struct VotingRegressor {
+    ///one weight per estimator, in the order they were added, `None` for equal weights.
+    pub weights : Option<Vec<f32>>,
+    estimators : Vec<(String, Box<dyn Estimator>)>,
+    n_features : usize,
+    fitted : bool,
+}
+
+impl VotingRegressor {
+
+    pub fn new() -> VotingRegressor {
+        VotingRegressor {
+            weights: None,
+            estimators: vec![],
+            n_features: 0,
+            fitted: false,
+        }
+    }
+
+    ///adds a member, the name is used to get it back with `estimator`.
+    pub fn add<E : Estimator + 'static>(&mut self, name : &str, estimator : E) {
+        self.estimators.push((name.to_owned(), Box::new(estimator)));
+        self.fitted = false;
+    }
+
+    ///one weight per member added so far, so add all the members first.
+    pub fn set_weights(&mut self, weights : Option<Vec<f32>>) -> Result<(), MLError> {
+        member_weights(weights.as_ref(), self.estimators.len())?;
+        self.weights = weights;
+        Ok(())
+    }
+
+    pub fn estimator(&self, name : &str) -> Option<&dyn Estimator> {
+        self.estimators.iter().find(|(other, _)| other == name).map(|(_, estimator)| estimator.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.estimators.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_members(&self.estimators)?;
+        member_weights(self.weights.as_ref(), self.estimators.len())?;
+        if !matches!(y_train, DataType::Floats(_)) {
+            return Err(MLError::InvalidTarget("a voting regressor needs a float target, use the voting classifier for classes".to_owned()));
+        }
+        self.fitted = false;
+        fit_members(&mut self.estimators, X_train, y_train, sample_weights)?;
+        self.n_features = X_train.cols();
+        self.fitted = true;
+        Ok(())
+    }
+}
+
+impl Default for VotingRegressor {
+    fn default() -> Self {
+        VotingRegressor::new()
+    }
+}
+
+impl MLalgo for VotingRegressor {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        members_take_weights(&self.estimators)
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.then_some(self.n_features)
+    }
+}
+
+impl Predict for VotingRegressor {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        let weights = member_weights(self.weights.as_ref(), self.estimators.len()).unwrap();
+        let total: f32 = self.estimators.iter().zip(&weights).map(|((name, estimator), weight)| match estimator.predict(point) {
+            ReturnType::Floats(value) => weight * value,
+            other => panic!("'{}' predicted {:?}, a voting regressor needs float predictions", name, other),
+        }).sum();
+        ReturnType::Floats(total / weights.iter().sum::<f32>())
+    }
+}
+
+impl Estimator for VotingRegressor {}
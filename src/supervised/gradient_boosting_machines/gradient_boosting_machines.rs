@@ -0,0 +1,896 @@
+//! #Gradient boosting machines#
+//!
+//! Histogram based gradient boosted trees. Every iteration fits small regression trees to the gradients(and hessians) of the
+//! loss with respect to the current predictions and adds them, shrunk by the learning rate, to the model.
+//!
+//! * Before training every feature is cut into atmost `max_bins` quantile bins, the trees only look at the bin of a value.
+//!   Finding a split is then a scan over a histogram of `max_bins` gradient sums instead of sorting the samples,
+//!   and the histogram of the bigger child is the parent's minus the smaller child's.
+//! * NaN values get a bin of their own. Every split learns which side the NaN's go to(the one with the bigger gain),
+//!   and NaN's seen at predict time follow that side. Features without NaN's in training send them to the left.
+//! * Losses : squared, absolute and Huber error for regression, logistic(two classes) and softmax(any number of classes) for classification.
+//! * Row subsampling(stochastic gradient boosting) per iteration and column subsampling per tree.
+//! * Early stopping on a validation set, either a given one(`fit_with_validation`) or a fraction split off the training set.
+
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix,
+    persistence::persistence::{Persist, PersistError, ensure}};
+
+
+///the bin NaN values go to, the real bins are below it.
+const MISSING_BIN : usize = 255;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GBLoss {
+    ///     (y - F)^2 / 2
+    SquaredError,
+    ///     |y - F|, the leaves are set to the median of their residuals.
+    AbsoluteError,
+    ///squared for residuals up to `delta`, absolute beyond, robust to outliers.
+    Huber { delta : f32 },
+    ///binary log loss, needs exactly two classes.
+    Logistic,
+    ///multinomial log loss, one tree per class and iteration(with two classes it is the same as `Logistic`).
+    Softmax,
+}
+
+impl GBLoss {
+    pub fn is_classification(&self) -> bool {
+        matches!(self, GBLoss::Logistic | GBLoss::Softmax)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            GBLoss::SquaredError => "squared_error",
+            GBLoss::AbsoluteError => "absolute_error",
+            GBLoss::Huber { .. } => "huber",
+            GBLoss::Logistic => "logistic",
+            GBLoss::Softmax => "softmax",
+        }
+    }
+
+    ///the losses without settings, `Huber` is built with its delta and given to `GradientBoosting::new`.
+    fn from_name(name : &str) -> Result<GBLoss, MLError> {
+        match name {
+            "squared_error" => Ok(GBLoss::SquaredError),
+            "absolute_error" => Ok(GBLoss::AbsoluteError),
+            "logistic" => Ok(GBLoss::Logistic),
+            "softmax" => Ok(GBLoss::Softmax),
+            "huber" => Err(MLError::InvalidParameter("the huber loss needs its delta, set it as GBLoss::Huber { delta }".to_owned())),
+            _ => Err(MLError::InvalidParameter(format!("unknown loss '{}'", name))),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HistNode {
+    Leaf { value : f32 },
+    ///`x[feature] <= threshold` goes left, NaN goes left if `missing_left`.
+    Split { feature : usize, threshold : f32, missing_left : bool, left : usize, right : usize, gain : f32 },
+}
+
+///A regression tree on the gradients, the root is node 0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistTree {
+    pub nodes : Vec<HistNode>,
+}
+
+impl HistTree {
+    pub fn predict(&self, point : &[f32]) -> f32 {
+        let mut index = 0;
+        loop {
+            match self.nodes[index] {
+                HistNode::Leaf { value } => return value,
+                HistNode::Split { feature, threshold, missing_left, left, right, .. } => {
+                    let value = point[feature];
+                    let go_left = if value.is_nan() { missing_left } else { value <= threshold };
+                    index = if go_left { left } else { right };
+                },
+            }
+        }
+    }
+}
+
+
+///the bin edges of every feature, a value goes to the first bin whose edge is >= the value.
+#[derive(Debug, Clone)]
+struct BinMapper {
+    edges : Vec<Vec<f32>>,
+}
+
+impl BinMapper {
+    fn fit(X : &Matrix, max_bins : usize) -> BinMapper {
+        let edges = (0..X.cols()).map(|feature| {
+            let mut values: Vec<f32> = X.column(feature).into_iter().filter(|value| !value.is_nan()).collect();
+            values.sort_by(|a, b| a.total_cmp(b));
+            values.dedup();
+            if values.len() <= max_bins {
+                values.windows(2).map(|pair| (pair[0] + pair[1]) / 2.0).collect()
+            } else {
+                //quantiles of the distinct values, the edge sits halfway between two neighbouring values.
+                let mut edges: Vec<f32> = (1..max_bins).map(|bin| {
+                    let position = bin * (values.len() - 1) / max_bins;
+                    (values[position] + values[position + 1]) / 2.0
+                }).collect();
+                edges.dedup();
+                edges
+            }
+        }).collect();
+        BinMapper { edges }
+    }
+
+    fn bin(&self, feature : usize, value : f32) -> u8 {
+        if value.is_nan() {
+            return MISSING_BIN as u8;
+        }
+        self.edges[feature].partition_point(|edge| *edge < value) as u8
+    }
+
+    ///column major, `binned[feature * rows + row]`.
+    fn transform(&self, X : &Matrix) -> Vec<u8> {
+        let mut binned = vec![0_u8; X.rows() * X.cols()];
+        for (row, point) in X.iter_rows().enumerate() {
+            for (feature, value) in point.iter().enumerate() {
+                binned[feature * X.rows() + row] = self.bin(feature, *value);
+            }
+        }
+        binned
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, Default)]
+struct HistogramBin {
+    gradient : f64,
+    hessian : f64,
+    count : u32,
+}
+
+///one histogram of 256 bins per feature.
+type Histogram = Vec<HistogramBin>;
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GradientBoosting {
+    pub loss : GBLoss,
+    pub n_estimators : usize,
+    ///shrinkage, every tree is multiplied by it before being added.
+    pub learning_rate : f32,
+    pub max_depth : usize,
+    pub min_samples_leaf : usize,
+    ///L2 penalty on the leaf values, `leaf = -G / (H + l2_regularization)`.
+    pub l2_regularization : f32,
+    ///number of bins per feature, atmost 255.
+    pub max_bins : usize,
+    ///fraction of the rows every iteration is trained on.
+    pub subsample : f32,
+    ///fraction of the features every tree can split on.
+    pub colsample : f32,
+    pub seed : u64,
+    ///fraction of the training set held out for early stopping when fitting with `fit`, `None` trains all iterations.
+    pub validation_fraction : Option<f32>,
+    ///stops when the validation loss did not improve by more than `tol` for this many iterations.
+    pub n_iter_no_change : usize,
+    pub tol : f32,
+    ///the starting raw prediction, one per tree of an iteration.
+    init : Vec<f32>,
+    ///the trees of every iteration, one per class for softmax and one otherwise.
+    trees : Vec<Vec<HistTree>>,
+    classes : Option<DataType>,
+    n_features : usize,
+    train_loss : Vec<f32>,
+    validation_loss : Vec<f32>,
+}
+
+///what the trees are fitted against.
+enum Targets {
+    Values(Vec<f32>),
+    Classes(Vec<usize>, usize),
+}
+
+impl GradientBoosting {
+
+    ///squared error regression.
+    pub fn regressor() -> GradientBoosting {
+        GradientBoosting::new(GBLoss::SquaredError)
+    }
+
+    ///softmax classification, works for any number of classes.
+    pub fn classifier() -> GradientBoosting {
+        GradientBoosting::new(GBLoss::Softmax)
+    }
+
+    pub fn new(loss : GBLoss) -> GradientBoosting {
+        GradientBoosting {
+            loss,
+            n_estimators: 100,
+            learning_rate: 0.1,
+            max_depth: 3,
+            min_samples_leaf: 20,
+            l2_regularization: 0.0,
+            max_bins: 255,
+            subsample: 1.0,
+            colsample: 1.0,
+            seed: 0,
+            validation_fraction: None,
+            n_iter_no_change: 10,
+            tol: 1e-7,
+            init: vec![],
+            trees: vec![],
+            classes: None,
+            n_features: 0,
+            train_loss: vec![],
+            validation_loss: vec![],
+        }
+    }
+
+    pub fn set_boosting(&mut self, n_estimators : usize, learning_rate : f32) {
+        self.n_estimators = n_estimators;
+        self.learning_rate = learning_rate;
+    }
+
+    pub fn set_tree(&mut self, max_depth : usize, min_samples_leaf : usize, l2_regularization : f32) {
+        self.max_depth = max_depth;
+        self.min_samples_leaf = min_samples_leaf;
+        self.l2_regularization = l2_regularization;
+    }
+
+    pub fn set_max_bins(&mut self, max_bins : usize) {
+        self.max_bins = max_bins;
+    }
+
+    ///row subsampling per iteration and column subsampling per tree, drawn with the seed.
+    pub fn set_subsampling(&mut self, subsample : f32, colsample : f32, seed : u64) {
+        self.subsample = subsample;
+        self.colsample = colsample;
+        self.seed = seed;
+    }
+
+    ///hold out `validation_fraction` of the training set in `fit` and stop after `n_iter_no_change` iterations without improvement.
+    pub fn set_early_stopping(&mut self, validation_fraction : Option<f32>, n_iter_no_change : usize, tol : f32) {
+        self.validation_fraction = validation_fraction;
+        self.n_iter_no_change = n_iter_no_change;
+        self.tol = tol;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///the number of boosting iterations kept, smaller than `n_estimators` after early stopping.
+    pub fn n_iter(&self) -> usize {
+        self.trees.len()
+    }
+
+    pub fn trees(&self) -> &[Vec<HistTree>] {
+        &self.trees
+    }
+
+    ///training loss after every iteration(on the rows used for training, before the early stopping cut).
+    pub fn train_loss(&self) -> &[f32] {
+        &self.train_loss
+    }
+
+    ///validation loss after every iteration, empty without early stopping.
+    pub fn validation_loss(&self) -> &[f32] {
+        &self.validation_loss
+    }
+
+    ///total split gain of every feature over all the trees, normalised to sum to 1.
+    pub fn feature_importances(&self) -> Vec<f32> {
+        let mut importances = vec![0.0_f32; self.n_features];
+        for node in self.trees.iter().flatten().flat_map(|tree| &tree.nodes) {
+            if let HistNode::Split { feature, gain, .. } = node {
+                importances[*feature] += gain;
+            }
+        }
+        let total: f32 = importances.iter().sum();
+        if total > 0.0 {
+            importances.iter_mut().for_each(|importance| *importance /= total);
+        }
+        importances
+    }
+
+    ///the raw(untransformed) scores, one per tree of an iteration.
+    pub fn decision_function(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        let mut scores = self.init.clone();
+        for iteration in &self.trees {
+            for (score, tree) in scores.iter_mut().zip(iteration) {
+                *score += tree.predict(point);
+            }
+        }
+        scores
+    }
+
+    ///class probabilities in the order of `classes()`, panics for a regression loss.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert!(self.loss.is_classification(), "predict_proba needs a classification loss");
+        let scores: Vec<f64> = self.decision_function(point).iter().map(|score| *score as f64).collect();
+        probabilities(&scores).iter().map(|p| *p as f32).collect()
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        let problem = if self.n_estimators == 0 {
+            Some("n_estimators needs to be > 0".to_owned())
+        } else if !(self.learning_rate > 0.0) {
+            Some("learning_rate needs to be > 0".to_owned())
+        } else if self.max_depth == 0 || self.min_samples_leaf == 0 {
+            Some("max_depth and min_samples_leaf need to be > 0".to_owned())
+        } else if !(2..=255).contains(&self.max_bins) {
+            Some(format!("max_bins needs to be in [2, 255], got {}", self.max_bins))
+        } else if !(self.subsample > 0.0 && self.subsample <= 1.0) || !(self.colsample > 0.0 && self.colsample <= 1.0) {
+            Some("subsample and colsample need to be in (0, 1]".to_owned())
+        } else if !(self.l2_regularization >= 0.0) {
+            Some("l2_regularization needs to be >= 0".to_owned())
+        } else if self.validation_fraction.map_or(false, |fraction| !(fraction > 0.0 && fraction < 1.0)) {
+            Some("validation_fraction needs to be in (0, 1)".to_owned())
+        } else if matches!(self.loss, GBLoss::Huber { delta } if !(delta > 0.0)) {
+            Some("the Huber delta needs to be > 0".to_owned())
+        } else {
+            None
+        };
+        problem.map_or(Ok(()), |problem| Err(MLError::InvalidParameter(problem)))
+    }
+
+    fn encode_targets(&self, y : &DataType) -> Result<(Targets, Option<DataType>), MLError> {
+        match (y, self.loss.is_classification()) {
+            (DataType::Floats(values), false) => Ok((Targets::Values(values.clone()), None)),
+            (DataType::Floats(_), true) => Err(MLError::InvalidTarget(format!("the {} loss needs category or string targets", self.loss.name()))),
+            (_, false) => Err(MLError::InvalidTarget(format!("the {} loss needs float targets", self.loss.name()))),
+            (_, true) => {
+                let (classes, indices) = y.unique_classes().unwrap();
+                if classes.len() < 2 {
+                    return Err(MLError::InvalidTarget("need atleast two different classes to fit".to_owned()));
+                }
+                if self.loss == GBLoss::Logistic && classes.len() > 2 {
+                    return Err(MLError::InvalidTarget(format!("the logistic loss is for two classes, got {}, use softmax", classes.len())));
+                }
+                Ok((Targets::Classes(indices, classes.len()), Some(classes)))
+            },
+        }
+    }
+
+    ///fits with early stopping against the given validation set.
+    pub fn fit_with_validation(&mut self, X_train : &Matrix, y_train : &DataType, X_validation : &Matrix, y_validation : &DataType) -> Result<(), MLError> {
+        check_fit_input(X_validation, y_validation)?;
+        if X_validation.cols() != X_train.cols() {
+            return Err(MLError::FeatureMismatch { expected: X_train.cols(), found: X_validation.cols() });
+        }
+        self.train(X_train, y_train, &vec![1.0; X_train.rows()], Some((X_validation, y_validation)))
+    }
+
+    ///the gradients and hessians of every sample are scaled by its weight, the validation set is unweighted.
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32], validation : Option<(&Matrix, &DataType)>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+        if !(sample_weights.iter().sum::<f32>() > 0.0) {
+            return Err(MLError::InvalidParameter("at least one sample weight needs to be > 0".to_owned()));
+        }
+        let (targets, classes) = self.encode_targets(y_train)?;
+        let validation_targets = match validation {
+            Some((_, y_validation)) => {
+                let (encoded, validation_classes) = self.encode_targets(y_validation)?;
+                //validation classes are looked up in the training classes, they may be fewer.
+                Some(match (encoded, &classes) {
+                    (Targets::Classes(indices, _), Some(classes)) => {
+                        let names = validation_classes.unwrap();
+                        let lookup: Result<Vec<usize>, MLError> = indices.iter().map(|index| {
+                            let name = names.value_at(*index);
+                            (0..classes.len()).find(|class| classes.value_at(*class) == name)
+                                .ok_or_else(|| MLError::InvalidTarget("the validation set has a class the training set does not".to_owned()))
+                        }).collect();
+                        Targets::Classes(lookup?, classes.len())
+                    },
+                    (encoded, _) => encoded,
+                })
+            },
+            None => None,
+        };
+
+        let n_samples = X_train.rows();
+        let n_scores = match &targets {
+            Targets::Classes(_, n_classes) if *n_classes > 2 => *n_classes,
+            _ => 1,
+        };
+        let init = initial_scores(self.loss, &targets, sample_weights, n_scores);
+        let mapper = BinMapper::fit(X_train, self.max_bins);
+        let binned = mapper.transform(X_train);
+        let mut rng = fastrand::Rng::with_seed(self.seed);
+
+        let mut raw = vec![init.clone(); n_samples];
+        let mut validation_raw = validation.map(|(X_validation, _)| vec![init.clone(); X_validation.rows()]);
+        let mut gradients = vec![vec![0.0_f64; n_samples]; n_scores];
+        let mut hessians = vec![vec![0.0_f64; n_samples]; n_scores];
+        let mut trees: Vec<Vec<HistTree>> = vec![];
+        let (mut best_loss, mut best_iteration, mut without_improvement) = (f32::MAX, 0, 0);
+        self.train_loss.clear();
+        self.validation_loss.clear();
+
+        for iteration in 0..self.n_estimators {
+            compute_gradients(self.loss, &targets, &raw, &mut gradients, &mut hessians);
+            for (gradient, hessian) in gradients.iter_mut().zip(hessians.iter_mut()) {
+                for (sample, weight) in sample_weights.iter().enumerate() {
+                    gradient[sample] *= *weight as f64;
+                    hessian[sample] *= *weight as f64;
+                }
+            }
+
+            let rows: Vec<usize> = if self.subsample < 1.0 {
+                let count = ((self.subsample * n_samples as f32) as usize).max(1);
+                let mut rows: Vec<usize> = (0..n_samples).collect();
+                rng.shuffle(&mut rows);
+                rows.truncate(count);
+                rows.sort();
+                rows
+            } else {
+                (0..n_samples).collect()
+            };
+
+            let mut iteration_trees = Vec::with_capacity(n_scores);
+            for score in 0..n_scores {
+                let features: Vec<usize> = if self.colsample < 1.0 {
+                    let count = ((self.colsample * X_train.cols() as f32) as usize).max(1);
+                    let mut features: Vec<usize> = (0..X_train.cols()).collect();
+                    rng.shuffle(&mut features);
+                    features.truncate(count);
+                    features
+                } else {
+                    (0..X_train.cols()).collect()
+                };
+                let mut grower = TreeGrower {
+                    booster: self,
+                    mapper: &mapper,
+                    binned: &binned,
+                    n_samples,
+                    gradients: &gradients[score],
+                    hessians: &hessians[score],
+                    features: &features,
+                    nodes: vec![],
+                    leaf_samples: vec![],
+                };
+                let histogram = grower.histogram(&rows);
+                grower.grow(rows.clone(), histogram, 0);
+                let (mut nodes, leaf_samples) = (grower.nodes, grower.leaf_samples);
+
+                //absolute and Huber leaves are refitted to the residuals, the Newton step of a constant hessian is only a direction.
+                if let (Targets::Values(values), GBLoss::AbsoluteError | GBLoss::Huber { .. }) = (&targets, self.loss) {
+                    for (node, samples) in leaf_samples {
+                        let residuals: Vec<f32> = samples.iter().map(|sample| values[*sample] - raw[*sample][0]).collect();
+                        let weights: Vec<f32> = samples.iter().map(|sample| sample_weights[*sample]).collect();
+                        nodes[node] = HistNode::Leaf { value: robust_leaf(self.loss, &residuals, &weights) };
+                    }
+                }
+                for node in nodes.iter_mut() {
+                    if let HistNode::Leaf { value } = node {
+                        *value *= self.learning_rate;
+                    }
+                }
+                let tree = HistTree { nodes };
+                for (point, scores) in X_train.iter_rows().zip(raw.iter_mut()) {
+                    scores[score] += tree.predict(point);
+                }
+                if let (Some(validation_raw), Some((X_validation, _))) = (validation_raw.as_mut(), validation) {
+                    for (point, scores) in X_validation.iter_rows().zip(validation_raw.iter_mut()) {
+                        scores[score] += tree.predict(point);
+                    }
+                }
+                iteration_trees.push(tree);
+            }
+            trees.push(iteration_trees);
+            self.train_loss.push(loss_value(self.loss, &targets, &raw, sample_weights));
+
+            if let (Some(validation_raw), Some(validation_targets)) = (validation_raw.as_ref(), validation_targets.as_ref()) {
+                let validation_loss = loss_value(self.loss, validation_targets, validation_raw, &vec![1.0; validation_raw.len()]);
+                self.validation_loss.push(validation_loss);
+                if validation_loss < best_loss - self.tol {
+                    best_loss = validation_loss;
+                    best_iteration = iteration;
+                    without_improvement = 0;
+                } else {
+                    without_improvement += 1;
+                    if without_improvement >= self.n_iter_no_change {
+                        break;
+                    }
+                }
+            }
+        }
+        if validation.is_some() {
+            trees.truncate(best_iteration + 1);
+        }
+
+        self.init = init;
+        self.trees = trees;
+        self.classes = classes;
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+}
+
+
+///softmax of the scores, or the sigmoid of the single binary score.
+fn probabilities(scores : &[f64]) -> Vec<f64> {
+    if scores.len() == 1 {
+        let p = 1.0 / (1.0 + (-scores[0]).exp());
+        return vec![1.0 - p, p];
+    }
+    let max = scores.iter().cloned().fold(f64::MIN, f64::max);
+    let exps: Vec<f64> = scores.iter().map(|score| (score - max).exp()).collect();
+    let total: f64 = exps.iter().sum();
+    exps.iter().map(|e| e / total).collect()
+}
+
+///weighted median, the mean of the two middle values when the weight splits exactly in half between them.
+///A set without weight falls back to the plain median.
+fn median(values : &[f32], weights : &[f32]) -> f32 {
+    let mut pairs: Vec<(f32, f32)> = values.iter().cloned().zip(weights.iter().cloned()).collect();
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut total: f32 = weights.iter().sum();
+    if !(total > 0.0) {
+        pairs.iter_mut().for_each(|pair| pair.1 = 1.0);
+        total = pairs.len() as f32;
+    }
+    let mut cumulative = 0.0_f32;
+    for (position, (value, weight)) in pairs.iter().enumerate() {
+        cumulative += weight;
+        if cumulative == total / 2.0 {
+            if let Some((next, _)) = pairs[position + 1..].iter().find(|(_, weight)| *weight > 0.0) {
+                return (value + next) / 2.0;
+            }
+        }
+        if cumulative >= total / 2.0 {
+            return *value;
+        }
+    }
+    pairs.last().map_or(0.0, |pair| pair.0)
+}
+
+///the optimal constant for the residuals of a leaf, median for absolute error and Friedman's one step approximation for Huber.
+fn robust_leaf(loss : GBLoss, residuals : &[f32], weights : &[f32]) -> f32 {
+    let centre = median(residuals, weights);
+    match loss {
+        GBLoss::Huber { delta } => {
+            let total: f32 = weights.iter().sum();
+            centre + residuals.iter().zip(weights)
+                .map(|(residual, weight)| weight * (residual - centre).signum() * (residual - centre).abs().min(delta))
+                .sum::<f32>() / total.max(f32::MIN_POSITIVE)
+        },
+        _ => centre,
+    }
+}
+
+fn initial_scores(loss : GBLoss, targets : &Targets, weights : &[f32], n_scores : usize) -> Vec<f32> {
+    let total: f32 = weights.iter().sum();
+    match targets {
+        Targets::Values(values) => {
+            match loss {
+                GBLoss::SquaredError => vec![values.iter().zip(weights).map(|(value, weight)| value * weight).sum::<f32>() / total],
+                _ => vec![median(values, weights)],
+            }
+        },
+        Targets::Classes(classes, n_classes) => {
+            let mut priors = vec![0.0_f64; *n_classes];
+            classes.iter().zip(weights).for_each(|(class, weight)| priors[*class] += (weight / total) as f64);
+            //a class the weights zero out would give an infinite log odds, it gets a tiny prior instead.
+            if n_scores == 1 {
+                vec![(priors[1].max(1e-12) / priors[0].max(1e-12)).ln() as f32]
+            } else {
+                priors.iter().map(|prior| prior.max(1e-12).ln() as f32).collect()
+            }
+        },
+    }
+}
+
+fn compute_gradients(loss : GBLoss, targets : &Targets, raw : &[Vec<f32>], gradients : &mut [Vec<f64>], hessians : &mut [Vec<f64>]) {
+    match targets {
+        Targets::Values(values) => {
+            for (sample, (value, scores)) in values.iter().zip(raw).enumerate() {
+                let residual = (scores[0] - value) as f64;
+                gradients[0][sample] = match loss {
+                    GBLoss::AbsoluteError => residual.signum(),
+                    GBLoss::Huber { delta } => residual.clamp(-delta as f64, delta as f64),
+                    _ => residual,
+                };
+                hessians[0][sample] = 1.0;
+            }
+        },
+        Targets::Classes(classes, _) => {
+            for (sample, (class, scores)) in classes.iter().zip(raw).enumerate() {
+                let scores: Vec<f64> = scores.iter().map(|score| *score as f64).collect();
+                let p = probabilities(&scores);
+                for score in 0..gradients.len() {
+                    let (probability, is_class) = if gradients.len() == 1 { (p[1], *class == 1) } else { (p[score], *class == score) };
+                    gradients[score][sample] = probability - is_class as u8 as f64;
+                    hessians[score][sample] = (probability * (1.0 - probability)).max(1e-16);
+                }
+            }
+        },
+    }
+}
+
+///weighted mean loss of the raw scores.
+fn loss_value(loss : GBLoss, targets : &Targets, raw : &[Vec<f32>], weights : &[f32]) -> f32 {
+    let total: f64 = match targets {
+        Targets::Values(values) => values.iter().zip(raw).zip(weights).map(|((value, scores), weight)| {
+            let residual = (value - scores[0]).abs() as f64;
+            let loss = match loss {
+                GBLoss::AbsoluteError => residual,
+                GBLoss::Huber { delta } if residual > delta as f64 => delta as f64 * (residual - delta as f64 / 2.0),
+                _ => residual * residual / 2.0,
+            };
+            loss * *weight as f64
+        }).sum(),
+        Targets::Classes(classes, _) => classes.iter().zip(raw).zip(weights).map(|((class, scores), weight)| {
+            let scores: Vec<f64> = scores.iter().map(|score| *score as f64).collect();
+            -probabilities(&scores)[*class].max(1e-300).ln() * *weight as f64
+        }).sum(),
+    };
+    (total / weights.iter().map(|weight| *weight as f64).sum::<f64>()) as f32
+}
+
+
+//***************************************
+//GROWING A TREE ON THE HISTOGRAMS
+//***************************************
+
+struct TreeGrower<'a> {
+    booster : &'a GradientBoosting,
+    mapper : &'a BinMapper,
+    binned : &'a [u8],
+    n_samples : usize,
+    gradients : &'a [f64],
+    hessians : &'a [f64],
+    features : &'a [usize],
+    nodes : Vec<HistNode>,
+    ///(leaf node, samples in it), used to refit the leaves of the robust losses.
+    leaf_samples : Vec<(usize, Vec<usize>)>,
+}
+
+///the best split of a node.
+struct BestSplit {
+    feature : usize,
+    bin : usize,
+    missing_left : bool,
+    gain : f64,
+}
+
+impl<'a> TreeGrower<'a> {
+
+    fn histogram(&self, rows : &[usize]) -> Histogram {
+        let mut histogram = vec![HistogramBin::default(); self.features.len() * 256];
+        for (slot, feature) in self.features.iter().enumerate() {
+            let column = &self.binned[feature * self.n_samples..(feature + 1) * self.n_samples];
+            let bins = &mut histogram[slot * 256..(slot + 1) * 256];
+            for &row in rows {
+                let bin = &mut bins[column[row] as usize];
+                bin.gradient += self.gradients[row];
+                bin.hessian += self.hessians[row];
+                bin.count += 1;
+            }
+        }
+        histogram
+    }
+
+    fn score(&self, gradient : f64, hessian : f64) -> f64 {
+        gradient * gradient / (hessian + self.booster.l2_regularization as f64)
+    }
+
+    fn leaf_value(&self, gradient : f64, hessian : f64) -> f32 {
+        (-gradient / (hessian + self.booster.l2_regularization as f64).max(1e-16)) as f32
+    }
+
+    fn best_split(&self, histogram : &Histogram, gradient : f64, hessian : f64, count : u32) -> Option<BestSplit> {
+        let min_leaf = self.booster.min_samples_leaf as u32;
+        let parent = self.score(gradient, hessian);
+        let mut best: Option<BestSplit> = None;
+
+        for (slot, &feature) in self.features.iter().enumerate() {
+            let bins = &histogram[slot * 256..(slot + 1) * 256];
+            let missing = bins[MISSING_BIN];
+            let n_bins = self.mapper.edges[feature].len() + 1;
+            let (mut left_gradient, mut left_hessian, mut left_count) = (0.0_f64, 0.0_f64, 0_u32);
+            //splitting after the last bin only separates the NaN's.
+            for bin in 0..n_bins - 1 {
+                left_gradient += bins[bin].gradient;
+                left_hessian += bins[bin].hessian;
+                left_count += bins[bin].count;
+                let directions: &[bool] = if missing.count > 0 { &[false, true] } else { &[true] };
+                for &missing_left in directions {
+                    let (mut g_left, mut h_left, mut c_left) = (left_gradient, left_hessian, left_count);
+                    if missing_left {
+                        g_left += missing.gradient;
+                        h_left += missing.hessian;
+                        c_left += missing.count;
+                    }
+                    let c_right = count - c_left;
+                    if c_left < min_leaf || c_right < min_leaf {
+                        continue;
+                    }
+                    let gain = self.score(g_left, h_left) + self.score(gradient - g_left, hessian - h_left) - parent;
+                    if gain > 1e-10 && best.as_ref().map_or(true, |best| gain > best.gain) {
+                        best = Some(BestSplit { feature, bin, missing_left, gain });
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    fn grow(&mut self, rows : Vec<usize>, histogram : Histogram, depth : usize) -> usize {
+        //every feature's histogram holds all the rows, the first one is enough for the totals.
+        let totals = histogram[..256].iter().fold((0.0, 0.0, 0_u32), |(g, h, c), bin| (g + bin.gradient, h + bin.hessian, c + bin.count));
+        let (gradient, hessian, count) = totals;
+        let position = self.nodes.len();
+        self.nodes.push(HistNode::Leaf { value: self.leaf_value(gradient, hessian) });
+
+        let split = if depth < self.booster.max_depth && count >= 2 * self.booster.min_samples_leaf as u32 {
+            self.best_split(&histogram, gradient, hessian, count)
+        } else {
+            None
+        };
+        let split = match split {
+            Some(split) => split,
+            None => {
+                self.leaf_samples.push((position, rows));
+                return position;
+            },
+        };
+
+        let column = &self.binned[split.feature * self.n_samples..(split.feature + 1) * self.n_samples];
+        let (left, right): (Vec<usize>, Vec<usize>) = rows.iter().partition(|row| {
+            let bin = column[**row] as usize;
+            if bin == MISSING_BIN { split.missing_left } else { bin <= split.bin }
+        });
+        //histogram subtraction, only the smaller child is counted.
+        let smaller_is_left = left.len() <= right.len();
+        let smaller = self.histogram(if smaller_is_left { &left } else { &right });
+        let mut larger = histogram;
+        for (larger_bin, smaller_bin) in larger.iter_mut().zip(&smaller) {
+            larger_bin.gradient -= smaller_bin.gradient;
+            larger_bin.hessian -= smaller_bin.hessian;
+            larger_bin.count -= smaller_bin.count;
+        }
+        let (left_histogram, right_histogram) = if smaller_is_left { (smaller, larger) } else { (larger, smaller) };
+
+        let left = self.grow(left, left_histogram, depth + 1);
+        let right = self.grow(right, right_histogram, depth + 1);
+        self.nodes[position] = HistNode::Split {
+            feature: split.feature,
+            threshold: self.mapper.edges[split.feature][split.bin],
+            missing_left: split.missing_left,
+            left,
+            right,
+            gain: split.gain as f32,
+        };
+        position
+    }
+}
+
+
+impl MLalgo for GradientBoosting {
+    ///uses `validation_fraction` of the samples(the last ones after a seeded shuffle) for early stopping if it is set.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///the weights scale the gradients and hessians of every sample.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+        let fraction = match self.validation_fraction {
+            Some(fraction) => fraction,
+            None => return self.train(X_train, y_train, sample_weights, None),
+        };
+        if X_train.rows() < 2 {
+            return Err(MLError::InvalidParameter(format!("early stopping needs atleast 2 samples to hold some out, got {}", X_train.rows())));
+        }
+        let mut rows: Vec<usize> = (0..X_train.rows()).collect();
+        fastrand::Rng::with_seed(self.seed ^ 0x5DEECE66D).shuffle(&mut rows);
+        let validation_size = ((fraction * rows.len() as f32) as usize).clamp(1, rows.len() - 1);
+        let (train_rows, validation_rows) = rows.split_at(rows.len() - validation_size);
+        let select = |indices : &[usize]| match y_train {
+            DataType::Floats(temp) => DataType::Floats(indices.iter().map(|i| temp[*i]).collect()),
+            DataType::Category(temp) => DataType::Category(indices.iter().map(|i| temp[*i]).collect()),
+            DataType::Strings(temp) => DataType::Strings(indices.iter().map(|i| temp[*i].clone()).collect()),
+        };
+        let train_weights: Vec<f32> = train_rows.iter().map(|row| sample_weights[*row]).collect();
+        self.train(&X_train.select_rows(train_rows), &select(train_rows), &train_weights,
+            Some((&X_train.select_rows(validation_rows), &select(validation_rows))))
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.trees.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("loss".to_owned(), ParamValue::Text(self.loss.name().to_owned()));
+        params.insert("n_estimators".to_owned(), ParamValue::Int(self.n_estimators as i64));
+        params.insert("learning_rate".to_owned(), ParamValue::Float(self.learning_rate));
+        params.insert("max_depth".to_owned(), ParamValue::Int(self.max_depth as i64));
+        params.insert("min_samples_leaf".to_owned(), ParamValue::Int(self.min_samples_leaf as i64));
+        params.insert("l2_regularization".to_owned(), ParamValue::Float(self.l2_regularization));
+        params.insert("max_bins".to_owned(), ParamValue::Int(self.max_bins as i64));
+        params.insert("subsample".to_owned(), ParamValue::Float(self.subsample));
+        params.insert("colsample".to_owned(), ParamValue::Float(self.colsample));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    ///`loss` takes the losses without settings, the name of the current loss leaves it(and its delta) as it is.
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "loss" => if value.as_str(name)? != self.loss.name() {
+                    self.loss = GBLoss::from_name(value.as_str(name)?)?;
+                },
+                "n_estimators" => self.n_estimators = value.as_usize(name)?,
+                "learning_rate" => self.learning_rate = value.as_f32(name)?,
+                "max_depth" => self.max_depth = value.as_usize(name)?,
+                "min_samples_leaf" => self.min_samples_leaf = value.as_usize(name)?,
+                "l2_regularization" => self.l2_regularization = value.as_f32(name)?,
+                "max_bins" => self.max_bins = value.as_usize(name)?,
+                "subsample" => self.subsample = value.as_f32(name)?,
+                "colsample" => self.colsample = value.as_f32(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for GradientBoosting {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        match &self.classes {
+            Some(classes) => {
+                let probabilities = self.predict_proba(point);
+                let best = probabilities.iter().enumerate().fold(0, |best, (index, p)| if *p > probabilities[best] { index } else { best });
+                classes.value_at(best)
+            },
+            None => ReturnType::Floats(self.decision_function(point)[0]),
+        }
+    }
+}
+
+impl Estimator for GradientBoosting {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        self.loss.is_classification().then(|| self.predict_proba(point))
+    }
+}
+
+impl Persist for GradientBoosting {
+    const MODEL_TYPE: &'static str = "GradientBoosting";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let n_scores = match &self.classes {
+            Some(classes) if classes.len() > 2 => classes.len(),
+            _ => 1,
+        };
+        ensure(self.trees.is_empty() || self.init.len() == n_scores, "the initial scores do not match the number of classes")?;
+        for tree in self.trees.iter().flatten() {
+            ensure(!tree.nodes.is_empty(), "a tree needs atleast its root node")?;
+            for (index, node) in tree.nodes.iter().enumerate() {
+                if let HistNode::Split { feature, left, right, .. } = node {
+                    ensure(*feature < self.n_features, "split on a feature that does not exist")?;
+                    ensure(*left > index && *right > index && *left < tree.nodes.len() && *right < tree.nodes.len(), "child index out of range")?;
+                }
+            }
+        }
+        ensure(self.trees.iter().all(|iteration| iteration.len() == n_scores), "every iteration needs one tree per score")
+    }
+}
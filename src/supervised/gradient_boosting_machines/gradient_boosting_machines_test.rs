@@ -0,0 +1,156 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::{MLalgo, Predict, MLError, ParamValue},
+    evaluation::accuracy::{score, r2_score}, persistence::persistence::{Persist, PersistError}};
+use super::gradient_boosting_machines::{GradientBoosting, GBLoss};
+
+
+///y = sin(3 x0) + x1^2, x2 is noise.
+fn regression_data(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for _ in 0..samples {
+        let row: Vec<f32> = (0..3).map(|_| rng.f32() * 2.0 - 1.0).collect();
+        targets.push((3.0 * row[0]).sin() + row[1] * row[1]);
+        rows.push(row);
+    }
+    (Matrix::from(rows), DataType::Floats(targets))
+}
+
+///three classes by the angle of (x0, x1) around the origin, x2 is noise.
+fn multiclass_data(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for _ in 0..samples {
+        let row: Vec<f32> = (0..3).map(|_| rng.f32() * 2.0 - 1.0).collect();
+        let angle = row[1].atan2(row[0]) + std::f32::consts::PI;
+        targets.push(((angle / (2.0 * std::f32::consts::PI / 3.0)) as u8).min(2));
+        rows.push(row);
+    }
+    (Matrix::from(rows), DataType::Category(targets))
+}
+
+#[test]
+fn regression_losses_fit_a_smooth_function() {
+    let (X_train, y_train) = regression_data(1, 500);
+    let (X_test, y_test) = regression_data(2, 200);
+    for loss in [GBLoss::SquaredError, GBLoss::AbsoluteError, GBLoss::Huber { delta: 0.5 }] {
+        let mut model = GradientBoosting::new(loss);
+        model.set_tree(3, 5, 0.0);
+        model.fit(&X_train, &y_train).unwrap();
+        let r2 = r2_score(&model, &X_test, &y_test);
+        assert!(r2 > 0.85, "{:?} r2 {}", loss, r2);
+        assert!(model.train_loss().first() > model.train_loss().last());
+    }
+}
+
+#[test]
+fn softmax_and_logistic_classify() {
+    let (X_train, y_train) = multiclass_data(3, 600);
+    let (X_test, y_test) = multiclass_data(4, 200);
+    let mut model = GradientBoosting::classifier();
+    model.set_tree(3, 5, 1.0);
+    model.set_subsampling(0.8, 0.67, 11);
+    model.fit(&X_train, &y_train).unwrap();
+    assert!(score(&model, &X_test, &y_test) > 0.85);
+    assert!(model.trees().iter().all(|iteration| iteration.len() == 3));
+    let probabilities = model.predict_proba(&[-0.9, -0.1, 0.0]);
+    assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    assert_eq!(model.predict(&[-0.9, -0.1, 0.0]), ReturnType::Category(0));
+
+    let binary = DataType::Strings(X_train.iter_rows().map(|row| if row[0] > 0.0 { "right".to_owned() } else { "left".to_owned() }).collect());
+    let mut model = GradientBoosting::new(GBLoss::Logistic);
+    model.fit(&X_train, &binary).unwrap();
+    assert!(model.trees().iter().all(|iteration| iteration.len() == 1));
+    assert_eq!(model.predict(&[0.7, 0.0, 0.0]), ReturnType::Strings("right".to_owned()));
+    assert!(matches!(model.fit(&X_train, &y_train), Err(MLError::InvalidTarget(_))));
+
+    //weights that zero out a class leave the log odds finite.
+    let weights: Vec<f32> = X_train.iter_rows().map(|row| if row[0] > 0.0 { 0.0 } else { 1.0 }).collect();
+    model.fit_weighted(&X_train, &binary, &weights).unwrap();
+    assert!(model.decision_function(&[0.7, 0.0, 0.0])[0].is_finite());
+    assert_eq!(model.predict(&[0.7, 0.0, 0.0]), ReturnType::Strings("left".to_owned()));
+}
+
+#[test]
+fn missing_values_learn_a_default_direction() {
+    //NaN's only appear where the target is high, so they should be sent along with the high values.
+    let mut rng = fastrand::Rng::with_seed(5);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for _ in 0..400 {
+        let x = rng.f32();
+        let missing = x > 0.5 && rng.bool();
+        rows.push(vec![if missing { f32::NAN } else { x }]);
+        targets.push(if x > 0.5 { 10.0 } else { 0.0 });
+    }
+    let mut model = GradientBoosting::regressor();
+    model.set_tree(2, 5, 0.0);
+    model.set_max_bins(16);
+    model.fit(&Matrix::from(rows), &DataType::Floats(targets)).unwrap();
+    match model.predict(&[f32::NAN]) {
+        ReturnType::Floats(value) => assert!(value > 9.0, "NaN predicted {}", value),
+        other => panic!("{:?}", other),
+    }
+    match model.predict(&[0.1]) {
+        ReturnType::Floats(value) => assert!(value < 1.0),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn early_stopping_cuts_the_iterations() {
+    let (X, y) = regression_data(6, 300);
+    let (X_validation, y_validation) = regression_data(7, 100);
+    let mut model = GradientBoosting::regressor();
+    model.set_boosting(2000, 0.3);
+    model.set_tree(4, 2, 0.0);
+    model.set_early_stopping(None, 5, 1e-6);
+    model.fit_with_validation(&X, &y, &X_validation, &y_validation).unwrap();
+    assert!(model.n_iter() < 2000);
+    let best = model.validation_loss().iter().cloned().fold(f32::MAX, f32::min);
+    assert_eq!(model.validation_loss()[model.n_iter() - 1], best);
+
+    let mut model = GradientBoosting::regressor();
+    model.set_boosting(2000, 0.3);
+    model.set_early_stopping(Some(0.2), 5, 1e-6);
+    model.fit(&X, &y).unwrap();
+    assert!(model.n_iter() < 2000 && !model.validation_loss().is_empty());
+    //a single sample can not be split into a training and a validation part.
+    assert!(matches!(model.fit(&X.select_rows(&[0]), &DataType::Floats(vec![1.0])), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn persistence_and_bad_parameters() {
+    let (X, y) = regression_data(8, 100);
+    let mut model = GradientBoosting::regressor();
+    model.set_boosting(10, 0.1);
+    assert_eq!(model.try_predict(&[0.0; 3]), Err(MLError::NotFitted));
+    model.fit(&X, &y).unwrap();
+    let restored = GradientBoosting::from_json(&model.to_json().unwrap()).unwrap();
+    assert_eq!(restored.predict(&[0.2, 0.3, 0.4]), model.predict(&[0.2, 0.3, 0.4]));
+    assert!((model.feature_importances().iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    //a tree without a root has nothing to predict with.
+    let mut value: serde_json::Value = serde_json::from_str(&model.to_json().unwrap()).unwrap();
+    value["model"]["trees"][0][0]["nodes"] = serde_json::json!([]);
+    let error = GradientBoosting::from_json(&value.to_string()).err().unwrap();
+    assert!(matches!(error.downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_))));
+
+    model.set_max_bins(300);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    let mut classifier = GradientBoosting::classifier();
+    assert!(matches!(classifier.fit(&X, &y), Err(MLError::InvalidTarget(_))));
+
+    //set_params takes everything get_params gives back, huber keeps its delta.
+    let mut huber = GradientBoosting::new(GBLoss::Huber { delta: 0.5 });
+    let mut params = huber.get_params();
+    huber.set_params(&params).unwrap();
+    assert_eq!(huber.loss, GBLoss::Huber { delta: 0.5 });
+    params.insert("loss".to_owned(), ParamValue::Text("absolute_error".to_owned()));
+    huber.set_params(&params).unwrap();
+    assert_eq!(huber.loss, GBLoss::AbsoluteError);
+    params.insert("loss".to_owned(), ParamValue::Text("huber".to_owned()));
+    assert!(matches!(huber.set_params(&params), Err(MLError::InvalidParameter(_))));
+}
@@ -0,0 +1,269 @@
+//! #K nearest neighbours#
+//!
+//! Predicts from the `k` training points closest to the query.
+//! * Classification -> the class with the most votes, or with `Weights::Distance` the class with the biggest sum of `1 / distance`.
+//! * Regression -> the mean(or the `1 / distance` weighted mean) of the neighbours' targets.
+//!
+//! Fitting only stores the training set in a `NeighbourIndex`(see `n_dimen::neighbours`), which picks brute force, a KD-tree
+//! or a ball tree from the dimensionality unless told otherwise. `index()` hands it out for reuse.
+//! Points at distance 0 from the query decide alone under `Weights::Distance`, since their weight would be infinite.
+//! Fitted with sample weights, the vote(or share of the mean) of every neighbour is also multiplied by its sample weight.
+
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::{matrix::Matrix, distance::DistanceMetric, neighbours::{NeighbourIndex, IndexKind, Neighbour}},
+    persistence::persistence::{Persist, PersistError, ensure}};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Weights {
+    ///every neighbour counts the same.
+    #[default]
+    Uniform,
+    ///a neighbour counts `1 / distance`.
+    Distance,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KNearestNeighbours {
+    pub k : usize,
+    pub weights : Weights,
+    pub metric : DistanceMetric,
+    pub index_kind : IndexKind,
+    classification : bool,
+    index : Option<NeighbourIndex>,
+    ///float targets for regression.
+    values : Vec<f32>,
+    ///index into `classes` for every training point.
+    labels : Vec<usize>,
+    classes : Option<DataType>,
+    ///weight of every training point, empty when fitted without weights.
+    #[serde(default)]
+    sample_weights : Vec<f32>,
+}
+
+impl KNearestNeighbours {
+
+    pub fn classifier(k : usize) -> KNearestNeighbours {
+        KNearestNeighbours::new(k, true)
+    }
+
+    pub fn regressor(k : usize) -> KNearestNeighbours {
+        KNearestNeighbours::new(k, false)
+    }
+
+    fn new(k : usize, classification : bool) -> KNearestNeighbours {
+        KNearestNeighbours {
+            k,
+            weights: Weights::Uniform,
+            metric: DistanceMetric::Euclidean,
+            index_kind: IndexKind::Auto,
+            classification,
+            index: None,
+            values: vec![],
+            labels: vec![],
+            classes: None,
+            sample_weights: vec![],
+        }
+    }
+
+    pub fn set_weights(&mut self, weights : Weights) {
+        self.weights = weights;
+    }
+
+    pub fn set_metric(&mut self, metric : DistanceMetric) {
+        self.metric = metric;
+    }
+
+    ///`IndexKind::Auto` by default.
+    pub fn set_index_kind(&mut self, index_kind : IndexKind) {
+        self.index_kind = index_kind;
+    }
+
+    pub fn is_classifier(&self) -> bool {
+        self.classification
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///the index over the training points, `None` before fitting.
+    pub fn index(&self) -> Option<&NeighbourIndex> {
+        self.index.as_ref()
+    }
+
+    ///the `k` training points closest to the point, closest first.
+    pub fn k_neighbours(&self, point : &[f32]) -> Vec<Neighbour> {
+        assert_predict_input(self, point);
+        self.index.as_ref().unwrap().k_nearest(point, self.k)
+    }
+
+    ///every training point within `radius` of each row of the points.
+    pub fn radius_neighbours(&self, points : &Matrix, radius : f32) -> Vec<Vec<Neighbour>> {
+        points.iter_rows().for_each(|point| assert_predict_input(self, point));
+        self.index.as_ref().unwrap().within_radius_batch(points, radius)
+    }
+
+    ///the weight of every neighbour, only the exact matches count if there are any.
+    fn neighbour_weights(&self, neighbours : &[Neighbour]) -> Vec<f32> {
+        let mut weights: Vec<f32> = match self.weights {
+            Weights::Uniform => vec![1.0; neighbours.len()],
+            Weights::Distance if neighbours.iter().any(|neighbour| neighbour.distance == 0.0) =>
+                neighbours.iter().map(|neighbour| (neighbour.distance == 0.0) as u8 as f32).collect(),
+            Weights::Distance => neighbours.iter().map(|neighbour| 1.0 / neighbour.distance).collect(),
+        };
+        if !self.sample_weights.is_empty() {
+            weights.iter_mut().zip(neighbours).for_each(|(weight, neighbour)| *weight *= self.sample_weights[neighbour.index]);
+            //every neighbour weighted 0, they count the same instead of dividing by 0.
+            if weights.iter().all(|weight| *weight == 0.0) {
+                weights.iter_mut().for_each(|weight| *weight = 1.0);
+            }
+        }
+        weights
+    }
+
+    fn proba_from(&self, neighbours : &[Neighbour]) -> Vec<f32> {
+        let mut votes = vec![0.0_f32; self.classes.as_ref().map_or(0, |classes| classes.len())];
+        for (neighbour, weight) in neighbours.iter().zip(self.neighbour_weights(neighbours)) {
+            votes[self.labels[neighbour.index]] += weight;
+        }
+        let total: f32 = votes.iter().sum();
+        votes.iter().map(|vote| vote / total).collect()
+    }
+
+    fn predict_from(&self, neighbours : &[Neighbour]) -> ReturnType {
+        if self.classification {
+            //ties go to the class that comes first.
+            let votes = self.proba_from(neighbours);
+            let best = votes.iter().enumerate().fold(0, |best, (class, vote)| if *vote > votes[best] { class } else { best });
+            self.classes.as_ref().unwrap().value_at(best)
+        } else {
+            let weights = self.neighbour_weights(neighbours);
+            let total: f32 = weights.iter().sum();
+            ReturnType::Floats(neighbours.iter().zip(&weights).map(|(neighbour, weight)| self.values[neighbour.index] * weight).sum::<f32>() / total)
+        }
+    }
+
+    ///class probabilities(the share of the neighbour weights) in the order of `classes()`, panics for a regressor.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert!(self.classification, "predict_proba needs a classifier");
+        self.proba_from(&self.k_neighbours(point))
+    }
+
+    ///`predict` for every row of the points, the neighbour queries run in parallel.
+    pub fn predict_many(&self, points : &Matrix) -> Vec<ReturnType> {
+        points.iter_rows().for_each(|point| assert_predict_input(self, point));
+        self.index.as_ref().unwrap().k_nearest_batch(points, self.k).iter().map(|neighbours| self.predict_from(neighbours)).collect()
+    }
+}
+
+impl MLalgo for KNearestNeighbours {
+    ///stores the training set in the neighbour index.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        if self.k == 0 || self.k > X_train.rows() {
+            return Err(MLError::InvalidParameter(format!("k needs to be in [1, {}](the number of training samples), got {}", X_train.rows(), self.k)));
+        }
+        match (y_train, self.classification) {
+            (DataType::Floats(values), false) => {
+                self.values = values.clone();
+                self.labels = vec![];
+                self.classes = None;
+            },
+            (DataType::Floats(_), true) => return Err(MLError::InvalidTarget("a k nearest neighbours classifier needs category or string targets".to_owned())),
+            (_, false) => return Err(MLError::InvalidTarget("a k nearest neighbours regressor needs float targets".to_owned())),
+            (_, true) => {
+                let (classes, labels) = y_train.unique_classes().unwrap();
+                self.values = vec![];
+                self.labels = labels;
+                self.classes = Some(classes);
+            },
+        }
+        self.sample_weights = vec![];
+        self.index = Some(NeighbourIndex::from_matrix(X_train, self.metric.clone(), self.index_kind).map_err(MLError::InvalidParameter)?);
+        Ok(())
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///also stores the weights, which scale the vote of every neighbour.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.fit(X_train, y_train)?;
+        self.sample_weights = sample_weights.to_vec();
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.index.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.index.as_ref().map(|index| index.dimensions())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("k".to_owned(), ParamValue::Int(self.k as i64));
+        params.insert("distance_weighted".to_owned(), ParamValue::Bool(self.weights == Weights::Distance));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "k" => {
+                    let k = value.as_usize(name)?;
+                    if k == 0 {
+                        return Err(MLError::InvalidParameter("k needs to be atleast 1".to_owned()));
+                    }
+                    //a fitted model predicts with the new k right away, so it has to fit the training set.
+                    if let Some(index) = &self.index {
+                        if k > index.len() {
+                            return Err(MLError::InvalidParameter(format!("k needs to be in [1, {}](the number of training samples), got {}", index.len(), k)));
+                        }
+                    }
+                    self.k = k;
+                },
+                "distance_weighted" => self.weights = if value.as_bool(name)? { Weights::Distance } else { Weights::Uniform },
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predict for KNearestNeighbours {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        self.predict_from(&self.k_neighbours(point))
+    }
+}
+
+impl Estimator for KNearestNeighbours {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        self.classification.then(|| self.predict_proba(point))
+    }
+}
+
+impl Persist for KNearestNeighbours {
+    const MODEL_TYPE: &'static str = "KNearestNeighbours";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let index = match &self.index {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        index.validate().map_err(PersistError::InvalidShape)?;
+        let targets = if self.classification { self.labels.len() } else { self.values.len() };
+        ensure(targets == index.len(), "the number of targets does not match the number of points")?;
+        ensure(self.k >= 1 && self.k <= index.len(), "k is bigger than the number of points")?;
+        ensure(self.sample_weights.is_empty() || self.sample_weights.len() == index.len(), "the number of sample weights does not match the number of points")?;
+        let n_classes = self.classes.as_ref().map_or(0, |classes| classes.len());
+        ensure(!self.classification || n_classes > 0, "a classifier needs atleast one class")?;
+        ensure(self.labels.iter().all(|label| *label < n_classes), "a label points to a class that does not exist")
+    }
+}
@@ -0,0 +1,94 @@
+#![cfg(test)]
+
+use crate::{n_dimen::{matrix::Matrix, neighbours::IndexKind}, data_frame::{data_type::DataType, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, MLError, Params, ParamValue}, evaluation::accuracy::{score, r2_score},
+    persistence::persistence::{Persist, PersistError}};
+use super::k_nearest_neighbours::{KNearestNeighbours, Weights};
+
+
+///two gaussian-ish blobs around (0, 0) and (3, 3).
+fn blobs(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for sample in 0..samples {
+        let class = sample % 2;
+        let centre = class as f32 * 3.0;
+        rows.push(vec![centre + rng.f32() * 2.0 - 1.0, centre + rng.f32() * 2.0 - 1.0]);
+        targets.push(if class == 0 { "low".to_owned() } else { "high".to_owned() });
+    }
+    (Matrix::from(rows), DataType::Strings(targets))
+}
+
+#[test]
+fn classifier_votes_with_every_index() {
+    let (X_train, y_train) = blobs(1, 200);
+    let (X_test, y_test) = blobs(2, 50);
+    for kind in [IndexKind::BruteForce, IndexKind::KdTree { leaf_size: 10 }, IndexKind::BallTree { leaf_size: 10 }] {
+        let mut model = KNearestNeighbours::classifier(5);
+        model.set_index_kind(kind);
+        model.fit(&X_train, &y_train).unwrap();
+        assert_eq!(score(&model, &X_test, &y_test), 1.0);
+        assert_eq!(model.predict(&[3.2, 2.9]), ReturnType::Strings("high".to_owned()));
+        let probabilities = model.predict_proba(&[0.1, 0.0]);
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn distance_weights_change_the_vote() {
+    //one close "a" against two far "b"'s.
+    let X = Matrix::from(vec![vec![0.0], vec![0.9], vec![1.0], vec![10.0]]);
+    let y = DataType::Category(vec![0, 1, 1, 1]);
+    let mut model = KNearestNeighbours::classifier(3);
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.predict(&[0.1]), ReturnType::Category(1));
+    model.set_weights(Weights::Distance);
+    assert_eq!(model.predict(&[0.1]), ReturnType::Category(0));
+    //an exact match decides alone.
+    assert_eq!(model.predict_proba(&[0.9]), vec![0.0, 1.0]);
+}
+
+#[test]
+fn regressor_averages_the_neighbours() {
+    let mut rng = fastrand::Rng::with_seed(3);
+    let rows: Vec<Vec<f32>> = (0..400).map(|_| vec![rng.f32() * 6.0, rng.f32()]).collect();
+    let targets: Vec<f32> = rows.iter().map(|row| row[0].sin()).collect();
+    let (X, y) = (Matrix::from(rows), DataType::Floats(targets));
+    let mut model = KNearestNeighbours::regressor(5);
+    model.set_weights(Weights::Distance);
+    model.fit(&X, &y).unwrap();
+    assert!(r2_score(&model, &X, &y) > 0.99);
+
+    let queries = Matrix::from(vec![vec![1.5, 0.5], vec![4.7, 0.2]]);
+    let many = model.predict_many(&queries);
+    assert_eq!(many[0], model.predict(queries.row(0)));
+    assert_eq!(model.radius_neighbours(&queries, 0.3).len(), 2);
+
+    let restored = KNearestNeighbours::from_json(&model.to_json().unwrap()).unwrap();
+    assert_eq!(restored.predict(queries.row(1)), many[1]);
+}
+
+#[test]
+fn bad_inputs_are_rejected() {
+    let (X, y) = blobs(4, 10);
+    let mut model = KNearestNeighbours::classifier(11);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    assert_eq!(model.try_predict(&[0.0, 0.0]), Err(MLError::NotFitted));
+    let mut regressor = KNearestNeighbours::regressor(3);
+    assert!(matches!(regressor.fit(&X, &y), Err(MLError::InvalidTarget(_))));
+
+    //k = 0 would divide the votes by 0, and a fitted model can not use more neighbours than it has samples.
+    let k = |k : i64| Params::from([("k".to_owned(), ParamValue::Int(k))]);
+    assert!(matches!(model.set_params(&k(0)), Err(MLError::InvalidParameter(_))));
+    model.set_params(&k(3)).unwrap();
+    model.fit(&X, &y).unwrap();
+    assert!(matches!(model.set_params(&k(11)), Err(MLError::InvalidParameter(_))));
+    assert_eq!(model.get_params().get("k"), Some(&ParamValue::Int(3)));
+
+    //a loaded index is checked too, a repeated point in the order would be searched twice.
+    let mut value: serde_json::Value = serde_json::from_str(&model.to_json().unwrap()).unwrap();
+    value["model"]["index"]["order"][0] = value["model"]["index"]["order"][1].clone();
+    let error = KNearestNeighbours::from_json(&value.to_string()).err().unwrap();
+    assert!(matches!(error.downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_))));
+}
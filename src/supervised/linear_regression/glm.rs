@@ -0,0 +1,464 @@
+//! #Generalised linear models#
+//!
+//! Regression for targets that are not normal : counts(Poisson), positive costs(Gamma) and
+//! costs with exact zeros(Tweedie with a power between 1 and 2). All of them use the log link,
+//!
+//!     E[y] = exp(X w + b + offset)
+//!
+//! and minimise the mean unit deviance plus an L2 penalty on the weights(the intercept is never penalised),
+//!
+//!     (1/2n) * sum(deviance(y, mu))  +  (alpha / 2) * ||w||^2
+//!
+//! The `offset` is added to the linear predictor as it is, for counts over different exposures(time, policy years...)
+//! pass the exposure to `fit_with_exposure` and the model learns the rate per unit of exposure.
+
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::DataType, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::{matrix::Matrix, linalg::{dot, cholesky, cholesky_solve}, optimise::{lbfgs, line_search, inf_norm}},
+    evaluation::accuracy::{tweedie_deviance, mean_tweedie_deviance, d2_tweedie},
+    persistence::persistence::{Persist, PersistError, ensure}};
+
+
+///The distribution of the target, the variance is `mu^power`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GlmDistribution {
+    ///counts, y >= 0, power 1.
+    Poisson,
+    ///positive continuous values, y > 0, power 2.
+    Gamma,
+    ///`power` 0 is normal, 1 < power < 2 compound Poisson-Gamma(y >= 0), power >= 2 needs y > 0.
+    Tweedie { power : f32 },
+}
+
+impl GlmDistribution {
+
+    pub fn power(&self) -> f32 {
+        match self {
+            GlmDistribution::Poisson => 1.0,
+            GlmDistribution::Gamma => 2.0,
+            GlmDistribution::Tweedie { power } => *power,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            GlmDistribution::Poisson => "poisson",
+            GlmDistribution::Gamma => "gamma",
+            GlmDistribution::Tweedie { .. } => "tweedie",
+        }
+    }
+
+    fn check_target(&self, y : &[f32]) -> Result<(), MLError> {
+        let power = self.power();
+        let valid = if power == 0.0 {
+            y.iter().all(|value| value.is_finite())
+        } else if power < 2.0 {
+            y.iter().all(|value| *value >= 0.0 && value.is_finite())
+        } else {
+            y.iter().all(|value| *value > 0.0 && value.is_finite())
+        };
+        if !valid {
+            return Err(MLError::InvalidTarget(format!("the {} distribution(power {}) needs {} targets", self.name(), power,
+                if power == 0.0 { "finite" } else if power < 2.0 { "non negative" } else { "positive" })));
+        }
+        if y.iter().sum::<f32>() <= 0.0 && power > 0.0 {
+            return Err(MLError::InvalidTarget("the targets can not all be zero with a log link".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GlmSolver {
+    ///iteratively reweighted least squares, Newton's method with the expected Hessian. Very few iterations,
+    ///but every one solves a `n_features` square system.
+    Irls,
+    ///limited memory BFGS keeping the last `memory` updates, for many features.
+    Lbfgs { memory : usize },
+}
+
+impl GlmSolver {
+    fn name(&self) -> &'static str {
+        match self {
+            GlmSolver::Irls => "irls",
+            GlmSolver::Lbfgs { .. } => "lbfgs",
+        }
+    }
+
+    ///only `irls` has no settings, `lbfgs` is built with its memory and given to `set_solver`.
+    fn from_name(name : &str) -> Result<GlmSolver, MLError> {
+        match name {
+            "irls" => Ok(GlmSolver::Irls),
+            "lbfgs" => Err(MLError::InvalidParameter("the lbfgs solver needs its memory, use set_solver".to_owned())),
+            _ => Err(MLError::InvalidParameter(format!("unknown solver '{}'", name))),
+        }
+    }
+}
+
+
+///The training problem, the parameters are the weights followed by the intercept(if there is one).
+struct Problem<'a> {
+    X : &'a Matrix,
+    y : &'a [f32],
+    offset : &'a [f32],
+    ///sample weights with a mean of 1.
+    weights : Vec<f64>,
+    power : f64,
+    alpha : f64,
+    fit_intercept : bool,
+}
+
+impl<'a> Problem<'a> {
+
+    fn linear_predictor(&self, theta : &[f64], sample : usize) -> f64 {
+        let x = self.X.row(sample);
+        let mut eta = x.iter().zip(theta).map(|(x, w)| *x as f64 * w).sum::<f64>() + self.offset[sample] as f64;
+        if self.fit_intercept {
+            eta += theta[x.len()];
+        }
+        //exp overflows past ~709, a prediction of e^300 is already absurd.
+        eta.clamp(-300.0, 300.0)
+    }
+
+    ///objective and its gradient.
+    fn loss_and_gradient(&self, theta : &[f64]) -> (f64, Vec<f64>) {
+        let n = self.X.rows() as f64;
+        let n_features = self.X.cols();
+        let mut gradient = vec![0.0_f64; theta.len()];
+        let mut loss = 0.0_f64;
+        for sample in 0..self.X.rows() {
+            let mu = self.linear_predictor(theta, sample).exp();
+            let y = self.y[sample] as f64;
+            let weight = self.weights[sample];
+            loss += weight * tweedie_deviance(y, mu, self.power) / (2.0 * n);
+            //d(deviance / 2) / d(eta) for the log link.
+            let slope = -weight * (y - mu) * mu.powf(1.0 - self.power) / n;
+            for (g, x) in gradient.iter_mut().zip(self.X.row(sample)) {
+                *g += slope * *x as f64;
+            }
+            if self.fit_intercept {
+                gradient[n_features] += slope;
+            }
+        }
+        for (g, w) in gradient.iter_mut().zip(&theta[..n_features]) {
+            *g += self.alpha * w;
+            loss += 0.5 * self.alpha * w * w;
+        }
+        (loss, gradient)
+    }
+
+    ///expected Hessian(Fisher information), `X^T diag(mu^(2 - power)) X / n` plus the penalty.
+    fn fisher(&self, theta : &[f64]) -> Matrix {
+        let size = theta.len();
+        let n = self.X.rows() as f64;
+        let mut hessian = vec![0.0_f64; size * size];
+        let mut extended = vec![1.0_f64; size];
+        for sample in 0..self.X.rows() {
+            let mu = self.linear_predictor(theta, sample).exp();
+            let weight = self.weights[sample] * mu.powf(2.0 - self.power) / n;
+            for (slot, x) in extended.iter_mut().zip(self.X.row(sample)) {
+                *slot = *x as f64;
+            }
+            for i in 0..size {
+                let factor = weight * extended[i];
+                for j in 0..=i {
+                    hessian[i * size + j] += factor * extended[j];
+                }
+            }
+        }
+        let mut fisher = Matrix::new(size, size);
+        for i in 0..size {
+            for j in 0..=i {
+                fisher[(i, j)] = hessian[i * size + j] as f32;
+                fisher[(j, i)] = hessian[i * size + j] as f32;
+            }
+            if i < self.X.cols() {
+                fisher[(i, i)] += self.alpha as f32;
+            }
+        }
+        fisher
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlmRegressor {
+    pub distribution : GlmDistribution,
+    ///strength of the L2 penalty, 0 for the plain maximum likelihood fit.
+    pub alpha : f32,
+    pub fit_intercept : bool,
+    pub solver : GlmSolver,
+    pub max_iter : usize,
+    ///stops once the largest gradient entry is below this.
+    pub tol : f32,
+    coefficients : Vec<f32>,
+    intercept : f32,
+    n_iter : usize,
+    fitted : bool,
+}
+
+impl GlmRegressor {
+
+    pub fn new(distribution : GlmDistribution) -> GlmRegressor {
+        GlmRegressor {
+            distribution,
+            alpha: 0.0,
+            fit_intercept: true,
+            solver: GlmSolver::Irls,
+            max_iter: 100,
+            tol: 1e-6,
+            coefficients: vec![],
+            intercept: 0.0,
+            n_iter: 0,
+            fitted: false,
+        }
+    }
+
+    ///for counts.
+    pub fn poisson() -> GlmRegressor {
+        GlmRegressor::new(GlmDistribution::Poisson)
+    }
+
+    ///for positive continuous targets like claim sizes.
+    pub fn gamma() -> GlmRegressor {
+        GlmRegressor::new(GlmDistribution::Gamma)
+    }
+
+    ///for non negative targets with exact zeros(1 < power < 2), like the total claim cost of a policy.
+    pub fn tweedie(power : f32) -> GlmRegressor {
+        GlmRegressor::new(GlmDistribution::Tweedie { power })
+    }
+
+    pub fn set_alpha(&mut self, alpha : f32) {
+        self.alpha = alpha;
+    }
+
+    pub fn set_solver(&mut self, solver : GlmSolver) {
+        self.solver = solver;
+    }
+
+    pub fn set_fit_intercept(&mut self, fit_intercept : bool) {
+        self.fit_intercept = fit_intercept;
+    }
+
+    ///iteration limit and convergence tolerance.
+    pub fn set_stopping(&mut self, max_iter : usize, tol : f32) {
+        self.max_iter = max_iter;
+        self.tol = tol;
+    }
+
+    ///the learned weights, one per feature, on the log scale.
+    pub fn coefficients(&self) -> &[f32] {
+        &self.coefficients
+    }
+
+    pub fn intercept(&self) -> f32 {
+        self.intercept
+    }
+
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if !(self.alpha >= 0.0) {
+            return Err(MLError::InvalidParameter(format!("alpha needs to be >= 0, got {}", self.alpha)));
+        }
+        let power = self.distribution.power();
+        if !(power == 0.0 || power >= 1.0) || !power.is_finite() {
+            return Err(MLError::InvalidParameter(format!("the tweedie power needs to be 0 or >= 1, got {}", power)));
+        }
+        if self.max_iter == 0 {
+            return Err(MLError::InvalidParameter("max_iter needs to be > 0".to_owned()));
+        }
+        match self.solver {
+            GlmSolver::Lbfgs { memory: 0 } => Err(MLError::InvalidParameter("lbfgs needs a memory > 0".to_owned())),
+            _ => Ok(()),
+        }
+    }
+
+    ///fits with `offset` added to the linear predictor of every sample.
+    pub fn fit_with_offset(&mut self, X_train : &Matrix, y_train : &DataType, offset : &[f32]) -> Result<(), MLError> {
+        self.fit_weighted_with_offset(X_train, y_train, offset, &vec![1.0; X_train.rows()])
+    }
+
+    ///fits with both an offset and a weight per sample, the deviance of every sample is scaled by its weight.
+    pub fn fit_weighted_with_offset(&mut self, X_train : &Matrix, y_train : &DataType, offset : &[f32], sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+        let y = match y_train {
+            DataType::Floats(temp) => temp,
+            _ => return Err(MLError::InvalidTarget("a generalised linear model needs a float target".to_owned())),
+        };
+        if offset.len() != y.len() {
+            return Err(MLError::SampleMismatch { samples: offset.len(), targets: y.len() });
+        }
+        self.distribution.check_target(y)?;
+        let total: f64 = sample_weights.iter().map(|weight| *weight as f64).sum();
+        if !(total > 0.0) {
+            return Err(MLError::InvalidParameter("at least one sample weight needs to be > 0".to_owned()));
+        }
+
+        let problem = Problem {
+            X: X_train,
+            y,
+            offset,
+            weights: sample_weights.iter().map(|weight| *weight as f64 * y.len() as f64 / total).collect(),
+            power: self.distribution.power() as f64,
+            alpha: self.alpha as f64,
+            fit_intercept: self.fit_intercept,
+        };
+        let n_features = X_train.cols();
+        let mut theta = vec![0.0_f64; n_features + self.fit_intercept as usize];
+        if self.fit_intercept {
+            //starting from the mean keeps the first steps small.
+            let mean_y = y.iter().zip(&problem.weights).map(|(value, weight)| *value as f64 * weight).sum::<f64>() / y.len() as f64;
+            let mean_offset = offset.iter().zip(&problem.weights).map(|(value, weight)| *value as f64 * weight).sum::<f64>() / offset.len() as f64;
+            theta[n_features] = mean_y.max(1e-10).ln() - mean_offset;
+        }
+        self.n_iter = match self.solver {
+            GlmSolver::Irls => self.solve_irls(&problem, &mut theta),
+            GlmSolver::Lbfgs { memory } => lbfgs(|theta| problem.loss_and_gradient(theta), &mut theta, memory, self.max_iter, self.tol as f64),
+        };
+        if theta.iter().any(|value| !value.is_finite()) {
+            return Err(MLError::InvalidParameter("the solver diverged, try some L2 regularisation or scaling the features".to_owned()));
+        }
+        self.coefficients = theta[..n_features].iter().map(|value| *value as f32).collect();
+        self.intercept = if self.fit_intercept { theta[n_features] as f32 } else { 0.0 };
+        self.fitted = true;
+        Ok(())
+    }
+
+    ///fits a rate per unit of `exposure`(> 0), the offset is `ln(exposure)`.
+    pub fn fit_with_exposure(&mut self, X_train : &Matrix, y_train : &DataType, exposure : &[f32]) -> Result<(), MLError> {
+        if exposure.iter().any(|value| !(*value > 0.0)) {
+            return Err(MLError::InvalidParameter("the exposure needs to be > 0".to_owned()));
+        }
+        let offset: Vec<f32> = exposure.iter().map(|value| value.ln()).collect();
+        self.fit_with_offset(X_train, y_train, &offset)
+    }
+
+    fn solve_irls(&self, problem : &Problem, theta : &mut Vec<f64>) -> usize {
+        let objective = |theta : &[f64]| problem.loss_and_gradient(theta);
+        let (mut loss, mut gradient) = objective(theta);
+        for iteration in 0..self.max_iter {
+            if inf_norm(&gradient) < self.tol as f64 {
+                return iteration;
+            }
+            let fisher = problem.fisher(theta);
+            let gradient_f32: Vec<f32> = gradient.iter().map(|g| *g as f32).collect();
+            let direction: Vec<f64> = match cholesky(&fisher) {
+                Ok(l) => cholesky_solve(&l, &gradient_f32).iter().map(|d| -(*d as f64)).collect(),
+                //collinear features without a penalty, fall back to plain gradient descent for this step.
+                Err(_) => gradient.iter().map(|g| -g).collect(),
+            };
+            match line_search(&objective, theta, loss, &gradient, &direction) {
+                Some((new_theta, new_loss, new_gradient)) => {
+                    *theta = new_theta;
+                    loss = new_loss;
+                    gradient = new_gradient;
+                },
+                None => return iteration + 1,
+            }
+        }
+        self.max_iter
+    }
+
+    ///the expected value with `offset` added to the linear predictor.
+    pub fn predict_with_offset(&self, point : &[f32], offset : f32) -> f32 {
+        assert_predict_input(self, point);
+        (dot(&self.coefficients, point) + self.intercept + offset).exp()
+    }
+
+    ///predicts every row of `X`.
+    pub fn predict_many(&self, X : &Matrix) -> Vec<f32> {
+        X.iter_rows().map(|row| self.predict_with_offset(row, 0.0)).collect()
+    }
+
+    ///mean deviance of the predictions on `X` under the model's distribution, lower is better.
+    pub fn deviance(&self, X : &Matrix, y : &[f32]) -> f32 {
+        mean_tweedie_deviance(y, &self.predict_many(X), self.distribution.power())
+    }
+
+    ///fraction of the deviance explained(D²), 1 is perfect and 0 no better than predicting the mean.
+    pub fn d2_score(&self, X : &Matrix, y : &[f32]) -> f32 {
+        d2_tweedie(y, &self.predict_many(X), self.distribution.power())
+    }
+}
+
+impl MLalgo for GlmRegressor {
+    ///needs a float target that fits the distribution, see `fit_with_offset` for exposures.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_with_offset(X_train, y_train, &vec![0.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///see `fit_weighted_with_offset` to combine the weights with exposures.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        self.fit_weighted_with_offset(X_train, y_train, &vec![0.0; X_train.rows()], sample_weights)
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.then_some(self.coefficients.len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params.insert("power".to_owned(), ParamValue::Float(self.distribution.power()));
+        params.insert("fit_intercept".to_owned(), ParamValue::Bool(self.fit_intercept));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params.insert("solver".to_owned(), ParamValue::Text(self.solver.name().to_owned()));
+        params
+    }
+
+    ///a new `power` makes the distribution a `Tweedie` one, `solver` takes `irls`(use `set_solver` for `lbfgs`).
+    ///The current power or solver name leaves them as they are.
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "alpha" => self.alpha = value.as_f32(name)?,
+                "power" => if value.as_f32(name)? != self.distribution.power() {
+                    self.distribution = GlmDistribution::Tweedie { power: value.as_f32(name)? };
+                },
+                "solver" => if value.as_str(name)? != self.solver.name() {
+                    self.solver = GlmSolver::from_name(value.as_str(name)?)?;
+                },
+                "fit_intercept" => self.fit_intercept = value.as_bool(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for GlmRegressor {
+    ///the expected value `exp(w.x + b)`.
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        ReturnType::Floats(self.predict_with_offset(point, 0.0))
+    }
+}
+
+impl Estimator for GlmRegressor {}
+
+impl Persist for GlmRegressor {
+    const MODEL_TYPE: &'static str = "GlmRegressor";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(!self.fitted || !self.coefficients.is_empty(), "a fitted model needs atleast one coefficient")?;
+        ensure(self.coefficients.iter().all(|value| value.is_finite()) && self.intercept.is_finite(), "the coefficients need to be finite")
+    }
+}
@@ -0,0 +1,138 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::{MLalgo, Predict, MLError, ParamValue},
+    evaluation::accuracy::{mean_tweedie_deviance, d2_tweedie}, persistence::persistence::Persist};
+use super::glm::{GlmRegressor, GlmSolver, GlmDistribution};
+
+
+fn poisson_sample(rng : &fastrand::Rng, mean : f32) -> f32 {
+    //Knuth, fine for the small means used here.
+    let limit = (-mean).exp();
+    let (mut count, mut product) = (0.0, rng.f32());
+    while product > limit {
+        count += 1.0;
+        product *= rng.f32();
+    }
+    count
+}
+
+///sum of `shape` exponentials, a Gamma with the given mean.
+fn gamma_sample(rng : &fastrand::Rng, shape : usize, mean : f32) -> f32 {
+    (0..shape).map(|_| -(1.0 - rng.f32()).ln()).sum::<f32>() * mean / shape as f32
+}
+
+///features in [-1, 1] and the log of the mean `0.5 x0 - 0.3 x1 + 1`.
+fn features(rng : &fastrand::Rng, samples : usize) -> (Matrix, Vec<f32>) {
+    let rows: Vec<Vec<f32>> = (0..samples).map(|_| vec![rng.f32() * 2.0 - 1.0, rng.f32() * 2.0 - 1.0]).collect();
+    let log_means = rows.iter().map(|row| 0.5 * row[0] - 0.3 * row[1] + 1.0).collect();
+    (Matrix::from(rows), log_means)
+}
+
+#[test]
+fn poisson_recovers_the_coefficients() {
+    let rng = fastrand::Rng::with_seed(0);
+    let (X, log_means) = features(&rng, 3000);
+    let y = DataType::Floats(log_means.iter().map(|eta| poisson_sample(&rng, eta.exp())).collect());
+
+    let mut irls = GlmRegressor::poisson();
+    irls.fit(&X, &y).unwrap();
+    assert!((irls.coefficients()[0] - 0.5).abs() < 0.06 && (irls.coefficients()[1] + 0.3).abs() < 0.06 && (irls.intercept() - 1.0).abs() < 0.05,
+        "{:?} {}", irls.coefficients(), irls.intercept());
+    assert!(irls.n_iter() < 20);
+
+    let mut lbfgs = GlmRegressor::poisson();
+    lbfgs.set_solver(GlmSolver::Lbfgs { memory: 10 });
+    lbfgs.fit(&X, &y).unwrap();
+    assert!(irls.coefficients().iter().zip(lbfgs.coefficients()).all(|(a, b)| (a - b).abs() < 1e-3));
+
+    let targets = match &y { DataType::Floats(values) => values.clone(), _ => unreachable!() };
+    assert!(irls.d2_score(&X, &targets) > 0.1);
+    assert!(irls.deviance(&X, &targets) < mean_tweedie_deviance(&targets, &vec![targets.iter().sum::<f32>() / 3000.0; 3000], 1.0));
+    match irls.predict(&[0.0, 0.0]) {
+        ReturnType::Floats(value) => assert!((value - 1.0_f32.exp()).abs() < 0.15),
+        other => panic!("{:?}", other),
+    }
+
+    let restored = GlmRegressor::from_json(&irls.to_json().unwrap()).unwrap();
+    assert_eq!(restored.predict_many(&X), irls.predict_many(&X));
+}
+
+#[test]
+fn exposure_gives_a_rate() {
+    let rng = fastrand::Rng::with_seed(1);
+    let (X, log_means) = features(&rng, 3000);
+    //every sample is observed for between 1 and 5 years, the counts grow with it.
+    let exposure: Vec<f32> = (0..3000).map(|_| 1.0 + rng.f32() * 4.0).collect();
+    let y = DataType::Floats(log_means.iter().zip(&exposure).map(|(eta, years)| poisson_sample(&rng, eta.exp() * years)).collect());
+
+    let mut model = GlmRegressor::poisson();
+    model.fit_with_exposure(&X, &y, &exposure).unwrap();
+    assert!((model.coefficients()[0] - 0.5).abs() < 0.05 && (model.intercept() - 1.0).abs() < 0.05, "{:?} {}", model.coefficients(), model.intercept());
+    //the plain prediction is the rate for one unit of exposure.
+    assert!((model.predict_with_offset(&[0.0, 0.0], 2.0_f32.ln()) - 2.0 * model.predict_with_offset(&[0.0, 0.0], 0.0)).abs() < 1e-4);
+
+    assert!(matches!(model.fit_with_exposure(&X, &y, &vec![0.0; 3000]), Err(MLError::InvalidParameter(_))));
+    assert_eq!(model.fit_with_offset(&X, &y, &[0.0]), Err(MLError::SampleMismatch { samples: 1, targets: 3000 }));
+}
+
+#[test]
+fn gamma_and_tweedie_fit_costs() {
+    let rng = fastrand::Rng::with_seed(2);
+    let (X, log_means) = features(&rng, 3000);
+    let costs: Vec<f32> = log_means.iter().map(|eta| gamma_sample(&rng, 4, eta.exp())).collect();
+    let mut gamma = GlmRegressor::gamma();
+    gamma.fit(&X, &DataType::Floats(costs.clone())).unwrap();
+    assert!((gamma.coefficients()[0] - 0.5).abs() < 0.06 && (gamma.coefficients()[1] + 0.3).abs() < 0.06, "{:?}", gamma.coefficients());
+
+    //claims : most policies cost nothing, the rest a Gamma amount per claim.
+    let claims: Vec<f32> = log_means.iter().map(|eta| {
+        let count = poisson_sample(&rng, 0.3 * eta.exp()) as usize;
+        (0..count).map(|_| gamma_sample(&rng, 2, 10.0)).sum()
+    }).collect();
+    assert!(claims.iter().filter(|cost| **cost == 0.0).count() > 1000);
+    let mut tweedie = GlmRegressor::tweedie(1.5);
+    tweedie.fit(&X, &DataType::Floats(claims.clone())).unwrap();
+    assert!((tweedie.coefficients()[0] - 0.5).abs() < 0.15 && (tweedie.coefficients()[1] + 0.3).abs() < 0.15, "{:?}", tweedie.coefficients());
+    assert!(tweedie.d2_score(&X, &claims) > 0.0);
+
+    //the penalty pulls the weights towards zero.
+    let unpenalised = tweedie.coefficients()[0];
+    tweedie.set_alpha(1.0);
+    tweedie.fit(&X, &DataType::Floats(claims.clone())).unwrap();
+    assert!(tweedie.coefficients()[0].abs() < 0.8 * unpenalised.abs());
+
+    assert!(matches!(gamma.fit(&X, &DataType::Floats(claims)), Err(MLError::InvalidTarget(_))));
+    assert!(matches!(GlmRegressor::poisson().fit(&Matrix::from(vec![vec![1.0]]), &DataType::Floats(vec![-1.0])), Err(MLError::InvalidTarget(_))));
+    assert!(matches!(GlmRegressor::tweedie(0.5).fit(&X, &DataType::Floats(costs.clone())), Err(MLError::InvalidParameter(_))));
+    assert!(matches!(GlmRegressor::gamma().fit(&X, &DataType::Category(vec![0; 3000])), Err(MLError::InvalidTarget(_))));
+    assert_eq!(GlmRegressor::new(GlmDistribution::Tweedie { power: 3.0 }).distribution.power(), 3.0);
+}
+
+#[test]
+fn params_round_trip() {
+    let mut model = GlmRegressor::gamma();
+    model.set_solver(GlmSolver::Lbfgs { memory: 7 });
+    let mut params = model.get_params();
+    model.set_params(&params).unwrap();
+    assert_eq!((model.distribution, model.solver.clone()), (GlmDistribution::Gamma, GlmSolver::Lbfgs { memory: 7 }));
+    params.insert("power".to_owned(), ParamValue::Float(1.5));
+    params.insert("solver".to_owned(), ParamValue::Text("irls".to_owned()));
+    model.set_params(&params).unwrap();
+    assert_eq!((model.distribution, model.solver.clone()), (GlmDistribution::Tweedie { power: 1.5 }, GlmSolver::Irls));
+    params.insert("power".to_owned(), ParamValue::Float(0.5));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+    params.insert("power".to_owned(), ParamValue::Float(1.5));
+    params.insert("solver".to_owned(), ParamValue::Text("lbfgs".to_owned()));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn deviance_metrics() {
+    let y_true = [1.0, 2.0, 4.0];
+    //power 0 is the squared error.
+    assert!((mean_tweedie_deviance(&y_true, &[1.5, 2.0, 3.0], 0.0) - (0.25 + 1.0) / 3.0).abs() < 1e-6);
+    assert_eq!(mean_tweedie_deviance(&y_true, &y_true, 1.0), 0.0);
+    assert!(mean_tweedie_deviance(&y_true, &y_true, 1.5).abs() < 1e-6);
+    assert_eq!(d2_tweedie(&y_true, &y_true, 2.0), 1.0);
+    assert!(d2_tweedie(&y_true, &[7.0 / 3.0; 3], 1.0).abs() < 1e-6);
+}
@@ -1,12 +1,405 @@
-use crate::trait_definition::MLalgo;
+//! #Linear regression#
+//!
+//! Fits `y = X w + b` by minimising
+//!
+//!     (1/2n) * ||y - X w - b||^2  +  alpha * l1_ratio * ||w||_1  +  (alpha * (1 - l1_ratio) / 2) * ||w||^2
+//!
+//! * `alpha = 0` is ordinary least squares.
+//! * `l1_ratio = 0` is Ridge(L2), `l1_ratio = 1` is Lasso(L1) and anything in between is ElasticNet.
+//!
+//! The intercept is never penalised, X and y are centred before solving and the intercept is recovered from the means.
+//! Because the squared error is averaged over the samples, the Ridge `alpha` here is `alpha_sum / n` of the un-averaged form.
+//! With sample weights the squared errors are weighted, the weights are rescaled to a mean of 1 so `alpha` keeps its meaning.
 
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::DataType, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::{matrix::Matrix, linalg::{dot, axpy, matvec, transpose, gram, cholesky, cholesky_solve, lstsq, pinv}},
+    persistence::persistence::{Persist, PersistError, ensure}};
 
 
+///How the coefficients are found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Solver {
+    ///closed form(QR, or Cholesky when there is an L2 penalty) without L1, coordinate descent with it.
+    Auto,
+    ///solves `(X^T X + n*alpha*I) w = X^T y` with a Cholesky decomposition, fastest when there are few features.
+    NormalEquation,
+    ///least squares through a Householder QR of X, more accurate than the normal equations on badly conditioned data.
+    ///Rank deficient problems fall back to the pseudo inverse. Only for ordinary least squares.
+    Qr,
+    ///gradient descent over the whole training set, L1 terms use the subgradient.
+    BatchGradientDescent { learning_rate : f32, max_iter : usize, tol : f32 },
+    ///gradient descent over shuffled batches of `batch_size` samples, `max_iter` counts epochs.
+    MiniBatchGradientDescent { learning_rate : f32, max_iter : usize, tol : f32, batch_size : usize, seed : u64 },
+    ///cyclic coordinate descent with soft thresholding, the usual solver for Lasso and ElasticNet.
+    CoordinateDescent { max_iter : usize, tol : f32 },
+}
+
+impl Solver {
+    fn name(&self) -> &'static str {
+        match self {
+            Solver::Auto => "auto",
+            Solver::NormalEquation => "normal_equation",
+            Solver::Qr => "qr",
+            Solver::BatchGradientDescent { .. } => "batch_gradient_descent",
+            Solver::MiniBatchGradientDescent { .. } => "mini_batch_gradient_descent",
+            Solver::CoordinateDescent { .. } => "coordinate_descent",
+        }
+    }
+
+    ///the solvers without settings, the iterative ones are built with their settings and given to `set_solver`.
+    fn from_name(name : &str) -> Result<Solver, MLError> {
+        match name {
+            "auto" => Ok(Solver::Auto),
+            "normal_equation" => Ok(Solver::NormalEquation),
+            "qr" => Ok(Solver::Qr),
+            "batch_gradient_descent" | "mini_batch_gradient_descent" | "coordinate_descent" =>
+                Err(MLError::InvalidParameter(format!("the '{}' solver needs its settings, use set_solver", name))),
+            _ => Err(MLError::InvalidParameter(format!("unknown solver '{}'", name))),
+        }
+    }
+}
 
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LinearRegression {
-    pub theta : Vec<f32>,
+    coefficients : Vec<f32>,
+    intercept : f32,
+    ///strength of the regularisation, 0 for ordinary least squares.
+    pub alpha : f32,
+    ///share of the penalty that is L1, the rest is L2.
+    pub l1_ratio : f32,
+    pub fit_intercept : bool,
+    pub solver : Solver,
+    ///number of iterations(or epochs) the iterative solver ran for, 0 for the closed form solvers.
+    n_iter : usize,
+    fitted : bool,
+}
+
+impl Default for LinearRegression {
+    fn default() -> Self {
+        LinearRegression::new()
+    }
 }
 
-/* impl MLalgo for LinearRegression {
-    
-} */
\ No newline at end of file
+impl LinearRegression {
+
+    ///ordinary least squares with an intercept.
+    pub fn new() -> LinearRegression {
+        LinearRegression {
+            coefficients: vec![],
+            intercept: 0.0,
+            alpha: 0.0,
+            l1_ratio: 0.0,
+            fit_intercept: true,
+            solver: Solver::Auto,
+            n_iter: 0,
+            fitted: false,
+        }
+    }
+
+    ///L2 regularised least squares.
+    pub fn ridge(alpha : f32) -> LinearRegression {
+        LinearRegression { alpha, l1_ratio: 0.0, ..LinearRegression::new() }
+    }
+
+    ///L1 regularised least squares, drives the coefficients of unimportant features to exactly zero.
+    pub fn lasso(alpha : f32) -> LinearRegression {
+        LinearRegression { alpha, l1_ratio: 1.0, ..LinearRegression::new() }
+    }
+
+    ///a mix of L1 and L2, `l1_ratio` of the penalty is L1.
+    pub fn elastic_net(alpha : f32, l1_ratio : f32) -> LinearRegression {
+        LinearRegression { alpha, l1_ratio, ..LinearRegression::new() }
+    }
+
+    pub fn set_solver(&mut self, solver : Solver) {
+        self.solver = solver;
+    }
+
+    pub fn set_fit_intercept(&mut self, fit_intercept : bool) {
+        self.fit_intercept = fit_intercept;
+    }
+
+    ///the learned weights, one per feature.
+    pub fn coefficients(&self) -> &[f32] {
+        &self.coefficients
+    }
+
+    pub fn intercept(&self) -> f32 {
+        self.intercept
+    }
+
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    ///predicts every row of `X`.
+    pub fn predict_many(&self, X : &Matrix) -> Vec<f32> {
+        X.iter_rows().map(|row| self.predict_value(row)).collect()
+    }
+
+    fn predict_value(&self, point : &[f32]) -> f32 {
+        dot(&self.coefficients, point) + self.intercept
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if !(self.alpha >= 0.0) {
+            return Err(MLError::InvalidParameter(format!("alpha needs to be >= 0, got {}", self.alpha)));
+        }
+        if !(0.0..=1.0).contains(&self.l1_ratio) {
+            return Err(MLError::InvalidParameter(format!("l1_ratio needs to be in [0, 1], got {}", self.l1_ratio)));
+        }
+        let has_l1 = self.alpha > 0.0 && self.l1_ratio > 0.0;
+        match &self.solver {
+            Solver::NormalEquation | Solver::Qr if has_l1 => Err(MLError::InvalidParameter(format!("the {} solver cannot handle an L1 penalty", self.solver.name()))),
+            Solver::Qr if self.alpha > 0.0 => Err(MLError::InvalidParameter("the qr solver is only for ordinary least squares".to_owned())),
+            Solver::BatchGradientDescent { learning_rate, .. } | Solver::MiniBatchGradientDescent { learning_rate, .. } if !(*learning_rate > 0.0) =>
+                Err(MLError::InvalidParameter(format!("learning_rate needs to be > 0, got {}", learning_rate))),
+            Solver::MiniBatchGradientDescent { batch_size: 0, .. } => Err(MLError::InvalidParameter("batch_size needs to be > 0".to_owned())),
+            _ => Ok(()),
+        }
+    }
+
+    //***************************************
+    //SOLVERS, all of them work on centred data.
+    //***************************************
+
+    fn solve_normal_equation(&self, X : &Matrix, y : &[f32]) -> Result<Vec<f32>, MLError> {
+        let mut system = gram(X);
+        let ridge = X.rows() as f32 * self.alpha * (1.0 - self.l1_ratio);
+        for i in 0..system.rows() {
+            system[(i, i)] += ridge;
+        }
+        let right_hand_side = matvec(&transpose(X), y);
+        match cholesky(&system) {
+            Ok(l) => Ok(cholesky_solve(&l, &right_hand_side)),
+            //X^T X is only semi definite when features are collinear, the pseudo inverse gives the minimum norm solution.
+            Err(_) => pinv(&system, 1e-6)
+                .map(|inverse| matvec(&inverse, &right_hand_side))
+                .map_err(|error| MLError::InvalidParameter(format!("could not solve the normal equations : {}", error))),
+        }
+    }
+
+    fn solve_qr(&self, X : &Matrix, y : &[f32]) -> Result<Vec<f32>, MLError> {
+        if X.rows() >= X.cols() {
+            if let Ok(coefficients) = lstsq(X, y) {
+                return Ok(coefficients);
+            }
+        }
+        pinv(X, 1e-6)
+            .map(|inverse| matvec(&inverse, y))
+            .map_err(|error| MLError::InvalidParameter(format!("could not solve the least squares problem : {}", error)))
+    }
+
+    ///gradient of the objective over the given rows, without the L1 term.
+    fn smooth_gradient(&self, X : &Matrix, y : &[f32], rows : &[usize], coefficients : &[f32], gradient : &mut [f32]) {
+        gradient.iter_mut().for_each(|value| *value = 0.0);
+        for &row in rows {
+            let residual = dot(X.row(row), coefficients) - y[row];
+            axpy(residual / rows.len() as f32, X.row(row), gradient);
+        }
+        axpy(self.alpha * (1.0 - self.l1_ratio), coefficients, gradient);
+    }
+
+    fn solve_gradient_descent(&mut self, X : &Matrix, y : &[f32], learning_rate : f32, max_iter : usize, tol : f32, batch_size : usize, seed : Option<u64>) -> Vec<f32> {
+        let mut coefficients = vec![0.0_f32; X.cols()];
+        let mut gradient = vec![0.0_f32; X.cols()];
+        let mut indices: Vec<usize> = (0..X.rows()).collect();
+        let mut rng = seed.map(fastrand::Rng::with_seed);
+        let l1 = self.alpha * self.l1_ratio;
+
+        self.n_iter = max_iter;
+        for epoch in 0..max_iter {
+            if let Some(rng) = rng.as_mut() {
+                rng.shuffle(&mut indices);
+            }
+            let mut largest_step = 0.0_f32;
+            for batch in indices.chunks(batch_size.max(1)) {
+                self.smooth_gradient(X, y, batch, &coefficients, &mut gradient);
+                for (weight, slope) in coefficients.iter_mut().zip(&gradient) {
+                    let step = learning_rate * (slope + l1 * weight.signum() * (*weight != 0.0) as u8 as f32);
+                    *weight -= step;
+                    largest_step = largest_step.max(step.abs());
+                }
+            }
+            if !largest_step.is_finite() {
+                break;
+            }
+            if largest_step < tol {
+                self.n_iter = epoch + 1;
+                break;
+            }
+        }
+        coefficients
+    }
+
+    fn solve_coordinate_descent(&mut self, X : &Matrix, y : &[f32], max_iter : usize, tol : f32) -> Vec<f32> {
+        let number_of_samples = X.rows() as f32;
+        //working on columns, so the transpose keeps every feature contiguous.
+        let columns = transpose(X);
+        let squared_norms: Vec<f32> = columns.iter_rows().map(|column| dot(column, column) / number_of_samples).collect();
+        let l1 = self.alpha * self.l1_ratio;
+        let l2 = self.alpha * (1.0 - self.l1_ratio);
+
+        let mut coefficients = vec![0.0_f32; X.cols()];
+        let mut residual = y.to_vec();
+        self.n_iter = max_iter;
+        for iteration in 0..max_iter {
+            let mut largest_change = 0.0_f32;
+            for (j, column) in columns.iter_rows().enumerate() {
+                if squared_norms[j] == 0.0 {
+                    continue;
+                }
+                let old = coefficients[j];
+                let rho = dot(column, &residual) / number_of_samples + squared_norms[j] * old;
+                let new = soft_threshold(rho, l1) / (squared_norms[j] + l2);
+                if new != old {
+                    axpy(old - new, column, &mut residual);
+                    coefficients[j] = new;
+                    largest_change = largest_change.max((new - old).abs());
+                }
+            }
+            if largest_change < tol {
+                self.n_iter = iteration + 1;
+                break;
+            }
+        }
+        coefficients
+    }
+}
+
+fn soft_threshold(value : f32, threshold : f32) -> f32 {
+    if value > threshold {
+        value - threshold
+    } else if value < -threshold {
+        value + threshold
+    } else {
+        0.0
+    }
+}
+
+///weighted column means of X, the weights sum to the number of rows.
+fn column_means(X : &Matrix, weights : &[f32]) -> Vec<f32> {
+    let mut means = vec![0.0_f32; X.cols()];
+    for (row, weight) in X.iter_rows().zip(weights) {
+        axpy(weight / X.rows() as f32, row, &mut means);
+    }
+    means
+}
+
+
+impl MLalgo for LinearRegression {
+
+    ///needs a float target.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///weighted least squares, every solver sees the centred rows scaled by the square root of their weight.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+        let y = match y_train {
+            DataType::Floats(temp) => temp,
+            _ => return Err(MLError::InvalidTarget("linear regression needs a float target".to_owned())),
+        };
+        let total: f32 = sample_weights.iter().sum();
+        if !(total > 0.0) {
+            return Err(MLError::InvalidParameter("at least one sample weight needs to be > 0".to_owned()));
+        }
+        let weights: Vec<f32> = sample_weights.iter().map(|weight| weight * y.len() as f32 / total).collect();
+
+        let (x_means, y_mean) = if self.fit_intercept {
+            (column_means(X_train, &weights), dot(&weights, y) / y.len() as f32)
+        } else {
+            (vec![0.0_f32; X_train.cols()], 0.0)
+        };
+        let mut X = X_train.clone();
+        for (row, weight) in X.iter_rows_mut().zip(&weights) {
+            axpy(-1.0, &x_means, row);
+            row.iter_mut().for_each(|value| *value *= weight.sqrt());
+        }
+        let y: Vec<f32> = y.iter().zip(&weights).map(|(value, weight)| (value - y_mean) * weight.sqrt()).collect();
+
+        let has_l1 = self.alpha > 0.0 && self.l1_ratio > 0.0;
+        self.n_iter = 0;
+        let coefficients = match self.solver.clone() {
+            Solver::Auto if has_l1 => self.solve_coordinate_descent(&X, &y, 1000, 1e-5),
+            Solver::Auto if self.alpha > 0.0 => self.solve_normal_equation(&X, &y)?,
+            Solver::Auto | Solver::Qr => self.solve_qr(&X, &y)?,
+            Solver::NormalEquation => self.solve_normal_equation(&X, &y)?,
+            Solver::BatchGradientDescent { learning_rate, max_iter, tol } =>
+                self.solve_gradient_descent(&X, &y, learning_rate, max_iter, tol, X.rows(), None),
+            Solver::MiniBatchGradientDescent { learning_rate, max_iter, tol, batch_size, seed } =>
+                self.solve_gradient_descent(&X, &y, learning_rate, max_iter, tol, batch_size, Some(seed)),
+            Solver::CoordinateDescent { max_iter, tol } => self.solve_coordinate_descent(&X, &y, max_iter, tol),
+        };
+        if coefficients.iter().any(|value| !value.is_finite()) {
+            return Err(MLError::InvalidParameter("the solver diverged, try a smaller learning rate or scaling the features".to_owned()));
+        }
+
+        self.intercept = y_mean - dot(&x_means, &coefficients);
+        self.coefficients = coefficients;
+        self.fitted = true;
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.then_some(self.coefficients.len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params.insert("l1_ratio".to_owned(), ParamValue::Float(self.l1_ratio));
+        params.insert("fit_intercept".to_owned(), ParamValue::Bool(self.fit_intercept));
+        params.insert("solver".to_owned(), ParamValue::Text(self.solver.name().to_owned()));
+        params
+    }
+
+    ///`solver` takes the solvers without settings, the name of the current solver leaves it(and its settings) as it is.
+    ///Use `set_solver` for the iterative ones.
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "alpha" => self.alpha = value.as_f32(name)?,
+                "l1_ratio" => self.l1_ratio = value.as_f32(name)?,
+                "fit_intercept" => self.fit_intercept = value.as_bool(name)?,
+                "solver" => if value.as_str(name)? != self.solver.name() {
+                    self.solver = Solver::from_name(value.as_str(name)?)?;
+                },
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for LinearRegression {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        ReturnType::Floats(self.predict_value(point))
+    }
+}
+
+impl Estimator for LinearRegression {}
+
+impl Persist for LinearRegression {
+    const MODEL_TYPE: &'static str = "LinearRegression";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(!self.fitted || !self.coefficients.is_empty(), "a fitted model needs atleast one coefficient")?;
+        ensure(self.coefficients.iter().all(|value| value.is_finite()) && self.intercept.is_finite(), "the coefficients need to be finite")
+    }
+}
@@ -0,0 +1,156 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::{MLalgo, Predict, MLError, Params, ParamValue},
+    persistence::persistence::Persist};
+use super::linear_regression::{LinearRegression, Solver};
+
+
+///y = 3 x0 - 2 x1 + 0.5 x2 + 4 on a fixed grid, no noise.
+fn exact_data() -> (Matrix, DataType) {
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for i in 0..30 {
+        let x0 = (i % 5) as f32 * 0.5;
+        let x1 = (i % 7) as f32 * 0.3 - 1.0;
+        let x2 = ((i * 3) % 11) as f32 * 0.2;
+        rows.push(vec![x0, x1, x2]);
+        targets.push(3.0 * x0 - 2.0 * x1 + 0.5 * x2 + 4.0);
+    }
+    (Matrix::from(rows), DataType::Floats(targets))
+}
+
+fn assert_close(found : &[f32], expected : &[f32], tolerance : f32) {
+    for (found, expected) in found.iter().zip(expected) {
+        assert!((found - expected).abs() < tolerance, "found {:?}, expected {:?}", found, expected);
+    }
+}
+
+#[test]
+fn every_least_squares_solver_recovers_the_coefficients() {
+    let (X, y) = exact_data();
+    let solvers = vec![
+        (Solver::Qr, 1e-3),
+        (Solver::NormalEquation, 1e-2),
+        (Solver::BatchGradientDescent { learning_rate: 0.3, max_iter: 20000, tol: 1e-7 }, 1e-2),
+        (Solver::MiniBatchGradientDescent { learning_rate: 0.05, max_iter: 3000, tol: 1e-7, batch_size: 8, seed: 7 }, 1e-2),
+        (Solver::CoordinateDescent { max_iter: 10000, tol: 1e-7 }, 1e-2),
+    ];
+    for (solver, tolerance) in solvers {
+        let mut model = LinearRegression::new();
+        model.set_solver(solver.clone());
+        model.fit(&X, &y).unwrap();
+        assert_close(model.coefficients(), &[3.0, -2.0, 0.5], tolerance);
+        assert!((model.intercept() - 4.0).abs() < tolerance * 5.0, "{:?} intercept {}", solver, model.intercept());
+    }
+}
+
+#[test]
+fn predict_returns_floats() {
+    let (X, y) = exact_data();
+    let mut model = LinearRegression::new();
+    model.fit(&X, &y).unwrap();
+    match model.predict(&[1.0, 0.0, 0.0]) {
+        ReturnType::Floats(value) => assert!((value - 7.0).abs() < 1e-3),
+        other => panic!("expected a float, got {:?}", other),
+    }
+    assert_eq!(model.try_predict(&[1.0]), Err(MLError::FeatureMismatch { expected: 3, found: 1 }));
+}
+
+#[test]
+fn without_intercept_the_line_goes_through_the_origin() {
+    let X = Matrix::from(vec![vec![1.0], vec![2.0], vec![3.0]]);
+    let mut model = LinearRegression::new();
+    model.set_fit_intercept(false);
+    model.fit(&X, &DataType::Floats(vec![3.0, 5.0, 7.0])).unwrap();
+    assert_eq!(model.intercept(), 0.0);
+    //least squares slope through the origin is sum(xy)/sum(x^2) = 34/14.
+    assert_close(model.coefficients(), &[34.0 / 14.0], 1e-4);
+}
+
+#[test]
+fn ridge_shrinks_towards_zero_and_solvers_agree() {
+    let (X, y) = exact_data();
+    let mut closed_form = LinearRegression::ridge(0.5);
+    closed_form.fit(&X, &y).unwrap();
+    let mut iterative = LinearRegression::ridge(0.5);
+    iterative.set_solver(Solver::CoordinateDescent { max_iter: 10000, tol: 1e-7 });
+    iterative.fit(&X, &y).unwrap();
+
+    assert_close(closed_form.coefficients(), iterative.coefficients(), 1e-3);
+    assert!(closed_form.coefficients()[0].abs() < 3.0 && closed_form.coefficients()[1].abs() < 2.0);
+}
+
+#[test]
+fn lasso_zeroes_out_the_weak_feature() {
+    let (X, y) = exact_data();
+    let mut model = LinearRegression::lasso(0.5);
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.coefficients()[2], 0.0);
+    assert!(model.coefficients()[0] > 2.0);
+    assert!(model.n_iter() > 0);
+
+    let mut elastic_net = LinearRegression::elastic_net(0.5, 0.5);
+    elastic_net.fit(&X, &y).unwrap();
+    assert!(elastic_net.coefficients()[2].abs() < model.coefficients()[0].abs());
+}
+
+#[test]
+fn bad_configurations_are_rejected() {
+    let (X, y) = exact_data();
+    let mut model = LinearRegression::lasso(0.1);
+    model.set_solver(Solver::Qr);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    assert!(matches!(LinearRegression::new().fit(&X, &DataType::Category(vec![0; 30])), Err(MLError::InvalidTarget(_))));
+
+    let mut params = Params::new();
+    params.insert("l1_ratio".to_owned(), ParamValue::Float(2.0));
+    assert!(LinearRegression::new().set_params(&params).is_err());
+}
+
+#[test]
+fn params_and_persistence() {
+    let (X, y) = exact_data();
+    let mut model = LinearRegression::new();
+    let mut params = Params::new();
+    params.insert("alpha".to_owned(), ParamValue::Float(0.1));
+    model.set_params(&params).unwrap();
+    assert_eq!(model.get_params().get("alpha"), Some(&ParamValue::Float(0.1)));
+    assert_eq!(model.get_params().get("solver"), Some(&ParamValue::Text("auto".to_owned())));
+    //everything get_params gives back is taken by set_params, the iterative solvers keep their settings.
+    let solver = Solver::CoordinateDescent { max_iter: 500, tol: 1e-5 };
+    model.set_solver(solver.clone());
+    model.set_params(&model.get_params()).unwrap();
+    assert_eq!(model.solver, solver);
+    params.insert("solver".to_owned(), ParamValue::Text("normal_equation".to_owned()));
+    model.set_params(&params).unwrap();
+    assert_eq!(model.solver, Solver::NormalEquation);
+    params.insert("solver".to_owned(), ParamValue::Text("coordinate_descent".to_owned()));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+    model.set_solver(Solver::Auto);
+
+    model.fit(&X, &y).unwrap();
+    let loaded = LinearRegression::from_json(&model.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.predict(&[1.0, 1.0, 1.0]), model.predict(&[1.0, 1.0, 1.0]));
+}
+
+#[test]
+fn zero_weights_leave_the_outliers_out() {
+    let (X, y) = exact_data();
+    let mut rows: Vec<Vec<f32>> = X.iter_rows().map(|row| row.to_vec()).collect();
+    let mut targets = match y { DataType::Floats(targets) => targets, _ => unreachable!() };
+    let mut weights = vec![1.0; rows.len()];
+    for i in 0..3 {
+        rows.push(vec![i as f32, 1.0, 0.5]);
+        targets.push(100.0);
+        weights.push(0.0);
+    }
+    let (X, y) = (Matrix::from(rows), DataType::Floats(targets));
+    for solver in [Solver::Qr, Solver::NormalEquation, Solver::CoordinateDescent { max_iter: 10000, tol: 1e-7 }] {
+        let mut model = LinearRegression::new();
+        model.set_solver(solver);
+        model.fit_weighted(&X, &y, &weights).unwrap();
+        assert_close(model.coefficients(), &[3.0, -2.0, 0.5], 1e-2);
+        assert!((model.intercept() - 4.0).abs() < 1e-2);
+    }
+    assert!(matches!(LinearRegression::new().fit_weighted(&X, &y, &vec![0.0; 33]), Err(MLError::InvalidParameter(_))));
+}
@@ -0,0 +1,879 @@
+//! #Quantile and robust linear regression#
+//!
+//! Linear models `y = X w + b` that are not thrown around by outliers the way least squares is.
+//! * `QuantileRegressor` -> minimises the pinball loss, fits the `quantile` of y given X(0.5 is least absolute deviations).
+//! * `HuberRegressor` -> squared loss for small residuals and absolute loss for the big ones, the scale of the residuals
+//!   is estimated along the way so `epsilon` is in units of it.
+//! * `Ransac` -> fits a `LinearRegression` on random minimal subsets and keeps the one with the most inliers, survives
+//!   any fraction of outliers as long as the inliers are the biggest consistent group.
+//! * `TheilSen` -> the spatial median of the exact fits through many small subsets, up to ~29% arbitrary outliers.
+//!
+//! `HuberRegressor` and `Ransac` report which training samples they treated as outliers.
+//! All of them take sample weights, which multiply the IRLS weights, for `Ransac` weight the final refit on the inliers
+//! and for `TheilSen` weight every subset by the product of the weights of its samples(the share of subsets it would get with repeated samples).
+
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::DataType, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::{matrix::Matrix, linalg::{dot, solve}},
+    supervised::linear_regression::linear_regression::LinearRegression,
+    persistence::persistence::{Persist, PersistError, ensure}};
+
+
+fn float_target<'a>(y_train : &'a DataType, model : &str) -> Result<&'a Vec<f32>, MLError> {
+    match y_train {
+        DataType::Floats(temp) => Ok(temp),
+        _ => Err(MLError::InvalidTarget(format!("{} needs a float target", model))),
+    }
+}
+
+fn median(values : &mut [f32]) -> f32 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 { (values[middle - 1] + values[middle]) / 2.0 } else { values[middle] }
+}
+
+///median absolute deviation scaled to be the standard deviation for normal data.
+fn mad_scale(residuals : &[f32]) -> f32 {
+    let mut absolute: Vec<f32> = residuals.iter().map(|residual| residual.abs()).collect();
+    median(&mut absolute) / 0.6745
+}
+
+fn residuals(X : &Matrix, y : &[f32], coefficients : &[f32], intercept : f32) -> Vec<f32> {
+    X.iter_rows().zip(y).map(|(row, target)| target - dot(coefficients, row) - intercept).collect()
+}
+
+///solves `min sum(weights_i * (y_i - x_i.w - b)^2) + l2 * ||w||^2`, returns the weights and the intercept.
+fn weighted_least_squares(X : &Matrix, y : &[f32], weights : &[f32], fit_intercept : bool, l2 : f32) -> Result<(Vec<f32>, f32), MLError> {
+    let n_features = X.cols();
+    let size = n_features + fit_intercept as usize;
+    let mut system = vec![0.0_f64; size * size];
+    let mut right_hand_side = vec![0.0_f64; size];
+    let mut extended = vec![1.0_f64; size];
+    for ((row, target), weight) in X.iter_rows().zip(y).zip(weights) {
+        let weight = *weight as f64;
+        if weight == 0.0 {
+            continue;
+        }
+        for (slot, value) in extended.iter_mut().zip(row) {
+            *slot = *value as f64;
+        }
+        for i in 0..size {
+            right_hand_side[i] += weight * extended[i] * *target as f64;
+            for j in 0..size {
+                system[i * size + j] += weight * extended[i] * extended[j];
+            }
+        }
+    }
+    //a tiny ridge keeps collinear or constant features solvable.
+    let trace: f64 = (0..size).map(|i| system[i * size + i]).sum();
+    let mut matrix = Matrix::new(size, size);
+    for i in 0..size {
+        for j in 0..size {
+            matrix[(i, j)] = system[i * size + j] as f32;
+        }
+        if i < n_features {
+            matrix[(i, i)] += l2 + (1e-10 * trace / size as f64) as f32;
+        }
+    }
+    let rhs: Vec<f32> = right_hand_side.iter().map(|value| *value as f32).collect();
+    let solution = solve(&matrix, &rhs).map_err(|error| MLError::InvalidParameter(format!("could not solve the weighted least squares problem : {}", error)))?;
+    let intercept = if fit_intercept { solution[n_features] } else { 0.0 };
+    Ok((solution[..n_features].to_vec(), intercept))
+}
+
+
+//***************************************
+//QUANTILE REGRESSION
+//***************************************
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuantileRegressor {
+    ///the quantile to predict, in (0, 1).
+    pub quantile : f32,
+    pub fit_intercept : bool,
+    pub max_iter : usize,
+    ///stops once no coefficient moves by more than this.
+    pub tol : f32,
+    coefficients : Vec<f32>,
+    intercept : f32,
+    n_iter : usize,
+    fitted : bool,
+}
+
+impl QuantileRegressor {
+
+    ///the median(0.5) is least absolute deviations regression.
+    pub fn new(quantile : f32) -> QuantileRegressor {
+        QuantileRegressor {
+            quantile,
+            fit_intercept: true,
+            max_iter: 200,
+            tol: 1e-6,
+            coefficients: vec![],
+            intercept: 0.0,
+            n_iter: 0,
+            fitted: false,
+        }
+    }
+
+    pub fn set_fit_intercept(&mut self, fit_intercept : bool) {
+        self.fit_intercept = fit_intercept;
+    }
+
+    ///iteration limit and convergence tolerance.
+    pub fn set_stopping(&mut self, max_iter : usize, tol : f32) {
+        self.max_iter = max_iter;
+        self.tol = tol;
+    }
+
+    pub fn coefficients(&self) -> &[f32] {
+        &self.coefficients
+    }
+
+    pub fn intercept(&self) -> f32 {
+        self.intercept
+    }
+
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    ///mean pinball loss of the training set for the current coefficients.
+    pub fn pinball_loss(&self, X : &Matrix, y : &[f32]) -> f32 {
+        residuals(X, y, &self.coefficients, self.intercept).iter()
+            .map(|residual| if *residual >= 0.0 { self.quantile * residual } else { (self.quantile - 1.0) * residual })
+            .sum::<f32>() / y.len() as f32
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if !(self.quantile > 0.0 && self.quantile < 1.0) {
+            return Err(MLError::InvalidParameter(format!("the quantile needs to be in (0, 1), got {}", self.quantile)));
+        }
+        if self.max_iter == 0 {
+            return Err(MLError::InvalidParameter("max_iter needs to be > 0".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+impl MLalgo for QuantileRegressor {
+    ///iteratively reweighted least squares on the pinball loss, every residual `r` gets the weight
+    ///`quantile / |r|` above the fit and `(1 - quantile) / |r|` below it.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+        let y = float_target(y_train, "quantile regression")?;
+        let scale = mad_scale(y).max(1e-6);
+        let (mut coefficients, mut intercept) = weighted_least_squares(X_train, y, sample_weights, self.fit_intercept, 0.0)?;
+        self.n_iter = self.max_iter;
+        for iteration in 0..self.max_iter {
+            let weights: Vec<f32> = residuals(X_train, y, &coefficients, intercept).iter().zip(sample_weights).map(|(residual, weight)| {
+                let side = if *residual >= 0.0 { self.quantile } else { 1.0 - self.quantile };
+                //the floor keeps the samples the fit goes through from getting infinite weights.
+                weight * side / residual.abs().max(1e-6 * scale)
+            }).collect();
+            let (new_coefficients, new_intercept) = weighted_least_squares(X_train, y, &weights, self.fit_intercept, 0.0)?;
+            let change = new_coefficients.iter().zip(&coefficients).map(|(a, b)| (a - b).abs()).fold((new_intercept - intercept).abs(), f32::max);
+            coefficients = new_coefficients;
+            intercept = new_intercept;
+            if change < self.tol {
+                self.n_iter = iteration + 1;
+                break;
+            }
+        }
+        self.coefficients = coefficients;
+        self.intercept = intercept;
+        self.fitted = true;
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.then_some(self.coefficients.len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("quantile".to_owned(), ParamValue::Float(self.quantile));
+        params.insert("fit_intercept".to_owned(), ParamValue::Bool(self.fit_intercept));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "quantile" => self.quantile = value.as_f32(name)?,
+                "fit_intercept" => self.fit_intercept = value.as_bool(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for QuantileRegressor {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        ReturnType::Floats(dot(&self.coefficients, point) + self.intercept)
+    }
+}
+
+impl Estimator for QuantileRegressor {}
+
+impl Persist for QuantileRegressor {
+    const MODEL_TYPE: &'static str = "QuantileRegressor";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(!self.fitted || !self.coefficients.is_empty(), "a fitted model needs atleast one coefficient")?;
+        ensure(self.coefficients.iter().all(|value| value.is_finite()) && self.intercept.is_finite(), "the coefficients need to be finite")
+    }
+}
+
+
+//***************************************
+//HUBER REGRESSION
+//***************************************
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HuberRegressor {
+    ///residuals above `epsilon * scale` are outliers and only count linearly, 1.35 keeps 95% of the efficiency of least squares on normal data.
+    pub epsilon : f32,
+    ///strength of the L2 penalty on the weights.
+    pub alpha : f32,
+    pub fit_intercept : bool,
+    pub max_iter : usize,
+    pub tol : f32,
+    coefficients : Vec<f32>,
+    intercept : f32,
+    ///robust estimate of the spread of the residuals.
+    scale : f32,
+    outliers : Vec<bool>,
+    n_iter : usize,
+    fitted : bool,
+}
+
+impl HuberRegressor {
+
+    pub fn new() -> HuberRegressor {
+        HuberRegressor {
+            epsilon: 1.35,
+            alpha: 1e-4,
+            fit_intercept: true,
+            max_iter: 100,
+            tol: 1e-5,
+            coefficients: vec![],
+            intercept: 0.0,
+            scale: 1.0,
+            outliers: vec![],
+            n_iter: 0,
+            fitted: false,
+        }
+    }
+
+    pub fn set_epsilon(&mut self, epsilon : f32) {
+        self.epsilon = epsilon;
+    }
+
+    pub fn set_alpha(&mut self, alpha : f32) {
+        self.alpha = alpha;
+    }
+
+    pub fn set_fit_intercept(&mut self, fit_intercept : bool) {
+        self.fit_intercept = fit_intercept;
+    }
+
+    ///iteration limit and convergence tolerance.
+    pub fn set_stopping(&mut self, max_iter : usize, tol : f32) {
+        self.max_iter = max_iter;
+        self.tol = tol;
+    }
+
+    pub fn coefficients(&self) -> &[f32] {
+        &self.coefficients
+    }
+
+    pub fn intercept(&self) -> f32 {
+        self.intercept
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    ///`true` for the training samples whose residual was beyond `epsilon * scale`.
+    pub fn outliers(&self) -> &[bool] {
+        &self.outliers
+    }
+
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if !(self.epsilon >= 1.0) {
+            return Err(MLError::InvalidParameter(format!("epsilon needs to be >= 1, got {}", self.epsilon)));
+        }
+        if !(self.alpha >= 0.0) {
+            return Err(MLError::InvalidParameter(format!("alpha needs to be >= 0, got {}", self.alpha)));
+        }
+        if self.max_iter == 0 {
+            return Err(MLError::InvalidParameter("max_iter needs to be > 0".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+impl Default for HuberRegressor {
+    fn default() -> Self {
+        HuberRegressor::new()
+    }
+}
+
+impl MLalgo for HuberRegressor {
+    ///iteratively reweighted least squares, the scale is re-estimated from the residuals(median absolute deviation) every round.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+        let y = float_target(y_train, "Huber regression")?;
+        let l2 = self.alpha * y.len() as f32;
+        let (mut coefficients, mut intercept) = weighted_least_squares(X_train, y, sample_weights, self.fit_intercept, l2)?;
+        let mut scale = 1.0_f32;
+        self.n_iter = self.max_iter;
+        for iteration in 0..self.max_iter {
+            let current = residuals(X_train, y, &coefficients, intercept);
+            scale = mad_scale(&current).max(1e-6);
+            let threshold = self.epsilon * scale;
+            let weights: Vec<f32> = current.iter().zip(sample_weights).map(|(residual, weight)| if residual.abs() <= threshold { *weight } else { weight * threshold / residual.abs() }).collect();
+            let (new_coefficients, new_intercept) = weighted_least_squares(X_train, y, &weights, self.fit_intercept, l2)?;
+            let change = new_coefficients.iter().zip(&coefficients).map(|(a, b)| (a - b).abs()).fold((new_intercept - intercept).abs(), f32::max);
+            coefficients = new_coefficients;
+            intercept = new_intercept;
+            if change < self.tol {
+                self.n_iter = iteration + 1;
+                break;
+            }
+        }
+        let threshold = self.epsilon * scale;
+        self.outliers = residuals(X_train, y, &coefficients, intercept).iter().map(|residual| residual.abs() > threshold).collect();
+        self.coefficients = coefficients;
+        self.intercept = intercept;
+        self.scale = scale;
+        self.fitted = true;
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.then_some(self.coefficients.len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("epsilon".to_owned(), ParamValue::Float(self.epsilon));
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params.insert("fit_intercept".to_owned(), ParamValue::Bool(self.fit_intercept));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "epsilon" => self.epsilon = value.as_f32(name)?,
+                "alpha" => self.alpha = value.as_f32(name)?,
+                "fit_intercept" => self.fit_intercept = value.as_bool(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for HuberRegressor {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        ReturnType::Floats(dot(&self.coefficients, point) + self.intercept)
+    }
+}
+
+impl Estimator for HuberRegressor {}
+
+impl Persist for HuberRegressor {
+    const MODEL_TYPE: &'static str = "HuberRegressor";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(!self.fitted || !self.coefficients.is_empty(), "a fitted model needs atleast one coefficient")?;
+        ensure(self.coefficients.iter().all(|value| value.is_finite()) && self.intercept.is_finite(), "the coefficients need to be finite")
+    }
+}
+
+
+//***************************************
+//RANSAC
+//***************************************
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ransac {
+    ///fitted on every random subset and on the final inliers, `LinearRegression::new()` by default.
+    pub estimator : LinearRegression,
+    ///size of the random subsets, `None` for the minimum `n_features + 1`.
+    pub min_samples : Option<usize>,
+    ///samples with an absolute residual up to this are inliers, `None` for the median absolute deviation of y.
+    pub residual_threshold : Option<f32>,
+    pub max_trials : usize,
+    pub seed : u64,
+    inlier_mask : Vec<bool>,
+    n_trials : usize,
+    fitted : bool,
+}
+
+impl Ransac {
+
+    pub fn new() -> Ransac {
+        Ransac {
+            estimator: LinearRegression::new(),
+            min_samples: None,
+            residual_threshold: None,
+            max_trials: 100,
+            seed: 0,
+            inlier_mask: vec![],
+            n_trials: 0,
+            fitted: false,
+        }
+    }
+
+    pub fn set_estimator(&mut self, estimator : LinearRegression) {
+        self.estimator = estimator;
+    }
+
+    pub fn set_min_samples(&mut self, min_samples : Option<usize>) {
+        self.min_samples = min_samples;
+    }
+
+    pub fn set_residual_threshold(&mut self, residual_threshold : Option<f32>) {
+        self.residual_threshold = residual_threshold;
+    }
+
+    ///number of random subsets to try and the seed for drawing them.
+    pub fn set_trials(&mut self, max_trials : usize, seed : u64) {
+        self.max_trials = max_trials;
+        self.seed = seed;
+    }
+
+    ///the final model, fitted on the inliers.
+    pub fn estimator(&self) -> &LinearRegression {
+        &self.estimator
+    }
+
+    ///`true` for the training samples the final model was fitted on.
+    pub fn inlier_mask(&self) -> &[bool] {
+        &self.inlier_mask
+    }
+
+    ///number of subsets that gave a usable model.
+    pub fn n_trials(&self) -> usize {
+        self.n_trials
+    }
+
+    ///the checks that do not need the data, `min_samples` is checked against the number of samples in fit.
+    fn validate_params(&self) -> Result<(), MLError> {
+        if self.min_samples == Some(0) {
+            return Err(MLError::InvalidParameter("min_samples needs to be > 0".to_owned()));
+        }
+        if self.max_trials == 0 {
+            return Err(MLError::InvalidParameter("max_trials needs to be > 0".to_owned()));
+        }
+        if let Some(threshold) = self.residual_threshold {
+            if !(threshold >= 0.0) {
+                return Err(MLError::InvalidParameter(format!("residual_threshold needs to be >= 0, got {}", threshold)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Ransac {
+    fn default() -> Self {
+        Ransac::new()
+    }
+}
+
+impl MLalgo for Ransac {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///the trials count the inliers unweighted, only the final refit on them uses the weights.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+        let y = float_target(y_train, "RANSAC")?;
+        let min_samples = self.min_samples.unwrap_or(X_train.cols() + 1);
+        if min_samples > y.len() {
+            return Err(MLError::InvalidParameter(format!("min_samples needs to be in [1, {}], got {}", y.len(), min_samples)));
+        }
+        let threshold = match self.residual_threshold {
+            Some(threshold) => threshold,
+            None => {
+                let y_median = median(&mut y.clone());
+                let mut deviations: Vec<f32> = y.iter().map(|value| (value - y_median).abs()).collect();
+                median(&mut deviations)
+            },
+        };
+
+        let mut rng = fastrand::Rng::with_seed(self.seed);
+        let mut indices: Vec<usize> = (0..y.len()).collect();
+        //(inliers, sum of the inlier residuals, mask)
+        let mut best: Option<(usize, f32, Vec<bool>)> = None;
+        self.n_trials = 0;
+        for _ in 0..self.max_trials {
+            //a partial Fisher-Yates shuffle draws the subset.
+            for position in 0..min_samples {
+                let other = rng.usize(position..indices.len());
+                indices.swap(position, other);
+            }
+            let subset = &indices[..min_samples];
+            let subset_y: Vec<f32> = subset.iter().map(|sample| y[*sample]).collect();
+            let mut candidate = self.estimator.clone();
+            if candidate.fit(&X_train.select_rows(subset), &DataType::Floats(subset_y)).is_err() {
+                continue;
+            }
+            self.n_trials += 1;
+            let predictions = candidate.predict_many(X_train);
+            let absolute: Vec<f32> = predictions.iter().zip(y).map(|(prediction, target)| (target - prediction).abs()).collect();
+            let mask: Vec<bool> = absolute.iter().map(|residual| *residual <= threshold).collect();
+            let inliers = mask.iter().filter(|inlier| **inlier).count();
+            let spread: f32 = absolute.iter().zip(&mask).filter(|(_, inlier)| **inlier).map(|(residual, _)| residual).sum();
+            let better = match &best {
+                Some((best_inliers, best_spread, _)) => inliers > *best_inliers || (inliers == *best_inliers && spread < *best_spread),
+                None => true,
+            };
+            if better {
+                best = Some((inliers, spread, mask));
+            }
+        }
+
+        let (inliers, _, mask) = best.ok_or_else(|| MLError::InvalidParameter("no random subset could be fitted".to_owned()))?;
+        if inliers < min_samples {
+            return Err(MLError::InvalidParameter(format!("the best model only has {} inliers, try a bigger residual_threshold", inliers)));
+        }
+        let selected: Vec<usize> = (0..y.len()).filter(|sample| mask[*sample]).collect();
+        let selected_y: Vec<f32> = selected.iter().map(|sample| y[*sample]).collect();
+        let selected_weights: Vec<f32> = selected.iter().map(|sample| sample_weights[*sample]).collect();
+        self.estimator.fit_weighted(&X_train.select_rows(&selected), &DataType::Floats(selected_y), &selected_weights)?;
+        self.inlier_mask = mask;
+        self.fitted = true;
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        if self.fitted { self.estimator.n_features_in() } else { None }
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("max_trials".to_owned(), ParamValue::Int(self.max_trials as i64));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "max_trials" => self.max_trials = value.as_usize(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for Ransac {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        self.estimator.predict(point)
+    }
+}
+
+impl Estimator for Ransac {}
+
+impl Persist for Ransac {
+    const MODEL_TYPE: &'static str = "Ransac";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(!self.fitted || self.estimator.is_fitted(), "a fitted model needs a fitted estimator")?;
+        self.estimator.validate()
+    }
+}
+
+
+//***************************************
+//THEIL-SEN
+//***************************************
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TheilSen {
+    pub fit_intercept : bool,
+    ///upper bound on the number of subsets, all of them are used when there are fewer.
+    pub max_subpopulation : usize,
+    pub seed : u64,
+    ///iterations of the Weiszfeld algorithm for the spatial median.
+    pub max_iter : usize,
+    pub tol : f32,
+    coefficients : Vec<f32>,
+    intercept : f32,
+    n_subpopulation : usize,
+    fitted : bool,
+}
+
+impl TheilSen {
+
+    pub fn new() -> TheilSen {
+        TheilSen {
+            fit_intercept: true,
+            max_subpopulation: 10000,
+            seed: 0,
+            max_iter: 300,
+            tol: 1e-5,
+            coefficients: vec![],
+            intercept: 0.0,
+            n_subpopulation: 0,
+            fitted: false,
+        }
+    }
+
+    pub fn set_fit_intercept(&mut self, fit_intercept : bool) {
+        self.fit_intercept = fit_intercept;
+    }
+
+    ///at most `max_subpopulation` random subsets, drawn with `seed`.
+    pub fn set_subpopulation(&mut self, max_subpopulation : usize, seed : u64) {
+        self.max_subpopulation = max_subpopulation;
+        self.seed = seed;
+    }
+
+    pub fn coefficients(&self) -> &[f32] {
+        &self.coefficients
+    }
+
+    pub fn intercept(&self) -> f32 {
+        self.intercept
+    }
+
+    ///number of subsets the median was taken over.
+    pub fn n_subpopulation(&self) -> usize {
+        self.n_subpopulation
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if self.max_subpopulation == 0 {
+            return Err(MLError::InvalidParameter("max_subpopulation needs to be > 0".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+impl Default for TheilSen {
+    fn default() -> Self {
+        TheilSen::new()
+    }
+}
+
+///every k element subset of 0..n in lexicographic order, `None` once there are more than `limit`.
+fn all_subsets(n : usize, k : usize, limit : usize) -> Option<Vec<Vec<usize>>> {
+    let mut subsets = vec![];
+    let mut current: Vec<usize> = (0..k).collect();
+    loop {
+        if subsets.len() == limit {
+            return None;
+        }
+        subsets.push(current.clone());
+        //the rightmost entry that can still move up, there is none after the last subset.
+        let position = match (0..k).rev().find(|position| current[*position] != *position + n - k) {
+            Some(position) => position,
+            None => return Some(subsets),
+        };
+        current[position] += 1;
+        for next in position + 1..k {
+            current[next] = current[next - 1] + 1;
+        }
+    }
+}
+
+///the point with the smallest weighted sum of distances to all the `points`, with Weiszfeld's algorithm.
+fn spatial_median(points : &[Vec<f64>], weights : &[f64], max_iter : usize, tol : f64) -> Vec<f64> {
+    let dimensions = points[0].len();
+    let total_weight: f64 = weights.iter().sum();
+    let mut centre: Vec<f64> = (0..dimensions).map(|d| points.iter().zip(weights).map(|(point, weight)| weight * point[d]).sum::<f64>() / total_weight).collect();
+    for _ in 0..max_iter {
+        let mut numerator = vec![0.0_f64; dimensions];
+        let mut denominator = 0.0_f64;
+        for (point, weight) in points.iter().zip(weights) {
+            let distance = point.iter().zip(&centre).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt();
+            //a point sitting on the centre would divide by zero, it simply does not pull.
+            if distance < 1e-12 {
+                continue;
+            }
+            numerator.iter_mut().zip(point).for_each(|(total, value)| *total += weight * value / distance);
+            denominator += weight / distance;
+        }
+        if denominator == 0.0 {
+            break;
+        }
+        let next: Vec<f64> = numerator.iter().map(|total| total / denominator).collect();
+        let change = next.iter().zip(&centre).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt();
+        centre = next;
+        if change < tol {
+            break;
+        }
+    }
+    centre
+}
+
+impl MLalgo for TheilSen {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///the subsets are drawn from the samples with a weight > 0, and each one counts with the product of the weights of its samples.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        let y = float_target(y_train, "Theil-Sen")?;
+        check_sample_weights(sample_weights, y.len())?;
+        self.validate_params()?;
+        let subset_size = X_train.cols() + self.fit_intercept as usize;
+        let samples: Vec<usize> = (0..y.len()).filter(|sample| sample_weights[*sample] > 0.0).collect();
+        if samples.len() < subset_size {
+            return Err(MLError::InvalidParameter(format!("Theil-Sen needs atleast {} samples with a weight > 0", subset_size)));
+        }
+
+        let subsets = match all_subsets(samples.len(), subset_size, self.max_subpopulation) {
+            Some(subsets) => subsets,
+            None => {
+                let mut rng = fastrand::Rng::with_seed(self.seed);
+                let mut indices: Vec<usize> = (0..samples.len()).collect();
+                (0..self.max_subpopulation).map(|_| {
+                    for position in 0..subset_size {
+                        let other = rng.usize(position..indices.len());
+                        indices.swap(position, other);
+                    }
+                    indices[..subset_size].to_vec()
+                }).collect()
+            },
+        };
+        //the weights relative to their mean, so the products stay around 1.
+        let mean_weight = samples.iter().map(|sample| sample_weights[*sample] as f64).sum::<f64>() / samples.len() as f64;
+        //the exact fit through every subset, degenerate subsets are skipped.
+        let (solutions, weights): (Vec<Vec<f64>>, Vec<f64>) = subsets.iter().filter_map(|subset| {
+            let subset: Vec<usize> = subset.iter().map(|position| samples[*position]).collect();
+            let subset_y: Vec<f32> = subset.iter().map(|sample| y[*sample]).collect();
+            let (coefficients, intercept) = weighted_least_squares(&X_train.select_rows(&subset), &subset_y, &vec![1.0; subset.len()], self.fit_intercept, 0.0).ok()?;
+            let solution: Vec<f64> = coefficients.iter().chain(std::iter::once(&intercept)).map(|value| *value as f64).collect();
+            let weight: f64 = subset.iter().map(|sample| sample_weights[*sample] as f64 / mean_weight).product();
+            solution.iter().all(|value| value.is_finite()).then_some((solution, weight))
+        }).unzip();
+        if solutions.is_empty() {
+            return Err(MLError::InvalidParameter("every subset was degenerate, are some features constant?".to_owned()));
+        }
+
+        let centre = spatial_median(&solutions, &weights, self.max_iter, self.tol as f64);
+        let n_features = X_train.cols();
+        self.coefficients = centre[..n_features].iter().map(|value| *value as f32).collect();
+        self.intercept = if self.fit_intercept { centre[n_features] as f32 } else { 0.0 };
+        self.n_subpopulation = solutions.len();
+        self.fitted = true;
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.then_some(self.coefficients.len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("fit_intercept".to_owned(), ParamValue::Bool(self.fit_intercept));
+        params.insert("max_subpopulation".to_owned(), ParamValue::Int(self.max_subpopulation as i64));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "fit_intercept" => self.fit_intercept = value.as_bool(name)?,
+                "max_subpopulation" => self.max_subpopulation = value.as_usize(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for TheilSen {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        ReturnType::Floats(dot(&self.coefficients, point) + self.intercept)
+    }
+}
+
+impl Estimator for TheilSen {}
+
+impl Persist for TheilSen {
+    const MODEL_TYPE: &'static str = "TheilSen";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(!self.fitted || !self.coefficients.is_empty(), "a fitted model needs atleast one coefficient")?;
+        ensure(self.coefficients.iter().all(|value| value.is_finite()) && self.intercept.is_finite(), "the coefficients need to be finite")
+    }
+}
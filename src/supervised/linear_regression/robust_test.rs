@@ -0,0 +1,136 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::{MLalgo, Predict, MLError, Params, ParamValue},
+    supervised::linear_regression::linear_regression::LinearRegression, persistence::persistence::Persist};
+use super::robust::{QuantileRegressor, HuberRegressor, Ransac, TheilSen};
+
+
+///`y = 2 x0 - 3 x1 + 1` plus a little noise, every `outlier_every`th target is pushed up by 50.
+fn contaminated(seed : u64, samples : usize, outlier_every : usize) -> (Matrix, Vec<f32>, Vec<bool>) {
+    let rng = fastrand::Rng::with_seed(seed);
+    let (mut rows, mut targets, mut outliers) = (vec![], vec![], vec![]);
+    for sample in 0..samples {
+        let row = vec![rng.f32() * 2.0 - 1.0, rng.f32() * 2.0 - 1.0];
+        let outlier = sample % outlier_every == 0;
+        targets.push(2.0 * row[0] - 3.0 * row[1] + 1.0 + (rng.f32() - 0.5) * 0.1 + if outlier { 50.0 } else { 0.0 });
+        outliers.push(outlier);
+        rows.push(row);
+    }
+    (Matrix::from(rows), targets, outliers)
+}
+
+fn close_to_truth(coefficients : &[f32], intercept : f32, tolerance : f32) -> bool {
+    (coefficients[0] - 2.0).abs() < tolerance && (coefficients[1] + 3.0).abs() < tolerance && (intercept - 1.0).abs() < tolerance
+}
+
+#[test]
+fn quantile_regression_follows_the_quantile() {
+    let (X, targets, _) = contaminated(0, 400, 10);
+    let y = DataType::Floats(targets.clone());
+
+    //least squares is dragged up by the outliers, the median is not.
+    let mut least_squares = LinearRegression::new();
+    least_squares.fit(&X, &y).unwrap();
+    assert!(least_squares.intercept() > 4.0);
+    let mut median = QuantileRegressor::new(0.5);
+    median.fit(&X, &y).unwrap();
+    assert!(close_to_truth(median.coefficients(), median.intercept(), 0.05), "{:?} {}", median.coefficients(), median.intercept());
+    assert_eq!(median.predict(&[0.0, 0.0]), ReturnType::Floats(median.intercept()));
+
+    //about 80% of the targets end up below the 0.8 quantile.
+    let mut upper = QuantileRegressor::new(0.8);
+    upper.fit(&X, &y).unwrap();
+    let below = X.iter_rows().zip(&targets).filter(|(row, target)| match upper.predict(row) { ReturnType::Floats(prediction) => **target <= prediction + 1e-4, _ => unreachable!() }).count();
+    assert!((below as f32 / 400.0 - 0.8).abs() < 0.03, "{}", below);
+    assert!(upper.intercept() > median.intercept() && upper.pinball_loss(&X, &targets) > 0.0);
+
+    assert!(matches!(QuantileRegressor::new(1.0).fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    assert!(matches!(median.fit(&X, &DataType::Category(vec![0; 400])), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn huber_flags_the_outliers() {
+    let (X, targets, outliers) = contaminated(1, 300, 15);
+    let y = DataType::Floats(targets);
+    let mut model = HuberRegressor::new();
+    model.fit(&X, &y).unwrap();
+    assert!(close_to_truth(model.coefficients(), model.intercept(), 0.05), "{:?} {}", model.coefficients(), model.intercept());
+    assert!(model.scale() < 0.1);
+    assert!(outliers.iter().zip(model.outliers()).all(|(truth, flagged)| !truth || *flagged));
+
+    let mut params = Params::new();
+    params.insert("epsilon".to_owned(), ParamValue::Float(0.5));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+
+    let restored = HuberRegressor::from_json(&model.to_json().unwrap()).unwrap();
+    assert_eq!(restored.predict(&[0.3, -0.2]), model.predict(&[0.3, -0.2]));
+    assert_eq!(restored.outliers(), model.outliers());
+}
+
+#[test]
+fn ransac_finds_the_consensus() {
+    //a third of the data is outliers.
+    let (X, targets, outliers) = contaminated(2, 300, 3);
+    let y = DataType::Floats(targets);
+    let mut model = Ransac::new();
+    model.set_residual_threshold(Some(0.5));
+    model.set_trials(50, 7);
+    model.fit(&X, &y).unwrap();
+    let estimator = model.estimator();
+    assert!(close_to_truth(estimator.coefficients(), estimator.intercept(), 0.05), "{:?} {}", estimator.coefficients(), estimator.intercept());
+    assert!(model.inlier_mask().iter().zip(&outliers).all(|(inlier, outlier)| inlier != outlier));
+    assert_eq!(model.predict(&[0.0, 0.0]), estimator.predict(&[0.0, 0.0]));
+
+    //the same seed draws the same subsets.
+    let mut again = Ransac::new();
+    again.set_residual_threshold(Some(0.5));
+    again.set_trials(50, 7);
+    again.fit(&X, &y).unwrap();
+    assert_eq!(again.estimator().coefficients(), model.estimator().coefficients());
+
+    model.set_min_samples(Some(301));
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    let restored = Ransac::from_json(&again.to_json().unwrap()).unwrap();
+    assert_eq!(restored.inlier_mask(), again.inlier_mask());
+
+    let mut params = Params::new();
+    params.insert("max_trials".to_owned(), ParamValue::Int(0));
+    assert!(matches!(again.set_params(&params), Err(MLError::InvalidParameter(_))));
+
+    //the inner estimator is checked like a file of its own.
+    let mut value: serde_json::Value = serde_json::from_str(&restored.to_json().unwrap()).unwrap();
+    value["model"]["estimator"]["coefficients"] = serde_json::json!([]);
+    assert!(Ransac::from_json(&value.to_string()).is_err());
+}
+
+#[test]
+fn theil_sen_ignores_a_few_outliers() {
+    let (X, targets, _) = contaminated(3, 40, 8);
+    let y = DataType::Floats(targets);
+    //40 choose 3 subsets fit under the default limit, so every one of them is used.
+    let mut model = TheilSen::new();
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.n_subpopulation(), 9880);
+    assert!(close_to_truth(model.coefficients(), model.intercept(), 0.1), "{:?} {}", model.coefficients(), model.intercept());
+
+    model.set_subpopulation(500, 3);
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.n_subpopulation(), 500);
+    assert!(close_to_truth(model.coefficients(), model.intercept(), 0.15));
+    assert_eq!(model.n_features_in(), Some(2));
+    assert!(matches!(model.fit(&Matrix::from(vec![vec![1.0, 2.0]]), &DataType::Floats(vec![1.0])), Err(MLError::InvalidParameter(_))));
+    let mut params = Params::new();
+    params.insert("max_subpopulation".to_owned(), ParamValue::Int(0));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+
+    //a weight of 0 leaves the sample out of every subset.
+    let (X, targets, outliers) = contaminated(3, 40, 8);
+    let weights: Vec<f32> = outliers.iter().map(|outlier| if *outlier { 0.0 } else { 1.0 }).collect();
+    let inliers: Vec<usize> = (0..40).filter(|sample| !outliers[*sample]).collect();
+    let mut weighted = TheilSen::new();
+    weighted.fit_weighted(&X, &DataType::Floats(targets.clone()), &weights).unwrap();
+    let mut clean = TheilSen::new();
+    clean.fit(&X.select_rows(&inliers), &DataType::Floats(inliers.iter().map(|sample| targets[*sample]).collect())).unwrap();
+    assert_eq!((weighted.coefficients(), weighted.intercept()), (clean.coefficients(), clean.intercept()));
+    assert_eq!(weighted.n_subpopulation(), clean.n_subpopulation());
+}
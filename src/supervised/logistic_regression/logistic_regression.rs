@@ -0,0 +1,538 @@
+//! #Logistic regression#
+//!
+//! Binary(sigmoid) and multinomial(softmax) logistic regression for `Category` and `Strings` targets.
+//! With two classes there is a single weight vector for the second class, with more every class gets its own.
+//!
+//! Minimises the (class/sample weighted) mean cross entropy plus
+//!
+//!     alpha * l1_ratio * ||W||_1  +  (alpha * (1 - l1_ratio) / 2) * ||W||^2
+//!
+//! the intercepts are never penalised.
+//! `Lbfgs` and `Newton` handle the smooth(L2) penalty, an L1 part needs the `Sgd` solver which applies it with a proximal step.
+
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::{matrix::Matrix, linalg::{cholesky, cholesky_solve}, optimise::{lbfgs, line_search, inf_norm}},
+    persistence::persistence::{Persist, PersistError, ensure}};
+pub use crate::trait_definition::ClassWeight;
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LogisticSolver {
+    ///`Lbfgs` without an L1 penalty, `Sgd` with one.
+    Auto,
+    ///limited memory BFGS keeping the last `memory` updates.
+    Lbfgs { memory : usize },
+    ///Newton's method with the full Hessian, converges in very few iterations but every one of them is
+    ///quadratic in `n_classes * n_features`, so only for small problems.
+    Newton,
+    ///mini-batch stochastic gradient descent, `max_iter` counts epochs.
+    Sgd { learning_rate : f32, batch_size : usize, seed : u64 },
+}
+
+impl LogisticSolver {
+    fn name(&self) -> &'static str {
+        match self {
+            LogisticSolver::Auto => "auto",
+            LogisticSolver::Lbfgs { .. } => "lbfgs",
+            LogisticSolver::Newton => "newton",
+            LogisticSolver::Sgd { .. } => "sgd",
+        }
+    }
+
+    ///the solvers without settings, `lbfgs` and `sgd` are built with their settings and given to `set_solver`.
+    fn from_name(name : &str) -> Result<LogisticSolver, MLError> {
+        match name {
+            "auto" => Ok(LogisticSolver::Auto),
+            "newton" => Ok(LogisticSolver::Newton),
+            "lbfgs" | "sgd" => Err(MLError::InvalidParameter(format!("the '{}' solver needs its settings, use set_solver", name))),
+            _ => Err(MLError::InvalidParameter(format!("unknown solver '{}'", name))),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogisticRegression {
+    ///sorted unique classes seen in fit.
+    classes : Option<DataType>,
+    ///one row per class(a single row for the second class when there are only two).
+    coefficients : Matrix,
+    intercepts : Vec<f32>,
+    pub alpha : f32,
+    pub l1_ratio : f32,
+    pub fit_intercept : bool,
+    pub class_weight : ClassWeight,
+    pub solver : LogisticSolver,
+    ///maximum number of iterations(epochs for `Sgd`).
+    pub max_iter : usize,
+    ///stops once the largest gradient entry(the largest parameter change for `Sgd`) is below this.
+    pub tol : f32,
+    n_iter : usize,
+}
+
+impl Default for LogisticRegression {
+    fn default() -> Self {
+        LogisticRegression::new()
+    }
+}
+
+impl LogisticRegression {
+
+    ///a lightly L2 regularised(alpha = 1e-4) model, the regularisation keeps the weights finite on separable data.
+    pub fn new() -> LogisticRegression {
+        LogisticRegression {
+            classes: None,
+            coefficients: Matrix::default(),
+            intercepts: vec![],
+            alpha: 1e-4,
+            l1_ratio: 0.0,
+            fit_intercept: true,
+            class_weight: ClassWeight::Uniform,
+            solver: LogisticSolver::Auto,
+            max_iter: 100,
+            tol: 1e-4,
+            n_iter: 0,
+        }
+    }
+
+    ///sets the strength of the penalty and the share of it that is L1.
+    pub fn set_penalty(&mut self, alpha : f32, l1_ratio : f32) {
+        self.alpha = alpha;
+        self.l1_ratio = l1_ratio;
+    }
+
+    pub fn set_solver(&mut self, solver : LogisticSolver) {
+        self.solver = solver;
+    }
+
+    pub fn set_class_weight(&mut self, class_weight : ClassWeight) {
+        self.class_weight = class_weight;
+    }
+
+    pub fn set_fit_intercept(&mut self, fit_intercept : bool) {
+        self.fit_intercept = fit_intercept;
+    }
+
+    ///iteration limit and convergence tolerance.
+    pub fn set_stopping(&mut self, max_iter : usize, tol : f32) {
+        self.max_iter = max_iter;
+        self.tol = tol;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    pub fn coefficients(&self) -> &Matrix {
+        &self.coefficients
+    }
+
+    pub fn intercepts(&self) -> &[f32] {
+        &self.intercepts
+    }
+
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    ///the probability of every class(in the order of `classes()`) for the given point.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        let scores: Vec<f64> = self.coefficients.iter_rows().zip(&self.intercepts)
+            .map(|(row, intercept)| row.iter().zip(point).map(|(w, x)| (*w as f64) * (*x as f64)).sum::<f64>() + *intercept as f64)
+            .collect();
+        let mut probabilities = vec![0.0_f64; self.classes.as_ref().unwrap().len()];
+        scores_to_probabilities(&scores, &mut probabilities);
+        probabilities.iter().map(|p| *p as f32).collect()
+    }
+
+    ///`predict_proba` for every row of `X`, one row of probabilities per sample.
+    pub fn predict_proba_many(&self, X : &Matrix) -> Matrix {
+        let rows: Vec<Vec<f32>> = X.iter_rows().map(|row| self.predict_proba(row)).collect();
+        Matrix::from(rows)
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if !(self.alpha >= 0.0) {
+            return Err(MLError::InvalidParameter(format!("alpha needs to be >= 0, got {}", self.alpha)));
+        }
+        if !(0.0..=1.0).contains(&self.l1_ratio) {
+            return Err(MLError::InvalidParameter(format!("l1_ratio needs to be in [0, 1], got {}", self.l1_ratio)));
+        }
+        if self.max_iter == 0 {
+            return Err(MLError::InvalidParameter("max_iter needs to be > 0".to_owned()));
+        }
+        let has_l1 = self.alpha > 0.0 && self.l1_ratio > 0.0;
+        match &self.solver {
+            LogisticSolver::Lbfgs { .. } | LogisticSolver::Newton if has_l1 =>
+                Err(MLError::InvalidParameter(format!("the {} solver cannot handle an L1 penalty, use Sgd", self.solver.name()))),
+            LogisticSolver::Lbfgs { memory: 0 } => Err(MLError::InvalidParameter("lbfgs needs a memory > 0".to_owned())),
+            LogisticSolver::Sgd { learning_rate, .. } if !(*learning_rate > 0.0) =>
+                Err(MLError::InvalidParameter(format!("learning_rate needs to be > 0, got {}", learning_rate))),
+            LogisticSolver::Sgd { batch_size: 0, .. } => Err(MLError::InvalidParameter("batch_size needs to be > 0".to_owned())),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+///softmax of the scores, or the sigmoid of the single score for two classes.
+fn scores_to_probabilities(scores : &[f64], probabilities : &mut [f64]) {
+    if probabilities.len() == 2 && scores.len() == 1 {
+        probabilities[1] = 1.0 / (1.0 + (-scores[0]).exp());
+        probabilities[0] = 1.0 - probabilities[1];
+        return;
+    }
+    let max = scores.iter().cloned().fold(f64::MIN, f64::max);
+    let mut total = 0.0;
+    for (probability, score) in probabilities.iter_mut().zip(scores) {
+        *probability = (score - max).exp();
+        total += *probability;
+    }
+    probabilities.iter_mut().for_each(|probability| *probability /= total);
+}
+
+
+///The training problem, the parameters are one flat vector with a row of `width` values per score,
+///the features followed by the intercept(if there is one).
+struct Problem<'a> {
+    X : &'a Matrix,
+    targets : &'a [usize],
+    sample_weights : &'a [f64],
+    n_classes : usize,
+    n_scores : usize,
+    width : usize,
+    l2 : f64,
+}
+
+impl<'a> Problem<'a> {
+
+    ///the class a score row belongs to, the single binary row is the second class.
+    fn class_of(&self, score : usize) -> usize {
+        if self.n_scores == 1 { 1 } else { score }
+    }
+
+    fn is_intercept(&self, parameter : usize) -> bool {
+        parameter % self.width == self.X.cols()
+    }
+
+    fn probabilities(&self, theta : &[f64], x : &[f32], scores : &mut [f64], probabilities : &mut [f64]) {
+        for (score, row) in scores.iter_mut().zip(theta.chunks_exact(self.width)) {
+            *score = x.iter().zip(row).map(|(x, w)| *x as f64 * w).sum::<f64>();
+            if row.len() > x.len() {
+                *score += row[x.len()];
+            }
+        }
+        scores_to_probabilities(scores, probabilities);
+    }
+
+    ///mean weighted cross entropy plus the L2 penalty and its gradient, over `samples`(all of them for `None`).
+    fn loss_and_gradient(&self, theta : &[f64], samples : Option<&[usize]>) -> (f64, Vec<f64>) {
+        let mut gradient = vec![0.0_f64; theta.len()];
+        let mut loss = 0.0_f64;
+        let mut total_weight = 0.0_f64;
+        let mut scores = vec![0.0_f64; self.n_scores];
+        let mut probabilities = vec![0.0_f64; self.n_classes];
+        let number_of_samples = samples.map_or(self.X.rows(), |samples| samples.len());
+
+        for position in 0..number_of_samples {
+            let sample = samples.map_or(position, |samples| samples[position]);
+            let weight = self.sample_weights[sample];
+            if weight == 0.0 {
+                continue;
+            }
+            let x = self.X.row(sample);
+            self.probabilities(theta, x, &mut scores, &mut probabilities);
+            let target = self.targets[sample];
+            loss -= weight * probabilities[target].max(1e-300).ln();
+            total_weight += weight;
+            for score in 0..self.n_scores {
+                let class = self.class_of(score);
+                let error = weight * (probabilities[class] - (class == target) as u8 as f64);
+                let row = &mut gradient[score*self.width..(score+1)*self.width];
+                for (slope, value) in row.iter_mut().zip(x) {
+                    *slope += error * *value as f64;
+                }
+                if self.width > x.len() {
+                    row[x.len()] += error;
+                }
+            }
+        }
+
+        let total_weight = total_weight.max(f64::MIN_POSITIVE);
+        loss /= total_weight;
+        gradient.iter_mut().for_each(|slope| *slope /= total_weight);
+        for (parameter, (slope, value)) in gradient.iter_mut().zip(theta).enumerate() {
+            if !self.is_intercept(parameter) {
+                *slope += self.l2 * value;
+                loss += 0.5 * self.l2 * value * value;
+            }
+        }
+        (loss, gradient)
+    }
+
+    ///Hessian of the objective, `n_scores * width` square.
+    fn hessian(&self, theta : &[f64]) -> Vec<Vec<f64>> {
+        let size = theta.len();
+        let mut hessian = vec![vec![0.0_f64; size]; size];
+        let mut scores = vec![0.0_f64; self.n_scores];
+        let mut probabilities = vec![0.0_f64; self.n_classes];
+        let mut extended = vec![1.0_f64; self.width];
+        let total_weight: f64 = self.sample_weights.iter().sum::<f64>().max(f64::MIN_POSITIVE);
+
+        for (sample, x) in self.X.iter_rows().enumerate() {
+            let weight = self.sample_weights[sample] / total_weight;
+            if weight == 0.0 {
+                continue;
+            }
+            self.probabilities(theta, x, &mut scores, &mut probabilities);
+            for (slot, value) in extended.iter_mut().zip(x) {
+                *slot = *value as f64;
+            }
+            for r in 0..self.n_scores {
+                let p_r = probabilities[self.class_of(r)];
+                for s in 0..self.n_scores {
+                    let p_s = probabilities[self.class_of(s)];
+                    let curvature = weight * (if r == s { p_r } else { 0.0 } - p_r * p_s);
+                    if curvature == 0.0 {
+                        continue;
+                    }
+                    for j in 0..self.width {
+                        let factor = curvature * extended[j];
+                        let hessian_row = &mut hessian[r*self.width + j][s*self.width..(s+1)*self.width];
+                        for (entry, value) in hessian_row.iter_mut().zip(&extended) {
+                            *entry += factor * value;
+                        }
+                    }
+                }
+            }
+        }
+        for (parameter, row) in hessian.iter_mut().enumerate() {
+            if !self.is_intercept(parameter) {
+                row[parameter] += self.l2;
+            }
+        }
+        hessian
+    }
+}
+
+
+impl LogisticRegression {
+
+    fn solve_lbfgs(&mut self, problem : &Problem, theta : &mut Vec<f64>, memory : usize) {
+        self.n_iter = lbfgs(|theta| problem.loss_and_gradient(theta, None), theta, memory, self.max_iter, self.tol as f64);
+    }
+
+    fn solve_newton(&mut self, problem : &Problem, theta : &mut Vec<f64>) {
+        let (mut loss, mut gradient) = problem.loss_and_gradient(theta, None);
+        self.n_iter = self.max_iter;
+
+        for iteration in 0..self.max_iter {
+            if inf_norm(&gradient) < self.tol as f64 {
+                self.n_iter = iteration;
+                return;
+            }
+            let hessian = problem.hessian(theta);
+            //the softmax Hessian is singular(the scores can all be shifted by the same amount), so damp it until it factors.
+            let mut damping = 1e-6_f64;
+            let direction = loop {
+                let mut system = Matrix::new(theta.len(), theta.len());
+                for (i, row) in hessian.iter().enumerate() {
+                    for (j, value) in row.iter().enumerate() {
+                        system[(i, j)] = (*value + if i == j { damping } else { 0.0 }) as f32;
+                    }
+                }
+                if let Ok(l) = cholesky(&system) {
+                    let gradient_f32: Vec<f32> = gradient.iter().map(|g| *g as f32).collect();
+                    break cholesky_solve(&l, &gradient_f32).iter().map(|d| -(*d as f64)).collect::<Vec<f64>>();
+                }
+                damping *= 10.0;
+                if damping > 1e6 {
+                    break gradient.iter().map(|g| -g).collect();
+                }
+            };
+            match line_search(&|theta : &[f64]| problem.loss_and_gradient(theta, None), theta, loss, &gradient, &direction) {
+                Some((new_theta, new_loss, new_gradient)) => {
+                    *theta = new_theta;
+                    loss = new_loss;
+                    gradient = new_gradient;
+                },
+                None => {
+                    self.n_iter = iteration + 1;
+                    return;
+                },
+            }
+        }
+    }
+
+    fn solve_sgd(&mut self, problem : &Problem, theta : &mut [f64], learning_rate : f32, batch_size : usize, seed : u64) {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let mut indices: Vec<usize> = (0..problem.X.rows()).collect();
+        let threshold = learning_rate as f64 * self.alpha as f64 * self.l1_ratio as f64;
+        self.n_iter = self.max_iter;
+
+        for epoch in 0..self.max_iter {
+            rng.shuffle(&mut indices);
+            let mut largest_change = 0.0_f64;
+            for batch in indices.chunks(batch_size) {
+                let (_, gradient) = problem.loss_and_gradient(theta, Some(batch));
+                for (parameter, (value, slope)) in theta.iter_mut().zip(&gradient).enumerate() {
+                    let old = *value;
+                    *value -= learning_rate as f64 * slope;
+                    //proximal step for the L1 part, this is what makes the weights exactly zero.
+                    if threshold > 0.0 && !problem.is_intercept(parameter) {
+                        *value = value.signum() * (value.abs() - threshold).max(0.0);
+                    }
+                    largest_change = largest_change.max((*value - old).abs());
+                }
+            }
+            if largest_change < self.tol as f64 {
+                self.n_iter = epoch + 1;
+                return;
+            }
+        }
+    }
+}
+
+
+impl MLalgo for LogisticRegression {
+
+    ///needs a `Category` or `Strings` target with atleast two classes.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///the sample weights are multiplied with the `class_weight` of each sample.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+        let (classes, targets) = y_train.unique_classes()
+            .ok_or_else(|| MLError::InvalidTarget("logistic regression needs category or string targets".to_owned()))?;
+        let n_classes = classes.len();
+        if n_classes < 2 {
+            return Err(MLError::InvalidTarget("need atleast two different classes to fit".to_owned()));
+        }
+
+        let mut counts = vec![0_usize; n_classes];
+        targets.iter().for_each(|target| counts[*target] += 1);
+        let class_weights = self.class_weight.weights(&counts)?;
+        let sample_weights: Vec<f64> = targets.iter().zip(sample_weights).map(|(target, weight)| (class_weights[*target] * weight) as f64).collect();
+
+        let n_features = X_train.cols();
+        let n_scores = if n_classes == 2 { 1 } else { n_classes };
+        let width = n_features + self.fit_intercept as usize;
+        let problem = Problem {
+            X: X_train,
+            targets: &targets,
+            sample_weights: &sample_weights,
+            n_classes,
+            n_scores,
+            width,
+            l2: self.alpha as f64 * (1.0 - self.l1_ratio as f64),
+        };
+
+        let mut theta = vec![0.0_f64; n_scores * width];
+        let has_l1 = self.alpha > 0.0 && self.l1_ratio > 0.0;
+        match self.solver.clone() {
+            LogisticSolver::Auto if has_l1 => self.solve_sgd(&problem, &mut theta, 0.1, 32, 0),
+            LogisticSolver::Auto => self.solve_lbfgs(&problem, &mut theta, 10),
+            LogisticSolver::Lbfgs { memory } => self.solve_lbfgs(&problem, &mut theta, memory),
+            LogisticSolver::Newton => self.solve_newton(&problem, &mut theta),
+            LogisticSolver::Sgd { learning_rate, batch_size, seed } => self.solve_sgd(&problem, &mut theta, learning_rate, batch_size, seed),
+        }
+        if theta.iter().any(|value| !value.is_finite()) {
+            return Err(MLError::InvalidParameter("the solver diverged, try a smaller learning rate or scaling the features".to_owned()));
+        }
+
+        let mut coefficients = Matrix::new(n_scores, n_features);
+        let mut intercepts = vec![0.0_f32; n_scores];
+        for (score, row) in theta.chunks_exact(width).enumerate() {
+            for (j, value) in row[..n_features].iter().enumerate() {
+                coefficients[(score, j)] = *value as f32;
+            }
+            if self.fit_intercept {
+                intercepts[score] = row[n_features] as f32;
+            }
+        }
+        self.coefficients = coefficients;
+        self.intercepts = intercepts;
+        self.classes = Some(classes);
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.classes.as_ref().map(|_| self.coefficients.cols())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params.insert("l1_ratio".to_owned(), ParamValue::Float(self.l1_ratio));
+        params.insert("fit_intercept".to_owned(), ParamValue::Bool(self.fit_intercept));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params.insert("solver".to_owned(), ParamValue::Text(self.solver.name().to_owned()));
+        params
+    }
+
+    ///`solver` takes the solvers without settings, the name of the current solver leaves it(and its settings) as it is.
+    ///Use `set_solver` for `lbfgs` and `sgd`.
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "alpha" => self.alpha = value.as_f32(name)?,
+                "l1_ratio" => self.l1_ratio = value.as_f32(name)?,
+                "fit_intercept" => self.fit_intercept = value.as_bool(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                "solver" => if value.as_str(name)? != self.solver.name() {
+                    self.solver = LogisticSolver::from_name(value.as_str(name)?)?;
+                },
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for LogisticRegression {
+    ///the most probable class.
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let probabilities = self.predict_proba(point);
+        let best = probabilities.iter().enumerate().fold(0, |best, (index, p)| if *p > probabilities[best] { index } else { best });
+        self.classes.as_ref().unwrap().value_at(best)
+    }
+}
+
+impl Estimator for LogisticRegression {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+impl Persist for LogisticRegression {
+    const MODEL_TYPE: &'static str = "LogisticRegression";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let classes = match &self.classes {
+            Some(classes) => classes,
+            None => return Ok(()),
+        };
+        ensure(!matches!(classes, DataType::Floats(_)), "classes must be categories or strings")?;
+        ensure(classes.len() >= 2, "a fitted model needs atleast two classes")?;
+        let n_scores = if classes.len() == 2 { 1 } else { classes.len() };
+        ensure(self.coefficients.is_consistent() && self.coefficients.rows() == n_scores, "coefficients do not match the number of classes")?;
+        ensure(self.intercepts.len() == n_scores, "intercepts do not match the number of classes")
+    }
+}
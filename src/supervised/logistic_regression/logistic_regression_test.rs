@@ -0,0 +1,181 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::{MLalgo, Predict, MLError, ParamValue},
+    evaluation::accuracy::{accuracy_score, balanced_accuracy_score}, persistence::persistence::Persist};
+use super::logistic_regression::{LogisticRegression, LogisticSolver, ClassWeight};
+
+
+///two overlapping blobs along the first feature, the second feature is noise.
+fn binary_data() -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(3);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for i in 0..200 {
+        let class = (i % 2) as u8;
+        let centre = if class == 0 { -1.0 } else { 1.0 };
+        rows.push(vec![centre + rng.f32() * 2.0 - 1.0, rng.f32()]);
+        targets.push(class);
+    }
+    (Matrix::from(rows), DataType::Category(targets))
+}
+
+///three well separated clusters with string labels.
+fn three_classes() -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(5);
+    let centres = [(0.0, 0.0, "left"), (4.0, 0.0, "right"), (2.0, 4.0, "top")];
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for i in 0..150 {
+        let (x, y, name) = centres[i % 3];
+        rows.push(vec![x + rng.f32() - 0.5, y + rng.f32() - 0.5]);
+        targets.push(name.to_owned());
+    }
+    (Matrix::from(rows), DataType::Strings(targets))
+}
+
+#[test]
+fn every_solver_separates_the_binary_classes() {
+    let (X, y) = binary_data();
+    let solvers = vec![
+        LogisticSolver::Lbfgs { memory: 10 },
+        LogisticSolver::Newton,
+        LogisticSolver::Sgd { learning_rate: 0.1, batch_size: 16, seed: 1 },
+    ];
+    let mut weights = vec![];
+    for solver in solvers {
+        let mut model = LogisticRegression::new();
+        model.set_solver(solver);
+        model.set_stopping(500, 1e-5);
+        model.fit(&X, &y).unwrap();
+        assert!(accuracy_score(&model, &X, &y) > 0.85);
+        assert_eq!(model.coefficients().shape(), (1, 2));
+        weights.push(model.coefficients()[(0, 0)]);
+    }
+    //the deterministic solvers reach the same optimum.
+    assert!((weights[0] - weights[1]).abs() < 1e-2, "{:?}", weights);
+    assert!(weights[2] > 0.0);
+}
+
+#[test]
+fn multinomial_with_string_labels() {
+    let (X, y) = three_classes();
+    let mut model = LogisticRegression::new();
+    model.fit(&X, &y).unwrap();
+    match model.classes() {
+        Some(DataType::Strings(classes)) => assert_eq!(classes, &vec!["left".to_owned(), "right".to_owned(), "top".to_owned()]),
+        other => panic!("expected string classes, got {:?}", other),
+    }
+    assert_eq!(model.coefficients().shape(), (3, 2));
+    assert_eq!(model.predict(&[4.0, 0.1]), ReturnType::Strings("right".to_owned()));
+    assert_eq!(model.predict(&[2.0, 3.8]), ReturnType::Strings("top".to_owned()));
+
+    let probabilities = model.predict_proba(&[0.0, 0.0]);
+    assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    //classes are sorted, "left" comes first.
+    assert!(probabilities[0] > 0.9);
+}
+
+#[test]
+fn newton_converges_in_few_iterations() {
+    let (X, y) = three_classes();
+    let mut model = LogisticRegression::new();
+    model.set_penalty(0.01, 0.0);
+    model.set_solver(LogisticSolver::Newton);
+    model.fit(&X, &y).unwrap();
+    assert!(model.n_iter() < 30, "took {} iterations", model.n_iter());
+    assert_eq!(accuracy_score(&model, &X, &y), 1.0);
+}
+
+#[test]
+fn l1_penalty_drops_the_noise_feature() {
+    let (X, y) = binary_data();
+    let mut model = LogisticRegression::new();
+    model.set_penalty(0.05, 1.0);
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.coefficients()[(0, 1)], 0.0);
+    assert!(model.coefficients()[(0, 0)] > 0.0);
+
+    model.set_solver(LogisticSolver::Lbfgs { memory: 5 });
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+}
+
+///90 samples of class 0 around -0.5 and 10 of class 1 around 0.5, heavily overlapping.
+fn imbalanced_data() -> (Matrix, DataType) {
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for i in 0..100 {
+        let class = (i % 10 == 0) as u8;
+        let offset = (i % 7) as f32 * 0.3 - 0.9;
+        rows.push(vec![if class == 1 { 0.5 } else { -0.5 } + offset]);
+        targets.push(class);
+    }
+    (Matrix::from(rows), DataType::Category(targets))
+}
+
+#[test]
+fn balanced_class_weights_help_the_minority_class() {
+    let (X, y) = imbalanced_data();
+
+    let mut plain = LogisticRegression::new();
+    plain.fit(&X, &y).unwrap();
+    let mut balanced = LogisticRegression::new();
+    balanced.set_class_weight(ClassWeight::Balanced);
+    balanced.fit(&X, &y).unwrap();
+    assert!(balanced.predict_proba(&[0.5])[1] > plain.predict_proba(&[0.5])[1]);
+
+    balanced.set_class_weight(ClassWeight::Custom(vec![1.0]));
+    assert!(matches!(balanced.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn sample_weights_and_fit_class_weighted() {
+    let (X, y) = imbalanced_data();
+    let mut plain = LogisticRegression::new();
+    plain.fit(&X, &y).unwrap();
+    let mut balanced = LogisticRegression::new();
+    balanced.set_class_weight(ClassWeight::Balanced);
+    balanced.fit(&X, &y).unwrap();
+    //the class weight setting and balanced sample weights are the same objective.
+    let mut weighted = LogisticRegression::new();
+    weighted.fit_class_weighted(&X, &y, &ClassWeight::Balanced).unwrap();
+    assert!((weighted.predict_proba(&[0.5])[1] - balanced.predict_proba(&[0.5])[1]).abs() < 1e-3);
+    assert!(balanced_accuracy_score(&balanced, &X, &y) > balanced_accuracy_score(&plain, &X, &y));
+
+    //a weight of 0 is the same as leaving the sample out.
+    let mut zeroed = LogisticRegression::new();
+    zeroed.fit_weighted(&X, &y, &(0..100).map(|i| (i >= 50) as u8 as f32).collect::<Vec<f32>>()).unwrap();
+    let mut half = LogisticRegression::new();
+    half.fit(&X.select_rows(&(50..100).collect::<Vec<usize>>()), &DataType::Category((50..100).map(|i| (i % 10 == 0) as u8).collect())).unwrap();
+    assert!((zeroed.predict_proba(&[0.0])[1] - half.predict_proba(&[0.0])[1]).abs() < 1e-3);
+
+    assert!(matches!(plain.fit_weighted(&X, &y, &[1.0; 3]), Err(MLError::SampleMismatch { .. })));
+    assert!(matches!(plain.fit_weighted(&X, &y, &[-1.0; 100]), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn bad_targets_are_rejected_and_models_persist() {
+    let (X, y) = binary_data();
+    let mut model = LogisticRegression::new();
+    assert!(matches!(model.fit(&X, &DataType::Floats(vec![0.0; 200])), Err(MLError::InvalidTarget(_))));
+    assert!(matches!(model.fit(&X, &DataType::Category(vec![1; 200])), Err(MLError::InvalidTarget(_))));
+    assert_eq!(model.try_predict(&[0.0, 0.0]), Err(MLError::NotFitted));
+
+    model.fit(&X, &y).unwrap();
+    let loaded = LogisticRegression::from_json(&model.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.predict_proba(&[0.3, 0.3]), model.predict_proba(&[0.3, 0.3]));
+}
+
+#[test]
+fn params_round_trip() {
+    let mut model = LogisticRegression::new();
+    let solver = LogisticSolver::Sgd { learning_rate: 0.05, batch_size: 16, seed: 3 };
+    model.set_solver(solver.clone());
+    let mut params = model.get_params();
+    model.set_params(&params).unwrap();
+    assert_eq!(model.solver, solver);
+    params.insert("solver".to_owned(), ParamValue::Text("newton".to_owned()));
+    model.set_params(&params).unwrap();
+    assert_eq!(model.solver, LogisticSolver::Newton);
+    params.insert("solver".to_owned(), ParamValue::Text("lbfgs".to_owned()));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+}
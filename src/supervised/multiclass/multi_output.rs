@@ -0,0 +1,188 @@
+//! #Multi-output models#
+//!
+//! Fit one copy of an estimator per target column, so any model in the crate can predict several targets at once.
+//! * `MultiOutputRegressor` -> the targets are the columns of a `Matrix`, like `NeuralNet::fit_multi_task_float` takes them,
+//!   and `predict` gives one value per column.
+//! * `MultiOutputClassifier` -> one `DataType` per target, categories or strings and each with its own classes.
+//!
+//! The copies know nothing of each other, so the targets are predicted independently. They are fitted in parallel with rayon.
+//! The predictions are a `Vec` per point, so these models do not implement `MLalgo`/`Predict`, which predict one target.
+
+use rayon::prelude::*;
+use crate::{data_frame::{data_type::DataType, return_type::ReturnType},
+    trait_definition::{Estimator, MLError, check_fit_input, check_sample_weights, assert_point},
+    n_dimen::matrix::Matrix,
+    supervised::ensemble::common::{encode_classes, fit_member}};
+
+
+///a copy of `base` fitted on every target, all of them on the same samples.
+fn fit_outputs(base : &dyn Estimator, X_train : &Matrix, targets : &[DataType], sample_weights : Option<&[f32]>) -> Result<Vec<Box<dyn Estimator>>, MLError> {
+    if targets.is_empty() {
+        return Err(MLError::InvalidTarget("there are no targets to fit on".to_owned()));
+    }
+    for target in targets {
+        check_fit_input(X_train, target)?;
+    }
+    if let Some(sample_weights) = sample_weights {
+        check_sample_weights(sample_weights, X_train.rows())?;
+    }
+    targets.par_iter().enumerate().map(|(output, target)| {
+        let mut estimator = base.clone_box();
+        fit_member(&format!("the estimator of target {}", output), estimator.as_mut(), X_train, target, sample_weights)?;
+        Ok(estimator)
+    }).collect()
+}
+
+
+#[derive(Clone)]
+pub struct MultiOutputRegressor {
+    base : Box<dyn Estimator>,
+    ///one fitted copy of `base` per target column.
+    estimators : Vec<Box<dyn Estimator>>,
+    n_features : usize,
+}
+
+impl MultiOutputRegressor {
+
+    ///one copy of the given regressor per target.
+    pub fn new<E : Estimator + 'static>(estimator : E) -> MultiOutputRegressor {
+        MultiOutputRegressor {
+            base: Box::new(estimator),
+            estimators: vec![],
+            n_features: 0,
+        }
+    }
+
+    ///fits a copy of the regressor on every column of `y_train`.
+    pub fn fit(&mut self, X_train : &Matrix, y_train : &Matrix) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    ///`fit` with a weight for every sample, the regressor needs to take sample weights.
+    pub fn fit_weighted(&mut self, X_train : &Matrix, y_train : &Matrix, sample_weights : &[f32]) -> Result<(), MLError> {
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &Matrix, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        if X_train.rows() != y_train.rows() {
+            return Err(MLError::SampleMismatch { samples: X_train.rows(), targets: y_train.rows() });
+        }
+        let targets: Vec<DataType> = (0..y_train.cols()).map(|column| DataType::Floats(y_train.column(column))).collect();
+        self.estimators = vec![];
+        self.estimators = fit_outputs(self.base.as_ref(), X_train, &targets, sample_weights)?;
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+
+    pub fn is_fitted(&self) -> bool {
+        !self.estimators.is_empty()
+    }
+
+    pub fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    ///number of targets the model was fitted on.
+    pub fn n_outputs(&self) -> usize {
+        self.estimators.len()
+    }
+
+    ///the fitted estimator of every target column.
+    pub fn estimators(&self) -> Vec<&dyn Estimator> {
+        self.estimators.iter().map(|estimator| estimator.as_ref()).collect()
+    }
+
+    ///one value per target column, panics if the model is not fitted.
+    pub fn predict(&self, point : &[f32]) -> Vec<f32> {
+        assert_point(self.n_features_in(), point);
+        self.estimators.iter().map(|estimator| match estimator.predict(point) {
+            ReturnType::Floats(value) => value,
+            other => panic!("a regressor predicted {:?}, which is not a float", other),
+        }).collect()
+    }
+
+    ///the predictions of every point, one row per point and one column per target.
+    pub fn predict_matrix(&self, X : &Matrix) -> Matrix {
+        let rows: Vec<Vec<f32>> = X.par_iter_rows().map(|row| self.predict(row)).collect();
+        Matrix::from_vec(X.rows(), self.n_outputs(), rows.concat())
+    }
+}
+
+
+#[derive(Clone)]
+pub struct MultiOutputClassifier {
+    base : Box<dyn Estimator>,
+    ///one fitted copy of `base` per target.
+    estimators : Vec<Box<dyn Estimator>>,
+    ///the sorted classes of every target.
+    classes : Vec<DataType>,
+    n_features : usize,
+}
+
+impl MultiOutputClassifier {
+
+    ///one copy of the given classifier per target.
+    pub fn new<E : Estimator + 'static>(estimator : E) -> MultiOutputClassifier {
+        MultiOutputClassifier {
+            base: Box::new(estimator),
+            estimators: vec![],
+            classes: vec![],
+            n_features: 0,
+        }
+    }
+
+    ///fits a copy of the classifier on every target, which need to be categories or strings.
+    pub fn fit(&mut self, X_train : &Matrix, y_train : &[DataType]) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    ///`fit` with a weight for every sample, the classifier needs to take sample weights.
+    pub fn fit_weighted(&mut self, X_train : &Matrix, y_train : &[DataType], sample_weights : &[f32]) -> Result<(), MLError> {
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &[DataType], sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        let classes = y_train.iter().map(|target| encode_classes(target, "a multi-output classifier").map(|(classes, _)| classes))
+            .collect::<Result<Vec<DataType>, MLError>>()?;
+        self.estimators = vec![];
+        self.estimators = fit_outputs(self.base.as_ref(), X_train, y_train, sample_weights)?;
+        self.classes = classes;
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+
+    pub fn is_fitted(&self) -> bool {
+        !self.estimators.is_empty()
+    }
+
+    pub fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    ///number of targets the model was fitted on.
+    pub fn n_outputs(&self) -> usize {
+        self.estimators.len()
+    }
+
+    ///the fitted estimator of every target.
+    pub fn estimators(&self) -> Vec<&dyn Estimator> {
+        self.estimators.iter().map(|estimator| estimator.as_ref()).collect()
+    }
+
+    ///the sorted classes of every target.
+    pub fn classes(&self) -> &[DataType] {
+        &self.classes
+    }
+
+    ///the predicted class of every target, panics if the model is not fitted.
+    pub fn predict(&self, point : &[f32]) -> Vec<ReturnType> {
+        assert_point(self.n_features_in(), point);
+        self.estimators.iter().map(|estimator| estimator.predict(point)).collect()
+    }
+
+    ///the class probabilities of every target in the order of its `classes()`, `None` for a classifier without probabilities.
+    pub fn predict_proba(&self, point : &[f32]) -> Option<Vec<Vec<f32>>> {
+        assert_point(self.n_features_in(), point);
+        self.estimators.iter().map(|estimator| estimator.probabilities(point)).collect()
+    }
+}
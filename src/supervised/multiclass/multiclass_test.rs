@@ -0,0 +1,116 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError}, evaluation::accuracy::accuracy_score,
+    supervised::{logistic_regression::logistic_regression::LogisticRegression, naive_bayes::gaussian_NB::GaussianNb,
+        support_vector_machines::support_vector_machines::{Svc, Kernel}, linear_regression::linear_regression::LinearRegression,
+        decision_trees::decision_trees::{DecisionTree, Criterion}}};
+use crate::supervised::test_fixtures;
+use super::{one_vs_rest::OneVsRest, one_vs_one::OneVsOne, multi_output::{MultiOutputRegressor, MultiOutputClassifier}};
+
+
+///the shared three blobs, labelled with strings.
+fn blobs(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let names = ["left", "right", "top"];
+    let (X, y) = test_fixtures::blobs(seed, samples);
+    let DataType::Category(classes) = y else { unreachable!() };
+    (X, DataType::Strings(classes.iter().map(|class| names[*class as usize].to_owned()).collect()))
+}
+
+#[test]
+fn one_vs_rest_with_and_without_probabilities() {
+    let (X, y) = blobs(0, 300);
+    let (X_test, y_test) = blobs(1, 300);
+
+    let mut model = OneVsRest::new(LogisticRegression::new());
+    assert!(!model.is_fitted());
+    model.fit(&X, &y).unwrap();
+    assert!(matches!(model.classes(), Some(DataType::Strings(classes)) if classes == &["left", "right", "top"]));
+    assert_eq!(model.estimators().len(), 3);
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.8);
+    assert_eq!(model.predict(&[2.0, 0.0]), ReturnType::Strings("right".to_owned()));
+    let probabilities = model.predict_proba(&[1.0, 2.0]);
+    assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5 && probabilities[2] > 0.5);
+    //the estimator of "top" tells it from the rest with 0 and 1.
+    assert_eq!(model.estimator(2).unwrap().predict(&[1.0, 2.0]), ReturnType::Category(1));
+    assert!(model.estimator(3).is_none());
+
+    //a base without probabilities only votes.
+    let mut model = OneVsRest::new(Svc::new(Kernel::Linear, 1.0));
+    model.fit(&X, &y).unwrap();
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.7);
+    assert!(model.decision_function(&[0.0, 0.0]).iter().all(|score| *score == 0.0 || *score == 1.0));
+
+    assert!(matches!(model.fit(&X, &DataType::Floats(vec![0.0; 300])), Err(MLError::InvalidTarget(_))));
+    assert!(matches!(model.fit(&X, &DataType::Category(vec![4; 300])), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn one_vs_one_votes_over_pairs() {
+    let (X, y) = blobs(2, 300);
+    let (X_test, y_test) = blobs(3, 300);
+    let mut model = OneVsOne::new(Svc::new(Kernel::Rbf { gamma: None }, 1.0));
+    model.fit(&X, &y).unwrap();
+    let pairs: Vec<(usize, usize)> = model.estimators().iter().map(|(pair, _)| *pair).collect();
+    assert_eq!(pairs, vec![(0, 1), (0, 2), (1, 2)]);
+    assert!(model.estimator(2, 1).is_some() && model.estimator(1, 1).is_none());
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.8);
+    assert_eq!(model.predict(&[0.0, 0.0]), ReturnType::Strings("left".to_owned()));
+    //deep in "top" both of its pairs vote for it.
+    let scores = model.decision_function(&[1.0, 3.0]);
+    assert!(scores[2] > 1.6 && scores[2] < 2.4 && scores[0] < 1.4 && scores[1] < 1.4, "{:?}", scores);
+    assert!(model.probabilities(&[1.0, 3.0]).is_none());
+
+    //with probabilities the confidences break the ties.
+    let mut model = OneVsOne::new(GaussianNb::new());
+    model.fit_weighted(&X, &y, &vec![1.0; 300]).unwrap();
+    assert!(accuracy_score(&model, &X_test, &y_test) > 0.8);
+    assert!(matches!(model.fit_weighted(&X, &y, &[1.0]), Err(MLError::SampleMismatch { .. })));
+}
+
+#[test]
+fn multi_output_regressor_fits_every_column() {
+    let rng = fastrand::Rng::with_seed(4);
+    let rows: Vec<Vec<f32>> = (0..100).map(|_| vec![rng.f32() * 2.0 - 1.0, rng.f32() * 2.0 - 1.0]).collect();
+    let targets: Vec<Vec<f32>> = rows.iter().map(|row| vec![2.0 * row[0] - row[1] + 1.0, 0.5 * row[1] - 3.0]).collect();
+    let (X, y) = (Matrix::from(rows), Matrix::from(targets));
+
+    let mut model = MultiOutputRegressor::new(LinearRegression::new());
+    assert!(!model.is_fitted());
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.n_outputs(), 2);
+    assert_eq!(model.n_features_in(), Some(2));
+    let prediction = model.predict(&[0.5, 0.5]);
+    assert!((prediction[0] - 1.5).abs() < 1e-3 && (prediction[1] + 2.75).abs() < 1e-3, "{:?}", prediction);
+    let predictions = model.predict_matrix(&X);
+    assert_eq!(predictions.shape(), (100, 2));
+    assert!((0..100).all(|row| (predictions.get(row, 1) - y.get(row, 1)).abs() < 1e-3));
+    match model.estimators()[1].predict(&[0.0, 1.0]) {
+        ReturnType::Floats(value) => assert!((value + 2.5).abs() < 1e-3),
+        other => panic!("{:?}", other),
+    }
+
+    assert!(matches!(model.fit(&X, &Matrix::new(10, 2)), Err(MLError::SampleMismatch { .. })));
+    assert!(matches!(model.fit(&X, &Matrix::new(100, 0)), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn multi_output_classifier_keeps_classes_per_target() {
+    let (X, y) = blobs(5, 300);
+    //a second target : which side of x = 1 the point is on.
+    let side = DataType::Category(X.iter_rows().map(|row| (row[0] > 1.0) as u8).collect());
+    let mut model = MultiOutputClassifier::new({ let mut tree = DecisionTree::new(Criterion::Gini); tree.set_max_depth(Some(4)); tree });
+    model.fit(&X, &[y.clone(), side]).unwrap();
+    assert_eq!(model.n_outputs(), 2);
+    assert!(matches!(&model.classes()[1], DataType::Category(classes) if classes == &[0, 1]));
+    assert_eq!(model.predict(&[2.0, 0.0]), vec![ReturnType::Strings("right".to_owned()), ReturnType::Category(1)]);
+    let probabilities = model.predict_proba(&[0.0, 0.0]).unwrap();
+    assert_eq!((probabilities[0].len(), probabilities[1].len()), (3, 2));
+    assert!(accuracy_score(model.estimators()[0], &X, &y) > 0.85);
+
+    let mut model = MultiOutputClassifier::new(Svc::new(Kernel::Linear, 1.0));
+    model.fit(&X, &[DataType::Category(X.iter_rows().map(|row| (row[1] > 1.0) as u8).collect())]).unwrap();
+    assert!(model.predict_proba(&[0.0, 0.0]).is_none());
+    assert!(matches!(model.fit(&X, &[y, DataType::Floats(vec![0.0; 300])]), Err(MLError::InvalidTarget(_))));
+    assert!(matches!(model.fit(&X, &[]), Err(MLError::InvalidTarget(_))));
+}
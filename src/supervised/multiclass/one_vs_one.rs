@@ -0,0 +1,136 @@
+//! #One-vs-one#
+//!
+//! `OneVsOne` turns any binary classifier into a multiclass one : a copy of the classifier is fitted for every pair of
+//! classes, only on the samples of the two classes(the targets are 0 for the first class of the pair and 1 for the second),
+//! and every copy votes for one of its two classes. The class with the most votes wins, ties go to the class the
+//! copies were the most sure of.
+//!
+//! That is `n * (n - 1) / 2` estimators for `n` classes, but each one is fitted on a small part of the data, which
+//! pays off for the models that get slow with many samples(like the kernel SVMs).
+
+use rayon::prelude::*;
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix,
+    supervised::ensemble::common::{argmax, encode_classes, fit_member}};
+use super::one_vs_rest::positive_score;
+
+
+#[derive(Clone)]
+pub struct OneVsOne {
+    base : Box<dyn Estimator>,
+    ///the(first, second) class indices of every pair with its fitted copy of `base`, in the order (0, 1), (0, 2).. (1, 2)..
+    estimators : Vec<((usize, usize), Box<dyn Estimator>)>,
+    classes : Option<DataType>,
+    n_features : usize,
+}
+
+impl OneVsOne {
+
+    ///one copy of the given binary classifier per pair of classes.
+    pub fn new<E : Estimator + 'static>(estimator : E) -> OneVsOne {
+        OneVsOne {
+            base: Box::new(estimator),
+            estimators: vec![],
+            classes: None,
+            n_features: 0,
+        }
+    }
+
+    ///the classifier as it was given, unfitted.
+    pub fn base_estimator(&self) -> &dyn Estimator {
+        self.base.as_ref()
+    }
+
+    ///the fitted estimator of every pair of classes(their indices in `classes()`), it predicts 1 for the second class of the pair.
+    pub fn estimators(&self) -> Vec<((usize, usize), &dyn Estimator)> {
+        self.estimators.iter().map(|(pair, estimator)| (*pair, estimator.as_ref())).collect()
+    }
+
+    ///the estimator of the given pair of classes, in either order.
+    pub fn estimator(&self, first : usize, second : usize) -> Option<&dyn Estimator> {
+        let pair = (first.min(second), first.max(second));
+        self.estimators.iter().find(|(other, _)| *other == pair).map(|(_, estimator)| estimator.as_ref())
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///the votes of every class, in the order of `classes()`, plus a bit less than half a vote that grows with how sure
+    ///the pairs were of the class, so the highest value is the predicted class.
+    pub fn decision_function(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        let n_classes = self.classes.as_ref().unwrap().len();
+        let (mut votes, mut confidences) = (vec![0.0_f32; n_classes], vec![0.0_f32; n_classes]);
+        for ((first, second), estimator) in &self.estimators {
+            let score = positive_score(estimator.as_ref(), point);
+            if score > 0.5 {
+                votes[*second] += 1.0;
+            } else {
+                votes[*first] += 1.0;
+            }
+            confidences[*second] += score - 0.5;
+            confidences[*first] += 0.5 - score;
+        }
+        //the confidences are squeezed into (-1/3, 1/3) so they can only break ties between the votes.
+        votes.iter().zip(&confidences).map(|(vote, confidence)| vote + confidence / (3.0 * (confidence.abs() + 1.0))).collect()
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        if let Some(sample_weights) = sample_weights {
+            check_sample_weights(sample_weights, X_train.rows())?;
+        }
+        let (classes, labels) = encode_classes(y_train, "one-vs-one")?;
+        let n_classes = classes.len();
+        if n_classes < 2 {
+            return Err(MLError::InvalidTarget("one-vs-one needs atleast two classes".to_owned()));
+        }
+        self.classes = None;
+        let pairs: Vec<(usize, usize)> = (0..n_classes).flat_map(|first| (first + 1..n_classes).map(move |second| (first, second))).collect();
+        self.estimators = pairs.into_par_iter().map(|(first, second)| {
+            let samples: Vec<usize> = (0..labels.len()).filter(|sample| labels[*sample] == first || labels[*sample] == second).collect();
+            let targets = DataType::Category(samples.iter().map(|sample| (labels[*sample] == second) as u8).collect());
+            let weights = sample_weights.map(|weights| samples.iter().map(|sample| weights[*sample]).collect::<Vec<f32>>());
+            let mut estimator = self.base.clone_box();
+            fit_member("the classifier", estimator.as_mut(), &X_train.select_rows(&samples), &targets, weights.as_deref())?;
+            Ok(((first, second), estimator))
+        }).collect::<Result<_, MLError>>()?;
+        self.classes = Some(classes);
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+}
+
+impl MLalgo for OneVsOne {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        self.base.supports_sample_weights()
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+}
+
+impl Predict for OneVsOne {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let scores = self.decision_function(point);
+        self.classes.as_ref().unwrap().value_at(argmax(&scores))
+    }
+}
+
+///the votes are not probabilities, so `OneVsOne` gives none.
+impl Estimator for OneVsOne {}
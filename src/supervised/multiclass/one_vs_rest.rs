@@ -0,0 +1,138 @@
+//! #One-vs-rest#
+//!
+//! `OneVsRest` turns any binary classifier into a multiclass one : a copy of the classifier is fitted for every class, on
+//! the targets 1 for the samples of the class and 0 for the rest, and the class whose copy is the most sure wins.
+//! A copy is as sure as the probability it gives the 1, a copy without probabilities only says 0 or 1, so ties between
+//! them go to the class that comes first.
+//!
+//! The targets can be categories or strings, the classes are sorted like everywhere else in the crate.
+
+use rayon::prelude::*;
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix,
+    supervised::ensemble::common::{argmax, encode_classes, fit_member}};
+
+
+///how sure a binary estimator(fitted on 0 and 1 targets) is of the 1, its probability of the 1 or its vote.
+pub(crate) fn positive_score(estimator : &dyn Estimator, point : &[f32]) -> f32 {
+    match estimator.probabilities(point) {
+        Some(probabilities) if probabilities.len() == 2 => probabilities[1],
+        _ => (estimator.predict(point) == ReturnType::Category(1)) as u8 as f32,
+    }
+}
+
+
+#[derive(Clone)]
+pub struct OneVsRest {
+    base : Box<dyn Estimator>,
+    ///one fitted copy of `base` per class, in the order of `classes()`.
+    estimators : Vec<Box<dyn Estimator>>,
+    classes : Option<DataType>,
+    n_features : usize,
+}
+
+impl OneVsRest {
+
+    ///one copy of the given binary classifier per class.
+    pub fn new<E : Estimator + 'static>(estimator : E) -> OneVsRest {
+        OneVsRest {
+            base: Box::new(estimator),
+            estimators: vec![],
+            classes: None,
+            n_features: 0,
+        }
+    }
+
+    ///the classifier as it was given, unfitted.
+    pub fn base_estimator(&self) -> &dyn Estimator {
+        self.base.as_ref()
+    }
+
+    ///the fitted estimator of every class, in the order of `classes()`.
+    pub fn estimators(&self) -> Vec<&dyn Estimator> {
+        self.estimators.iter().map(|estimator| estimator.as_ref()).collect()
+    }
+
+    ///the estimator that tells the given class(its index in `classes()`) from the rest.
+    pub fn estimator(&self, class : usize) -> Option<&dyn Estimator> {
+        self.estimators.get(class).map(|estimator| estimator.as_ref())
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///how sure the estimator of every class is of its class, in the order of `classes()`.
+    pub fn decision_function(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.estimators.iter().map(|estimator| positive_score(estimator.as_ref(), point)).collect()
+    }
+
+    ///the scores of `decision_function` normalised to sum to 1, uniform if no estimator claims the point.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        let scores = self.decision_function(point);
+        let total: f32 = scores.iter().sum();
+        if total > 0.0 {
+            scores.iter().map(|score| score / total).collect()
+        } else {
+            vec![1.0 / scores.len() as f32; scores.len()]
+        }
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        if let Some(sample_weights) = sample_weights {
+            check_sample_weights(sample_weights, X_train.rows())?;
+        }
+        let (classes, labels) = encode_classes(y_train, "one-vs-rest")?;
+        if classes.len() < 2 {
+            return Err(MLError::InvalidTarget("one-vs-rest needs atleast two classes".to_owned()));
+        }
+        self.classes = None;
+        self.estimators = (0..classes.len()).into_par_iter().map(|class| {
+            let targets = DataType::Category(labels.iter().map(|label| (*label == class) as u8).collect());
+            let mut estimator = self.base.clone_box();
+            fit_member("the classifier", estimator.as_mut(), X_train, &targets, sample_weights)?;
+            Ok(estimator)
+        }).collect::<Result<_, MLError>>()?;
+        self.classes = Some(classes);
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+}
+
+impl MLalgo for OneVsRest {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        self.base.supports_sample_weights()
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+}
+
+impl Predict for OneVsRest {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let scores = self.decision_function(point);
+        self.classes.as_ref().unwrap().value_at(argmax(&scores))
+    }
+}
+
+impl Estimator for OneVsRest {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
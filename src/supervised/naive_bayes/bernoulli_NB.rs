@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType}, trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input, partial_fit_classes, check_batch_features},
+    n_dimen::matrix::Matrix, persistence::persistence::{Persist, PersistError, ensure}};
+use super::common::{encode_classes, check_alpha, log_priors, probabilities, best_class};
+
+
+///Used when the features say whether something is present or not.
+/// -for example: which words of a vocabulary appear in a short text.
+///Values above `binarize` count as present, unlike `MultinomialNb` the absence of a feature is evidence as well.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BernoulliNb {
+    ///additive smoothing, 1 is Laplace smoothing and values below 1 Lidstone smoothing.
+    pub alpha : f32,
+    ///values > binarize are 1 and the rest 0, `None` if the features are already 0 or 1.
+    pub binarize : Option<f32>,
+    ///fixed class priors in the order of the sorted classes, `None` uses the class frequencies.
+    pub class_prior : Option<Vec<f32>>,
+    classes : Option<DataType>,
+    class_counts : Vec<f32>,
+    ///number of samples of every class in which every feature is present.
+    feature_counts : Vec<Vec<f32>>,
+    class_log_prior : Vec<f32>,
+    ///log of P(feature present | class) and log of P(feature absent | class).
+    feature_log_prob : Vec<Vec<(f32, f32)>>,
+}
+
+impl BernoulliNb {
+
+    pub fn new() -> BernoulliNb {
+        BernoulliNb {
+            alpha: 1.0,
+            binarize: Some(0.0),
+            class_prior: None,
+            classes: None,
+            class_counts: vec![],
+            feature_counts: vec![],
+            class_log_prior: vec![],
+            feature_log_prob: vec![],
+        }
+    }
+
+    pub fn set_alpha(&mut self, alpha : f32) {
+        self.alpha = alpha;
+    }
+
+    pub fn set_binarize(&mut self, binarize : Option<f32>) {
+        self.binarize = binarize;
+    }
+
+    pub fn set_class_prior(&mut self, class_prior : Option<Vec<f32>>) {
+        self.class_prior = class_prior;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    fn is_present(&self, value : f32) -> bool {
+        match self.binarize {
+            Some(threshold) => value > threshold,
+            None => value != 0.0,
+        }
+    }
+
+    ///updates the model with a new batch, see `GaussianNb::partial_fit`. The counts add up, so the result is the same as one `fit` on all the batches.
+    pub fn partial_fit(&mut self, X_batch : &Matrix, y_batch : &DataType, classes : Option<&DataType>) -> Result<(), MLError> {
+        check_fit_input(X_batch, y_batch)?;
+        check_alpha(self.alpha)?;
+        check_batch_features(self.n_features_in(), X_batch)?;
+        let (classes, labels) = partial_fit_classes(self.classes.as_ref(), classes, y_batch)?;
+        if self.classes.is_none() {
+            self.class_counts = vec![0.0; classes.len()];
+            self.feature_counts = vec![vec![0.0; X_batch.cols()]; classes.len()];
+        }
+        self.accumulate(X_batch, &labels, &vec![1.0; labels.len()]);
+        self.update_log_probabilities()?;
+        self.classes = Some(classes);
+        Ok(())
+    }
+
+    fn accumulate(&mut self, X_batch : &Matrix, labels : &[usize], sample_weights : &[f32]) {
+        for ((row, label), weight) in X_batch.iter_rows().zip(labels).zip(sample_weights) {
+            self.class_counts[*label] += weight;
+            for (feature, value) in row.iter().enumerate() {
+                self.feature_counts[*label][feature] += weight * self.is_present(*value) as u8 as f32;
+            }
+        }
+    }
+
+    ///recomputes the log probabilities from the counts.
+    fn update_log_probabilities(&mut self) -> Result<(), MLError> {
+        self.class_log_prior = log_priors(&self.class_counts, self.class_prior.as_ref())?;
+        //a tiny floor keeps alpha = 0 from taking the log of 0.
+        let alpha = self.alpha.max(1e-10);
+        self.feature_log_prob = self.feature_counts.iter().zip(&self.class_counts).map(|(counts, class_count)| {
+            counts.iter().map(|count| {
+                let p = (count + alpha) / (class_count + 2.0 * alpha);
+                (p.ln(), (1.0 - p).ln())
+            }).collect()
+        }).collect();
+        Ok(())
+    }
+
+    ///log P(class) + log P(point | class) for every class.
+    pub fn joint_log_likelihood(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.class_log_prior.iter().zip(&self.feature_log_prob).zip(&self.class_counts).map(|((prior, features), count)| {
+            //a class declared to partial_fit that has not been seen yet can not win.
+            if *count == 0.0 {
+                return f32::NEG_INFINITY;
+            }
+            prior + point.iter().zip(features).map(|(value, (present, absent))| if self.is_present(*value) { present } else { absent }).sum::<f32>()
+        }).collect()
+    }
+
+    ///class probabilities in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        probabilities(&self.joint_log_likelihood(point))
+    }
+}
+
+impl MLalgo for BernoulliNb {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        check_alpha(self.alpha)?;
+        let (classes, labels) = encode_classes(y_train, "BernoulliNb")?;
+        self.class_counts = vec![0.0; classes.len()];
+        self.feature_counts = vec![vec![0.0; X_train.cols()]; classes.len()];
+        self.accumulate(X_train, &labels, sample_weights);
+        self.update_log_probabilities()?;
+        self.classes = Some(classes);
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.classes.as_ref().map(|_| self.feature_counts[0].len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        if let Some(binarize) = self.binarize {
+            params.insert("binarize".to_owned(), ParamValue::Float(binarize));
+        }
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "alpha" => self.alpha = value.as_f32(name)?,
+                "binarize" => self.binarize = Some(value.as_f32(name)?),
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        check_alpha(self.alpha)
+    }
+}
+
+impl Predict for BernoulliNb {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        best_class(self.classes.as_ref().unwrap(), &self.joint_log_likelihood(point))
+    }
+}
+
+impl Estimator for BernoulliNb {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+impl Persist for BernoulliNb {
+    const MODEL_TYPE: &'static str = "BernoulliNb";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let number_of_classes = self.classes.as_ref().map_or(0, |classes| classes.len());
+        ensure(self.classes.is_none() || number_of_classes > 0, "a fitted model needs atleast one class")?;
+        ensure(self.class_counts.len() == number_of_classes && self.class_log_prior.len() == number_of_classes, "class counts do not match the number of classes")?;
+        ensure(self.feature_counts.len() == number_of_classes && self.feature_log_prob.len() == number_of_classes, "feature counts do not match the number of classes")?;
+        let number_of_features = self.feature_counts.first().map_or(0, |counts| counts.len());
+        ensure(self.feature_counts.iter().zip(&self.feature_log_prob).all(|(counts, log_prob)| counts.len() == number_of_features && log_prob.len() == number_of_features),
+            "every class needs the same number of features")
+    }
+}
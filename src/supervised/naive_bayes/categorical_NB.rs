@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType}, trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input, partial_fit_classes, check_batch_features},
+    n_dimen::matrix::Matrix, persistence::persistence::{Persist, PersistError, ensure}};
+use super::common::{encode_classes, check_alpha, log_priors, probabilities, best_class};
+
+///the biggest category code `fit` accepts, the counts of every feature are stored densely up to its biggest code.
+pub const MAX_CATEGORY_CODE : f32 = u16::MAX as f32;
+
+
+///Used when every feature is a discrete category, encoded as 0, 1, 2...(like the codes `DataFrame::encode` gives).
+///Every feature gets its own distribution over its categories for every class.
+///A category that was never seen in training(a code past the biggest one) gets only the smoothing mass.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoricalNb {
+    ///additive smoothing, 1 is Laplace smoothing and values below 1 Lidstone smoothing.
+    pub alpha : f32,
+    ///fixed class priors in the order of the sorted classes, `None` uses the class frequencies.
+    pub class_prior : Option<Vec<f32>>,
+    classes : Option<DataType>,
+    class_counts : Vec<f32>,
+    ///`category_counts[class][feature][category]`.
+    category_counts : Vec<Vec<Vec<f32>>>,
+    class_log_prior : Vec<f32>,
+    category_log_prob : Vec<Vec<Vec<f32>>>,
+    ///log probability of a category that was not seen in training, per class and feature.
+    unseen_log_prob : Vec<Vec<f32>>,
+}
+
+impl CategoricalNb {
+
+    pub fn new() -> CategoricalNb {
+        CategoricalNb {
+            alpha: 1.0,
+            class_prior: None,
+            classes: None,
+            class_counts: vec![],
+            category_counts: vec![],
+            class_log_prior: vec![],
+            category_log_prob: vec![],
+            unseen_log_prob: vec![],
+        }
+    }
+
+    pub fn set_alpha(&mut self, alpha : f32) {
+        self.alpha = alpha;
+    }
+
+    pub fn set_class_prior(&mut self, class_prior : Option<Vec<f32>>) {
+        self.class_prior = class_prior;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///the number of categories of every feature.
+    pub fn n_categories(&self) -> Vec<usize> {
+        self.category_counts.first().map_or(vec![], |features| features.iter().map(|counts| counts.len()).collect())
+    }
+
+    ///updates the model with a new batch, see `GaussianNb::partial_fit`. The counts add up, so the result is the same as one `fit` on all the batches.
+    ///Category codes bigger than any seen before extend the categories of their feature.
+    pub fn partial_fit(&mut self, X_batch : &Matrix, y_batch : &DataType, classes : Option<&DataType>) -> Result<(), MLError> {
+        check_fit_input(X_batch, y_batch)?;
+        check_alpha(self.alpha)?;
+        check_categories(X_batch)?;
+        check_batch_features(self.n_features_in(), X_batch)?;
+        let (classes, labels) = partial_fit_classes(self.classes.as_ref(), classes, y_batch)?;
+        if self.classes.is_none() {
+            self.class_counts = vec![0.0; classes.len()];
+            self.category_counts = vec![vec![vec![]; X_batch.cols()]; classes.len()];
+        }
+        self.accumulate(X_batch, &labels, &vec![1.0; labels.len()]);
+        self.update_log_probabilities()?;
+        self.classes = Some(classes);
+        Ok(())
+    }
+
+    ///counts the categories, every class keeps the same number of categories per feature.
+    fn accumulate(&mut self, X_batch : &Matrix, labels : &[usize], sample_weights : &[f32]) {
+        for feature in 0..X_batch.cols() {
+            let needed = X_batch.column(feature).iter().fold(0.0_f32, |max, value| max.max(*value)) as usize + 1;
+            for class in self.category_counts.iter_mut() {
+                if class[feature].len() < needed {
+                    class[feature].resize(needed, 0.0);
+                }
+            }
+        }
+        for ((row, label), weight) in X_batch.iter_rows().zip(labels).zip(sample_weights) {
+            self.class_counts[*label] += weight;
+            for (feature, value) in row.iter().enumerate() {
+                self.category_counts[*label][feature][*value as usize] += weight;
+            }
+        }
+    }
+
+    ///recomputes the log probabilities from the counts.
+    fn update_log_probabilities(&mut self) -> Result<(), MLError> {
+        self.class_log_prior = log_priors(&self.class_counts, self.class_prior.as_ref())?;
+        let alpha = self.alpha.max(1e-10);
+        self.category_log_prob = self.category_counts.iter().zip(&self.class_counts).map(|(features, class_count)| {
+            features.iter().map(|counts| {
+                let total = class_count + alpha * counts.len() as f32;
+                counts.iter().map(|count| ((count + alpha) / total).ln()).collect()
+            }).collect()
+        }).collect();
+        self.unseen_log_prob = self.category_counts.iter().zip(&self.class_counts).map(|(features, class_count)| {
+            features.iter().map(|counts| (alpha / (class_count + alpha * counts.len() as f32)).ln()).collect()
+        }).collect();
+        Ok(())
+    }
+
+    ///log P(class) + log P(point | class) for every class.
+    pub fn joint_log_likelihood(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        assert!(point.iter().all(|value| is_category(*value)), "CategoricalNb needs the features to be category codes 0, 1, 2...");
+        (0..self.class_log_prior.len()).map(|class| {
+            //a class declared to partial_fit that has not been seen yet can not win.
+            if self.class_counts[class] == 0.0 {
+                return f32::NEG_INFINITY;
+            }
+            self.class_log_prior[class] + point.iter().enumerate().map(|(feature, value)| {
+                *self.category_log_prob[class][feature].get(*value as usize).unwrap_or(&self.unseen_log_prob[class][feature])
+            }).sum::<f32>()
+        }).collect()
+    }
+
+    ///class probabilities in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        probabilities(&self.joint_log_likelihood(point))
+    }
+}
+
+fn is_category(value : f32) -> bool {
+    value >= 0.0 && value.fract() == 0.0
+}
+
+fn check_categories(X : &Matrix) -> Result<(), MLError> {
+    if !X.as_slice().iter().all(|value| is_category(*value)) {
+        return Err(MLError::InvalidParameter("CategoricalNb needs the features to be category codes 0, 1, 2...".to_owned()));
+    }
+    if let Some(code) = X.as_slice().iter().find(|value| **value > MAX_CATEGORY_CODE) {
+        return Err(MLError::InvalidParameter(format!("category codes can be atmost {}, got {}", MAX_CATEGORY_CODE, code)));
+    }
+    Ok(())
+}
+
+impl MLalgo for CategoricalNb {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        check_alpha(self.alpha)?;
+        check_categories(X_train)?;
+        let (classes, labels) = encode_classes(y_train, "CategoricalNb")?;
+        self.class_counts = vec![0.0; classes.len()];
+        self.category_counts = vec![vec![vec![]; X_train.cols()]; classes.len()];
+        self.accumulate(X_train, &labels, sample_weights);
+        self.update_log_probabilities()?;
+        self.classes = Some(classes);
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.classes.as_ref().map(|_| self.category_counts[0].len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "alpha" => self.alpha = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        check_alpha(self.alpha)
+    }
+}
+
+impl Predict for CategoricalNb {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        best_class(self.classes.as_ref().unwrap(), &self.joint_log_likelihood(point))
+    }
+}
+
+impl Estimator for CategoricalNb {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+impl Persist for CategoricalNb {
+    const MODEL_TYPE: &'static str = "CategoricalNb";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let number_of_classes = self.classes.as_ref().map_or(0, |classes| classes.len());
+        ensure(self.classes.is_none() || number_of_classes > 0, "a fitted model needs atleast one class")?;
+        ensure(self.class_counts.len() == number_of_classes && self.class_log_prior.len() == number_of_classes, "class counts do not match the number of classes")?;
+        ensure(self.category_counts.len() == number_of_classes && self.category_log_prob.len() == number_of_classes
+            && self.unseen_log_prob.len() == number_of_classes, "category counts do not match the number of classes")?;
+        let n_categories = self.n_categories();
+        ensure(self.category_counts.iter().zip(&self.category_log_prob).all(|(counts, log_prob)|
+            counts.iter().map(|counts| counts.len()).eq(n_categories.iter().cloned()) && log_prob.iter().map(|log_prob| log_prob.len()).eq(n_categories.iter().cloned())),
+            "every class needs the same categories")?;
+        ensure(self.unseen_log_prob.iter().all(|unseen| unseen.len() == n_categories.len()), "every class needs an unseen category probability for every feature")
+    }
+}
@@ -0,0 +1,54 @@
+//! The parts every naive Bayes model shares : class encoding, priors, smoothing checks and turning joint log likelihoods into predictions.
+
+use crate::{data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::MLError};
+
+
+///sorted unique classes and the class index of every sample, only for category and string targets.
+pub(crate) fn encode_classes(y_train : &DataType, model : &str) -> Result<(DataType, Vec<usize>), MLError> {
+    y_train.unique_classes().ok_or_else(|| MLError::InvalidTarget(format!("You cannot train {} with float as a target, for this model type", model)))
+}
+
+///the additive(Laplace for 1, Lidstone below 1) smoothing needs to be >= 0.
+pub(crate) fn check_alpha(alpha : f32) -> Result<(), MLError> {
+    if alpha >= 0.0 && alpha.is_finite() {
+        Ok(())
+    } else {
+        Err(MLError::InvalidParameter(format!("the smoothing alpha needs to be >= 0, got {}", alpha)))
+    }
+}
+
+///log of the class priors, the given ones or the class frequencies.
+pub(crate) fn log_priors(class_counts : &[f32], class_prior : Option<&Vec<f32>>) -> Result<Vec<f32>, MLError> {
+    match class_prior {
+        Some(prior) => {
+            if prior.len() != class_counts.len() {
+                return Err(MLError::InvalidParameter(format!("{} class priors were given for {} classes", prior.len(), class_counts.len())));
+            }
+            if prior.iter().any(|p| !(*p >= 0.0)) || (prior.iter().sum::<f32>() - 1.0).abs() > 1e-4 {
+                return Err(MLError::InvalidParameter("the class priors need to be >= 0 and sum to 1".to_owned()));
+            }
+            Ok(prior.iter().map(|p| p.ln()).collect())
+        },
+        None => {
+            let total: f32 = class_counts.iter().sum();
+            Ok(class_counts.iter().map(|count| (count / total).ln()).collect())
+        },
+    }
+}
+
+///the index of the biggest value, ties go to the class that comes first.
+pub(crate) fn argmax(values : &[f32]) -> usize {
+    values.iter().enumerate().fold(0, |best, (index, value)| if *value > values[best] { index } else { best })
+}
+
+///normalised probabilities from joint log likelihoods.
+pub(crate) fn probabilities(joint_log_likelihood : &[f32]) -> Vec<f32> {
+    let max = joint_log_likelihood.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let exps: Vec<f32> = joint_log_likelihood.iter().map(|value| (value - max).exp()).collect();
+    let total: f32 = exps.iter().sum();
+    exps.iter().map(|value| value / total).collect()
+}
+
+pub(crate) fn best_class(classes : &DataType, joint_log_likelihood : &[f32]) -> ReturnType {
+    classes.value_at(argmax(joint_log_likelihood))
+}
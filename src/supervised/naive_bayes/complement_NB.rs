@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType}, trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input, partial_fit_classes, check_batch_features},
+    n_dimen::matrix::Matrix, persistence::persistence::{Persist, PersistError, ensure}};
+use super::common::{encode_classes, check_alpha, log_priors, probabilities, best_class};
+
+
+///Complement naive Bayes(Rennie et al. 2003) for counts, like `MultinomialNb`, but every class is described by the
+///feature counts of all the OTHER classes. Small classes borrow the statistics of the big ones,
+///which makes it a lot more stable than `MultinomialNb` on imbalanced text classes.
+///
+///Following the paper the decision ignores the class priors, unless `class_prior` is given explicitly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplementNb {
+    ///additive smoothing, 1 is Laplace smoothing and values below 1 Lidstone smoothing.
+    pub alpha : f32,
+    ///normalise the weights of every class by their total, the "weight normalised" variant of the paper.
+    pub norm : bool,
+    ///fixed class priors in the order of the sorted classes, added to the decision when given.
+    pub class_prior : Option<Vec<f32>>,
+    classes : Option<DataType>,
+    class_counts : Vec<f32>,
+    ///summed feature values of every class.
+    feature_counts : Vec<Vec<f32>>,
+    class_log_prior : Vec<f32>,
+    ///the weight of every feature for every class, a bigger weight is more evidence for the class.
+    feature_weights : Vec<Vec<f32>>,
+}
+
+impl ComplementNb {
+
+    pub fn new() -> ComplementNb {
+        ComplementNb {
+            alpha: 1.0,
+            norm: false,
+            class_prior: None,
+            classes: None,
+            class_counts: vec![],
+            feature_counts: vec![],
+            class_log_prior: vec![],
+            feature_weights: vec![],
+        }
+    }
+
+    pub fn set_alpha(&mut self, alpha : f32) {
+        self.alpha = alpha;
+    }
+
+    pub fn set_norm(&mut self, norm : bool) {
+        self.norm = norm;
+    }
+
+    pub fn set_class_prior(&mut self, class_prior : Option<Vec<f32>>) {
+        self.class_prior = class_prior;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///updates the model with a new batch, see `GaussianNb::partial_fit`. The counts add up, so the result is the same as one `fit` on all the batches.
+    pub fn partial_fit(&mut self, X_batch : &Matrix, y_batch : &DataType, classes : Option<&DataType>) -> Result<(), MLError> {
+        check_fit_input(X_batch, y_batch)?;
+        check_alpha(self.alpha)?;
+        check_non_negative(X_batch)?;
+        check_batch_features(self.n_features_in(), X_batch)?;
+        let (classes, labels) = partial_fit_classes(self.classes.as_ref(), classes, y_batch)?;
+        if self.classes.is_none() {
+            self.class_counts = vec![0.0; classes.len()];
+            self.feature_counts = vec![vec![0.0; X_batch.cols()]; classes.len()];
+        }
+        self.accumulate(X_batch, &labels, &vec![1.0; labels.len()]);
+        self.update_weights()?;
+        self.classes = Some(classes);
+        Ok(())
+    }
+
+    fn accumulate(&mut self, X_batch : &Matrix, labels : &[usize], sample_weights : &[f32]) {
+        for ((row, label), weight) in X_batch.iter_rows().zip(labels).zip(sample_weights) {
+            self.class_counts[*label] += weight;
+            self.feature_counts[*label].iter_mut().zip(row).for_each(|(count, value)| *count += weight * value);
+        }
+    }
+
+    ///recomputes the weights from the counts.
+    fn update_weights(&mut self) -> Result<(), MLError> {
+        self.class_log_prior = log_priors(&self.class_counts, self.class_prior.as_ref())?;
+        let alpha = self.alpha.max(1e-10);
+        let n_features = self.feature_counts.first().map_or(0, |counts| counts.len());
+        let mut totals = vec![0.0_f32; n_features];
+        for counts in &self.feature_counts {
+            totals.iter_mut().zip(counts).for_each(|(total, count)| *total += count);
+        }
+        self.feature_weights = self.feature_counts.iter().map(|counts| {
+            let complement: Vec<f32> = totals.iter().zip(counts).map(|(total, count)| total - count + alpha).collect();
+            let complement_total: f32 = complement.iter().sum();
+            //the complement log probability counts against the class.
+            let mut weights: Vec<f32> = complement.iter().map(|count| -(count / complement_total).ln()).collect();
+            if self.norm {
+                let scale: f32 = weights.iter().map(|weight| weight.abs()).sum();
+                weights.iter_mut().for_each(|weight| *weight /= scale);
+            }
+            weights
+        }).collect();
+        Ok(())
+    }
+
+    ///the score of every class, the biggest wins.
+    pub fn joint_log_likelihood(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.feature_weights.iter().enumerate().map(|(class, weights)| {
+            //a class declared to partial_fit that has not been seen yet can not win.
+            if self.class_counts[class] == 0.0 {
+                return f32::NEG_INFINITY;
+            }
+            let prior = if self.class_prior.is_some() { self.class_log_prior[class] } else { 0.0 };
+            prior + point.iter().zip(weights).map(|(value, weight)| value * weight).sum::<f32>()
+        }).collect()
+    }
+
+    ///the scores turned into probabilities in the order of `classes()`, only a rough confidence for this model.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        probabilities(&self.joint_log_likelihood(point))
+    }
+}
+
+fn check_non_negative(X : &Matrix) -> Result<(), MLError> {
+    if X.as_slice().iter().any(|value| !(*value >= 0.0)) {
+        return Err(MLError::InvalidParameter("ComplementNb needs non negative features".to_owned()));
+    }
+    Ok(())
+}
+
+impl MLalgo for ComplementNb {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///the features need to be counts or frequencies(>= 0).
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        check_alpha(self.alpha)?;
+        check_non_negative(X_train)?;
+        let (classes, labels) = encode_classes(y_train, "ComplementNb")?;
+        self.class_counts = vec![0.0; classes.len()];
+        self.feature_counts = vec![vec![0.0; X_train.cols()]; classes.len()];
+        self.accumulate(X_train, &labels, sample_weights);
+        self.update_weights()?;
+        self.classes = Some(classes);
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.classes.as_ref().map(|_| self.feature_counts[0].len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params.insert("norm".to_owned(), ParamValue::Bool(self.norm));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "alpha" => self.alpha = value.as_f32(name)?,
+                "norm" => self.norm = value.as_bool(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        check_alpha(self.alpha)
+    }
+}
+
+impl Predict for ComplementNb {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        best_class(self.classes.as_ref().unwrap(), &self.joint_log_likelihood(point))
+    }
+}
+
+impl Estimator for ComplementNb {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+impl Persist for ComplementNb {
+    const MODEL_TYPE: &'static str = "ComplementNb";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let number_of_classes = self.classes.as_ref().map_or(0, |classes| classes.len());
+        ensure(self.classes.is_none() || number_of_classes > 0, "a fitted model needs atleast one class")?;
+        ensure(self.class_counts.len() == number_of_classes && self.class_log_prior.len() == number_of_classes, "class counts do not match the number of classes")?;
+        ensure(self.feature_counts.len() == number_of_classes && self.feature_weights.len() == number_of_classes, "feature counts do not match the number of classes")?;
+        let number_of_features = self.feature_counts.first().map_or(0, |counts| counts.len());
+        ensure(self.feature_counts.iter().zip(&self.feature_weights).all(|(counts, weights)| counts.len() == number_of_features && weights.len() == number_of_features),
+            "every class needs the same number of features")
+    }
+}
@@ -1,15 +1,22 @@
-use core::panic;
-use std::collections::HashMap;
-use rayon::prelude::{IndexedParallelIterator, ParallelIterator};
-use crate::{data_frame::{data_type::*, return_type::*}, trait_definition::{MLalgo, Predict}};
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::*, return_type::*}, trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, check_predict_input, assert_predict_input, partial_fit_classes, check_batch_features}, n_dimen::matrix::Matrix, persistence::persistence::{Persist, PersistError, ensure}};
+use super::common::{encode_classes, log_priors, probabilities, best_class};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 
 ///used when the features represent some continuous variable.
 /// we assume that they follow atleast 
 pub struct GaussianNb {
+    ///fixed class priors in the order of the sorted classes, `None` uses the class frequencies.
+    #[serde(default)]
+    pub class_prior : Option<Vec<f32>>,
+    ///this fraction of the largest variance is added to every variance, so a feature that is constant within a class does not give inf/NaN likelihoods.
+    #[serde(default = "default_var_smoothing")]
+    pub var_smoothing : f32,
+    #[serde(default)]
+    variance_floor : f32,
     target_classes: Option<DataType>,//we store all the unique target classes , order sensitive. we are going to follow the same order for storing the other parameters.
-    target_class_distributions: Vec<usize>,
+    target_class_distributions: Vec<f32>,
     total_number_of_cases: u32,
     means_and_std_devs: Vec<Vec<(f32 , f32)>>,
 }
@@ -19,6 +26,9 @@ impl GaussianNb {
     ///creates a gaussian naive bayes object.
     pub fn new() -> GaussianNb {
         GaussianNb { 
+            class_prior: None,
+            var_smoothing: default_var_smoothing(),
+            variance_floor: 0.0,
             target_classes: None, 
             means_and_std_devs: vec![vec![]],
             total_number_of_cases: 0,
@@ -27,160 +37,202 @@ impl GaussianNb {
         
     }
 
-}
+    ///on a fitted model the priors need to match its classes, before fitting only their values are checked.
+    pub fn set_class_prior(&mut self, class_prior : Option<Vec<f32>>) -> Result<(), MLError> {
+        let number_of_classes = self.target_classes.as_ref().map(|classes| classes.len());
+        check_class_prior(class_prior.as_ref(), number_of_classes)?;
+        self.class_prior = class_prior;
+        Ok(())
+    }
 
+    ///takes effect right away on a fitted model.
+    pub fn set_var_smoothing(&mut self, var_smoothing : f32) -> Result<(), MLError> {
+        check_var_smoothing(var_smoothing)?;
+        self.var_smoothing = var_smoothing;
+        self.update_variance_floor();
+        Ok(())
+    }
 
+    pub fn classes(&self) -> Option<&DataType> {
+        self.target_classes.as_ref()
+    }
 
+    ///(weighted) number of training samples of every class, in the order of `classes()`.
+    pub fn class_counts(&self) -> &Vec<f32> {
+        &self.target_class_distributions
+    }
 
-impl MLalgo for GaussianNb {
-    
-    fn fit(&mut self, X_train : &Vec<Vec<f32>> , y_train : &DataType) {   
-
-        //dbg!(y_train); 
-
-        if let DataType::Category(temp) = y_train {
-            
-            //creating a hashmap and giving a index to each different category, we may sometimes have the same value for all key value pairs , but it is not worth the risk.
-            let mut counter: HashMap<u8, usize> = HashMap::new();
-            let mut count = 0_usize;
-            for i in temp {
-                if !counter.contains_key(i) {
-                    counter.insert(*i, count);
-                    count += 1;
-                }
-            }
+    ///`means()[class][feature]`.
+    pub fn means(&self) -> Vec<Vec<f32>> {
+        self.means_and_std_devs.iter().map(|class| class.iter().map(|(mean, _)| *mean).collect()).collect()
+    }
 
-            //the vector first stores the sum and squares sum for each class for each feature.
-            let mut output_main = vec![vec![(0.0_f32 , 0.0_f32) ; X_train[0].len()] ; counter.len()];
-            let mut distribution_count = vec![0.0_f32 ; counter.len()];
-
-            for ( i , row ) in X_train.iter().enumerate() {
-                let index = counter.get(&temp[i]).unwrap();
-                distribution_count[*index] += 1.0_f32;
-                for (j , element) in row.iter().enumerate() {
-                    output_main[*index][j].0 += *element;
-                    output_main[*index][j].1 += element.powf(2.0);
-                }
-            }
+    ///`variances()[class][feature]`, with the `var_smoothing` floor added like in the predictions.
+    pub fn variances(&self) -> Vec<Vec<f32>> {
+        self.means_and_std_devs.iter().map(|class| class.iter().map(|(_, std_dev)| std_dev * std_dev + self.variance_floor).collect()).collect()
+    }
+
+    ///the log of the class priors used in the predictions.
+    pub fn class_log_prior(&self) -> Result<Vec<f32>, MLError> {
+        log_priors(&self.target_class_distributions, self.class_prior.as_ref())
+    }
 
-            for i in 0..distribution_count.len() {
-                let number = distribution_count[i];
-                let number_sqrt = number.sqrt();
-
-                for j in 0..X_train[0].len() {
-                    output_main[i][j].0 = if output_main[i][j].0 != 0.0 {
-                        output_main[i][j].0 / number
-                    } else {
-                        1.0_f32 / number//laplacian smoothing.(idk man, it sounds fancy)
-                    };
-                    //calculating and setting the standard deviation at the same place at which previously the sum of squares are present.
-                    output_main[i][j].1 = (output_main[i][j].1 - (distribution_count[i] * output_main[i][j].0.powf(2.0))) / number_sqrt;
-                }
+    ///log P(class) + log P(point | class) for every class, fails if `class_prior` was changed into priors that do not fit the classes.
+    pub fn joint_log_likelihood(&self, point : &[f32]) -> Result<Vec<f32>, MLError> {
+        assert_predict_input(self, point);
+        let class_log_prior = self.class_log_prior()?;
+        Ok(self.means_and_std_devs.iter().zip(&self.target_class_distributions).zip(class_log_prior).map(|((class, count), prior)| {
+            //classes that were declared to partial_fit but have not been seen yet.
+            if *count == 0.0 {
+                return f32::NEG_INFINITY;
             }
+            prior + class.iter().zip(point).map(|((mean, std_dev), value)| {
+                let variance = std_dev * std_dev + self.variance_floor;
+                -0.5 * ((2.0 * std::f32::consts::PI * variance).ln() + (value - mean).powi(2) / variance)
+            }).sum::<f32>()
+        }).collect())
+    }
 
+    ///class probabilities in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Result<Vec<f32>, MLError> {
+        Ok(probabilities(&self.joint_log_likelihood(point)?))
+    }
 
-            //iterating over a hashmap is the dumbest idea i ever had , it is not fucking folling the order , fuck you chatGPT.
-            let mut in_order_keys: Vec<u8> = vec![0 ; counter.len()];
-            for (k , v) in &counter {
-                in_order_keys[*v] = *k;
-            }
+    fn check_parameters(&self, number_of_classes : usize) -> Result<(), MLError> {
+        check_var_smoothing(self.var_smoothing)?;
+        check_class_prior(self.class_prior.as_ref(), Some(number_of_classes))
+    }
 
-            self.means_and_std_devs = output_main;
-            self.target_classes = Some(DataType::Category(in_order_keys));
-            self.total_number_of_cases = X_train.len().try_into().unwrap();
-            self.target_class_distributions = distribution_count.iter().map(|x| *x as usize).collect();
-
-            return;
-
-        } else if let DataType::Strings(temp) = y_train {
-                //creating a hashmap and giving a index to each different string type, we may sometimes have the same value for all key value pairs , but it is not worth the risk.
-                let mut counter: HashMap<String, usize> = HashMap::new();
-                let mut count = 0_usize;
-                for i in temp {
-                    if !counter.contains_key(i) {
-                        counter.insert(i.to_string(), count);
-                        count += 1;
-                    }
-                }
-
-                //dbg!(&counter);
-                //the vector first stores the sum and squares sum for each class for each feature.
-                let mut output_main = vec![vec![(0.0_f32 , 0.0_f32) ; X_train[0].len()] ; counter.len()];
-                let mut distribution_count = vec![0.0_f32 ; counter.len()];
-    
-                for ( i , row ) in X_train.iter().enumerate() {
-                    let index = counter.get(&temp[i]).unwrap();
-                    distribution_count[*index] += 1.0_f32;
-                    for (j , element) in row.iter().enumerate() {
-                        output_main[*index][j].0 += *element;
-                        output_main[*index][j].1 += element.powf(2.0);
-                    }
-                }
-
-                for i in 0..distribution_count.len() {
-                    let number = distribution_count[i];
-                    let number_sqrt = number.sqrt();
-                    for j in 0..X_train[0].len() {
-                        output_main[i][j].0 = if output_main[i][j].0 != 0.0 {
-                            output_main[i][j].0 / number
-                        } else {
-                            1.0_f32 / number//laplacian smoothing.(idk man, it sounds fancy)
-                        };
-                        //calculating and setting the standard deviation at the same place at which previously the sum of squares are present.
-                        output_main[i][j].1 = (output_main[i][j].1 - (distribution_count[i] * (output_main[i][j].0).powf(2.0))) / number_sqrt;
-                    }
-                }
-
-                let mut in_order_keys: Vec<String> = vec![String::new() ; counter.len()];
-                for (k , v) in &counter {
-                    in_order_keys[*v] = k.to_owned();
-                }
-    
-                self.means_and_std_devs = output_main;
-                self.target_classes = Some(DataType::Strings(in_order_keys));
-                self.total_number_of_cases = X_train.len().try_into().unwrap();
-                self.target_class_distributions = distribution_count.iter().map(|x| *x as usize).collect();            
-            
-                return;
+    ///updates the model with a new batch instead of retraining on everything seen so far.
+    ///The first call needs the full list of `classes`(a batch does not have to contain all of them), later calls can pass `None`.
+    ///The means and standard deviations are merged with Welford's update, so the result is the same as one `fit` on all the batches.
+    pub fn partial_fit(&mut self, X_batch : &Matrix, y_batch : &DataType, classes : Option<&DataType>) -> Result<(), MLError> {
+        check_fit_input(X_batch, y_batch)?;
+        check_batch_features(self.n_features_in(), X_batch)?;
+        let (classes, labels) = partial_fit_classes(self.target_classes.as_ref(), classes, y_batch)?;
+        self.check_parameters(classes.len())?;
+        if self.target_classes.is_none() {
+            self.target_class_distributions = vec![0.0 ; classes.len()];
+            self.means_and_std_devs = vec![vec![(0.0_f32 , 0.0_f32) ; X_batch.cols()] ; classes.len()];
+            self.total_number_of_cases = 0;
+        }
+        self.accumulate(X_batch, &labels, &vec![1.0; labels.len()]);
+        self.target_classes = Some(classes);
+        Ok(())
+    }
 
+    ///adds the samples to the running(weighted) means and standard deviations of their classes.
+    fn accumulate(&mut self, X_batch : &Matrix, labels : &[usize], sample_weights : &[f32]) {
+        //(mean, weighted sum of squared deviations) and the total weight in f64 for the batch, only the standard deviation is stored.
+        let mut running: Vec<Vec<(f64, f64)>> = self.means_and_std_devs.iter().zip(&self.target_class_distributions).map(|(class, count)| {
+            class.iter().map(|(mean, std_dev)| (*mean as f64, (*std_dev as f64).powi(2) * *count as f64)).collect()
+        }).collect();
+        let mut totals: Vec<f64> = self.target_class_distributions.iter().map(|count| *count as f64).collect();
+        for ((row, label), weight) in X_batch.iter_rows().zip(labels).zip(sample_weights) {
+            if *weight == 0.0 {
+                continue;
+            }
+            let weight = *weight as f64;
+            totals[*label] += weight;
+            for ((mean, squared_deviations), value) in running[*label].iter_mut().zip(row) {
+                let delta = *value as f64 - *mean;
+                *mean += delta * weight / totals[*label];
+                *squared_deviations += weight * delta * (*value as f64 - *mean);
+            }
+        }
+        self.target_class_distributions = totals.iter().map(|total| *total as f32).collect();
+        for ((class, statistics), total) in self.means_and_std_devs.iter_mut().zip(&running).zip(&totals) {
+            for (stored, (mean, squared_deviations)) in class.iter_mut().zip(statistics) {
+                *stored = (*mean as f32, if *total > 0.0 { (squared_deviations / total).max(0.0).sqrt() as f32 } else { 0.0 });
+            }
         }
+        self.update_variance_floor();
+        self.total_number_of_cases += labels.len() as u32;
+    }
 
-        panic!("You cannot train gaussian_NB with float as a target, for this model type");
-    
+    ///`var_smoothing` times the largest variance, recomputed whenever either of them changes.
+    fn update_variance_floor(&mut self) {
+        let largest_variance = self.means_and_std_devs.iter().flatten().map(|(_, std_dev)| std_dev * std_dev).fold(0.0_f32, f32::max);
+        //with only constant features there is no scale to take the fraction of.
+        self.variance_floor = self.var_smoothing * if largest_variance > 0.0 { largest_variance } else { 1.0 };
     }
 
 }
 
 
-impl Predict for GaussianNb {
 
-    fn predict(&self, point : &Vec<f32>) -> ReturnType {
-        //first we are going to calculate the numerator of the posterior for all the class types.
 
+impl MLalgo for GaussianNb {
+    
+    fn fit(&mut self, X_train : &Matrix , y_train : &DataType) -> Result<(), MLError> {   
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
 
-        let mut present_max = (f32::MIN , -1_i32);//-1 to not have any bugs.
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
 
-        for i in 0..self.target_class_distributions.len() {
-            let mut product_of_conditional = 1.0_f32;
-            for j in 0..self.means_and_std_devs[0].len() {
-                product_of_conditional *= gaussian_distribution(self.means_and_std_devs[i][j].0 , self.means_and_std_devs[i][j].1, point[j]);
-            }
-            if (present_max.0 < (product_of_conditional * self.target_class_distributions[i] as f32)) {
-                present_max = (product_of_conditional * self.target_class_distributions[i] as f32 , i as i32 );
-            }
-            //dbg!(&present_max);
-        }
+    ///the means, variances and class priors are the weighted ones.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        let (classes, labels) = encode_classes(y_train, "gaussian_NB")?;
+        self.check_parameters(classes.len())?;
+
+        //a fresh model is just one big batch.
+        self.target_class_distributions = vec![0.0 ; classes.len()];
+        self.means_and_std_devs = vec![vec![(0.0_f32 , 0.0_f32) ; X_train.cols()] ; classes.len()];
+        self.total_number_of_cases = 0;
+        self.accumulate(X_train, &labels, sample_weights);
+        self.target_classes = Some(classes);
 
-        match self.target_classes.as_ref().unwrap() {
-            DataType::Category(temp) => {
-                return ReturnType::Category(temp[present_max.1 as usize]);
+        Ok(())
+
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.target_classes.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.target_classes.as_ref().map(|_| self.means_and_std_devs[0].len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("var_smoothing".to_owned(), ParamValue::Float(self.var_smoothing));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "var_smoothing" => {
+                    let var_smoothing = value.as_f32(name)?;
+                    check_var_smoothing(var_smoothing)?;
+                    self.var_smoothing = var_smoothing;
+                },
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
             }
-            DataType::Strings(temp) => {
-                return ReturnType::Strings(temp[present_max.1 as usize].clone());
-            },
-            _ => panic!("No fucking way this reached here"),
         }
-        
+        self.update_variance_floor();
+        Ok(())
+    }
+
+}
+
+
+impl Predict for GaussianNb {
+
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        self.try_predict(point).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    fn try_predict(&self, point : &[f32]) -> Result<ReturnType, MLError> {
+        check_predict_input(self, point)?;
+        let joint_log_likelihood = self.joint_log_likelihood(point)?;
+        Ok(best_class(self.target_classes.as_ref().unwrap(), &joint_log_likelihood))
     }
 
 }
@@ -200,8 +252,50 @@ impl GaussianNb {
     }
 }
 
-fn gaussian_distribution(mean : f32 , sigma : f32 , x : f32) -> f32 {
-    return ((0.3989422_f32/sigma)*(2.71828182_f32.powf(-0.5_f32 * ((x - mean) / sigma).powf(2.0))));
+fn default_var_smoothing() -> f32 {
+    1e-9
 }
 
+fn check_var_smoothing(var_smoothing : f32) -> Result<(), MLError> {
+    if !(var_smoothing >= 0.0 && var_smoothing.is_finite()) {
+        return Err(MLError::InvalidParameter(format!("var_smoothing needs to be >= 0, got {}", var_smoothing)));
+    }
+    Ok(())
+}
 
+///`None` classes(not fitted yet) only checks the values, the number of priors is checked at the next fit.
+fn check_class_prior(class_prior : Option<&Vec<f32>>, number_of_classes : Option<usize>) -> Result<(), MLError> {
+    match class_prior {
+        Some(prior) => log_priors(&vec![1.0; number_of_classes.unwrap_or(prior.len())], Some(prior)).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+
+
+impl Estimator for GaussianNb {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        self.predict_proba(point).ok()
+    }
+}
+
+impl Persist for GaussianNb {
+    const MODEL_TYPE: &'static str = "GaussianNb";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let number_of_classes = match &self.target_classes {
+            //an unfitted model has nothing learned to check.
+            None => return Ok(()),
+            Some(DataType::Category(temp)) => temp.len(),
+            Some(DataType::Strings(temp)) => temp.len(),
+            _ => return Err(PersistError::InvalidShape("target classes must be categories or strings".to_owned())),
+        };
+        ensure(number_of_classes > 0, "a fitted model needs atleast one class")?;
+        ensure(self.target_class_distributions.len() == number_of_classes, "class distribution does not match the number of classes")?;
+        ensure(self.class_prior.as_ref().map_or(true, |prior| prior.len() == number_of_classes), "class priors do not match the number of classes")?;
+        ensure(self.means_and_std_devs.len() == number_of_classes, "mean and standard deviation rows do not match the number of classes")?;
+        let number_of_features = self.means_and_std_devs.first().map_or(0, |class| class.len());
+        ensure(self.means_and_std_devs.iter().all(|class| class.len() == number_of_features), "every class needs the same number of features")?;
+        Ok(())
+    }
+}
@@ -1,16 +1,22 @@
-use std::collections::{HashMap, hash_map::Entry};
-use crate::{data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::{MLalgo, Predict}};
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType}, trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input, partial_fit_classes, check_batch_features}, n_dimen::matrix::Matrix, persistence::persistence::{Persist, PersistError, ensure}};
+use super::common::{encode_classes, check_alpha, log_priors, probabilities, best_class};
 
 
 ///Mainly used when the features represent counts or frequencies of different categories.
 /// -for example: like classifying document type, etc...
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MultinomialNb {
+    ///additive smoothing, 1 is Laplace smoothing and values below 1 Lidstone smoothing.
+    pub alpha : f32,
+    ///fixed class priors in the order of the sorted classes, `None` uses the class frequencies.
+    pub class_prior : Option<Vec<f32>>,
     target_classes: Option<DataType>,//we store all the unique target classes , order sensitive. we are going to follow the same order for storing the other parameters.
-    target_class_distributions: Vec<usize>,
+    target_class_distributions: Vec<f32>,
     total_number_of_cases: usize,
-    count_bin: Vec<Vec<HashMap<i32, usize>>>,
-    word_count_bin: Vec<i64>,//here we are going to store the total number of words in each class so we need not calculate the probabilities before predicting.
-    //taking i64 just in case.
+    feature_counts: Vec<Vec<f32>>,//the summed counts of every feature in each class.
+    class_log_prior: Vec<f32>,
+    feature_log_prob: Vec<Vec<f32>>,//we store the smoothed log probabilities so we need not calculate them before predicting.
 }
 
 ///creating the multinomial_NB object.
@@ -18,168 +24,213 @@ pub fn multinomial_NB() -> MultinomialNb {
     println!("WARNING! This algorithm assumes that your data represents frequency(assumes the values are integers)");
 
     MultinomialNb {
+        alpha: 1.0,
+        class_prior: None,
         target_classes: None,
         target_class_distributions: vec![],
         total_number_of_cases: 0,
-        count_bin: vec![vec![]],
-        word_count_bin: vec![]
+        feature_counts: vec![vec![]],
+        class_log_prior: vec![],
+        feature_log_prob: vec![],
     }
 }
 
 
 
-//TODO -- the functions reallly have big if else statements which is not good but i am not finding any way to make it better.
-impl MLalgo for MultinomialNb {
-    
-    ///Method to be called on the multinomial_NB struct , will fit the model according to the given data.
-    ///assumes the data is the frequency of something occuring so, will be treated as an integer.
-    fn fit(&mut self, X_train : &Vec<Vec<f32>>, y_train : &DataType) {
-
-        if let DataType::Category(temp) = y_train {
-            
-            //creating a hashmap and giving a index to each different category.
-            let mut counter: HashMap<u8, usize> = HashMap::new();
-            let mut count = 0_usize;
-            for i in temp {
-                if !counter.contains_key(i) {
-                    counter.insert(*i, count);
-                    count += 1;
-                }
-            }
+impl MultinomialNb {
 
-            let mut output_main = vec![vec![HashMap::new()] ; counter.len()];
-            let mut distribution_count = vec![0_usize ; counter.len()];
-            let mut word_count = vec![0_i64 ; counter.len()];
-
-            //noting down the number of times each feature appeared in each class.
-            //and also counting the number of data points in each class and number of total word counts of al features in each class.
-            for ( i , row ) in X_train.iter().enumerate() {
-                let &index = counter.get(&temp[i]).unwrap();
-                distribution_count[index] += 1;
-                for (j , &element) in row.iter().enumerate() {
-                    word_count[index] += element as i64;//this is to count the probabilities while prediction.
-                    //will calculate the sum of frequencies for each class.
-                    //entering the values in the hashmap.
-                    match output_main[index][j].entry(element as i32) {
-                        Entry::Occupied(mut entry) => {
-                            *entry.get_mut() += 1_usize;
-                        },
-                        Entry::Vacant(entry) => {
-                            entry.insert(1_usize);
-                        },
-                    }
-                }
-            }
+    pub fn set_alpha(&mut self, alpha : f32) {
+        self.alpha = alpha;
+    }
 
-            let mut in_order_keys: Vec<u8> = vec![0 ; counter.len()];
-            for (k , v) in &counter {
-                in_order_keys[*v] = *k;
-            }
+    pub fn set_class_prior(&mut self, class_prior : Option<Vec<f32>>) {
+        self.class_prior = class_prior;
+    }
 
-            self.count_bin = output_main;
-            self.target_classes = Some(DataType::Category(in_order_keys));
-            self.total_number_of_cases = X_train.len().try_into().unwrap();
-            self.target_class_distributions = distribution_count.iter().map(|x| *x as usize).collect();
-            self.word_count_bin = word_count;
-
-            return;
-
-        } else if let DataType::Strings(temp) = y_train {
-                
-            //creating a hashmap and giving a index to each different category.
-            let mut counter: HashMap<String, usize> = HashMap::new();
-            let mut count = 0_usize;
-            for i in temp {
-                if !counter.contains_key(i) {
-                    counter.insert(i.to_string() , count);
-                    count += 1;
-                }
-            }
+    pub fn classes(&self) -> Option<&DataType> {
+        self.target_classes.as_ref()
+    }
 
-            let mut output_main = vec![vec![HashMap::new()] ; counter.len()];
-            let mut distribution_count = vec![0_usize ; counter.len()];
-            let mut word_count = vec![0_i64 ; counter.len()];
-
-            //noting down the number of times each feature appeared in each class.
-            //and also counting the number of data points in each class and number of total word counts of al features in each class.
-            for ( i , row ) in X_train.iter().enumerate() {
-                let &index = counter.get(&temp[i]).unwrap();
-                distribution_count[index] += 1;
-                for (j , &element) in row.iter().enumerate() {
-                    word_count[index] += element as i64;//this is to count the probabilities while prediction.
-                    //will calculate the sum of frequencies for each class.
-                    //entering the values in the hashmap.
-                    match output_main[index][j].entry(element as i32) {
-                        Entry::Occupied(mut entry) => {
-                            *entry.get_mut() += 1_usize;
-                        },
-                        Entry::Vacant(entry) => {
-                            entry.insert(1_usize);
-                        },
-                    }
-                }
-            }
+    ///(weighted) number of training samples of every class, in the order of `classes()`.
+    pub fn class_counts(&self) -> &Vec<f32> {
+        &self.target_class_distributions
+    }
 
-            let mut in_order_keys: Vec<String> = vec![String::new() ; counter.len()];
-            for (k , v) in &counter {
-                in_order_keys[*v] = k.to_string();
-            }
-            
-            self.count_bin = output_main;
-            self.target_classes = Some(DataType::Strings(in_order_keys));
-            self.total_number_of_cases = X_train.len().try_into().unwrap();
-            self.target_class_distributions = distribution_count.iter().map(|x| *x as usize).collect();
-            self.word_count_bin = word_count;
+    ///`feature_counts()[class][feature]`, the summed feature values of every class.
+    pub fn feature_counts(&self) -> &Vec<Vec<f32>> {
+        &self.feature_counts
+    }
+
+    ///the log of the class priors used in the predictions.
+    pub fn class_log_prior(&self) -> &Vec<f32> {
+        &self.class_log_prior
+    }
 
-            return;
+    ///`feature_log_prob()[class][feature]`, the smoothed log P(feature | class).
+    pub fn feature_log_prob(&self) -> &Vec<Vec<f32>> {
+        &self.feature_log_prob
+    }
 
-        } else {
-            panic!("You cannot train gaussian_NB with float as a target, for this model type");
+    ///updates the model with a new batch instead of retraining on everything seen so far.
+    ///The first call needs the full list of `classes`(a batch does not have to contain all of them), later calls can pass `None`.
+    ///The counts simply add up, so the result is the same as one `fit` on all the batches.
+    pub fn partial_fit(&mut self, X_batch : &Matrix, y_batch : &DataType, classes : Option<&DataType>) -> Result<(), MLError> {
+        check_fit_input(X_batch, y_batch)?;
+        check_parameters(self.alpha, X_batch)?;
+        check_batch_features(self.n_features_in(), X_batch)?;
+        let (classes, labels) = partial_fit_classes(self.target_classes.as_ref(), classes, y_batch)?;
+        log_priors(&vec![1.0; classes.len()], self.class_prior.as_ref())?;
+        if self.target_classes.is_none() {
+            self.reset(classes.len(), X_batch.cols());
         }
-        
+        self.accumulate(X_batch, &labels, &vec![1.0; labels.len()]);
+        self.update_log_probabilities()?;
+        self.target_classes = Some(classes);
+        Ok(())
     }
 
-    //we need to implement another kind of fit for which we can use the
-    //using the kernel smoothing technique
-    /* fn smooth_fit(&mut self, X_train : &Vec<Vec<f32>>, y_train : &DataType) {
-        
-    } */
+    fn reset(&mut self, number_of_classes : usize, number_of_features : usize) {
+        self.feature_counts = vec![vec![0.0 ; number_of_features] ; number_of_classes];
+        self.target_class_distributions = vec![0.0 ; number_of_classes];
+        self.total_number_of_cases = 0;
+    }
+
+    ///noting down the(weighted) total count of each feature in each class, and also counting the data points in each class.
+    fn accumulate(&mut self, X_batch : &Matrix, labels : &[usize], sample_weights : &[f32]) {
+        for ((row, &index), weight) in X_batch.iter_rows().zip(labels).zip(sample_weights) {
+            self.target_class_distributions[index] += weight;
+            self.feature_counts[index].iter_mut().zip(row).for_each(|(count, value)| *count += weight * value);
+        }
+        self.total_number_of_cases += labels.len();
+    }
+
+    ///recomputes the log probabilities from the counts.
+    fn update_log_probabilities(&mut self) -> Result<(), MLError> {
+        self.class_log_prior = log_priors(&self.target_class_distributions, self.class_prior.as_ref())?;
+        let alpha = self.alpha.max(1e-10);
+        self.feature_log_prob = self.feature_counts.iter().map(|counts| {
+            let total = counts.iter().sum::<f32>() + alpha * counts.len() as f32;
+            counts.iter().map(|count| ((count + alpha) / total).ln()).collect()
+        }).collect();
+        Ok(())
+    }
+
+    ///log P(class) + log P(point | class) for every class.
+    pub fn joint_log_likelihood(&self, x : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, x);
+        self.feature_log_prob.iter().enumerate().map(|(i, log_prob)| {
+            //classes that were declared to partial_fit but have not been seen yet.
+            if self.target_class_distributions[i] == 0.0 {
+                return f32::NEG_INFINITY;
+            }
+            self.class_log_prior[i] + x.iter().zip(log_prob).map(|(value, log_prob)| value * log_prob).sum::<f32>()
+        }).collect()
+    }
+
+    ///class probabilities in the order of `classes()`.
+    pub fn predict_proba(&self, x : &[f32]) -> Vec<f32> {
+        probabilities(&self.joint_log_likelihood(x))
+    }
 
 }
 
-//TODO -- way too many type castings, please improve it the code looks messy as shit.
+fn check_parameters(alpha : f32, X : &Matrix) -> Result<(), MLError> {
+    check_alpha(alpha)?;
+    if X.as_slice().iter().any(|value| !(*value >= 0.0)) {
+        return Err(MLError::InvalidParameter("multinomial_NB needs non negative features".to_owned()));
+    }
+    Ok(())
+}
 
-impl Predict for MultinomialNb {
+
+impl MLalgo for MultinomialNb {
     
-    fn predict (&self, x : &Vec<f32>) -> ReturnType {
+    ///Method to be called on the multinomial_NB struct , will fit the model according to the given data.
+    ///assumes the data is the frequency of something occuring, so it needs to be non negative.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///every sample adds its counts times its weight.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        check_parameters(self.alpha, X_train)?;
+        let (classes, labels) = encode_classes(y_train, "multinomial_NB")?;
+        log_priors(&vec![1.0; classes.len()], self.class_prior.as_ref())?;
+
+        //a fresh model is just one big batch.
+        self.reset(classes.len(), X_train.cols());
+        self.accumulate(X_train, &labels, sample_weights);
+        self.update_log_probabilities()?;
+        self.target_classes = Some(classes);
+
+        Ok(())
         
-        let mut present_max = (f32::MIN , -1_i32);//-1 to not have any bugs.
-
-        for (i , bin_size) in self.target_class_distributions.iter().enumerate() {
-            //initializing with the class priors.
-            let mut product_of_conditional = *bin_size as f32 / self.total_number_of_cases as f32;
-            for (j , element) in self.count_bin[i].iter().enumerate() {
-                product_of_conditional *= match element.get(&(x[j] as i32)) {
-                    Some(temp) => (((*temp as f32) * (x[j]))/(self.word_count_bin[i] as f32)).powf(x[j]),
-                    None => (1.0 / (self.word_count_bin[i] as f32 + (self.count_bin[0].len() as f32))).powf(x[j]),
-                };
-
-                if product_of_conditional > present_max.0 {
-                    present_max = (product_of_conditional , i.try_into().unwrap());
-                }      
-            }
-        }
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.target_classes.is_some()
+    }
 
-        match self.target_classes.as_ref().unwrap() {
-            DataType::Category(temp) => {
-                return ReturnType::Category(temp[present_max.1 as usize]);
+    fn n_features_in(&self) -> Option<usize> {
+        self.target_classes.as_ref().map(|_| self.feature_counts[0].len())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "alpha" => self.alpha = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
             }
-            DataType::Strings(temp) => {
-                return ReturnType::Strings(temp[present_max.1 as usize].clone());
-            },
-            _ => panic!("First train this data then use the predict method, and also you can only train this data on categorical or string targets"),
         }
+        check_alpha(self.alpha)
+    }
+
+}
 
+impl Predict for MultinomialNb {
+    
+    fn predict (&self, x : &[f32]) -> ReturnType {
+        let joint_log_likelihood = self.joint_log_likelihood(x);
+        best_class(self.target_classes.as_ref().unwrap(), &joint_log_likelihood)
     }
 
-}
\ No newline at end of file
+}
+impl Estimator for MultinomialNb {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+impl Persist for MultinomialNb {
+    const MODEL_TYPE: &'static str = "MultinomialNb";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        let number_of_classes = match &self.target_classes {
+            //an unfitted model has nothing learned to check.
+            None => return Ok(()),
+            Some(DataType::Category(temp)) => temp.len(),
+            Some(DataType::Strings(temp)) => temp.len(),
+            _ => return Err(PersistError::InvalidShape("target classes must be categories or strings".to_owned())),
+        };
+        ensure(number_of_classes > 0, "a fitted model needs atleast one class")?;
+        ensure(self.target_class_distributions.len() == number_of_classes, "class distribution does not match the number of classes")?;
+        ensure(self.feature_counts.len() == number_of_classes, "feature counts do not match the number of classes")?;
+        ensure(self.class_log_prior.len() == number_of_classes && self.feature_log_prob.len() == number_of_classes, "log probabilities do not match the number of classes")?;
+        let number_of_features = self.feature_counts.first().map_or(0, |class| class.len());
+        ensure(self.feature_counts.iter().chain(&self.feature_log_prob).all(|class| class.len() == number_of_features), "every class needs the same number of features")?;
+        Ok(())
+    }
+}
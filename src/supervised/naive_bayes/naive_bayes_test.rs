@@ -1,6 +1,8 @@
 use std::time;
 
-use crate::{file_handling::read_from::read_csv, evaluation::accuracy::accuracy_score, supervised::naive_bayes::gaussian_NB::GaussianNb, trait_definition::MLalgo};
+use crate::{file_handling::read_from::read_csv, evaluation::accuracy::accuracy_score, supervised::naive_bayes::{gaussian_NB::GaussianNb, multinomial_NB::{multinomial_NB, MultinomialNb},
+    bernoulli_NB::BernoulliNb, complement_NB::ComplementNb, categorical_NB::CategoricalNb}, persistence::persistence::Persist,
+    trait_definition::{MLalgo, Predict, MLError, Params, ParamValue}, n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType}};
 
 
 
@@ -16,7 +18,7 @@ fn test_sample_gaussian() {
     let h = data.train_test_split(0.8, 4 , true);
     println!("splitting the data : {:?}", start_time.elapsed());
     let mut hava = GaussianNb::new();
-    hava.fit(&h.0, &h.1);
+    hava.fit(&h.0, &h.1).unwrap();
     println!("fitting the data : {:?}", start_time.elapsed());
     dbg!(accuracy_score(&hava, &h.2, &h.3));
     print!("{:?}", start_time.elapsed());
@@ -28,4 +30,305 @@ fn test_sample_multinomial() {
     let df = read_csv("", true, false).unwrap();
     let h = df.train_test_split(0.4, 0, true);
     print!("{:?}", start_time.elapsed());   
-}
\ No newline at end of file
+}
+
+
+fn two_clusters() -> (Matrix, DataType) {
+    let X_train = Matrix::from(vec![vec![1.0, 2.0], vec![1.0, 3.0], vec![2.0, 2.0], vec![7.0, 8.0], vec![8.0, 8.0], vec![7.0, 9.0]]);
+    (X_train, DataType::Category(vec![0, 0, 0, 1, 1, 1]))
+}
+
+#[test]
+fn unfitted_model_refuses_to_predict() {
+    let model = GaussianNb::new();
+    assert!(!model.is_fitted());
+    assert_eq!(model.n_features_in(), None);
+    assert_eq!(model.try_predict(&[1.0, 2.0]), Err(MLError::NotFitted));
+}
+
+#[test]
+fn fitted_model_checks_the_number_of_features() {
+    let (X_train, y_train) = two_clusters();
+    let mut model = GaussianNb::new();
+    model.fit(&X_train, &y_train).unwrap();
+    assert!(model.is_fitted());
+    assert_eq!(model.n_features_in(), Some(2));
+    assert_eq!(model.try_predict(&[1.0, 2.0, 3.0]), Err(MLError::FeatureMismatch { expected: 2, found: 3 }));
+    assert_eq!(model.try_predict(&[7.5, 8.5]), Ok(ReturnType::Category(1)));
+}
+
+#[test]
+#[should_panic(expected = "not fitted")]
+fn predict_panics_when_not_fitted() {
+    GaussianNb::new().predict(&[1.0, 2.0]);
+}
+
+#[test]
+fn fit_rejects_bad_input() {
+    let (X_train, _) = two_clusters();
+    let mut model = GaussianNb::new();
+    assert_eq!(model.fit(&X_train, &DataType::Category(vec![0, 1])), Err(MLError::SampleMismatch { samples: 6, targets: 2 }));
+    assert!(matches!(model.fit(&X_train, &DataType::Floats(vec![0.0; 6])), Err(MLError::InvalidTarget(_))));
+    assert_eq!(model.fit(&Matrix::new(0, 0), &DataType::Category(vec![])), Err(MLError::EmptyInput));
+    assert!(!model.is_fitted());
+}
+
+#[test]
+fn multinomial_fits_every_feature() {
+    let X_train = Matrix::from(vec![vec![3.0, 0.0, 1.0], vec![2.0, 0.0, 0.0], vec![0.0, 4.0, 1.0], vec![0.0, 3.0, 2.0]]);
+    let mut model = multinomial_NB();
+    model.fit(&X_train, &DataType::Category(vec![0, 0, 1, 1])).unwrap();
+    assert_eq!(model.n_features_in(), Some(3));
+    assert!(model.try_predict(&[0.0, 3.0, 1.0]).is_ok());
+}
+
+#[test]
+fn unknown_parameters_are_rejected() {
+    let mut model = GaussianNb::new();
+    assert_eq!(model.get_params().keys().collect::<Vec<_>>(), vec!["var_smoothing"]);
+    let mut params = Params::new();
+    params.insert("alpha".to_owned(), ParamValue::Float(1.0));
+    assert_eq!(model.set_params(&params), Err(MLError::InvalidParameter("unknown parameter 'alpha'".to_owned())));
+}
+
+
+//the vocabulary is [free, money, meeting, report, lunch], "spam" mails talk about money, "work" mails about meetings.
+fn word_presence() -> (Matrix, DataType) {
+    let X_train = Matrix::from(vec![
+        vec![1.0, 1.0, 0.0, 0.0, 0.0], vec![1.0, 1.0, 0.0, 0.0, 1.0], vec![0.0, 1.0, 0.0, 0.0, 0.0],
+        vec![0.0, 0.0, 1.0, 1.0, 0.0], vec![0.0, 0.0, 1.0, 0.0, 1.0], vec![0.0, 0.0, 1.0, 1.0, 1.0], vec![0.0, 0.0, 0.0, 1.0, 0.0],
+    ]);
+    let y_train = DataType::Strings(["spam", "spam", "spam", "work", "work", "work", "work"].iter().map(|s| s.to_string()).collect());
+    (X_train, y_train)
+}
+
+#[test]
+fn bernoulli_uses_presence_and_absence() {
+    let (X_train, y_train) = word_presence();
+    let mut model = BernoulliNb::new();
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.predict(&[3.0, 0.0, 0.0, 0.0, 0.0]), ReturnType::Strings("spam".to_owned()));
+    assert_eq!(model.predict(&[0.0, 0.0, 0.0, 0.0, 1.0]), ReturnType::Strings("work".to_owned()));
+    let probabilities = model.predict_proba(&[1.0, 1.0, 0.0, 0.0, 0.0]);
+    assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5 && probabilities[0] > 0.9);
+
+    //with the threshold at 2 only counts above 2 are present, so a single "free" says nothing anymore.
+    model.set_binarize(Some(2.0));
+    model.set_class_prior(Some(vec![0.5, 0.5]));
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.predict_proba(&[1.0, 0.0, 0.0, 0.0, 0.0]), model.predict_proba(&[0.0; 5]));
+
+    model.set_class_prior(Some(vec![0.3, 0.3]));
+    assert!(matches!(model.fit(&X_train, &y_train), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn complement_handles_imbalanced_classes() {
+    //ten big class documents and two small class ones.
+    let mut rows = vec![vec![5.0, 1.0, 1.0, 0.0]; 10];
+    rows.extend(vec![vec![0.0, 1.0, 1.0, 4.0]; 2]);
+    let mut labels = vec![0_u8; 10];
+    labels.extend([1, 1]);
+    let (X_train, y_train) = (Matrix::from(rows), DataType::Category(labels));
+    let mut model = ComplementNb::new();
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.predict(&[0.0, 1.0, 0.0, 2.0]), ReturnType::Category(1));
+    assert_eq!(model.predict(&[2.0, 0.0, 1.0, 0.0]), ReturnType::Category(0));
+    model.set_norm(true);
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.predict(&[0.0, 1.0, 0.0, 2.0]), ReturnType::Category(1));
+
+    assert!(matches!(model.fit(&Matrix::from(vec![vec![-1.0]]), &DataType::Category(vec![0])), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn categorical_learns_a_distribution_per_feature() {
+    //feature 0 is the colour(0 red, 1 green, 2 yellow), feature 1 the size(0 small, 1 big).
+    let X_train = Matrix::from(vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![0.0, 0.0], vec![1.0, 1.0], vec![2.0, 1.0], vec![2.0, 1.0]]);
+    let y_train = DataType::Strings(["cherry", "cherry", "cherry", "melon", "melon", "melon"].iter().map(|s| s.to_string()).collect());
+    let mut model = CategoricalNb::new();
+    model.set_alpha(0.5);
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.n_categories(), vec![3, 2]);
+    assert_eq!(model.predict(&[0.0, 0.0]), ReturnType::Strings("cherry".to_owned()));
+    assert_eq!(model.predict(&[2.0, 1.0]), ReturnType::Strings("melon".to_owned()));
+    //a colour never seen before leaves the decision to the size.
+    assert_eq!(model.predict(&[7.0, 1.0]), ReturnType::Strings("melon".to_owned()));
+
+    let restored = CategoricalNb::from_json(&model.to_json().unwrap()).unwrap();
+    assert_eq!(restored.predict_proba(&[1.0, 0.0]), model.predict_proba(&[1.0, 0.0]));
+    assert!(matches!(model.fit(&Matrix::from(vec![vec![0.5]]), &DataType::Category(vec![0])), Err(MLError::InvalidParameter(_))));
+    //codes past the limit would need a huge dense table(or overflow it), they are refused.
+    for code in [1e30, 70_000.0] {
+        assert!(matches!(model.fit(&Matrix::from(vec![vec![code]]), &DataType::Category(vec![0])), Err(MLError::InvalidParameter(_))));
+    }
+}
+
+#[test]
+fn partial_fit_in_batches_matches_fit() {
+    let (X_train, y_train) = two_clusters();
+    let classes = DataType::Category(vec![0, 1]);
+    let batches = [(vec![vec![1.0, 2.0], vec![7.0, 8.0]], vec![0, 1]), (vec![vec![1.0, 3.0], vec![2.0, 2.0]], vec![0, 0]), (vec![vec![8.0, 8.0], vec![7.0, 9.0]], vec![1, 1])];
+
+    let mut full = GaussianNb::new();
+    full.fit(&X_train, &y_train).unwrap();
+    let mut incremental = GaussianNb::new();
+    for (index, (rows, labels)) in batches.iter().enumerate() {
+        let declared = if index == 0 { Some(&classes) } else { None };
+        incremental.partial_fit(&Matrix::from(rows.clone()), &DataType::Category(labels.clone()), declared).unwrap();
+    }
+    for point in [[1.5, 2.5], [7.5, 8.5], [4.0, 5.0]] {
+        assert_eq!(incremental.predict(&point), full.predict(&point));
+    }
+
+    let mut full = BernoulliNb::new();
+    full.fit(&X_train, &y_train).unwrap();
+    let mut incremental = BernoulliNb::new();
+    let mut multinomial = multinomial_NB();
+    for (rows, labels) in batches.iter() {
+        incremental.partial_fit(&Matrix::from(rows.clone()), &DataType::Category(labels.clone()), Some(&classes)).unwrap();
+        multinomial.partial_fit(&Matrix::from(rows.clone()), &DataType::Category(labels.clone()), Some(&classes)).unwrap();
+    }
+    assert_eq!(incremental.predict_proba(&[1.0, 0.0]), full.predict_proba(&[1.0, 0.0]));
+    assert_eq!(multinomial.predict(&[7.0, 9.0]), ReturnType::Category(1));
+}
+
+#[test]
+fn partial_fit_checks_the_classes() {
+    let batch = Matrix::from(vec![vec![0.0, 1.0], vec![2.0, 1.0]]);
+    let mut model = CategoricalNb::new();
+    assert!(matches!(model.partial_fit(&batch, &DataType::Category(vec![0, 1]), None), Err(MLError::InvalidParameter(_))));
+    assert!(matches!(model.partial_fit(&batch, &DataType::Category(vec![0, 0]), Some(&DataType::Category(vec![0, 0]))), Err(MLError::InvalidParameter(_))));
+
+    //class 2 is declared but only shows up in the second batch.
+    let classes = DataType::Category(vec![0, 1, 2]);
+    model.partial_fit(&batch, &DataType::Category(vec![0, 1]), Some(&classes)).unwrap();
+    assert_eq!(model.n_categories(), vec![3, 2]);
+    assert!(matches!(model.partial_fit(&batch, &DataType::Category(vec![0, 3]), None), Err(MLError::InvalidTarget(_))));
+    assert!(matches!(model.partial_fit(&batch, &DataType::Category(vec![0, 1]), Some(&DataType::Category(vec![0, 1]))), Err(MLError::InvalidParameter(_))));
+    assert_eq!(model.partial_fit(&Matrix::from(vec![vec![1.0]]), &DataType::Category(vec![0]), None), Err(MLError::FeatureMismatch { expected: 2, found: 1 }));
+    model.partial_fit(&Matrix::from(vec![vec![4.0, 0.0], vec![4.0, 0.0]]), &DataType::Category(vec![2, 2]), None).unwrap();
+    assert_eq!(model.n_categories(), vec![5, 2]);
+    assert_eq!(model.predict(&[4.0, 0.0]), ReturnType::Category(2));
+
+    //an unseen class never wins for the models that do not look at the priors.
+    let mut complement = ComplementNb::new();
+    complement.partial_fit(&batch, &DataType::Category(vec![0, 1]), Some(&classes)).unwrap();
+    assert_ne!(complement.predict(&[0.0, 5.0]), ReturnType::Category(2));
+    let mut gaussian = GaussianNb::new();
+    gaussian.partial_fit(&Matrix::from(vec![vec![0.0, 1.0], vec![2.0, 3.0]]), &DataType::Category(vec![0, 0]), Some(&classes)).unwrap();
+    assert_eq!(gaussian.predict(&[1.0, 2.0]), ReturnType::Category(0));
+    //not even with a prior that favours it.
+    let mut bernoulli = BernoulliNb::new();
+    bernoulli.set_class_prior(Some(vec![0.01, 0.01, 0.98]));
+    bernoulli.partial_fit(&batch, &DataType::Category(vec![0, 1]), Some(&classes)).unwrap();
+    assert_eq!(bernoulli.joint_log_likelihood(&[0.0, 1.0])[2], f32::NEG_INFINITY);
+    assert_ne!(bernoulli.predict(&[0.0, 1.0]), ReturnType::Category(2));
+    let mut categorical = CategoricalNb::new();
+    categorical.set_class_prior(Some(vec![0.01, 0.01, 0.98]));
+    categorical.partial_fit(&batch, &DataType::Category(vec![0, 1]), Some(&classes)).unwrap();
+    assert_ne!(categorical.predict(&[0.0, 1.0]), ReturnType::Category(2));
+}
+
+#[test]
+fn gaussian_survives_constant_features() {
+    //the second feature never changes inside a class, without the variance floor that is a division by zero.
+    let X_train = Matrix::from(vec![vec![1.0, 5.0], vec![2.0, 5.0], vec![6.0, 9.0], vec![7.0, 9.0]]);
+    let y_train = DataType::Category(vec![0, 0, 1, 1]);
+    let mut model = GaussianNb::new();
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.predict(&[1.5, 5.0]), ReturnType::Category(0));
+    assert!(model.joint_log_likelihood(&[1.5, 6.0]).unwrap().iter().all(|value| value.is_finite()));
+    assert_eq!(model.class_counts(), &vec![2.0, 2.0]);
+    assert_eq!(model.means(), vec![vec![1.5, 5.0], vec![6.5, 9.0]]);
+    assert!(model.variances()[0][1] > 0.0 && (model.variances()[0][0] - 0.25).abs() < 1e-6);
+
+    //a strong prior moves a point in the middle to the favoured class.
+    let X_middle = Matrix::from(vec![vec![1.0], vec![2.0], vec![6.0], vec![7.0]]);
+    model.set_class_prior(Some(vec![0.001, 0.999])).unwrap();
+    model.fit(&X_middle, &y_train).unwrap();
+    let probabilities = model.predict_proba(&[4.0]).unwrap();
+    assert!(probabilities[1] > 0.99);
+    //bad priors are refused right away, a fitted model would not be able to predict with them.
+    assert!(matches!(model.set_class_prior(Some(vec![1.0])), Err(MLError::InvalidParameter(_))));
+    assert!(matches!(model.set_class_prior(Some(vec![0.5, 0.6])), Err(MLError::InvalidParameter(_))));
+    assert_eq!(model.class_prior, Some(vec![0.001, 0.999]));
+    //the field is public, priors set on it directly only fail once they are used.
+    model.class_prior = Some(vec![1.0]);
+    assert!(matches!(model.try_predict(&[4.0]), Err(MLError::InvalidParameter(_))));
+    assert!(matches!(model.fit(&X_train, &y_train), Err(MLError::InvalidParameter(_))));
+    let mut unfitted = GaussianNb::new();
+    unfitted.set_class_prior(Some(vec![0.2, 0.3, 0.5])).unwrap();
+    assert!(matches!(unfitted.set_class_prior(Some(vec![-0.5, 1.5])), Err(MLError::InvalidParameter(_))));
+
+    let mut params = Params::new();
+    params.insert("var_smoothing".to_owned(), ParamValue::Float(1e-3));
+    model.set_params(&params).unwrap();
+    assert_eq!(model.var_smoothing, 1e-3);
+    for bad in [-1.0, f32::NAN] {
+        params.insert("var_smoothing".to_owned(), ParamValue::Float(bad));
+        assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+        assert!(matches!(model.set_var_smoothing(bad), Err(MLError::InvalidParameter(_))));
+    }
+    assert_eq!(model.var_smoothing, 1e-3);
+    //the floor follows the smoothing without refitting, the largest variance of the last fit is 0.25.
+    assert!((model.variances()[0][0] - (0.25 + 0.25e-3)).abs() < 1e-6);
+    model.set_class_prior(None).unwrap();
+    let before = model.predict_proba(&[3.0]).unwrap();
+    model.set_var_smoothing(1.0).unwrap();
+    assert!((model.variances()[0][0] - 0.5).abs() < 1e-6);
+    assert!(model.predict_proba(&[3.0]).unwrap()[1] > before[1]);
+}
+
+#[test]
+fn multinomial_smoothing_and_priors() {
+    let X_train = Matrix::from(vec![vec![3.0, 0.0, 1.0], vec![2.0, 0.0, 0.0], vec![0.0, 4.0, 1.0], vec![0.0, 3.0, 2.0]]);
+    let y_train = DataType::Category(vec![0, 0, 1, 1]);
+    let mut model = multinomial_NB();
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.feature_counts(), &vec![vec![5.0, 0.0, 1.0], vec![0.0, 7.0, 3.0]]);
+    //(5 + 1) / (6 + 3)
+    assert!((model.feature_log_prob()[0][0] - (6.0_f32 / 9.0).ln()).abs() < 1e-6);
+    assert_eq!(model.predict(&[1.0, 0.0, 0.0]), ReturnType::Category(0));
+    assert_eq!(model.predict(&[0.0, 2.0, 1.0]), ReturnType::Category(1));
+
+    //without smoothing a feature never seen in a class rules the class out.
+    model.set_alpha(0.0);
+    model.fit(&X_train, &y_train).unwrap();
+    assert!(model.predict_proba(&[0.0, 1.0, 0.0])[0] < 1e-6);
+    model.set_alpha(1.0);
+    model.set_class_prior(Some(vec![0.999, 0.001]));
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.predict(&[0.0, 0.0, 1.0]), ReturnType::Category(0));
+    assert!((model.class_log_prior()[0] - 0.999_f32.ln()).abs() < 1e-6);
+
+    assert!(matches!(model.fit(&Matrix::from(vec![vec![-1.0]]), &DataType::Category(vec![0])), Err(MLError::InvalidParameter(_))));
+    let restored = MultinomialNb::from_json(&{ model.fit(&X_train, &y_train).unwrap(); model.to_json().unwrap() }).unwrap();
+    assert_eq!(restored.predict_proba(&[1.0, 1.0, 1.0]), model.predict_proba(&[1.0, 1.0, 1.0]));
+}
+
+#[test]
+fn sample_weights_count_like_repeated_samples() {
+    let X_train = Matrix::from(vec![vec![3.0, 0.0, 1.0], vec![2.0, 0.0, 0.0], vec![0.0, 4.0, 1.0], vec![0.0, 3.0, 2.0]]);
+    let y_train = DataType::Category(vec![0, 0, 1, 1]);
+    let weights = [2.0, 1.0, 0.0, 1.0];
+    let X_repeated = Matrix::from(vec![vec![3.0, 0.0, 1.0], vec![3.0, 0.0, 1.0], vec![2.0, 0.0, 0.0], vec![0.0, 3.0, 2.0]]);
+    let y_repeated = DataType::Category(vec![0, 0, 0, 1]);
+
+    let (mut weighted, mut repeated) = (multinomial_NB(), multinomial_NB());
+    weighted.fit_weighted(&X_train, &y_train, &weights).unwrap();
+    repeated.fit(&X_repeated, &y_repeated).unwrap();
+    assert_eq!(weighted.class_counts(), repeated.class_counts());
+    assert_eq!(weighted.feature_counts(), repeated.feature_counts());
+
+    let (mut weighted, mut repeated) = (GaussianNb::new(), GaussianNb::new());
+    weighted.fit_weighted(&X_train, &y_train, &weights).unwrap();
+    repeated.fit(&X_repeated, &y_repeated).unwrap();
+    assert_eq!(weighted.class_counts(), &vec![3.0, 1.0]);
+    for (a, b) in weighted.predict_proba(&[1.0, 1.0, 1.0]).unwrap().iter().zip(repeated.predict_proba(&[1.0, 1.0, 1.0]).unwrap()) {
+        assert!((a - b).abs() < 1e-4);
+    }
+
+    assert!(matches!(weighted.fit_weighted(&X_train, &y_train, &[1.0; 3]), Err(MLError::SampleMismatch { .. })));
+    assert!(matches!(BernoulliNb::new().fit_weighted(&X_train, &y_train, &[1.0, f32::NAN, 1.0, 1.0]), Err(MLError::InvalidParameter(_))));
+}
@@ -0,0 +1,325 @@
+//! #Random forest#
+//!
+//! An ensemble of CART trees, each one grown on a bootstrap sample of the training set and looking at a random subset of the
+//! features at every split. Classification forests average the class probabilities of the trees, regression forests the predictions.
+//!
+//! The trees are trained in parallel with rayon. Every tree draws its bootstrap sample and feature subsets from its own seed,
+//! derived from the forest's `seed`, so the same seed gives the same forest whatever the number of threads.
+//!
+//! A bootstrap sample is given to the tree as sample weights(how many times each sample was drawn), so `min_samples_split` and
+//! `min_samples_leaf` count distinct samples.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix,
+    evaluation::accuracy::r2,
+    supervised::decision_trees::decision_trees::{DecisionTree, Criterion, MaxFeatures},
+    persistence::persistence::{Persist, PersistError, ensure}};
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RandomForest {
+    pub n_estimators : usize,
+    pub criterion : Criterion,
+    pub max_depth : Option<usize>,
+    pub min_samples_split : usize,
+    pub min_samples_leaf : usize,
+    ///features considered at every split of every tree.
+    pub max_features : MaxFeatures,
+    ///grow every tree on a bootstrap sample, otherwise every tree sees the whole training set(and only the feature subsampling differs).
+    pub bootstrap : bool,
+    ///estimate the generalisation score from the samples each tree did not see, needs `bootstrap`.
+    pub oob_score : bool,
+    pub seed : u64,
+    trees : Vec<DecisionTree>,
+    classes : Option<DataType>,
+    n_features : usize,
+    oob_score_value : Option<f32>,
+}
+
+impl RandomForest {
+
+    ///a classification forest, Gini criterion and sqrt(n_features) features per split.
+    pub fn classifier(n_estimators : usize) -> RandomForest {
+        RandomForest::new(n_estimators, Criterion::Gini, MaxFeatures::Sqrt)
+    }
+
+    ///a regression forest, MSE criterion and all the features at every split.
+    pub fn regressor(n_estimators : usize) -> RandomForest {
+        RandomForest::new(n_estimators, Criterion::MSE, MaxFeatures::All)
+    }
+
+    pub fn new(n_estimators : usize, criterion : Criterion, max_features : MaxFeatures) -> RandomForest {
+        RandomForest {
+            n_estimators,
+            criterion,
+            max_depth: None,
+            min_samples_split: 2,
+            min_samples_leaf: 1,
+            max_features,
+            bootstrap: true,
+            oob_score: false,
+            seed: 0,
+            trees: vec![],
+            classes: None,
+            n_features: 0,
+            oob_score_value: None,
+        }
+    }
+
+    pub fn set_max_depth(&mut self, max_depth : Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn set_min_samples(&mut self, min_samples_split : usize, min_samples_leaf : usize) {
+        self.min_samples_split = min_samples_split;
+        self.min_samples_leaf = min_samples_leaf;
+    }
+
+    pub fn set_max_features(&mut self, max_features : MaxFeatures) {
+        self.max_features = max_features;
+    }
+
+    pub fn set_bootstrap(&mut self, bootstrap : bool, oob_score : bool) {
+        self.bootstrap = bootstrap;
+        self.oob_score = oob_score;
+    }
+
+    pub fn set_seed(&mut self, seed : u64) {
+        self.seed = seed;
+    }
+
+    pub fn trees(&self) -> &[DecisionTree] {
+        &self.trees
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///out-of-bag accuracy(classification) or R²(regression), `None` if `oob_score` was off.
+    pub fn oob_score(&self) -> Option<f32> {
+        self.oob_score_value
+    }
+
+    ///mean of the impurity based importances of the trees, normalised to sum to 1.
+    ///Biased towards features with many distinct values, see `evaluation::importance::permutation_importance` for an unbiased alternative.
+    pub fn feature_importances(&self) -> Vec<f32> {
+        let mut importances = vec![0.0_f32; self.n_features];
+        for tree in &self.trees {
+            for (total, importance) in importances.iter_mut().zip(tree.feature_importances()) {
+                *total += importance;
+            }
+        }
+        let total: f32 = importances.iter().sum();
+        if total > 0.0 {
+            importances.iter_mut().for_each(|importance| *importance /= total);
+        }
+        importances
+    }
+
+    ///mean of the class probabilities of the trees, in the order of `classes()`. Panics for a regression forest.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        let mut probabilities = vec![0.0_f32; self.classes.as_ref().expect("predict_proba needs a classification forest").len()];
+        for tree in &self.trees {
+            for (total, p) in probabilities.iter_mut().zip(tree.predict_proba(point)) {
+                *total += p / self.trees.len() as f32;
+            }
+        }
+        probabilities
+    }
+
+    fn predict_value(&self, point : &[f32]) -> f32 {
+        self.trees.iter().map(|tree| match tree.predict(point) {
+            ReturnType::Floats(value) => value,
+            _ => unreachable!(),
+        }).sum::<f32>() / self.trees.len() as f32
+    }
+
+    ///the seed of the `index`'th tree, splitmix64 so that neighbouring seeds give unrelated streams.
+    fn tree_seed(&self, index : usize) -> u64 {
+        let mut z = self.seed.wrapping_add((index as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if self.n_estimators == 0 {
+            return Err(MLError::InvalidParameter("a forest needs atleast one tree".to_owned()));
+        }
+        if self.oob_score && !self.bootstrap {
+            return Err(MLError::InvalidParameter("the out-of-bag score needs bootstrap sampling".to_owned()));
+        }
+        Ok(())
+    }
+
+    ///out-of-bag score, every sample is predicted only by the trees that did not see it.
+    fn compute_oob_score(&self, X_train : &Matrix, y_train : &DataType, bags : &[Vec<f32>]) -> Option<f32> {
+        let mut used = vec![];
+        match &self.classes {
+            Some(_) => {
+                let (_, targets) = y_train.unique_classes().unwrap();
+                let mut correct = 0;
+                for (sample, point) in X_train.iter_rows().enumerate() {
+                    let mut votes: Option<Vec<f32>> = None;
+                    for (tree, bag) in self.trees.iter().zip(bags).filter(|(_, bag)| bag[sample] == 0.0) {
+                        let probabilities = tree.predict_proba(point);
+                        match votes.as_mut() {
+                            Some(votes) => votes.iter_mut().zip(&probabilities).for_each(|(vote, p)| *vote += p),
+                            None => votes = Some(probabilities),
+                        }
+                    }
+                    if let Some(votes) = votes {
+                        let best = votes.iter().enumerate().fold(0, |best, (index, vote)| if *vote > votes[best] { index } else { best });
+                        correct += (best == targets[sample]) as usize;
+                        used.push(sample);
+                    }
+                }
+                (!used.is_empty()).then(|| correct as f32 / used.len() as f32)
+            },
+            None => {
+                let targets = match y_train { DataType::Floats(temp) => temp, _ => unreachable!() };
+                let mut y_true = vec![];
+                let mut y_pred = vec![];
+                for (sample, point) in X_train.iter_rows().enumerate() {
+                    let predictions: Vec<f32> = self.trees.iter().zip(bags).filter(|(_, bag)| bag[sample] == 0.0)
+                        .map(|(tree, _)| match tree.predict(point) { ReturnType::Floats(value) => value, _ => unreachable!() })
+                        .collect();
+                    if !predictions.is_empty() {
+                        y_true.push(targets[sample]);
+                        y_pred.push(predictions.iter().sum::<f32>() / predictions.len() as f32);
+                    }
+                }
+                (!y_true.is_empty()).then(|| r2(&y_true, &y_pred))
+            },
+        }
+    }
+}
+
+
+impl MLalgo for RandomForest {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    ///every tree gets the bootstrap counts times the sample weights.
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        self.validate_params()?;
+
+        let number_of_samples = X_train.rows();
+        //the bootstrap samples as weights, how many times each sample was drawn.
+        let bags: Vec<Vec<f32>> = (0..self.n_estimators).map(|index| {
+            let mut bag = vec![if self.bootstrap { 0.0 } else { 1.0 }; number_of_samples];
+            if self.bootstrap {
+                let mut rng = fastrand::Rng::with_seed(self.tree_seed(index));
+                for _ in 0..number_of_samples {
+                    bag[rng.usize(0..number_of_samples)] += 1.0;
+                }
+            }
+            bag
+        }).collect();
+
+        let trees: Result<Vec<DecisionTree>, MLError> = bags.par_iter().enumerate().map(|(index, bag)| {
+            let mut tree = DecisionTree::new(self.criterion);
+            tree.set_max_depth(self.max_depth);
+            tree.set_min_samples(self.min_samples_split, self.min_samples_leaf);
+            //a different stream than the bootstrap one.
+            tree.set_max_features(self.max_features, !self.tree_seed(index));
+            let weights: Vec<f32> = bag.iter().zip(sample_weights).map(|(count, weight)| count * weight).collect();
+            tree.fit_weighted(X_train, y_train, &weights)?;
+            Ok(tree)
+        }).collect();
+
+        self.trees = trees?;
+        self.classes = self.trees[0].classes().cloned();
+        self.n_features = X_train.cols();
+        self.oob_score_value = if self.oob_score { self.compute_oob_score(X_train, y_train, &bags) } else { None };
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.trees.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    ///`max_depth` is -1 when there is no limit.
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("n_estimators".to_owned(), ParamValue::Int(self.n_estimators as i64));
+        params.insert("max_depth".to_owned(), ParamValue::Int(self.max_depth.map_or(-1, |depth| depth as i64)));
+        params.insert("min_samples_split".to_owned(), ParamValue::Int(self.min_samples_split as i64));
+        params.insert("min_samples_leaf".to_owned(), ParamValue::Int(self.min_samples_leaf as i64));
+        params.insert("bootstrap".to_owned(), ParamValue::Bool(self.bootstrap));
+        params.insert("oob_score".to_owned(), ParamValue::Bool(self.oob_score));
+        params.insert("seed".to_owned(), ParamValue::Int(self.seed as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "n_estimators" => self.n_estimators = value.as_usize(name)?,
+                "max_depth" => self.max_depth = match value {
+                    ParamValue::Int(depth) if *depth < 0 => None,
+                    _ => Some(value.as_usize(name)?),
+                },
+                "min_samples_split" => self.min_samples_split = value.as_usize(name)?,
+                "min_samples_leaf" => self.min_samples_leaf = value.as_usize(name)?,
+                "bootstrap" => self.bootstrap = value.as_bool(name)?,
+                "oob_score" => self.oob_score = value.as_bool(name)?,
+                "seed" => self.seed = value.as_usize(name)? as u64,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for RandomForest {
+    ///the class with the highest mean probability, or the mean of the tree predictions for regression.
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        match &self.classes {
+            Some(classes) => {
+                let probabilities = self.predict_proba(point);
+                let best = probabilities.iter().enumerate().fold(0, |best, (index, p)| if *p > probabilities[best] { index } else { best });
+                classes.value_at(best)
+            },
+            None => {
+                assert_predict_input(self, point);
+                ReturnType::Floats(self.predict_value(point))
+            },
+        }
+    }
+}
+
+impl Estimator for RandomForest {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        self.classes.is_some().then(|| self.predict_proba(point))
+    }
+}
+
+impl Persist for RandomForest {
+    const MODEL_TYPE: &'static str = "RandomForest";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        for tree in &self.trees {
+            tree.validate()?;
+            ensure(tree.n_features_in() == Some(self.n_features), "a tree was fitted on a different number of features")?;
+            ensure(tree.classes().map(|classes| classes.len()) == self.classes.as_ref().map(|classes| classes.len()), "a tree has different classes than the forest")?;
+        }
+        Ok(())
+    }
+}
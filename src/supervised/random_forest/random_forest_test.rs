@@ -0,0 +1,110 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType}, trait_definition::{MLalgo, Predict, MLError},
+    evaluation::{accuracy::{score, r2_score}, importance::permutation_importance}, persistence::persistence::Persist};
+use super::random_forest::RandomForest;
+
+
+///the class depends on the first two of the five features(x0 + x1 > 1), the rest is noise.
+fn classification_data(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for _ in 0..samples {
+        let row: Vec<f32> = (0..5).map(|_| rng.f32()).collect();
+        targets.push((row[0] + row[1] > 1.0) as u8);
+        rows.push(row);
+    }
+    (Matrix::from(rows), DataType::Category(targets))
+}
+
+///y = sin(3 x0) + x1^2, x2 is noise.
+fn regression_data(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for _ in 0..samples {
+        let row: Vec<f32> = (0..3).map(|_| rng.f32() * 2.0 - 1.0).collect();
+        targets.push((3.0 * row[0]).sin() + row[1] * row[1]);
+        rows.push(row);
+    }
+    (Matrix::from(rows), DataType::Floats(targets))
+}
+
+#[test]
+fn classification_forest_generalises() {
+    let (X_train, y_train) = classification_data(1, 300);
+    let (X_test, y_test) = classification_data(2, 200);
+    let mut forest = RandomForest::classifier(50);
+    forest.set_bootstrap(true, true);
+    forest.set_seed(42);
+    forest.fit(&X_train, &y_train).unwrap();
+
+    assert_eq!(forest.trees().len(), 50);
+    let test_accuracy = score(&forest, &X_test, &y_test);
+    assert!(test_accuracy > 0.85, "test accuracy {}", test_accuracy);
+    let oob = forest.oob_score().unwrap();
+    assert!((oob - test_accuracy).abs() < 0.1, "oob {} test {}", oob, test_accuracy);
+
+    let probabilities = forest.predict_proba(&[0.9, 0.9, 0.5, 0.5, 0.5]);
+    assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    assert_eq!(forest.predict(&[0.9, 0.9, 0.5, 0.5, 0.5]), ReturnType::Category(1));
+}
+
+#[test]
+fn the_same_seed_gives_the_same_forest() {
+    let (X, y) = classification_data(3, 100);
+    let fit = |seed| {
+        let mut forest = RandomForest::classifier(10);
+        forest.set_seed(seed);
+        forest.fit(&X, &y).unwrap();
+        forest
+    };
+    let (first, second, other) = (fit(7), fit(7), fit(8));
+    assert_eq!(first.to_json().unwrap(), second.to_json().unwrap());
+    assert_ne!(first.to_json().unwrap(), other.to_json().unwrap());
+}
+
+#[test]
+fn importances_find_the_informative_features() {
+    let (X, y) = classification_data(4, 300);
+    let mut forest = RandomForest::classifier(30);
+    forest.fit(&X, &y).unwrap();
+
+    let impurity = forest.feature_importances();
+    assert!((impurity.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    assert!(impurity[0] > impurity[2] && impurity[1] > impurity[4]);
+
+    let (means, std_devs) = permutation_importance(&forest, &X, &y, 3, 0);
+    assert_eq!(std_devs.len(), 5);
+    assert!(means[0] > 0.1 && means[1] > 0.1);
+    assert!(means[2..].iter().all(|mean| mean.abs() < 0.05), "{:?}", means);
+}
+
+#[test]
+fn regression_forest_and_oob_r2() {
+    let (X_train, y_train) = regression_data(5, 400);
+    let (X_test, y_test) = regression_data(6, 200);
+    let mut forest = RandomForest::regressor(40);
+    forest.set_bootstrap(true, true);
+    forest.fit(&X_train, &y_train).unwrap();
+
+    assert!(r2_score(&forest, &X_test, &y_test) > 0.8);
+    assert!(forest.oob_score().unwrap() > 0.7);
+    assert!(forest.feature_importances()[2] < 0.1);
+}
+
+#[test]
+fn bad_configurations_are_rejected() {
+    let (X, y) = classification_data(7, 20);
+    let mut forest = RandomForest::classifier(0);
+    assert!(matches!(forest.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+
+    let mut forest = RandomForest::classifier(5);
+    forest.set_bootstrap(false, true);
+    assert!(matches!(forest.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    assert_eq!(forest.try_predict(&[0.0; 5]), Err(MLError::NotFitted));
+
+    let mut regressor = RandomForest::regressor(5);
+    assert!(matches!(regressor.fit(&X, &y), Err(MLError::InvalidTarget(_))));
+}
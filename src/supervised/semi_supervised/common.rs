@@ -0,0 +1,20 @@
+//! The parts the semi-supervised models share : telling the labelled samples from the unlabelled ones.
+
+use crate::{data_frame::data_type::{DataType, UNLABELLED}, trait_definition::MLError};
+
+
+///the sorted classes of the labelled samples and, for every sample, the index of its class or `None` if it is `UNLABELLED`.
+pub(crate) fn split_labels(y : &DataType, model : &str) -> Result<(DataType, Vec<Option<usize>>), MLError> {
+    let targets = match y {
+        DataType::Category(temp) => temp,
+        _ => return Err(MLError::InvalidTarget(format!("{} needs category targets, with UNLABELLED for the samples without a label", model))),
+    };
+    let mut classes: Vec<u8> = targets.iter().copied().filter(|target| *target != UNLABELLED).collect();
+    classes.sort();
+    classes.dedup();
+    if classes.len() < 2 {
+        return Err(MLError::InvalidTarget(format!("{} needs labelled samples of atleast two classes, got {}", model, classes.len())));
+    }
+    let labels = targets.iter().map(|target| classes.binary_search(target).ok()).collect();
+    Ok((DataType::Category(classes), labels))
+}
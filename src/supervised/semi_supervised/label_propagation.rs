@@ -0,0 +1,546 @@
+//! #Label propagation and label spreading#
+//!
+//! Graph based semi-supervised classification : every sample(labelled or not) is a node, the edges are weighted by how
+//! close the samples are, and the labels flow along the edges until they settle.
+//! The unlabelled samples are marked with `UNLABELLED` in a `Category` target.
+//! * `LabelPropagation` -> every step a node takes the weighted mean of the class distributions of its neighbours, the
+//!   labelled nodes are clamped back to their labels.
+//! * `LabelSpreading` -> the edges are normalised symmetrically and every step a node keeps `1 - alpha` of its initial
+//!   label, so noisy labels can be overruled by their neighbours.
+//!
+//! `GraphKernel::Rbf` connects every pair of samples, which takes `n^2` memory, `GraphKernel::Knn` only the nearest ones
+//! and keeps them as lists of neighbours, so it scales to many more samples.
+//! After fitting, `transduction()` has the label given to every training sample. New points get the weighted mean of the
+//! distributions of the training samples the kernel connects them to, the nearest sample's one if it connects them to none.
+//!
+//! Sample weights scale the edges leaving a sample, so a sample of weight 2 pulls on its neighbours like two copies of it would,
+//! and a sample of weight 0 still gets a label but passes none on.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::{matrix::Matrix, linalg::matmul, distance::{DistanceMetric, squared_euclidean}, neighbours::{NeighbourIndex, IndexKind}},
+    persistence::persistence::{Persist, PersistError, ensure},
+    supervised::ensemble::common::argmax};
+use super::common::split_labels;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GraphKernel {
+    ///     exp(-gamma |x - y|^2) between every pair of samples.
+    Rbf { gamma : f32 },
+    ///     1 between a sample and each of its `n_neighbors` nearest samples(both ways), 0 otherwise.
+    Knn { n_neighbors : usize },
+}
+
+impl Default for GraphKernel {
+    fn default() -> Self {
+        GraphKernel::Rbf { gamma: 20.0 }
+    }
+}
+
+impl GraphKernel {
+    fn check(&self, samples : usize) -> Result<(), MLError> {
+        match self {
+            GraphKernel::Rbf { gamma } if !(*gamma > 0.0 && gamma.is_finite()) => Err(MLError::InvalidParameter(format!("gamma needs to be > 0, got {}", gamma))),
+            GraphKernel::Knn { n_neighbors } if *n_neighbors == 0 || *n_neighbors >= samples =>
+                Err(MLError::InvalidParameter(format!("n_neighbors needs to be in [1, {}], got {}", samples - 1, n_neighbors))),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_params(&self, params : &mut Params) {
+        match self {
+            GraphKernel::Rbf { gamma } => {
+                params.insert("kernel".to_owned(), ParamValue::Text("rbf".to_owned()));
+                params.insert("gamma".to_owned(), ParamValue::Float(*gamma));
+            },
+            GraphKernel::Knn { n_neighbors } => {
+                params.insert("kernel".to_owned(), ParamValue::Text("knn".to_owned()));
+                params.insert("n_neighbors".to_owned(), ParamValue::Int(*n_neighbors as i64));
+            },
+        }
+    }
+
+    ///`kernel` first(with its default values), then `gamma` or `n_neighbors` of that kernel. The other names are left to the model.
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        if let Some(value) = params.get("kernel") {
+            *self = match value.as_str("kernel")? {
+                "rbf" => GraphKernel::Rbf { gamma: 20.0 },
+                "knn" => GraphKernel::Knn { n_neighbors: 7 },
+                other => return Err(MLError::InvalidParameter(format!("unknown kernel '{}', use rbf or knn", other))),
+            };
+        }
+        for (name, value) in params {
+            match (name.as_str(), &mut *self) {
+                ("gamma", GraphKernel::Rbf { gamma }) => *gamma = value.as_f32(name)?,
+                ("n_neighbors", GraphKernel::Knn { n_neighbors }) => *n_neighbors = value.as_usize(name)?,
+                ("gamma", _) | ("n_neighbors", _) => return Err(MLError::InvalidParameter(format!("'{}' does not belong to the {:?} kernel", name, self))),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+
+///what both models learn from the graph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FittedGraph {
+    ///the kernel the graph was built with, new points are connected with it as well.
+    kernel : GraphKernel,
+    ///the training samples.
+    index : NeighbourIndex,
+    ///the class distribution of every training sample, one row per sample and one column per class.
+    distributions : Matrix,
+    ///how much every training sample pulls on its neighbours.
+    sample_weights : Vec<f32>,
+    classes : DataType,
+    n_iter : usize,
+}
+
+impl FittedGraph {
+
+    ///`alpha` is `None` for propagation and the share of the neighbours for spreading.
+    fn fit(kernel : GraphKernel, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32], alpha : Option<f32>, max_iter : usize, tol : f32) -> Result<FittedGraph, MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        let model = if alpha.is_some() { "label spreading" } else { "label propagation" };
+        let (classes, labels) = split_labels(y_train, model)?;
+        kernel.check(X_train.rows())?;
+        if let Some(alpha) = alpha {
+            if !(alpha > 0.0 && alpha < 1.0) {
+                return Err(MLError::InvalidParameter(format!("alpha needs to be in (0, 1), got {}", alpha)));
+            }
+        }
+        let index = NeighbourIndex::from_matrix(X_train, DistanceMetric::Euclidean, IndexKind::Auto).map_err(MLError::InvalidParameter)?;
+        let (samples, n_classes) = (X_train.rows(), classes.len());
+
+        let mut graph = Graph::new(kernel, &index, alpha.is_some());
+        //the column of a sample holds the edges its distribution flows along.
+        graph.scale_edges(|_, column, value| value * sample_weights[column]);
+        let degrees = graph.degrees();
+        //propagation walks the rows, spreading uses D^-1/2 W D^-1/2. A node without edges stays where it is.
+        graph.scale_edges(|row, column, value| {
+            let scale = match alpha {
+                None => degrees[row],
+                Some(_) => (degrees[row] * degrees[column]).sqrt(),
+            };
+            if scale > 0.0 { value / scale } else { 0.0 }
+        });
+
+        let mut initial = Matrix::new(samples, n_classes);
+        labels.iter().enumerate().filter_map(|(sample, label)| label.map(|label| (sample, label))).for_each(|(sample, label)| initial.set(sample, label, 1.0));
+        let mut distributions = initial.clone();
+        let mut n_iter = 0;
+        while n_iter < max_iter {
+            let mut next = graph.times(&distributions);
+            match alpha {
+                None => labels.iter().enumerate().filter(|(_, label)| label.is_some()).for_each(|(sample, _)| next.row_mut(sample).copy_from_slice(initial.row(sample))),
+                Some(alpha) => next.as_mut_slice().iter_mut().zip(initial.as_slice()).for_each(|(value, initial)| *value = alpha * *value + (1.0 - alpha) * initial),
+            }
+            let change: f32 = next.as_slice().iter().zip(distributions.as_slice()).map(|(new, old)| (new - old).abs()).sum();
+            distributions = next;
+            n_iter += 1;
+            if change < tol {
+                break;
+            }
+        }
+        distributions.iter_rows_mut().for_each(normalise);
+        Ok(FittedGraph { kernel, index, distributions, sample_weights: sample_weights.to_vec(), classes, n_iter })
+    }
+
+    ///the weighted mean of the distributions of the training samples the kernel connects the point to.
+    fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        let mut probabilities = vec![0.0_f32; self.classes.len()];
+        let mut add = |sample : usize, weight : f32| probabilities.iter_mut().zip(self.distributions.row(sample))
+            .for_each(|(total, p)| *total += self.sample_weights[sample] * weight * p);
+        match self.kernel {
+            GraphKernel::Rbf { gamma } => self.index.points().iter_rows().enumerate().for_each(|(sample, other)| add(sample, (-gamma * squared_euclidean(point, other)).exp())),
+            GraphKernel::Knn { n_neighbors } => self.index.k_nearest(point, n_neighbors).iter().for_each(|neighbour| add(neighbour.index, 1.0)),
+        }
+        if probabilities.iter().sum::<f32>() <= 0.0 {
+            let nearest = self.index.k_nearest(point, 1)[0].index;
+            probabilities.copy_from_slice(self.distributions.row(nearest));
+        }
+        normalise(&mut probabilities);
+        probabilities
+    }
+
+    fn transduction(&self) -> DataType {
+        let labels: Vec<usize> = self.distributions.iter_rows().map(argmax).collect();
+        match &self.classes {
+            DataType::Category(classes) => DataType::Category(labels.iter().map(|label| classes[*label]).collect()),
+            other => unreachable!("the classes of a graph model are categories, got {:?}", other),
+        }
+    }
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(matches!(self.classes, DataType::Category(_)), "the classes need to be categories")?;
+        self.index.validate().map_err(PersistError::InvalidShape)?;
+        ensure(self.kernel.check(self.index.len()).is_ok(), "the kernel does not fit the number of samples")?;
+        ensure(self.distributions.rows() == self.index.len(), "the number of distributions does not match the number of samples")?;
+        ensure(self.sample_weights.len() == self.index.len() && self.sample_weights.iter().all(|weight| *weight >= 0.0 && weight.is_finite()),
+            "there should be one finite weight >= 0 for every sample")?;
+        ensure(self.distributions.cols() == self.classes.len(), "the distributions do not match the number of classes")
+    }
+}
+
+///the edge weights of the training graph.
+enum Graph {
+    ///every pair of samples, for the rbf kernel.
+    Dense(Matrix),
+    ///the edges of every sample as (other sample, weight) sorted by the other sample, for the knn kernel.
+    Sparse(Vec<Vec<(usize, f32)>>),
+}
+
+impl Graph {
+
+    ///`self_loops` false leaves out the edge from a sample to itself.
+    fn new(kernel : GraphKernel, index : &NeighbourIndex, self_loops : bool) -> Graph {
+        let samples = index.len();
+        match kernel {
+            GraphKernel::Rbf { gamma } => {
+                let mut affinity = Matrix::new(samples, samples);
+                affinity.par_iter_rows_mut().enumerate().for_each(|(row, values)| {
+                    let point = index.point(row);
+                    values.iter_mut().zip(index.points().iter_rows()).for_each(|(value, other)| *value = (-gamma * squared_euclidean(point, other)).exp());
+                    if !self_loops {
+                        values[row] = 0.0;
+                    }
+                });
+                Graph::Dense(affinity)
+            },
+            GraphKernel::Knn { n_neighbors } => {
+                //the sample itself comes back as its own nearest neighbour, so one more is asked for.
+                let neighbours = index.k_nearest_batch(index.points(), n_neighbors + 1);
+                let mut edges: Vec<Vec<(usize, f32)>> = vec![vec![]; samples];
+                for (sample, nearest) in neighbours.iter().enumerate() {
+                    for neighbour in nearest.iter().filter(|neighbour| neighbour.index != sample).take(n_neighbors) {
+                        edges[sample].push((neighbour.index, 1.0));
+                        edges[neighbour.index].push((sample, 1.0));
+                    }
+                }
+                //two samples that are both among each other's neighbours are connected once.
+                edges.par_iter_mut().for_each(|row| {
+                    row.sort_unstable_by_key(|(other, _)| *other);
+                    row.dedup_by_key(|(other, _)| *other);
+                });
+                Graph::Sparse(edges)
+            },
+        }
+    }
+
+    ///replaces every edge weight with `scale(row, column, weight)`.
+    fn scale_edges(&mut self, scale : impl Fn(usize, usize, f32) -> f32 + Sync) {
+        match self {
+            Graph::Dense(affinity) => affinity.par_iter_rows_mut().enumerate().for_each(|(row, values)| {
+                values.iter_mut().enumerate().for_each(|(column, value)| *value = scale(row, column, *value));
+            }),
+            Graph::Sparse(edges) => edges.par_iter_mut().enumerate().for_each(|(row, values)| {
+                values.iter_mut().for_each(|(column, value)| *value = scale(row, *column, *value));
+            }),
+        }
+    }
+
+    ///the sum of the edge weights of every sample.
+    fn degrees(&self) -> Vec<f32> {
+        match self {
+            Graph::Dense(affinity) => affinity.iter_rows().map(|row| row.iter().sum()).collect(),
+            Graph::Sparse(edges) => edges.iter().map(|row| row.iter().map(|(_, value)| value).sum()).collect(),
+        }
+    }
+
+    ///the graph times the distributions, every row the weighted sum of the rows of its neighbours.
+    fn times(&self, distributions : &Matrix) -> Matrix {
+        match self {
+            Graph::Dense(affinity) => matmul(affinity, distributions),
+            Graph::Sparse(edges) => {
+                let mut product = Matrix::new(distributions.rows(), distributions.cols());
+                product.par_iter_rows_mut().zip(edges.par_iter()).for_each(|(values, row)| {
+                    for (other, weight) in row {
+                        values.iter_mut().zip(distributions.row(*other)).for_each(|(total, p)| *total += weight * p);
+                    }
+                });
+                product
+            },
+        }
+    }
+}
+
+///scales the values to sum to 1, uniform if they are all 0.
+fn normalise(values : &mut [f32]) {
+    let total: f32 = values.iter().sum();
+    let count = values.len() as f32;
+    values.iter_mut().for_each(|value| *value = if total > 0.0 { *value / total } else { 1.0 / count });
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelPropagation {
+    pub kernel : GraphKernel,
+    pub max_iter : usize,
+    ///stops once the distributions change less than this(summed over all the samples) in a step.
+    pub tol : f32,
+    fitted : Option<FittedGraph>,
+}
+
+impl LabelPropagation {
+
+    ///at most 1000 steps, `GraphKernel::default()` is rbf with gamma 20.
+    pub fn new(kernel : GraphKernel) -> LabelPropagation {
+        LabelPropagation {
+            kernel,
+            max_iter: 1000,
+            tol: 1e-3,
+            fitted: None,
+        }
+    }
+
+    pub fn set_kernel(&mut self, kernel : GraphKernel) {
+        self.kernel = kernel;
+    }
+
+    pub fn set_max_iter(&mut self, max_iter : usize, tol : f32) {
+        self.max_iter = max_iter;
+        self.tol = tol;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.fitted.as_ref().map(|fitted| &fitted.classes)
+    }
+
+    ///the class distribution of every training sample, one row per sample in the order of `classes()`.
+    pub fn label_distributions(&self) -> Option<&Matrix> {
+        self.fitted.as_ref().map(|fitted| &fitted.distributions)
+    }
+
+    ///the label every training sample ended up with, the unlabelled ones included.
+    pub fn transduction(&self) -> Option<DataType> {
+        self.fitted.as_ref().map(FittedGraph::transduction)
+    }
+
+    ///number of steps the labels took to settle.
+    pub fn n_iter(&self) -> usize {
+        self.fitted.as_ref().map_or(0, |fitted| fitted.n_iter)
+    }
+
+    ///the class probabilities in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.fitted.as_ref().unwrap().predict_proba(point)
+    }
+}
+
+impl Default for LabelPropagation {
+    fn default() -> Self {
+        LabelPropagation::new(GraphKernel::default())
+    }
+}
+
+impl MLalgo for LabelPropagation {
+    ///`y_train` are categories, `UNLABELLED` for the samples without a label.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        self.fitted = None;
+        self.fitted = Some(FittedGraph::fit(self.kernel, X_train, y_train, sample_weights, None, self.max_iter, self.tol)?);
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.as_ref().map(|fitted| fitted.index.dimensions())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        self.kernel.get_params(&mut params);
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        self.kernel.set_params(params)?;
+        for (name, value) in params {
+            match name.as_str() {
+                "kernel" | "gamma" | "n_neighbors" => {},
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predict for LabelPropagation {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let probabilities = self.predict_proba(point);
+        self.fitted.as_ref().unwrap().classes.value_at(argmax(&probabilities))
+    }
+}
+
+impl Estimator for LabelPropagation {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+impl Persist for LabelPropagation {
+    const MODEL_TYPE: &'static str = "LabelPropagation";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        self.fitted.as_ref().map_or(Ok(()), FittedGraph::validate)
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelSpreading {
+    pub kernel : GraphKernel,
+    ///how much of its neighbours a sample takes every step, the rest comes from its own initial label.
+    pub alpha : f32,
+    pub max_iter : usize,
+    ///stops once the distributions change less than this(summed over all the samples) in a step.
+    pub tol : f32,
+    fitted : Option<FittedGraph>,
+}
+
+impl LabelSpreading {
+
+    ///alpha 0.2, at most 30 steps.
+    pub fn new(kernel : GraphKernel) -> LabelSpreading {
+        LabelSpreading {
+            kernel,
+            alpha: 0.2,
+            max_iter: 30,
+            tol: 1e-3,
+            fitted: None,
+        }
+    }
+
+    pub fn set_kernel(&mut self, kernel : GraphKernel) {
+        self.kernel = kernel;
+    }
+
+    pub fn set_alpha(&mut self, alpha : f32) {
+        self.alpha = alpha;
+    }
+
+    pub fn set_max_iter(&mut self, max_iter : usize, tol : f32) {
+        self.max_iter = max_iter;
+        self.tol = tol;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.fitted.as_ref().map(|fitted| &fitted.classes)
+    }
+
+    ///the class distribution of every training sample, one row per sample in the order of `classes()`.
+    pub fn label_distributions(&self) -> Option<&Matrix> {
+        self.fitted.as_ref().map(|fitted| &fitted.distributions)
+    }
+
+    ///the label every training sample ended up with, the labelled ones can change too.
+    pub fn transduction(&self) -> Option<DataType> {
+        self.fitted.as_ref().map(FittedGraph::transduction)
+    }
+
+    ///number of steps the labels took to settle.
+    pub fn n_iter(&self) -> usize {
+        self.fitted.as_ref().map_or(0, |fitted| fitted.n_iter)
+    }
+
+    ///the class probabilities in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.fitted.as_ref().unwrap().predict_proba(point)
+    }
+}
+
+impl Default for LabelSpreading {
+    fn default() -> Self {
+        LabelSpreading::new(GraphKernel::default())
+    }
+}
+
+impl MLalgo for LabelSpreading {
+    ///`y_train` are categories, `UNLABELLED` for the samples without a label.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        self.fitted = None;
+        self.fitted = Some(FittedGraph::fit(self.kernel, X_train, y_train, sample_weights, Some(self.alpha), self.max_iter, self.tol)?);
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.fitted.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.fitted.as_ref().map(|fitted| fitted.index.dimensions())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        self.kernel.get_params(&mut params);
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        self.kernel.set_params(params)?;
+        for (name, value) in params {
+            match name.as_str() {
+                "kernel" | "gamma" | "n_neighbors" => {},
+                "alpha" => self.alpha = value.as_f32(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predict for LabelSpreading {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let probabilities = self.predict_proba(point);
+        self.fitted.as_ref().unwrap().classes.value_at(argmax(&probabilities))
+    }
+}
+
+impl Estimator for LabelSpreading {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
+
+impl Persist for LabelSpreading {
+    const MODEL_TYPE: &'static str = "LabelSpreading";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        self.fitted.as_ref().map_or(Ok(()), FittedGraph::validate)
+    }
+}
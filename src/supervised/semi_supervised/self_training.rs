@@ -0,0 +1,256 @@
+//! #Self-training#
+//!
+//! `SelfTrainingClassifier` wraps any classifier with probabilities and grows its training set with its own predictions :
+//! the classifier is fitted on the labelled samples, the unlabelled samples it is sure enough about get the predicted
+//! class as a pseudo-label, and it is fitted again, until no sample is added, every sample is labelled or `max_iter`
+//! rounds have run. The unlabelled samples are marked with `UNLABELLED` in a `Category` target.
+//! * `SelfTrainingCriterion::Threshold` -> every sample whose highest probability is above the threshold is added.
+//! * `SelfTrainingCriterion::KBest` -> the `k` samples with the highest probabilities are added every round.
+//!
+//! A wrong pseudo-label is learnt as if it was true, so a threshold that is too low can drift the classifier away.
+
+use serde::{Deserialize, Serialize};
+use crate::{data_frame::{data_type::{DataType, UNLABELLED, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix,
+    supervised::ensemble::common::{argmax, fit_member}};
+use super::common::split_labels;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SelfTrainingCriterion {
+    ///adds the samples whose highest probability is above the threshold.
+    Threshold(f32),
+    ///adds the `k` samples with the highest probabilities.
+    KBest(usize),
+}
+
+///why the last round was the last one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TerminationCondition {
+    MaxIterations,
+    ///no unlabelled sample was sure enough.
+    NoChange,
+    AllLabelled,
+}
+
+
+#[derive(Clone)]
+pub struct SelfTrainingClassifier {
+    pub criterion : SelfTrainingCriterion,
+    ///most rounds of pseudo-labelling, `None` for no limit.
+    pub max_iter : Option<usize>,
+    base : Box<dyn Estimator>,
+    ///`base` fitted on the labelled and pseudo-labelled samples of the last round.
+    estimator : Option<Box<dyn Estimator>>,
+    classes : Option<DataType>,
+    ///the class every training sample ended up with, `None` for the ones that were never labelled.
+    labels : Vec<Option<usize>>,
+    ///the round in which every training sample got its label, 0 for the labelled ones and `None` for the ones never labelled.
+    labeled_iter : Vec<Option<usize>>,
+    n_iter : usize,
+    termination_condition : Option<TerminationCondition>,
+}
+
+impl SelfTrainingClassifier {
+
+    ///self-trains the given classifier, which needs to give probabilities. Threshold 0.75 and at most 10 rounds by default.
+    pub fn new<E : Estimator + 'static>(estimator : E) -> SelfTrainingClassifier {
+        SelfTrainingClassifier {
+            criterion: SelfTrainingCriterion::Threshold(0.75),
+            max_iter: Some(10),
+            base: Box::new(estimator),
+            estimator: None,
+            classes: None,
+            labels: vec![],
+            labeled_iter: vec![],
+            n_iter: 0,
+            termination_condition: None,
+        }
+    }
+
+    pub fn set_criterion(&mut self, criterion : SelfTrainingCriterion) {
+        self.criterion = criterion;
+    }
+
+    pub fn set_max_iter(&mut self, max_iter : Option<usize>) {
+        self.max_iter = max_iter;
+    }
+
+    ///the classifier as it was given, unfitted.
+    pub fn base_estimator(&self) -> &dyn Estimator {
+        self.base.as_ref()
+    }
+
+    ///the classifier fitted in the last round, `None` before fitting.
+    pub fn estimator(&self) -> Option<&dyn Estimator> {
+        self.estimator.as_deref()
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///the label every training sample ended up with, `UNLABELLED` for the ones that were never sure enough.
+    pub fn transduction(&self) -> Option<DataType> {
+        let classes = match self.classes.as_ref()? {
+            DataType::Category(classes) => classes,
+            other => unreachable!("the classes of self-training are categories, got {:?}", other),
+        };
+        Some(DataType::Category(self.labels.iter().map(|label| label.map_or(UNLABELLED, |label| classes[label])).collect()))
+    }
+
+    ///the round in which every training sample got its label, 0 for the ones labelled from the start.
+    pub fn labeled_iter(&self) -> &[Option<usize>] {
+        &self.labeled_iter
+    }
+
+    ///number of rounds that added pseudo-labels.
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    pub fn termination_condition(&self) -> Option<TerminationCondition> {
+        self.termination_condition
+    }
+
+    ///the class probabilities of the last round's classifier, in the order of `classes()`.
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.estimator.as_ref().unwrap().probabilities(point).unwrap()
+    }
+
+    ///fits a copy of the classifier on the samples that have a label.
+    fn fit_labelled(&self, X_train : &Matrix, classes : &[u8], labels : &[Option<usize>], sample_weights : Option<&[f32]>) -> Result<Box<dyn Estimator>, MLError> {
+        let samples: Vec<usize> = (0..labels.len()).filter(|sample| labels[*sample].is_some()).collect();
+        let targets = DataType::Category(samples.iter().map(|sample| classes[labels[*sample].unwrap()]).collect());
+        let weights = sample_weights.map(|weights| samples.iter().map(|sample| weights[*sample]).collect::<Vec<f32>>());
+        let mut estimator = self.base.clone_box();
+        fit_member("the classifier", estimator.as_mut(), &X_train.select_rows(&samples), &targets, weights.as_deref())?;
+        Ok(estimator)
+    }
+
+    fn train(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : Option<&[f32]>) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        if let Some(sample_weights) = sample_weights {
+            check_sample_weights(sample_weights, X_train.rows())?;
+        }
+        match self.criterion {
+            SelfTrainingCriterion::Threshold(threshold) if !(0.0..1.0).contains(&threshold) =>
+                return Err(MLError::InvalidParameter(format!("the threshold needs to be in [0, 1), got {}", threshold))),
+            SelfTrainingCriterion::KBest(0) => return Err(MLError::InvalidParameter("k_best needs to be atleast 1".to_owned())),
+            _ => {},
+        }
+        let (classes, mut labels) = split_labels(y_train, "self-training")?;
+        let codes = match &classes {
+            DataType::Category(codes) => codes.clone(),
+            _ => unreachable!(),
+        };
+        self.estimator = None;
+        let mut labeled_iter: Vec<Option<usize>> = labels.iter().map(|label| label.map(|_| 0)).collect();
+        let mut n_iter = 0;
+        //every round is fitted on the labels so far, so the one that stops is already fitted on the final labels.
+        let (estimator, termination) = loop {
+            let estimator = self.fit_labelled(X_train, &codes, &labels, sample_weights)?;
+            let unlabelled: Vec<usize> = (0..labels.len()).filter(|sample| labels[*sample].is_none()).collect();
+            if unlabelled.is_empty() {
+                break (estimator, TerminationCondition::AllLabelled);
+            }
+            if self.max_iter.is_some_and(|max_iter| n_iter >= max_iter) {
+                break (estimator, TerminationCondition::MaxIterations);
+            }
+            //(sample, predicted class, its probability) of every unlabelled sample.
+            let mut candidates = unlabelled.iter().map(|sample| {
+                match estimator.probabilities(X_train.row(*sample)) {
+                    Some(probabilities) if probabilities.len() == classes.len() => {
+                        let class = argmax(&probabilities);
+                        Ok((*sample, class, probabilities[class]))
+                    },
+                    Some(probabilities) => Err(MLError::InvalidParameter(format!("the classifier gave {} probabilities for {} classes", probabilities.len(), classes.len()))),
+                    None => Err(MLError::InvalidParameter("the classifier has no probabilities to self-train with".to_owned())),
+                }
+            }).collect::<Result<Vec<(usize, usize, f32)>, MLError>>()?;
+            match self.criterion {
+                SelfTrainingCriterion::Threshold(threshold) => candidates.retain(|(_, _, probability)| *probability > threshold),
+                SelfTrainingCriterion::KBest(k) => {
+                    candidates.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.cmp(&b.0)));
+                    candidates.truncate(k);
+                },
+            }
+            if candidates.is_empty() {
+                break (estimator, TerminationCondition::NoChange);
+            }
+            n_iter += 1;
+            for (sample, class, _) in candidates {
+                labels[sample] = Some(class);
+                labeled_iter[sample] = Some(n_iter);
+            }
+        };
+        self.estimator = Some(estimator);
+        self.classes = Some(classes);
+        self.labels = labels;
+        self.labeled_iter = labeled_iter;
+        self.n_iter = n_iter;
+        self.termination_condition = Some(termination);
+        Ok(())
+    }
+}
+
+impl MLalgo for SelfTrainingClassifier {
+    ///`y_train` are categories, `UNLABELLED` for the samples without a label.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.train(X_train, y_train, None)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        self.base.supports_sample_weights()
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        self.train(X_train, y_train, Some(sample_weights))
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.estimator.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.estimator.as_ref().and_then(|estimator| estimator.n_features_in())
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        match self.criterion {
+            SelfTrainingCriterion::Threshold(threshold) => params.insert("threshold".to_owned(), ParamValue::Float(threshold)),
+            SelfTrainingCriterion::KBest(k) => params.insert("k_best".to_owned(), ParamValue::Int(k as i64)),
+        };
+        //0 stands for no limit.
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter.map_or(0, |max_iter| max_iter as i64)));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "threshold" => self.criterion = SelfTrainingCriterion::Threshold(value.as_f32(name)?),
+                "k_best" => self.criterion = SelfTrainingCriterion::KBest(value.as_usize(name)?),
+                "max_iter" => self.max_iter = Some(value.as_usize(name)?).filter(|max_iter| *max_iter > 0),
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predict for SelfTrainingClassifier {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        self.estimator.as_ref().unwrap().predict(point)
+    }
+}
+
+impl Estimator for SelfTrainingClassifier {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        Some(self.predict_proba(point))
+    }
+}
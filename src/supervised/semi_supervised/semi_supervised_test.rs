@@ -0,0 +1,190 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::{DataType, UNLABELLED}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue}, evaluation::accuracy::accuracy_score,
+    persistence::persistence::{Persist, PersistError},
+    supervised::{naive_bayes::gaussian_NB::GaussianNb, support_vector_machines::support_vector_machines::{Svc, Kernel}}};
+use super::{label_propagation::{LabelPropagation, LabelSpreading, GraphKernel},
+    self_training::{SelfTrainingClassifier, SelfTrainingCriterion, TerminationCondition}};
+
+
+///two interleaved half circles, the first `labelled` samples of every moon keep their label and the rest are `UNLABELLED`.
+///Returns the data, the partly labelled targets and all the true targets.
+fn moons(seed : u64, samples : usize, labelled : usize) -> (Matrix, DataType, DataType) {
+    let rng = fastrand::Rng::with_seed(seed);
+    let (mut rows, mut targets, mut truth) = (vec![], vec![], vec![]);
+    for sample in 0..samples {
+        let (moon, step) = (sample % 2, sample / 2);
+        let angle = std::f32::consts::PI * rng.f32();
+        let (x, y) = if moon == 0 { (angle.cos(), angle.sin()) } else { (1.0 - angle.cos(), 0.5 - angle.sin()) };
+        rows.push(vec![x + (rng.f32() - 0.5) * 0.1, y + (rng.f32() - 0.5) * 0.1]);
+        targets.push(if step < labelled { moon as u8 } else { UNLABELLED });
+        truth.push(moon as u8);
+    }
+    (Matrix::from(rows), DataType::Category(targets), DataType::Category(truth))
+}
+
+fn agreement(first : &DataType, second : &DataType) -> f32 {
+    match (first, second) {
+        (DataType::Category(first), DataType::Category(second)) =>
+            first.iter().zip(second).filter(|(a, b)| a == b).count() as f32 / first.len() as f32,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn label_propagation_follows_the_moons() {
+    let (X, y, truth) = moons(0, 300, 5);
+    for kernel in [GraphKernel::Knn { n_neighbors: 7 }, GraphKernel::Rbf { gamma: 20.0 }] {
+        let mut model = LabelPropagation::new(kernel);
+        assert!(!model.is_fitted());
+        model.fit(&X, &y).unwrap();
+        assert!(agreement(&model.transduction().unwrap(), &truth) > 0.95, "{:?}", kernel);
+        let distributions = model.label_distributions().unwrap();
+        assert_eq!(distributions.shape(), (300, 2));
+        assert!(distributions.iter_rows().all(|row| (row.iter().sum::<f32>() - 1.0).abs() < 1e-4));
+        //the labelled samples are clamped to their labels.
+        assert_eq!(distributions.row(0), &[1.0, 0.0]);
+        assert!(model.n_iter() > 0 && model.n_iter() <= 1000);
+        assert_eq!(model.predict(&[-1.0, 0.0]), ReturnType::Category(0));
+        assert_eq!(model.predict(&[2.0, 0.5]), ReturnType::Category(1));
+    }
+
+    let (X_test, _, truth_test) = moons(1, 200, 0);
+    let mut model = LabelPropagation::new(GraphKernel::Knn { n_neighbors: 7 });
+    model.fit(&X, &y).unwrap();
+    assert!(accuracy_score(&model, &X_test, &truth_test) > 0.95);
+    let restored = LabelPropagation::from_json(&model.to_json().unwrap()).unwrap();
+    assert!(X_test.iter_rows().all(|row| restored.predict_proba(row) == model.predict_proba(row)));
+    //the stored index is checked on load, an order pointing past the samples would be read out of bounds.
+    let mut value: serde_json::Value = serde_json::from_str(&model.to_json().unwrap()).unwrap();
+    value["model"]["fitted"]["index"]["order"][0] = serde_json::json!(300);
+    let error = LabelPropagation::from_json(&value.to_string()).err().unwrap();
+    assert!(matches!(error.downcast_ref::<PersistError>(), Some(PersistError::InvalidShape(_))));
+    //far from every sample the rbf kernel connects to none of them, the nearest one decides.
+    let mut model = LabelPropagation::new(GraphKernel::Rbf { gamma: 20.0 });
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.predict(&[-10.0, 0.0]), ReturnType::Category(0));
+}
+
+#[test]
+fn label_spreading_overrules_noisy_labels() {
+    let (X, y, truth) = moons(2, 300, 20);
+    //every sample is labelled, one of the first moon as the second.
+    let noisy = match &truth {
+        DataType::Category(targets) => { let mut targets = targets.clone(); targets[0] = 1; DataType::Category(targets) },
+        _ => unreachable!(),
+    };
+    let mut model = LabelSpreading::new(GraphKernel::Knn { n_neighbors: 7 });
+    model.set_alpha(0.9);
+    model.set_max_iter(100, 1e-3);
+    model.fit(&X, &noisy).unwrap();
+    let transduction = model.transduction().unwrap();
+    assert!(matches!(&transduction, DataType::Category(labels) if labels[0] == 0));
+    assert!(agreement(&transduction, &truth) > 0.95);
+    assert!(model.probabilities(&[0.0, 1.0]).unwrap()[0] > 0.5);
+
+    //propagation keeps the label it was given.
+    let mut propagation = LabelPropagation::new(GraphKernel::Knn { n_neighbors: 7 });
+    propagation.fit(&X, &noisy).unwrap();
+    assert!(matches!(propagation.transduction().unwrap(), DataType::Category(labels) if labels[0] == 1));
+
+    let mut params = Params::new();
+    params.insert("kernel".to_owned(), ParamValue::Text("rbf".to_owned()));
+    params.insert("gamma".to_owned(), ParamValue::Float(10.0));
+    params.insert("alpha".to_owned(), ParamValue::Float(0.5));
+    model.set_params(&params).unwrap();
+    assert_eq!((model.kernel, model.alpha), (GraphKernel::Rbf { gamma: 10.0 }, 0.5));
+    assert_eq!(model.get_params()["kernel"], ParamValue::Text("rbf".to_owned()));
+    let mut params = Params::new();
+    params.insert("n_neighbors".to_owned(), ParamValue::Int(3));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+
+    model.set_alpha(1.0);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    model.set_alpha(0.2);
+    assert!(matches!(model.fit(&X, &truth), Ok(())));
+    assert!(matches!(model.fit(&X, &DataType::Floats(vec![0.0; 300])), Err(MLError::InvalidTarget(_))));
+    assert!(matches!(model.fit(&X, &DataType::Category(vec![UNLABELLED; 300])), Err(MLError::InvalidTarget(_))));
+    assert!(!model.is_fitted());
+}
+
+#[test]
+fn graph_models_take_sample_weights() {
+    let (X, y, _) = moons(3, 100, 5);
+    let labels = match &y { DataType::Category(labels) => labels.clone(), _ => unreachable!() };
+    let mut weights = vec![1.0; 100];
+    weights[40] = 2.0;
+    weights[41] = 0.0;
+    //the same graph with sample 41 left out and sample 40 twice.
+    let rows: Vec<usize> = (0..100).filter(|sample| *sample != 41).chain([40]).collect();
+    let (X_copies, y_copies) = (X.select_rows(&rows), DataType::Category(rows.iter().map(|sample| labels[*sample]).collect()));
+    let close = |weighted : &Matrix, copies : &Matrix| rows[..99].iter().enumerate()
+        .all(|(position, sample)| weighted.row(*sample).iter().zip(copies.row(position)).all(|(a, b)| (a - b).abs() < 1e-4));
+
+    let mut weighted = LabelPropagation::new(GraphKernel::Rbf { gamma: 20.0 });
+    weighted.set_max_iter(200, 0.0);
+    let mut copies = weighted.clone();
+    weighted.fit_weighted(&X, &y, &weights).unwrap();
+    copies.fit(&X_copies, &y_copies).unwrap();
+    assert!(close(weighted.label_distributions().unwrap(), copies.label_distributions().unwrap()));
+    let restored = LabelPropagation::from_json(&weighted.to_json().unwrap()).unwrap();
+    assert_eq!(restored.predict_proba(&[0.5, 0.2]), weighted.predict_proba(&[0.5, 0.2]));
+    assert!((weighted.predict_proba(&[0.5, 0.2])[0] - copies.predict_proba(&[0.5, 0.2])[0]).abs() < 1e-4);
+
+    //spreading leaves out the self loops, so only the left out sample matches exactly.
+    weights[40] = 1.0;
+    let rows: Vec<usize> = (0..100).filter(|sample| *sample != 41).collect();
+    let mut weighted = LabelSpreading::new(GraphKernel::Rbf { gamma: 20.0 });
+    weighted.set_max_iter(200, 0.0);
+    let mut copies = weighted.clone();
+    weighted.fit_weighted(&X, &y, &weights).unwrap();
+    copies.fit(&X.select_rows(&rows), &DataType::Category(rows.iter().map(|sample| labels[*sample]).collect())).unwrap();
+    let (weighted, copies) = (weighted.label_distributions().unwrap(), copies.label_distributions().unwrap());
+    assert!(rows.iter().enumerate().all(|(position, sample)| weighted.row(*sample).iter().zip(copies.row(position)).all(|(a, b)| (a - b).abs() < 1e-4)));
+    assert!(matches!(LabelSpreading::default().fit_weighted(&X, &y, &[1.0; 3]), Err(MLError::SampleMismatch { .. })));
+}
+
+#[test]
+fn self_training_pseudo_labels_confident_samples() {
+    let (X, y, truth) = moons(3, 300, 10);
+    let mut model = SelfTrainingClassifier::new(GaussianNb::new());
+    model.fit(&X, &y).unwrap();
+    assert!(model.n_iter() >= 1);
+    let transduction = model.transduction().unwrap();
+    let labelled = match &transduction {
+        DataType::Category(labels) => labels.iter().filter(|label| **label != UNLABELLED).count(),
+        _ => unreachable!(),
+    };
+    assert!(labelled > 20);
+    assert!(model.labeled_iter().iter().take(20).all(|round| *round == Some(0)));
+    assert!(model.labeled_iter().iter().any(|round| *round == Some(1)));
+    assert!(accuracy_score(&model, &X, &truth) > 0.8);
+    assert!(matches!(model.classes(), Some(DataType::Category(classes)) if classes == &[0, 1]));
+
+    //k best adds exactly k samples a round.
+    model.set_criterion(SelfTrainingCriterion::KBest(15));
+    model.set_max_iter(Some(3));
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.termination_condition(), Some(TerminationCondition::MaxIterations));
+    assert_eq!(model.labeled_iter().iter().filter(|round| round.is_some()).count(), 20 + 3 * 15);
+    assert_eq!(model.labeled_iter().iter().filter(|round| **round == Some(3)).count(), 15);
+
+    //a fully labelled target has nothing to add.
+    model.fit(&X, &truth).unwrap();
+    assert_eq!((model.termination_condition(), model.n_iter()), (Some(TerminationCondition::AllLabelled), 0));
+
+    let mut params = Params::new();
+    params.insert("threshold".to_owned(), ParamValue::Float(0.99999));
+    params.insert("max_iter".to_owned(), ParamValue::Int(0));
+    model.set_params(&params).unwrap();
+    assert_eq!((model.criterion, model.max_iter), (SelfTrainingCriterion::Threshold(0.99999), None));
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.termination_condition(), Some(TerminationCondition::NoChange));
+
+    let mut model = SelfTrainingClassifier::new(Svc::new(Kernel::Linear, 1.0));
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    model.set_criterion(SelfTrainingCriterion::Threshold(1.5));
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    assert!(!model.is_fitted());
+}
@@ -0,0 +1,759 @@
+//! #Stochastic gradient descent linear models#
+//!
+//! `LinearSgd` fits `w.x + b` one sample at a time, minimising
+//!
+//!     mean(loss(w.x + b, y))  +  alpha * l1_ratio * ||w||_1  +  (alpha * (1 - l1_ratio) / 2) * ||w||^2
+//!
+//! * `LinearSgd::classifier()` -> hinge loss, a linear SVM. `Log` gives logistic regression and `ModifiedHuber` a smooth,
+//!   outlier robust version of the hinge, both with probabilities. More than two classes are handled one-vs-rest.
+//! * `LinearSgd::regressor()` -> squared error, `Huber` and `EpsilonInsensitive` for robust regression and a linear SVR.
+//! * `LinearSgd::perceptron()` -> the perceptron, no penalty and a constant learning rate of 1.
+//!
+//! A step only touches the non zero features of its sample : the L2 shrinkage is kept as one scale for the whole weight vector,
+//! the L1 part is the cumulative penalty of Tsuruoka et al. 2009 and the averaged weights are summed lazily.
+//! So training directly on the sparse vectors of `Tokens::sparse_counts` costs O(non zeros) per sample,
+//! and `partial_fit` keeps learning from data that arrives in batches.
+
+use serde::{Deserialize, Serialize};
+use sprs::CsVec;
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input, partial_fit_classes},
+    n_dimen::matrix::Matrix,
+    supervised::support_vector_machines::support_vector_machines::to_sparse,
+    persistence::persistence::{Persist, PersistError, ensure}};
+
+
+///The loss of a single prediction `p`, classification losses get `y` as -1 or 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SgdLoss {
+    ///     max(0, 1 - y p), a linear SVM.
+    Hinge,
+    ///     ln(1 + exp(-y p)), logistic regression.
+    Log,
+    ///     max(0, 1 - y p)^2 for y p >= -1 and -4 y p below, quadratically smoothed hinge that is linear for bad mistakes.
+    ModifiedHuber,
+    ///     max(0, -y p), the perceptron.
+    Perceptron,
+    ///     (p - y)^2 / 2, ordinary least squares.
+    SquaredError,
+    ///squared error within `epsilon` of the target and linear outside of it.
+    Huber { epsilon : f32 },
+    ///     max(0, |p - y| - epsilon), a linear SVR.
+    EpsilonInsensitive { epsilon : f32 },
+}
+
+impl SgdLoss {
+
+    pub fn is_classification(&self) -> bool {
+        matches!(self, SgdLoss::Hinge | SgdLoss::Log | SgdLoss::ModifiedHuber | SgdLoss::Perceptron)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SgdLoss::Hinge => "hinge",
+            SgdLoss::Log => "log",
+            SgdLoss::ModifiedHuber => "modified_huber",
+            SgdLoss::Perceptron => "perceptron",
+            SgdLoss::SquaredError => "squared_error",
+            SgdLoss::Huber { .. } => "huber",
+            SgdLoss::EpsilonInsensitive { .. } => "epsilon_insensitive",
+        }
+    }
+
+    ///the losses without settings, the ones with an `epsilon` are built with it and given to `set_loss`.
+    fn from_name(name : &str) -> Result<SgdLoss, MLError> {
+        match name {
+            "hinge" => Ok(SgdLoss::Hinge),
+            "log" => Ok(SgdLoss::Log),
+            "modified_huber" => Ok(SgdLoss::ModifiedHuber),
+            "perceptron" => Ok(SgdLoss::Perceptron),
+            "squared_error" => Ok(SgdLoss::SquaredError),
+            "huber" | "epsilon_insensitive" => Err(MLError::InvalidParameter(format!("the '{}' loss needs its epsilon, use set_loss", name))),
+            _ => Err(MLError::InvalidParameter(format!("unknown loss '{}'", name))),
+        }
+    }
+
+    pub fn loss(&self, p : f64, y : f64) -> f64 {
+        match *self {
+            SgdLoss::Hinge => (1.0 - p * y).max(0.0),
+            SgdLoss::Log => {
+                let z = p * y;
+                if z > 18.0 { (-z).exp() } else if z < -18.0 { -z } else { (-z).exp().ln_1p() }
+            },
+            SgdLoss::ModifiedHuber => {
+                let z = p * y;
+                if z >= 1.0 { 0.0 } else if z >= -1.0 { (1.0 - z).powi(2) } else { -4.0 * z }
+            },
+            SgdLoss::Perceptron => (-p * y).max(0.0),
+            SgdLoss::SquaredError => 0.5 * (p - y).powi(2),
+            SgdLoss::Huber { epsilon } => {
+                let (r, epsilon) = ((p - y).abs(), epsilon as f64);
+                if r <= epsilon { 0.5 * r * r } else { epsilon * r - 0.5 * epsilon * epsilon }
+            },
+            SgdLoss::EpsilonInsensitive { epsilon } => ((p - y).abs() - epsilon as f64).max(0.0),
+        }
+    }
+
+    ///derivative of the loss with respect to `p`.
+    pub fn dloss(&self, p : f64, y : f64) -> f64 {
+        match *self {
+            SgdLoss::Hinge => if p * y < 1.0 { -y } else { 0.0 },
+            SgdLoss::Log => {
+                let z = p * y;
+                if z > 18.0 { -y * (-z).exp() } else if z < -18.0 { -y } else { -y / (z.exp() + 1.0) }
+            },
+            SgdLoss::ModifiedHuber => {
+                let z = p * y;
+                if z >= 1.0 { 0.0 } else if z >= -1.0 { -2.0 * (1.0 - z) * y } else { -4.0 * y }
+            },
+            SgdLoss::Perceptron => if p * y <= 0.0 { -y } else { 0.0 },
+            SgdLoss::SquaredError => p - y,
+            SgdLoss::Huber { epsilon } => (p - y).clamp(-epsilon as f64, epsilon as f64),
+            SgdLoss::EpsilonInsensitive { epsilon } => {
+                let r = p - y;
+                if r > epsilon as f64 { 1.0 } else if r < -epsilon as f64 { -1.0 } else { 0.0 }
+            },
+        }
+    }
+}
+
+
+///The step size of the update after `t` updates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LearningRate {
+    ///the same `eta0` for every update.
+    Constant { eta0 : f32 },
+    ///`1 / (alpha * (t0 + t))` with Bottou's heuristic for `t0`, needs `alpha > 0`.
+    Optimal,
+    ///`eta0 / t^power_t`.
+    InvScaling { eta0 : f32, power_t : f32 },
+    ///`eta0` while the training loss keeps going down, divided by 5 every time it stalls for `n_iter_no_change` epochs.
+    Adaptive { eta0 : f32 },
+}
+
+impl LearningRate {
+    fn name(&self) -> &'static str {
+        match self {
+            LearningRate::Constant { .. } => "constant",
+            LearningRate::Optimal => "optimal",
+            LearningRate::InvScaling { .. } => "invscaling",
+            LearningRate::Adaptive { .. } => "adaptive",
+        }
+    }
+
+    ///only `optimal` has no settings, the schedules with an `eta0` are built with it and given to `set_learning_rate`.
+    fn from_name(name : &str) -> Result<LearningRate, MLError> {
+        match name {
+            "optimal" => Ok(LearningRate::Optimal),
+            "constant" | "invscaling" | "adaptive" => Err(MLError::InvalidParameter(format!("the '{}' learning rate needs its eta0, use set_learning_rate", name))),
+            _ => Err(MLError::InvalidParameter(format!("unknown learning rate '{}'", name))),
+        }
+    }
+}
+
+
+///The weights of one linear model and everything SGD needs to carry on training it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SgdWeights {
+    ///the weights are `scale * weights`, so the L2 shrinkage of a step only changes `scale`.
+    weights : Vec<f64>,
+    scale : f64,
+    intercept : f64,
+    ///number of updates so far.
+    t : f64,
+    ///the L1 penalty every weight could have received so far, and what each one actually received.
+    l1_total : f64,
+    l1_applied : Vec<f64>,
+    ///the current step of `LearningRate::Adaptive`.
+    adaptive_eta : f64,
+    ///lazily summed weights for averaging, `weight_sums[i]` is up to date until the total scale reached `scale_sum_at[i]`.
+    weight_sums : Vec<f64>,
+    scale_sum_at : Vec<f64>,
+    scale_sum : f64,
+    intercept_sum : f64,
+    n_averaged : f64,
+}
+
+///The hyperparameters a training step needs.
+struct StepSettings {
+    loss : SgdLoss,
+    alpha : f64,
+    l1_ratio : f64,
+    learning_rate : LearningRate,
+    fit_intercept : bool,
+    average : bool,
+    optimal_t0 : f64,
+}
+
+impl StepSettings {
+    fn eta(&self, weights : &SgdWeights) -> f64 {
+        match self.learning_rate {
+            LearningRate::Constant { eta0 } => eta0 as f64,
+            LearningRate::Optimal => 1.0 / (self.alpha * (self.optimal_t0 + weights.t)),
+            LearningRate::InvScaling { eta0, power_t } => eta0 as f64 / (weights.t + 1.0).powf(power_t as f64),
+            LearningRate::Adaptive { .. } => weights.adaptive_eta,
+        }
+    }
+}
+
+impl SgdWeights {
+
+    fn new(n_features : usize, learning_rate : LearningRate) -> SgdWeights {
+        SgdWeights {
+            weights: vec![0.0; n_features],
+            scale: 1.0,
+            intercept: 0.0,
+            t: 0.0,
+            l1_total: 0.0,
+            l1_applied: vec![0.0; n_features],
+            adaptive_eta: match learning_rate { LearningRate::Adaptive { eta0 } => eta0 as f64, _ => 0.0 },
+            weight_sums: vec![0.0; n_features],
+            scale_sum_at: vec![0.0; n_features],
+            scale_sum: 0.0,
+            intercept_sum: 0.0,
+            n_averaged: 0.0,
+        }
+    }
+
+    fn decision(&self, point : &CsVec<f32>) -> f64 {
+        self.scale * point.iter().map(|(index, value)| self.weights[index] * *value as f64).sum::<f64>() + self.intercept
+    }
+
+    ///brings the averaging sum of a weight up to now, needed before the weight changes.
+    fn catch_up(&mut self, index : usize) {
+        self.weight_sums[index] += self.weights[index] * (self.scale_sum - self.scale_sum_at[index]);
+        self.scale_sum_at[index] = self.scale_sum;
+    }
+
+    ///one update on a single sample, the loss gradient is scaled by its weight. Returns its weighted loss before the update.
+    fn step(&mut self, point : &CsVec<f32>, y : f64, sample_weight : f64, settings : &StepSettings) -> f64 {
+        let p = self.decision(point);
+        let dloss = sample_weight * settings.loss.dloss(p, y);
+        let eta = settings.eta(self);
+
+        let shrink = 1.0 - eta * settings.alpha * (1.0 - settings.l1_ratio);
+        if shrink > 1e-9 {
+            self.scale *= shrink;
+        } else {
+            //a step this big wipes the weights out completely.
+            (0..self.weights.len()).for_each(|index| if settings.average { self.catch_up(index) });
+            self.weights.iter_mut().for_each(|weight| *weight = 0.0);
+            self.scale = 1.0;
+        }
+        if dloss != 0.0 {
+            for (index, value) in point.iter() {
+                if settings.average {
+                    self.catch_up(index);
+                }
+                self.weights[index] -= eta * dloss * *value as f64 / self.scale;
+            }
+            if settings.fit_intercept {
+                self.intercept -= eta * dloss;
+            }
+        }
+        if settings.l1_ratio > 0.0 && settings.alpha > 0.0 {
+            self.l1_total += eta * settings.alpha * settings.l1_ratio;
+            for (index, _) in point.iter() {
+                if settings.average {
+                    self.catch_up(index);
+                }
+                //clip the weight towards zero by the penalty it has not received yet.
+                let weight = self.scale * self.weights[index];
+                let clipped = if weight > 0.0 {
+                    (weight - (self.l1_total + self.l1_applied[index])).max(0.0)
+                } else if weight < 0.0 {
+                    (weight + (self.l1_total - self.l1_applied[index])).min(0.0)
+                } else {
+                    0.0
+                };
+                self.l1_applied[index] += clipped - weight;
+                self.weights[index] = clipped / self.scale;
+            }
+        }
+        if self.scale < 1e-9 {
+            if settings.average {
+                (0..self.weights.len()).for_each(|index| self.catch_up(index));
+            }
+            let scale = self.scale;
+            self.weights.iter_mut().for_each(|weight| *weight *= scale);
+            self.scale = 1.0;
+        }
+        if settings.average {
+            self.scale_sum += self.scale;
+            self.intercept_sum += self.intercept;
+            self.n_averaged += 1.0;
+        }
+        self.t += 1.0;
+        sample_weight * settings.loss.loss(p, y)
+    }
+
+    ///the weights and intercept a prediction uses, the averaged ones with `average`.
+    fn coefficients(&self, average : bool) -> (Vec<f32>, f32) {
+        if average && self.n_averaged > 0.0 {
+            let weights = self.weights.iter().enumerate()
+                .map(|(index, weight)| ((self.weight_sums[index] + weight * (self.scale_sum - self.scale_sum_at[index])) / self.n_averaged) as f32)
+                .collect();
+            (weights, (self.intercept_sum / self.n_averaged) as f32)
+        } else {
+            (self.weights.iter().map(|weight| (weight * self.scale) as f32).collect(), self.intercept as f32)
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinearSgd {
+    ///decides between classification and regression.
+    pub loss : SgdLoss,
+    ///strength of the regularisation.
+    pub alpha : f32,
+    ///share of the penalty that is L1, the rest is L2.
+    pub l1_ratio : f32,
+    pub learning_rate : LearningRate,
+    pub fit_intercept : bool,
+    ///predict with the average of the weights over all the updates.
+    pub average : bool,
+    ///maximum number of epochs of `fit`.
+    pub max_iter : usize,
+    ///`fit` stops once the epoch loss has not improved by `tol` for `n_iter_no_change` epochs.
+    pub tol : f32,
+    pub n_iter_no_change : usize,
+    ///the samples are visited in a random order every epoch.
+    pub shuffle : bool,
+    pub seed : u64,
+    ///sorted classes for classification.
+    classes : Option<DataType>,
+    ///one model for regression and two classes, one per class(one vs rest) otherwise.
+    machines : Vec<SgdWeights>,
+    ///the weights and intercepts used for predicting, averaged with `average`.
+    coefficients : Vec<Vec<f32>>,
+    intercepts : Vec<f32>,
+    n_features : usize,
+    n_iter : usize,
+}
+
+impl LinearSgd {
+
+    ///the loss decides whether this is a classifier or a regressor.
+    pub fn new(loss : SgdLoss) -> LinearSgd {
+        LinearSgd {
+            loss,
+            alpha: 1e-4,
+            l1_ratio: 0.0,
+            learning_rate: if loss.is_classification() { LearningRate::Optimal } else { LearningRate::InvScaling { eta0: 0.01, power_t: 0.25 } },
+            fit_intercept: true,
+            average: false,
+            max_iter: 1000,
+            tol: 1e-3,
+            n_iter_no_change: 5,
+            shuffle: true,
+            seed: 0,
+            classes: None,
+            machines: vec![],
+            coefficients: vec![],
+            intercepts: vec![],
+            n_features: 0,
+            n_iter: 0,
+        }
+    }
+
+    ///a linear SVM(hinge loss) with a light L2 penalty.
+    pub fn classifier() -> LinearSgd {
+        LinearSgd::new(SgdLoss::Hinge)
+    }
+
+    ///least squares with a light L2 penalty.
+    pub fn regressor() -> LinearSgd {
+        LinearSgd::new(SgdLoss::SquaredError)
+    }
+
+    ///the classic perceptron : no penalty and a constant learning rate of 1.
+    pub fn perceptron() -> LinearSgd {
+        LinearSgd { alpha: 0.0, learning_rate: LearningRate::Constant { eta0: 1.0 }, ..LinearSgd::new(SgdLoss::Perceptron) }
+    }
+
+    pub fn set_loss(&mut self, loss : SgdLoss) {
+        self.loss = loss;
+    }
+
+    ///sets the strength of the penalty and the share of it that is L1.
+    pub fn set_penalty(&mut self, alpha : f32, l1_ratio : f32) {
+        self.alpha = alpha;
+        self.l1_ratio = l1_ratio;
+    }
+
+    pub fn set_learning_rate(&mut self, learning_rate : LearningRate) {
+        self.learning_rate = learning_rate;
+    }
+
+    pub fn set_fit_intercept(&mut self, fit_intercept : bool) {
+        self.fit_intercept = fit_intercept;
+    }
+
+    pub fn set_average(&mut self, average : bool) {
+        self.average = average;
+    }
+
+    ///epoch limit of `fit` and when it counts as converged.
+    pub fn set_stopping(&mut self, max_iter : usize, tol : f32, n_iter_no_change : usize) {
+        self.max_iter = max_iter;
+        self.tol = tol;
+        self.n_iter_no_change = n_iter_no_change;
+    }
+
+    pub fn set_shuffle(&mut self, shuffle : bool, seed : u64) {
+        self.shuffle = shuffle;
+        self.seed = seed;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    ///one weight vector per model, see `intercepts`.
+    pub fn coefficients(&self) -> &[Vec<f32>] {
+        &self.coefficients
+    }
+
+    pub fn intercepts(&self) -> &[f32] {
+        &self.intercepts
+    }
+
+    ///epochs the last `fit` ran for.
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    ///total number of updates so far, `partial_fit` keeps adding to it.
+    pub fn t(&self) -> usize {
+        self.machines.first().map_or(0, |machine| machine.t as usize)
+    }
+
+    fn validate_params(&self) -> Result<(), MLError> {
+        if !(self.alpha >= 0.0) {
+            return Err(MLError::InvalidParameter(format!("alpha needs to be >= 0, got {}", self.alpha)));
+        }
+        if !(0.0..=1.0).contains(&self.l1_ratio) {
+            return Err(MLError::InvalidParameter(format!("l1_ratio needs to be in [0, 1], got {}", self.l1_ratio)));
+        }
+        if self.max_iter == 0 || self.n_iter_no_change == 0 {
+            return Err(MLError::InvalidParameter("max_iter and n_iter_no_change need to be > 0".to_owned()));
+        }
+        match self.loss {
+            SgdLoss::Huber { epsilon } | SgdLoss::EpsilonInsensitive { epsilon } if !(epsilon >= 0.0) =>
+                return Err(MLError::InvalidParameter(format!("epsilon needs to be >= 0, got {}", epsilon))),
+            _ => {},
+        }
+        match self.learning_rate {
+            LearningRate::Optimal if !(self.alpha > 0.0) =>
+                Err(MLError::InvalidParameter("the optimal learning rate needs alpha > 0".to_owned())),
+            LearningRate::Constant { eta0 } | LearningRate::InvScaling { eta0, .. } | LearningRate::Adaptive { eta0 } if !(eta0 > 0.0) =>
+                Err(MLError::InvalidParameter(format!("eta0 needs to be > 0, got {}", eta0))),
+            _ => Ok(()),
+        }
+    }
+
+    fn step_settings(&self) -> StepSettings {
+        //Bottou's heuristic, the first step is about the size of a typical weight.
+        let typical_weight = (1.0 / (self.alpha as f64).sqrt()).sqrt();
+        let eta0 = typical_weight / self.loss.dloss(-typical_weight, 1.0).abs().max(1.0);
+        StepSettings {
+            loss: self.loss,
+            alpha: self.alpha as f64,
+            l1_ratio: self.l1_ratio as f64,
+            learning_rate: self.learning_rate,
+            fit_intercept: self.fit_intercept,
+            average: self.average,
+            optimal_t0: 1.0 / (eta0 * self.alpha as f64),
+        }
+    }
+
+    ///the target every model sees for every sample, -1/1 for classification.
+    fn targets(&self, y : &DataType, labels : Option<&[usize]>) -> Result<Vec<Vec<f64>>, MLError> {
+        match labels {
+            Some(labels) => {
+                let n_classes = self.classes.as_ref().unwrap().len();
+                let positives: Vec<usize> = if n_classes == 2 { vec![1] } else { (0..n_classes).collect() };
+                Ok(positives.iter().map(|positive| labels.iter().map(|label| if label == positive { 1.0 } else { -1.0 }).collect()).collect())
+            },
+            None => match y {
+                DataType::Floats(values) => Ok(vec![values.iter().map(|value| *value as f64).collect()]),
+                _ => Err(MLError::InvalidTarget(format!("the {} loss needs float targets", self.loss.name()))),
+            },
+        }
+    }
+
+    ///class labels for classification, `None` for regression.
+    fn labels(&mut self, y : &DataType, classes : Option<&DataType>, incremental : bool) -> Result<Option<Vec<usize>>, MLError> {
+        if !self.loss.is_classification() {
+            self.classes = None;
+            return Ok(None);
+        }
+        let (classes, labels) = if incremental {
+            partial_fit_classes(self.classes.as_ref(), classes, y)?
+        } else {
+            y.unique_classes().ok_or_else(|| MLError::InvalidTarget(format!("the {} loss needs category or string targets", self.loss.name())))?
+        };
+        if classes.len() < 2 {
+            return Err(MLError::InvalidTarget("need atleast two different classes to fit".to_owned()));
+        }
+        self.classes = Some(classes);
+        Ok(Some(labels))
+    }
+
+    fn check_sparse_input(X : &[CsVec<f32>], n_features : usize, y : &DataType) -> Result<(), MLError> {
+        if X.is_empty() || n_features == 0 {
+            return Err(MLError::EmptyInput);
+        }
+        if X.len() != y.len() {
+            return Err(MLError::SampleMismatch { samples: X.len(), targets: y.len() });
+        }
+        match X.iter().find(|point| point.dim() != n_features) {
+            Some(point) => Err(MLError::FeatureMismatch { expected: n_features, found: point.dim() }),
+            None => Ok(()),
+        }
+    }
+
+    ///fits from scratch on sparse samples of dimension `n_features`, e.g. the output of `Tokens::sparse_counts`.
+    pub fn fit_sparse(&mut self, X_train : &[CsVec<f32>], n_features : usize, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_sparse_weighted(X_train, n_features, y_train, &vec![1.0; X_train.len()])
+    }
+
+    ///`fit_sparse` where the step on every sample is scaled by its weight.
+    pub fn fit_sparse_weighted(&mut self, X_train : &[CsVec<f32>], n_features : usize, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        LinearSgd::check_sparse_input(X_train, n_features, y_train)?;
+        check_sample_weights(sample_weights, X_train.len())?;
+        self.validate_params()?;
+        let labels = self.labels(y_train, None, false)?;
+        let targets = self.targets(y_train, labels.as_deref())?;
+        self.machines = vec![SgdWeights::new(n_features, self.learning_rate); targets.len()];
+        self.n_features = n_features;
+
+        let settings = self.step_settings();
+        let mut rng = fastrand::Rng::with_seed(self.seed);
+        let mut order: Vec<usize> = (0..X_train.len()).collect();
+        self.n_iter = 0;
+        for (machine, targets) in self.machines.iter_mut().zip(&targets) {
+            let (mut best_loss, mut no_improvement) = (f64::INFINITY, 0);
+            for epoch in 0..self.max_iter {
+                if self.shuffle {
+                    rng.shuffle(&mut order);
+                }
+                let epoch_loss = order.iter().map(|sample| machine.step(&X_train[*sample], targets[*sample], sample_weights[*sample] as f64, &settings)).sum::<f64>() / order.len() as f64;
+                self.n_iter = self.n_iter.max(epoch + 1);
+                if !epoch_loss.is_finite() {
+                    break;
+                }
+                if epoch_loss > best_loss - self.tol as f64 {
+                    no_improvement += 1;
+                } else {
+                    no_improvement = 0;
+                }
+                best_loss = best_loss.min(epoch_loss);
+                if no_improvement >= self.n_iter_no_change {
+                    match self.learning_rate {
+                        LearningRate::Adaptive { .. } if machine.adaptive_eta > 1e-6 => {
+                            machine.adaptive_eta /= 5.0;
+                            no_improvement = 0;
+                        },
+                        _ => break,
+                    }
+                }
+            }
+        }
+        self.update_coefficients()
+    }
+
+    ///one epoch over the batch, starting from the current weights(or zero on the first call).
+    ///For classification the first call needs the full list of `classes`, later calls can pass `None`.
+    pub fn partial_fit_sparse(&mut self, X_batch : &[CsVec<f32>], n_features : usize, y_batch : &DataType, classes : Option<&DataType>) -> Result<(), MLError> {
+        LinearSgd::check_sparse_input(X_batch, n_features, y_batch)?;
+        self.validate_params()?;
+        if !self.machines.is_empty() && n_features != self.n_features {
+            return Err(MLError::FeatureMismatch { expected: self.n_features, found: n_features });
+        }
+        let labels = self.labels(y_batch, classes, true)?;
+        let targets = self.targets(y_batch, labels.as_deref())?;
+        if self.machines.is_empty() {
+            self.machines = vec![SgdWeights::new(n_features, self.learning_rate); targets.len()];
+            self.n_features = n_features;
+        }
+
+        let settings = self.step_settings();
+        //a different order for every batch, but the same for the same number of updates.
+        let mut rng = fastrand::Rng::with_seed(self.seed.wrapping_add(self.t() as u64));
+        let mut order: Vec<usize> = (0..X_batch.len()).collect();
+        if self.shuffle {
+            rng.shuffle(&mut order);
+        }
+        for (machine, targets) in self.machines.iter_mut().zip(&targets) {
+            for sample in &order {
+                machine.step(&X_batch[*sample], targets[*sample], 1.0, &settings);
+            }
+        }
+        self.n_iter = 1;
+        self.update_coefficients()
+    }
+
+    ///dense version of `partial_fit_sparse`.
+    pub fn partial_fit(&mut self, X_batch : &Matrix, y_batch : &DataType, classes : Option<&DataType>) -> Result<(), MLError> {
+        check_fit_input(X_batch, y_batch)?;
+        let rows: Vec<CsVec<f32>> = X_batch.iter_rows().map(to_sparse).collect();
+        self.partial_fit_sparse(&rows, X_batch.cols(), y_batch, classes)
+    }
+
+    fn update_coefficients(&mut self) -> Result<(), MLError> {
+        let (coefficients, intercepts): (Vec<Vec<f32>>, Vec<f32>) = self.machines.iter().map(|machine| machine.coefficients(self.average)).unzip();
+        if coefficients.iter().flatten().chain(&intercepts).any(|value| !value.is_finite()) {
+            //a diverged model is not fitted at all, not even with the coefficients of an earlier call.
+            self.machines.clear();
+            self.coefficients.clear();
+            self.intercepts.clear();
+            self.classes = None;
+            self.n_features = 0;
+            self.n_iter = 0;
+            return Err(MLError::InvalidParameter("sgd diverged, try a smaller learning rate or scaling the features".to_owned()));
+        }
+        self.coefficients = coefficients;
+        self.intercepts = intercepts;
+        Ok(())
+    }
+
+    ///`w.x + b` of every model, one value for regression and two classes and one per class otherwise.
+    pub fn decision_function_sparse(&self, point : &CsVec<f32>) -> Vec<f32> {
+        assert!(self.is_fitted(), "{}", MLError::NotFitted);
+        assert!(point.dim() == self.n_features, "{}", MLError::FeatureMismatch { expected: self.n_features, found: point.dim() });
+        self.coefficients.iter().zip(&self.intercepts).map(|(weights, intercept)| point.iter().map(|(index, value)| weights[index] * value).sum::<f32>() + intercept).collect()
+    }
+
+    pub fn decision_function(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.decision_function_sparse(&to_sparse(point))
+    }
+
+    ///class probabilities in the order of `classes()`, only for the `Log` and `ModifiedHuber` losses.
+    ///With more than two classes the one vs rest probabilities are normalised to sum to 1.
+    pub fn predict_proba_sparse(&self, point : &CsVec<f32>) -> Vec<f32> {
+        let to_probability = match self.loss {
+            SgdLoss::Log => (|decision : f32| 1.0 / (1.0 + (-decision).exp())) as fn(f32) -> f32,
+            SgdLoss::ModifiedHuber => |decision : f32| (decision.clamp(-1.0, 1.0) + 1.0) / 2.0,
+            _ => panic!("predict_proba needs the Log or ModifiedHuber loss, not {}", self.loss.name()),
+        };
+        let probabilities: Vec<f32> = self.decision_function_sparse(point).into_iter().map(to_probability).collect();
+        if probabilities.len() == 1 {
+            return vec![1.0 - probabilities[0], probabilities[0]];
+        }
+        let total: f32 = probabilities.iter().sum();
+        if total > 0.0 {
+            probabilities.iter().map(|probability| probability / total).collect()
+        } else {
+            vec![1.0 / probabilities.len() as f32; probabilities.len()]
+        }
+    }
+
+    pub fn predict_proba(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.predict_proba_sparse(&to_sparse(point))
+    }
+
+    pub fn predict_sparse(&self, point : &CsVec<f32>) -> ReturnType {
+        let decisions = self.decision_function_sparse(point);
+        match &self.classes {
+            Some(classes) if decisions.len() == 1 => classes.value_at((decisions[0] > 0.0) as usize),
+            Some(classes) => classes.value_at(decisions.iter().enumerate().fold(0, |best, (class, value)| if *value > decisions[best] { class } else { best })),
+            None => ReturnType::Floats(decisions[0]),
+        }
+    }
+}
+
+impl MLalgo for LinearSgd {
+    ///the rows are turned into sparse vectors, use `fit_sparse` directly for data that is sparse to begin with.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        let rows: Vec<CsVec<f32>> = X_train.iter_rows().map(to_sparse).collect();
+        self.fit_sparse(&rows, X_train.cols(), y_train)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        let rows: Vec<CsVec<f32>> = X_train.iter_rows().map(to_sparse).collect();
+        self.fit_sparse_weighted(&rows, X_train.cols(), y_train, sample_weights)
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.coefficients.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("loss".to_owned(), ParamValue::Text(self.loss.name().to_owned()));
+        params.insert("alpha".to_owned(), ParamValue::Float(self.alpha));
+        params.insert("l1_ratio".to_owned(), ParamValue::Float(self.l1_ratio));
+        params.insert("learning_rate".to_owned(), ParamValue::Text(self.learning_rate.name().to_owned()));
+        params.insert("fit_intercept".to_owned(), ParamValue::Bool(self.fit_intercept));
+        params.insert("average".to_owned(), ParamValue::Bool(self.average));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params
+    }
+
+    ///`loss` and `learning_rate` take the ones without settings, the current name leaves them(and their settings) as they are.
+    ///Use `set_loss` and `set_learning_rate` for the others.
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "loss" => if value.as_str(name)? != self.loss.name() {
+                    self.loss = SgdLoss::from_name(value.as_str(name)?)?;
+                },
+                "learning_rate" => if value.as_str(name)? != self.learning_rate.name() {
+                    self.learning_rate = LearningRate::from_name(value.as_str(name)?)?;
+                },
+                "alpha" => self.alpha = value.as_f32(name)?,
+                "l1_ratio" => self.l1_ratio = value.as_f32(name)?,
+                "fit_intercept" => self.fit_intercept = value.as_bool(name)?,
+                "average" => self.average = value.as_bool(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        self.validate_params()
+    }
+}
+
+impl Predict for LinearSgd {
+    ///the class with the biggest decision value for classification, `w.x + b` as a float for regression.
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        self.predict_sparse(&to_sparse(point))
+    }
+}
+
+impl Estimator for LinearSgd {
+    fn probabilities(&self, point : &[f32]) -> Option<Vec<f32>> {
+        (self.classes.is_some() && matches!(self.loss, SgdLoss::Log | SgdLoss::ModifiedHuber)).then(|| self.predict_proba(point))
+    }
+}
+
+impl Persist for LinearSgd {
+    const MODEL_TYPE: &'static str = "LinearSgd";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(self.coefficients.len() == self.intercepts.len() && self.coefficients.len() == self.machines.len(), "the number of models and intercepts do not match")?;
+        ensure(self.coefficients.iter().all(|weights| weights.len() == self.n_features), "weight vector of the wrong length")?;
+        ensure(self.machines.iter().all(|machine| machine.weights.len() == self.n_features && machine.l1_applied.len() == self.n_features
+            && machine.weight_sums.len() == self.n_features && machine.scale_sum_at.len() == self.n_features), "training state of the wrong length")?;
+        match &self.classes {
+            Some(classes) => ensure(self.machines.is_empty() || self.machines.len() == if classes.len() == 2 { 1 } else { classes.len() }, "the number of models does not match the classes"),
+            None => ensure(self.machines.len() <= 1, "a regressor has a single model"),
+        }
+    }
+}
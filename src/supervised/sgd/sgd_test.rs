@@ -0,0 +1,194 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType, data_frame::DataFrame},
+    trait_definition::{MLalgo, Predict, MLError, ParamValue}, evaluation::accuracy::{score, r2_score},
+    feature_extraction::tokenisation::Tokens, persistence::persistence::Persist};
+use super::sgd::{LinearSgd, SgdLoss, LearningRate};
+
+
+///blobs around the given centres with a spread of 0.5 in every direction.
+fn blobs(seed : u64, samples : usize, centres : &[(f32, f32)]) -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let (mut rows, mut targets) = (vec![], vec![]);
+    for sample in 0..samples {
+        let class = sample % centres.len();
+        rows.push(vec![centres[class].0 + rng.f32() - 0.5, centres[class].1 + rng.f32() - 0.5]);
+        targets.push(class as u8);
+    }
+    (Matrix::from(rows), DataType::Category(targets))
+}
+
+///`y = 2 x0 - 3 x1 + 1` plus a little noise, the last feature is pure noise.
+fn linear_data(seed : u64, samples : usize) -> (Matrix, Vec<f32>) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let (mut rows, mut targets) = (vec![], vec![]);
+    for _ in 0..samples {
+        let row = vec![rng.f32() * 2.0 - 1.0, rng.f32() * 2.0 - 1.0, rng.f32() * 2.0 - 1.0];
+        targets.push(2.0 * row[0] - 3.0 * row[1] + 1.0 + (rng.f32() - 0.5) * 0.01);
+        rows.push(row);
+    }
+    (Matrix::from(rows), targets)
+}
+
+#[test]
+fn classifier_losses_separate_blobs() {
+    let (X, y) = blobs(0, 200, &[(0.0, 0.0), (3.0, 3.0)]);
+    for loss in [SgdLoss::Hinge, SgdLoss::Log, SgdLoss::ModifiedHuber, SgdLoss::Perceptron] {
+        let mut model = LinearSgd::new(loss);
+        model.fit(&X, &y).unwrap();
+        assert!(score(&model, &X, &y) > 0.98, "{:?}", loss);
+        assert_eq!(model.coefficients().len(), 1);
+    }
+    let mut model = LinearSgd::new(SgdLoss::Log);
+    model.fit(&X, &y).unwrap();
+    let probabilities = model.predict_proba(&[3.0, 3.0]);
+    assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5 && probabilities[1] > 0.9);
+
+    //three classes are one vs rest.
+    let (X, y) = blobs(1, 300, &[(0.0, 0.0), (4.0, 0.0), (2.0, 4.0)]);
+    let mut model = LinearSgd::classifier();
+    model.set_average(true);
+    model.fit(&X, &y).unwrap();
+    assert_eq!(model.coefficients().len(), 3);
+    assert!(score(&model, &X, &y) > 0.95);
+    assert_eq!(model.predict(&[2.0, 4.0]), ReturnType::Category(2));
+    assert!(matches!(model.fit(&X, &DataType::Floats(vec![0.0; 300])), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn regressor_losses_fit_a_line() {
+    let (X, targets) = linear_data(2, 300);
+    let y = DataType::Floats(targets.clone());
+    let mut model = LinearSgd::regressor();
+    model.set_penalty(0.0, 0.0);
+    model.set_learning_rate(LearningRate::Constant { eta0: 0.05 });
+    model.fit(&X, &y).unwrap();
+    assert!(r2_score(&model, &X, &y) > 0.99);
+    let coefficients = &model.coefficients()[0];
+    assert!((coefficients[0] - 2.0).abs() < 0.05 && (coefficients[1] + 3.0).abs() < 0.05 && (model.intercepts()[0] - 1.0).abs() < 0.05);
+
+    //a few wild targets pull the squared error away, the robust losses barely move.
+    let mut corrupted = targets.clone();
+    (0..300).step_by(30).for_each(|sample| corrupted[sample] += 200.0);
+    let corrupted = DataType::Floats(corrupted);
+    for loss in [SgdLoss::Huber { epsilon: 0.1 }, SgdLoss::EpsilonInsensitive { epsilon: 0.01 }] {
+        let mut model = LinearSgd::new(loss);
+        model.set_penalty(0.0, 0.0);
+        model.set_learning_rate(LearningRate::Constant { eta0: 0.05 });
+        model.fit(&X, &corrupted).unwrap();
+        assert!(r2_score(&model, &X, &y) > 0.95, "{:?}", loss);
+    }
+    assert!(matches!(model.fit(&X, &DataType::Category(vec![0; 300])), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn l1_penalty_drops_the_noise_feature() {
+    let (X, targets) = linear_data(3, 500);
+    let y = DataType::Floats(targets);
+    let mut model = LinearSgd::regressor();
+    model.set_penalty(0.01, 1.0);
+    model.fit(&X, &y).unwrap();
+    let coefficients = &model.coefficients()[0];
+    assert!(coefficients[2].abs() < 0.01 && coefficients[0] > 1.5 && coefficients[1] < -2.5, "{:?}", coefficients);
+
+    model.set_penalty(0.01, 1.5);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+    model.set_penalty(0.0, 0.0);
+    model.set_learning_rate(LearningRate::Optimal);
+    assert!(matches!(model.fit(&X, &y), Err(MLError::InvalidParameter(_))));
+}
+
+#[test]
+fn partial_fit_keeps_learning() {
+    let (X, y) = blobs(4, 400, &[(0.0, 0.0), (3.0, 3.0), (0.0, 3.0)]);
+    let classes = DataType::Category(vec![0, 1, 2]);
+    let mut model = LinearSgd::new(SgdLoss::Log);
+    assert!(matches!(model.partial_fit(&X, &y, None), Err(MLError::InvalidParameter(_))));
+
+    let rows: Vec<Vec<f32>> = X.iter_rows().map(|row| row.to_vec()).collect();
+    let labels = match &y { DataType::Category(labels) => labels.clone(), _ => unreachable!() };
+    for epoch in 0..5 {
+        for (batch, batch_labels) in rows.chunks(50).zip(labels.chunks(50)) {
+            model.partial_fit(&Matrix::from(batch.to_vec()), &DataType::Category(batch_labels.to_vec()), if epoch == 0 { Some(&classes) } else { None }).unwrap();
+        }
+    }
+    assert_eq!(model.t(), 2000);
+    assert!(score(&model, &X, &y) > 0.95);
+    assert!(matches!(model.partial_fit(&X, &DataType::Category(vec![5; 400]), None), Err(MLError::InvalidTarget(_))));
+    assert_eq!(model.partial_fit(&Matrix::from(vec![vec![1.0]]), &DataType::Category(vec![0]), None), Err(MLError::FeatureMismatch { expected: 2, found: 1 }));
+
+    let restored = LinearSgd::from_json(&model.to_json().unwrap()).unwrap();
+    assert_eq!(restored.decision_function(&[1.0, 2.0]), model.decision_function(&[1.0, 2.0]));
+    //the training state is saved as well, so a loaded model can carry on.
+    let mut restored = restored;
+    restored.partial_fit(&X, &y, None).unwrap();
+    assert_eq!(restored.t(), 2400);
+}
+
+#[test]
+fn diverged_partial_fit_leaves_the_model_unfitted() {
+    let (X, targets) = linear_data(5, 100);
+    let mut model = LinearSgd::regressor();
+    model.set_penalty(0.0, 0.0);
+    model.set_learning_rate(LearningRate::Constant { eta0: 0.01 });
+    model.partial_fit(&X, &DataType::Floats(targets.clone()), None).unwrap();
+    assert!(model.is_fitted());
+
+    model.set_learning_rate(LearningRate::Constant { eta0: 1e10 });
+    let huge = DataType::Floats(targets.iter().map(|target| target * 1e30).collect());
+    assert!(matches!(model.partial_fit(&X, &huge, None), Err(MLError::InvalidParameter(_))));
+    assert!(!model.is_fitted());
+    assert!(model.coefficients().is_empty() && model.intercepts().is_empty());
+}
+
+#[test]
+fn sgd_on_sparse_token_counts() {
+    let sentences = ["great movie loved it", "loved the great acting", "what a great film", "i loved this movie",
+        "terrible movie hated it", "hated the awful acting", "what an awful film", "i hated this movie"];
+    let frame = DataFrame {
+        data: vec![DataType::Strings(sentences.iter().map(|sentence| sentence.to_string()).collect())],
+        headers: vec!["review".to_owned()],
+        number_of_features: 1,
+        number_of_samples: sentences.len() as u32,
+        max_vector: vec![],
+        min_vector: vec![],
+        normalized: false,
+    };
+    let mut tokens = Tokens::new(sentences.len());
+    tokens.tokenise(&frame, 0, 1, None);
+    let (counts, dimension) = tokens.sparse_counts();
+    let y = DataType::Strings((0..8).map(|i| if i < 4 { "positive".to_owned() } else { "negative".to_owned() }).collect());
+
+    let mut model = LinearSgd::new(SgdLoss::ModifiedHuber);
+    model.set_penalty(1e-3, 0.15);
+    model.fit_sparse(&counts, dimension, &y).unwrap();
+    for (point, label) in counts.iter().zip(0..8) {
+        assert_eq!(model.predict_sparse(point), ReturnType::Strings(if label < 4 { "positive" } else { "negative" }.to_owned()));
+    }
+    //the classes are sorted, so the single model scores "positive".
+    let weights = &model.coefficients()[0];
+    assert!(weights[tokens.token_map_index["loved"].0] > 0.0 && weights[tokens.token_map_index["hated"].0] < 0.0);
+    assert!(model.predict_proba_sparse(&counts[0])[1] > 0.5);
+
+    let mut perceptron = LinearSgd::perceptron();
+    perceptron.partial_fit_sparse(&counts, dimension, &y, Some(&DataType::Strings(vec!["negative".to_owned(), "positive".to_owned()]))).unwrap();
+    assert_eq!(perceptron.partial_fit_sparse(&counts, dimension + 1, &y, None), Err(MLError::FeatureMismatch { expected: dimension + 1, found: dimension }));
+}
+
+#[test]
+fn params_round_trip() {
+    let mut model = LinearSgd::new(SgdLoss::Huber { epsilon: 0.3 });
+    model.set_learning_rate(LearningRate::InvScaling { eta0: 0.05, power_t: 0.3 });
+    let mut params = model.get_params();
+    model.set_params(&params).unwrap();
+    assert_eq!((model.loss, model.learning_rate), (SgdLoss::Huber { epsilon: 0.3 }, LearningRate::InvScaling { eta0: 0.05, power_t: 0.3 }));
+    params.insert("loss".to_owned(), ParamValue::Text("squared_error".to_owned()));
+    params.insert("learning_rate".to_owned(), ParamValue::Text("optimal".to_owned()));
+    model.set_params(&params).unwrap();
+    assert_eq!((model.loss, model.learning_rate), (SgdLoss::SquaredError, LearningRate::Optimal));
+    params.insert("loss".to_owned(), ParamValue::Text("epsilon_insensitive".to_owned()));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+    params.insert("loss".to_owned(), ParamValue::Text("squared_error".to_owned()));
+    params.insert("learning_rate".to_owned(), ParamValue::Text("constant".to_owned()));
+    assert!(matches!(model.set_params(&params), Err(MLError::InvalidParameter(_))));
+}
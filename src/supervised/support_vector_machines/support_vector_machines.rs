@@ -0,0 +1,879 @@
+//! #Support vector machines#
+//!
+//! * `Svc` -> soft margin kernel classifier. The dual problem is solved with SMO(the second order working set selection of LIBSVM),
+//!   more than two classes are handled one-vs-one(default, one machine per pair of classes) or one-vs-rest.
+//! * `Svr` -> epsilon-insensitive kernel regression, solved by the same SMO solver on the doubled dual problem.
+//! * `LinearSvc` -> linear classifier trained with dual coordinate descent, never builds a kernel matrix and works directly on
+//!   sparse vectors(`sprs::CsVec`), so it scales to the high dimensional bag of words features from `Tokens::sparse_counts`.
+//!
+//! The kernel rows the SMO solver asks for are kept in an LRU cache of `cache_size_mb` megabytes,
+//! so nothing of size n^2 is ever allocated.
+//! Sample weights rescale `C` per sample(`C * weight`), samples with a weight of 0 are left out.
+
+use std::{collections::HashMap, rc::Rc};
+use serde::{Deserialize, Serialize};
+use sprs::CsVec;
+use crate::{data_frame::{data_type::{DataType, length}, return_type::ReturnType},
+    trait_definition::{MLalgo, Predict, Estimator, MLError, Params, ParamValue, check_fit_input, check_sample_weights, assert_predict_input},
+    n_dimen::matrix::Matrix,
+    persistence::persistence::{Persist, PersistError, ensure}};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Kernel {
+    ///     x.y
+    Linear,
+    ///     (gamma x.y + coef0)^degree
+    Polynomial { degree : u32, gamma : Option<f32>, coef0 : f32 },
+    ///     exp(-gamma |x - y|^2)
+    Rbf { gamma : Option<f32> },
+    ///     tanh(gamma x.y + coef0)
+    Sigmoid { gamma : Option<f32>, coef0 : f32 },
+}
+
+impl Default for Kernel {
+    fn default() -> Self {
+        Kernel::Rbf { gamma: None }
+    }
+}
+
+fn dot(point_1 : &[f32], point_2 : &[f32]) -> f32 {
+    point_1.iter().zip(point_2).map(|(x, y)| x * y).sum()
+}
+
+impl Kernel {
+    pub fn compute(&self, point_1 : &[f32], point_2 : &[f32]) -> f32 {
+        match *self {
+            Kernel::Linear => dot(point_1, point_2),
+            Kernel::Polynomial { degree, gamma, coef0 } => (gamma.unwrap_or(1.0) * dot(point_1, point_2) + coef0).powi(degree as i32),
+            Kernel::Rbf { gamma } => (-gamma.unwrap_or(1.0) * point_1.iter().zip(point_2).map(|(x, y)| (x - y) * (x - y)).sum::<f32>()).exp(),
+            Kernel::Sigmoid { gamma, coef0 } => (gamma.unwrap_or(1.0) * dot(point_1, point_2) + coef0).tanh(),
+        }
+    }
+
+    ///replaces a `None` gamma with `1 / (n_features * variance of X)`, the same scale for every dataset.
+    pub(crate) fn resolve(&self, X : &Matrix) -> Kernel {
+        let values = X.as_slice();
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let variance = values.iter().map(|value| (value - mean) * (value - mean)).sum::<f32>() / values.len() as f32;
+        let scale = if variance > 0.0 { 1.0 / (X.cols() as f32 * variance) } else { 1.0 };
+        match *self {
+            Kernel::Linear => Kernel::Linear,
+            Kernel::Polynomial { degree, gamma, coef0 } => Kernel::Polynomial { degree, gamma: Some(gamma.unwrap_or(scale)), coef0 },
+            Kernel::Rbf { gamma } => Kernel::Rbf { gamma: Some(gamma.unwrap_or(scale)) },
+            Kernel::Sigmoid { gamma, coef0 } => Kernel::Sigmoid { gamma: Some(gamma.unwrap_or(scale)), coef0 },
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), MLError> {
+        match *self {
+            Kernel::Polynomial { degree: 0, .. } => Err(MLError::InvalidParameter("the polynomial degree needs to be > 0".to_owned())),
+            Kernel::Polynomial { gamma: Some(gamma), .. } | Kernel::Rbf { gamma: Some(gamma) } | Kernel::Sigmoid { gamma: Some(gamma), .. } if !(gamma > 0.0) =>
+                Err(MLError::InvalidParameter(format!("gamma needs to be > 0, got {}", gamma))),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum MultiClass {
+    ///one machine for every pair of classes, the class with the most wins is predicted.
+    #[default]
+    OneVsOne,
+    ///one machine per class against all the others, the biggest decision value wins.
+    OneVsRest,
+}
+
+
+//***************************************
+//KERNEL CACHE AND SMO SOLVER
+//***************************************
+
+///LRU cache of kernel rows `K(i, .)` over the training points.
+pub(crate) struct KernelCache<'a> {
+    kernel : Kernel,
+    points : &'a [&'a [f32]],
+    rows : HashMap<usize, (Rc<Vec<f32>>, u64)>,
+    capacity : usize,
+    clock : u64,
+}
+
+impl<'a> KernelCache<'a> {
+    pub(crate) fn new(kernel : Kernel, points : &'a [&'a [f32]], cache_size_mb : usize) -> KernelCache<'a> {
+        let row_bytes = (points.len() * std::mem::size_of::<f32>()).max(1);
+        KernelCache { kernel, points, rows: HashMap::new(), capacity: (cache_size_mb * 1024 * 1024 / row_bytes).max(2), clock: 0 }
+    }
+
+    fn row(&mut self, index : usize) -> Rc<Vec<f32>> {
+        self.clock += 1;
+        if let Some((row, used)) = self.rows.get_mut(&index) {
+            *used = self.clock;
+            return row.clone();
+        }
+        if self.rows.len() >= self.capacity {
+            let oldest = *self.rows.iter().min_by_key(|(_, (_, used))| *used).unwrap().0;
+            self.rows.remove(&oldest);
+        }
+        let point = self.points[index];
+        let row = Rc::new(self.points.iter().map(|other| self.kernel.compute(point, other)).collect::<Vec<f32>>());
+        self.rows.insert(index, (row.clone(), self.clock));
+        row
+    }
+}
+
+///the dual problem
+///     min 1/2 a^T Q a + p^T a     with 0 <= a_t <= C_t and sum(y_t a_t) fixed,
+///where `Q(s, t) = y_s y_t K(base(s), base(t))` and variable `t` belongs to the training point `t % n`.
+pub(crate) struct SmoProblem {
+    pub(crate) linear : Vec<f64>,
+    pub(crate) signs : Vec<f64>,
+    pub(crate) upper : Vec<f64>,
+}
+
+///returns the optimal `a`, `rho`(the decision function is `sum(y_t a_t K(x_t, x)) - rho`) and the number of iterations.
+pub(crate) fn smo_solve(problem : &SmoProblem, cache : &mut KernelCache, tol : f64, max_iter : usize) -> (Vec<f64>, f64, usize) {
+    smo_solve_from(problem, cache, vec![0.0; problem.linear.len()], tol, max_iter)
+}
+
+///same as `smo_solve`, starting from a feasible `alpha` instead of 0(the one-class problem has no feasible point at 0).
+pub(crate) fn smo_solve_from(problem : &SmoProblem, cache : &mut KernelCache, mut alpha : Vec<f64>, tol : f64, max_iter : usize) -> (Vec<f64>, f64, usize) {
+    const TAU : f64 = 1e-12;
+    let size = problem.linear.len();
+    let n = cache.points.len();
+    let (y, upper) = (&problem.signs, &problem.upper);
+    let diagonal: Vec<f64> = (0..size).map(|t| cache.kernel.compute(cache.points[t % n], cache.points[t % n]) as f64).collect();
+    let q = |row : &Vec<f32>, s : usize, t : usize| y[s] * y[t] * row[t % n] as f64;
+    let mut gradient = problem.linear.clone();
+    for s in (0..size).filter(|s| alpha[*s] != 0.0) {
+        let row = cache.row(s % n);
+        for t in 0..size {
+            gradient[t] += q(&row, s, t) * alpha[s];
+        }
+    }
+    let is_upper = |alpha : &[f64], t : usize| alpha[t] >= upper[t];
+    let is_lower = |alpha : &[f64], t : usize| alpha[t] <= 0.0;
+
+    let mut iterations = 0;
+    while iterations < max_iter {
+        //first index, the most violating variable.
+        let (mut g_max, mut i) = (f64::NEG_INFINITY, usize::MAX);
+        for t in 0..size {
+            if y[t] > 0.0 && !is_upper(&alpha, t) && -gradient[t] >= g_max {
+                g_max = -gradient[t];
+                i = t;
+            } else if y[t] < 0.0 && !is_lower(&alpha, t) && gradient[t] >= g_max {
+                g_max = gradient[t];
+                i = t;
+            }
+        }
+        if i == usize::MAX {
+            break;
+        }
+        //second index, the biggest decrease of the objective.
+        let row_i = cache.row(i % n);
+        let (mut g_max2, mut j, mut best_decrease) = (f64::NEG_INFINITY, usize::MAX, f64::INFINITY);
+        for t in 0..size {
+            let (gradient_difference, quadratic) = if y[t] > 0.0 && !is_lower(&alpha, t) {
+                g_max2 = g_max2.max(gradient[t]);
+                (g_max + gradient[t], diagonal[i] + diagonal[t] - 2.0 * y[i] * q(&row_i, i, t))
+            } else if y[t] < 0.0 && !is_upper(&alpha, t) {
+                g_max2 = g_max2.max(-gradient[t]);
+                (g_max - gradient[t], diagonal[i] + diagonal[t] + 2.0 * y[i] * q(&row_i, i, t))
+            } else {
+                continue;
+            };
+            if gradient_difference > 0.0 {
+                let decrease = -gradient_difference * gradient_difference / quadratic.max(TAU);
+                if decrease <= best_decrease {
+                    best_decrease = decrease;
+                    j = t;
+                }
+            }
+        }
+        if g_max + g_max2 < tol || j == usize::MAX {
+            break;
+        }
+        iterations += 1;
+
+        let row_j = cache.row(j % n);
+        let (old_i, old_j) = (alpha[i], alpha[j]);
+        let q_ij = q(&row_i, i, j);
+        if y[i] != y[j] {
+            let quadratic = (diagonal[i] + diagonal[j] + 2.0 * q_ij).max(TAU);
+            let delta = (-gradient[i] - gradient[j]) / quadratic;
+            let difference = alpha[i] - alpha[j];
+            alpha[i] += delta;
+            alpha[j] += delta;
+            if difference > 0.0 {
+                if alpha[j] < 0.0 { alpha[j] = 0.0; alpha[i] = difference; }
+            } else if alpha[i] < 0.0 { alpha[i] = 0.0; alpha[j] = -difference; }
+            if difference > upper[i] - upper[j] {
+                if alpha[i] > upper[i] { alpha[i] = upper[i]; alpha[j] = upper[i] - difference; }
+            } else if alpha[j] > upper[j] { alpha[j] = upper[j]; alpha[i] = upper[j] + difference; }
+        } else {
+            let quadratic = (diagonal[i] + diagonal[j] - 2.0 * q_ij).max(TAU);
+            let delta = (gradient[i] - gradient[j]) / quadratic;
+            let sum = alpha[i] + alpha[j];
+            alpha[i] -= delta;
+            alpha[j] += delta;
+            if sum > upper[i] {
+                if alpha[i] > upper[i] { alpha[i] = upper[i]; alpha[j] = sum - upper[i]; }
+            } else if alpha[j] < 0.0 { alpha[j] = 0.0; alpha[i] = sum; }
+            if sum > upper[j] {
+                if alpha[j] > upper[j] { alpha[j] = upper[j]; alpha[i] = sum - upper[j]; }
+            } else if alpha[i] < 0.0 { alpha[i] = 0.0; alpha[j] = sum; }
+        }
+        let (delta_i, delta_j) = (alpha[i] - old_i, alpha[j] - old_j);
+        for t in 0..size {
+            gradient[t] += q(&row_i, i, t) * delta_i + q(&row_j, j, t) * delta_j;
+        }
+    }
+
+    //rho from the free variables, or the middle of the feasible interval if there are none.
+    let (mut free, mut free_sum, mut upper_bound, mut lower_bound) = (0, 0.0, f64::INFINITY, f64::NEG_INFINITY);
+    for t in 0..size {
+        let y_gradient = y[t] * gradient[t];
+        if is_upper(&alpha, t) {
+            if y[t] < 0.0 { upper_bound = upper_bound.min(y_gradient) } else { lower_bound = lower_bound.max(y_gradient) }
+        } else if is_lower(&alpha, t) {
+            if y[t] > 0.0 { upper_bound = upper_bound.min(y_gradient) } else { lower_bound = lower_bound.max(y_gradient) }
+        } else {
+            free += 1;
+            free_sum += y_gradient;
+        }
+    }
+    let rho = if free > 0 { free_sum / free as f64 } else { (upper_bound + lower_bound) / 2.0 };
+    (alpha, rho, iterations)
+}
+
+
+///a two class kernel machine, `decision(x) = sum(coefficients_i K(sv_i, x)) - rho`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KernelMachine {
+    pub support_vectors : Vec<Vec<f32>>,
+    pub coefficients : Vec<f32>,
+    pub rho : f32,
+}
+
+impl KernelMachine {
+    pub fn decision(&self, kernel : &Kernel, point : &[f32]) -> f32 {
+        self.support_vectors.iter().zip(&self.coefficients).map(|(vector, coefficient)| coefficient * kernel.compute(vector, point)).sum::<f32>() - self.rho
+    }
+
+    ///keeps the points with a non zero coefficient.
+    pub(crate) fn from_solution(points : &[&[f32]], coefficients : impl Iterator<Item = f64>, rho : f64) -> KernelMachine {
+        let (support_vectors, coefficients) = points.iter().zip(coefficients)
+            .filter(|(_, coefficient)| *coefficient != 0.0)
+            .map(|(point, coefficient)| (point.to_vec(), coefficient as f32))
+            .unzip();
+        KernelMachine { support_vectors, coefficients, rho: rho as f32 }
+    }
+}
+
+fn validate_svm(c : f32, tol : f32, max_iter : usize) -> Result<(), MLError> {
+    if !(c > 0.0) {
+        return Err(MLError::InvalidParameter(format!("C needs to be > 0, got {}", c)));
+    }
+    if !(tol > 0.0) || max_iter == 0 {
+        return Err(MLError::InvalidParameter("tol and max_iter need to be > 0".to_owned()));
+    }
+    Ok(())
+}
+
+
+//***************************************
+//KERNEL CLASSIFIER
+//***************************************
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Svc {
+    pub kernel : Kernel,
+    ///the soft margin penalty, smaller values allow more points inside the margin.
+    pub c : f32,
+    pub multiclass : MultiClass,
+    ///stops when the biggest violation of the optimality conditions is below this.
+    pub tol : f32,
+    pub max_iter : usize,
+    pub cache_size_mb : usize,
+    ///the kernel with gamma resolved from the training data.
+    fitted_kernel : Kernel,
+    ///one vs one -> `(positive, Some(negative))` per pair, one vs rest -> `(class, None)`, binary -> `(1, Some(0))`.
+    pairs : Vec<(usize, Option<usize>)>,
+    machines : Vec<KernelMachine>,
+    classes : Option<DataType>,
+    n_features : usize,
+    n_iter : usize,
+}
+
+impl Svc {
+
+    pub fn new(kernel : Kernel, c : f32) -> Svc {
+        Svc {
+            kernel,
+            c,
+            multiclass: MultiClass::OneVsOne,
+            tol: 1e-3,
+            max_iter: 1_000_000,
+            cache_size_mb: 200,
+            fitted_kernel: kernel,
+            pairs: vec![],
+            machines: vec![],
+            classes: None,
+            n_features: 0,
+            n_iter: 0,
+        }
+    }
+
+    pub fn set_multiclass(&mut self, multiclass : MultiClass) {
+        self.multiclass = multiclass;
+    }
+
+    pub fn set_stopping(&mut self, tol : f32, max_iter : usize) {
+        self.tol = tol;
+        self.max_iter = max_iter;
+    }
+
+    pub fn set_cache_size(&mut self, cache_size_mb : usize) {
+        self.cache_size_mb = cache_size_mb;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    pub fn machines(&self) -> &[KernelMachine] {
+        &self.machines
+    }
+
+    ///total number of support vectors over all the machines.
+    pub fn n_support_vectors(&self) -> usize {
+        self.machines.iter().map(|machine| machine.support_vectors.len()).sum()
+    }
+
+    ///SMO iterations summed over all the machines.
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    ///one value per machine, positive is a vote for the first class of the pair(or for the class itself with one vs rest).
+    pub fn decision_function(&self, point : &[f32]) -> Vec<f32> {
+        assert_predict_input(self, point);
+        self.machines.iter().map(|machine| machine.decision(&self.fitted_kernel, point)).collect()
+    }
+
+    ///trains one machine separating `positive`(label +1) from the rest of the given samples.
+    fn train_machine(&self, points : &[&[f32]], positive : &[bool], weights : &[f32]) -> (KernelMachine, usize) {
+        let mut cache = KernelCache::new(self.fitted_kernel, points, self.cache_size_mb);
+        let problem = SmoProblem {
+            linear: vec![-1.0; points.len()],
+            signs: positive.iter().map(|positive| if *positive { 1.0 } else { -1.0 }).collect(),
+            upper: weights.iter().map(|weight| (self.c * weight) as f64).collect(),
+        };
+        let (alpha, rho, iterations) = smo_solve(&problem, &mut cache, self.tol as f64, self.max_iter);
+        let coefficients = alpha.iter().zip(&problem.signs).map(|(alpha, sign)| alpha * sign);
+        (KernelMachine::from_solution(points, coefficients, rho), iterations)
+    }
+}
+
+impl MLalgo for Svc {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        validate_svm(self.c, self.tol, self.max_iter)?;
+        self.kernel.validate()?;
+        let (classes, labels) = y_train.unique_classes()
+            .ok_or_else(|| MLError::InvalidTarget("an Svc needs category or string targets, use Svr for floats".to_owned()))?;
+        if classes.len() < 2 {
+            return Err(MLError::InvalidTarget("need atleast two different classes to fit".to_owned()));
+        }
+        //a machine without samples on one side has no free variable and no bound to take rho from.
+        if let Some(class) = (0..classes.len()).find(|class| !(0..labels.len()).any(|sample| labels[sample] == *class && sample_weights[sample] > 0.0)) {
+            return Err(MLError::InvalidParameter(format!("every class needs atleast one sample with a weight > 0, class {} has none", class)));
+        }
+        self.fitted_kernel = self.kernel.resolve(X_train);
+        let rows: Vec<&[f32]> = X_train.iter_rows().collect();
+
+        let pairs: Vec<(usize, Option<usize>)> = match (classes.len(), self.multiclass) {
+            (2, _) => vec![(1, Some(0))],
+            (n_classes, MultiClass::OneVsOne) => (0..n_classes).flat_map(|first| (first + 1..n_classes).map(move |second| (first, Some(second)))).collect(),
+            (n_classes, MultiClass::OneVsRest) => (0..n_classes).map(|class| (class, None)).collect(),
+        };
+        let mut machines = Vec::with_capacity(pairs.len());
+        let mut total_iterations = 0;
+        for (positive, negative) in &pairs {
+            let members: Vec<usize> = (0..rows.len()).filter(|sample| sample_weights[*sample] > 0.0)
+                .filter(|sample| negative.map_or(true, |negative| labels[*sample] == *positive || labels[*sample] == negative))
+                .collect();
+            let points: Vec<&[f32]> = members.iter().map(|sample| rows[*sample]).collect();
+            let is_positive: Vec<bool> = members.iter().map(|sample| labels[*sample] == *positive).collect();
+            let weights: Vec<f32> = members.iter().map(|sample| sample_weights[*sample]).collect();
+            let (machine, iterations) = self.train_machine(&points, &is_positive, &weights);
+            machines.push(machine);
+            total_iterations += iterations;
+        }
+
+        self.pairs = pairs;
+        self.machines = machines;
+        self.classes = Some(classes);
+        self.n_features = X_train.cols();
+        self.n_iter = total_iterations;
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.machines.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("c".to_owned(), ParamValue::Float(self.c));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("cache_size_mb".to_owned(), ParamValue::Int(self.cache_size_mb as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "c" => self.c = value.as_f32(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "cache_size_mb" => self.cache_size_mb = value.as_usize(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        validate_svm(self.c, self.tol, self.max_iter)
+    }
+}
+
+impl Predict for Svc {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        let decisions = self.decision_function(point);
+        let classes = self.classes.as_ref().unwrap();
+        let winner = match self.pairs.first() {
+            Some((_, None)) => decisions.iter().enumerate().fold(0, |best, (class, value)| if *value > decisions[best] { class } else { best }),
+            _ => {
+                //ties go to the class that comes first.
+                let mut votes = vec![0_usize; classes.len()];
+                for ((positive, negative), decision) in self.pairs.iter().zip(&decisions) {
+                    votes[if *decision > 0.0 { *positive } else { negative.unwrap() }] += 1;
+                }
+                votes.iter().enumerate().fold(0, |best, (class, count)| if *count > votes[best] { class } else { best })
+            },
+        };
+        classes.value_at(winner)
+    }
+}
+
+impl Estimator for Svc {}
+
+impl Persist for Svc {
+    const MODEL_TYPE: &'static str = "Svc";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(self.pairs.len() == self.machines.len(), "the number of machines does not match the class pairs")?;
+        let n_classes = self.classes.as_ref().map_or(0, |classes| classes.len());
+        ensure(self.pairs.iter().all(|(positive, negative)| *positive < n_classes && negative.map_or(true, |negative| negative < n_classes)), "a machine points to a class that does not exist")?;
+        ensure(self.machines.iter().all(|machine| machine.support_vectors.len() == machine.coefficients.len()
+            && machine.support_vectors.iter().all(|vector| vector.len() == self.n_features)), "support vectors of the wrong shape")
+    }
+}
+
+
+//***************************************
+//KERNEL REGRESSION
+//***************************************
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Svr {
+    pub kernel : Kernel,
+    pub c : f32,
+    ///errors smaller than epsilon are not penalised.
+    pub epsilon : f32,
+    pub tol : f32,
+    pub max_iter : usize,
+    pub cache_size_mb : usize,
+    fitted_kernel : Kernel,
+    machine : Option<KernelMachine>,
+    n_features : usize,
+}
+
+impl Svr {
+
+    pub fn new(kernel : Kernel, c : f32, epsilon : f32) -> Svr {
+        Svr { kernel, c, epsilon, tol: 1e-3, max_iter: 1_000_000, cache_size_mb: 200, fitted_kernel: kernel, machine: None, n_features: 0 }
+    }
+
+    pub fn set_stopping(&mut self, tol : f32, max_iter : usize) {
+        self.tol = tol;
+        self.max_iter = max_iter;
+    }
+
+    pub fn set_cache_size(&mut self, cache_size_mb : usize) {
+        self.cache_size_mb = cache_size_mb;
+    }
+
+    pub fn machine(&self) -> Option<&KernelMachine> {
+        self.machine.as_ref()
+    }
+}
+
+impl MLalgo for Svr {
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_weighted(X_train, y_train, &vec![1.0; X_train.rows()])
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        check_sample_weights(sample_weights, X_train.rows())?;
+        validate_svm(self.c, self.tol, self.max_iter)?;
+        self.kernel.validate()?;
+        if !(self.epsilon >= 0.0) {
+            return Err(MLError::InvalidParameter(format!("epsilon needs to be >= 0, got {}", self.epsilon)));
+        }
+        let targets = match y_train {
+            DataType::Floats(values) => values,
+            _ => return Err(MLError::InvalidTarget("an Svr needs float targets".to_owned())),
+        };
+        self.fitted_kernel = self.kernel.resolve(X_train);
+        let members: Vec<usize> = (0..X_train.rows()).filter(|sample| sample_weights[*sample] > 0.0).collect();
+        if members.is_empty() {
+            return Err(MLError::InvalidParameter("at least one sample weight needs to be > 0".to_owned()));
+        }
+        let rows: Vec<&[f32]> = members.iter().map(|sample| X_train.row(*sample)).collect();
+        let targets: Vec<f32> = members.iter().map(|sample| targets[*sample]).collect();
+        let upper: Vec<f64> = members.iter().map(|sample| (self.c * sample_weights[*sample]) as f64).collect();
+        let n = rows.len();
+        //the first n variables push the prediction up, the last n down.
+        let problem = SmoProblem {
+            linear: targets.iter().map(|y| (self.epsilon - y) as f64).chain(targets.iter().map(|y| (self.epsilon + y) as f64)).collect(),
+            signs: (0..2 * n).map(|t| if t < n { 1.0 } else { -1.0 }).collect(),
+            upper: upper.iter().chain(&upper).cloned().collect(),
+        };
+        let mut cache = KernelCache::new(self.fitted_kernel, &rows, self.cache_size_mb);
+        let (alpha, rho, _) = smo_solve(&problem, &mut cache, self.tol as f64, self.max_iter);
+        self.machine = Some(KernelMachine::from_solution(&rows, (0..n).map(|t| alpha[t] - alpha[t + n]), rho));
+        self.n_features = X_train.cols();
+        Ok(())
+    }
+
+    fn is_fitted(&self) -> bool {
+        self.machine.is_some()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("c".to_owned(), ParamValue::Float(self.c));
+        params.insert("epsilon".to_owned(), ParamValue::Float(self.epsilon));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "c" => self.c = value.as_f32(name)?,
+                "epsilon" => self.epsilon = value.as_f32(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        validate_svm(self.c, self.tol, self.max_iter)
+    }
+}
+
+impl Predict for Svr {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        ReturnType::Floats(self.machine.as_ref().unwrap().decision(&self.fitted_kernel, point))
+    }
+}
+
+impl Estimator for Svr {}
+
+impl Persist for Svr {
+    const MODEL_TYPE: &'static str = "Svr";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(self.machine.as_ref().map_or(true, |machine| machine.support_vectors.len() == machine.coefficients.len()
+            && machine.support_vectors.iter().all(|vector| vector.len() == self.n_features)), "support vectors of the wrong shape")
+    }
+}
+
+
+//***************************************
+//LINEAR SVM ON SPARSE FEATURES
+//***************************************
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum LinearLoss {
+    ///     max(0, 1 - y w.x)
+    Hinge,
+    ///     max(0, 1 - y w.x)^2, smooth and usually converges faster.
+    #[default]
+    SquaredHinge,
+}
+
+fn sparse_dot(weights : &[f32], point : &CsVec<f32>) -> f32 {
+    point.iter().map(|(index, value)| weights[index] * value).sum()
+}
+
+///the non zero entries of a dense row.
+pub(crate) fn to_sparse(point : &[f32]) -> CsVec<f32> {
+    let (indices, values): (Vec<usize>, Vec<f32>) = point.iter().enumerate().filter(|(_, value)| **value != 0.0).map(|(index, value)| (index, *value)).unzip();
+    CsVec::new(point.len(), indices, values)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinearSvc {
+    pub c : f32,
+    pub loss : LinearLoss,
+    ///adds a constant feature of 1, its weight(the intercept) is regularised like the others.
+    pub fit_intercept : bool,
+    pub tol : f32,
+    pub max_iter : usize,
+    pub seed : u64,
+    ///one weight vector per machine, one machine for two classes and one per class(one vs rest) otherwise.
+    weights : Vec<Vec<f32>>,
+    intercepts : Vec<f32>,
+    classes : Option<DataType>,
+    n_features : usize,
+}
+
+impl LinearSvc {
+
+    pub fn new(c : f32) -> LinearSvc {
+        LinearSvc {
+            c,
+            loss: LinearLoss::SquaredHinge,
+            fit_intercept: true,
+            tol: 1e-3,
+            max_iter: 1000,
+            seed: 0,
+            weights: vec![],
+            intercepts: vec![],
+            classes: None,
+            n_features: 0,
+        }
+    }
+
+    pub fn set_loss(&mut self, loss : LinearLoss) {
+        self.loss = loss;
+    }
+
+    pub fn set_fit_intercept(&mut self, fit_intercept : bool) {
+        self.fit_intercept = fit_intercept;
+    }
+
+    ///`tol` bounds the spread of the projected gradients, `max_iter` the number of passes over the data.
+    pub fn set_stopping(&mut self, tol : f32, max_iter : usize) {
+        self.tol = tol;
+        self.max_iter = max_iter;
+    }
+
+    ///the samples are visited in a random order every pass.
+    pub fn set_seed(&mut self, seed : u64) {
+        self.seed = seed;
+    }
+
+    pub fn classes(&self) -> Option<&DataType> {
+        self.classes.as_ref()
+    }
+
+    pub fn weights(&self) -> &[Vec<f32>] {
+        &self.weights
+    }
+
+    pub fn intercepts(&self) -> &[f32] {
+        &self.intercepts
+    }
+
+    ///fits on sparse samples of dimension `n_features`, e.g. the output of `Tokens::sparse_counts`.
+    pub fn fit_sparse(&mut self, X_train : &[CsVec<f32>], n_features : usize, y_train : &DataType) -> Result<(), MLError> {
+        self.fit_sparse_weighted(X_train, n_features, y_train, &vec![1.0; X_train.len()])
+    }
+
+    ///`fit_sparse` with `C * weight` for every sample.
+    pub fn fit_sparse_weighted(&mut self, X_train : &[CsVec<f32>], n_features : usize, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        if X_train.is_empty() {
+            return Err(MLError::EmptyInput);
+        }
+        if X_train.len() != y_train.len() {
+            return Err(MLError::SampleMismatch { samples: X_train.len(), targets: y_train.len() });
+        }
+        if let Some(point) = X_train.iter().find(|point| point.dim() != n_features) {
+            return Err(MLError::FeatureMismatch { expected: n_features, found: point.dim() });
+        }
+        check_sample_weights(sample_weights, X_train.len())?;
+        validate_svm(self.c, self.tol, self.max_iter)?;
+        let (classes, labels) = y_train.unique_classes()
+            .ok_or_else(|| MLError::InvalidTarget("a LinearSvc needs category or string targets".to_owned()))?;
+        if classes.len() < 2 {
+            return Err(MLError::InvalidTarget("need atleast two different classes to fit".to_owned()));
+        }
+        let positives: Vec<usize> = if classes.len() == 2 { vec![1] } else { (0..classes.len()).collect() };
+        let (mut weights, mut intercepts) = (vec![], vec![]);
+        for positive in positives {
+            let signs: Vec<f32> = labels.iter().map(|label| if *label == positive { 1.0 } else { -1.0 }).collect();
+            let (weight, intercept) = self.dual_coordinate_descent(X_train, n_features, &signs, sample_weights);
+            weights.push(weight);
+            intercepts.push(intercept);
+        }
+        self.weights = weights;
+        self.intercepts = intercepts;
+        self.classes = Some(classes);
+        self.n_features = n_features;
+        Ok(())
+    }
+
+    ///dual coordinate descent(Hsieh et al. 2008), returns the weights and the intercept.
+    fn dual_coordinate_descent(&self, X_train : &[CsVec<f32>], n_features : usize, signs : &[f32], sample_weights : &[f32]) -> (Vec<f32>, f32) {
+        let bias = if self.fit_intercept { 1.0 } else { 0.0 };
+        //the box and the diagonal shift of every sample, from its own C.
+        let (diagonal_shift, upper): (Vec<f32>, Vec<f32>) = sample_weights.iter().map(|weight| match self.loss {
+            LinearLoss::Hinge => (0.0, self.c * weight),
+            LinearLoss::SquaredHinge => (0.5 / (self.c * weight), f32::INFINITY),
+        }).unzip();
+        let squared_norms: Vec<f32> = X_train.iter().zip(&diagonal_shift).map(|(point, shift)| point.iter().map(|(_, value)| value * value).sum::<f32>() + bias * bias + shift).collect();
+        let mut weights = vec![0.0_f32; n_features];
+        let mut intercept = 0.0_f32;
+        let mut alpha = vec![0.0_f32; X_train.len()];
+        let mut order: Vec<usize> = (0..X_train.len()).collect();
+        let mut rng = fastrand::Rng::with_seed(self.seed);
+
+        for _ in 0..self.max_iter {
+            rng.shuffle(&mut order);
+            let (mut max_projected, mut min_projected) = (f32::NEG_INFINITY, f32::INFINITY);
+            for &sample in order.iter().filter(|sample| sample_weights[**sample] > 0.0) {
+                let point = &X_train[sample];
+                let gradient = signs[sample] * (sparse_dot(&weights, point) + intercept * bias) - 1.0 + diagonal_shift[sample] * alpha[sample];
+                let projected = if alpha[sample] <= 0.0 { gradient.min(0.0) } else if alpha[sample] >= upper[sample] { gradient.max(0.0) } else { gradient };
+                max_projected = max_projected.max(projected);
+                min_projected = min_projected.min(projected);
+                if projected.abs() > 1e-12 && squared_norms[sample] > 0.0 {
+                    let old = alpha[sample];
+                    alpha[sample] = (old - gradient / squared_norms[sample]).clamp(0.0, upper[sample]);
+                    let step = (alpha[sample] - old) * signs[sample];
+                    for (index, value) in point.iter() {
+                        weights[index] += step * value;
+                    }
+                    intercept += step * bias;
+                }
+            }
+            if max_projected - min_projected < self.tol {
+                break;
+            }
+        }
+        (weights, intercept)
+    }
+
+    ///one value per machine, see `Svc::decision_function`.
+    pub fn decision_function_sparse(&self, point : &CsVec<f32>) -> Vec<f32> {
+        assert!(self.is_fitted(), "{}", MLError::NotFitted);
+        assert!(point.dim() == self.n_features, "{}", MLError::FeatureMismatch { expected: self.n_features, found: point.dim() });
+        self.weights.iter().zip(&self.intercepts).map(|(weights, intercept)| sparse_dot(weights, point) + intercept).collect()
+    }
+
+    pub fn predict_sparse(&self, point : &CsVec<f32>) -> ReturnType {
+        let decisions = self.decision_function_sparse(point);
+        let winner = if decisions.len() == 1 {
+            (decisions[0] > 0.0) as usize
+        } else {
+            decisions.iter().enumerate().fold(0, |best, (class, value)| if *value > decisions[best] { class } else { best })
+        };
+        self.classes.as_ref().unwrap().value_at(winner)
+    }
+}
+
+impl MLalgo for LinearSvc {
+    ///the rows are turned into sparse vectors, use `fit_sparse` directly for data that is sparse to begin with.
+    fn fit(&mut self, X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        let rows: Vec<CsVec<f32>> = X_train.iter_rows().map(to_sparse).collect();
+        self.fit_sparse(&rows, X_train.cols(), y_train)
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn fit_weighted(&mut self, X_train : &Matrix, y_train : &DataType, sample_weights : &[f32]) -> Result<(), MLError> {
+        check_fit_input(X_train, y_train)?;
+        let rows: Vec<CsVec<f32>> = X_train.iter_rows().map(to_sparse).collect();
+        self.fit_sparse_weighted(&rows, X_train.cols(), y_train, sample_weights)
+    }
+
+    fn is_fitted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+
+    fn n_features_in(&self) -> Option<usize> {
+        self.is_fitted().then_some(self.n_features)
+    }
+
+    fn get_params(&self) -> Params {
+        let mut params = Params::new();
+        params.insert("c".to_owned(), ParamValue::Float(self.c));
+        params.insert("tol".to_owned(), ParamValue::Float(self.tol));
+        params.insert("max_iter".to_owned(), ParamValue::Int(self.max_iter as i64));
+        params.insert("fit_intercept".to_owned(), ParamValue::Bool(self.fit_intercept));
+        params
+    }
+
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        for (name, value) in params {
+            match name.as_str() {
+                "c" => self.c = value.as_f32(name)?,
+                "tol" => self.tol = value.as_f32(name)?,
+                "max_iter" => self.max_iter = value.as_usize(name)?,
+                "fit_intercept" => self.fit_intercept = value.as_bool(name)?,
+                _ => return Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            }
+        }
+        validate_svm(self.c, self.tol, self.max_iter)
+    }
+}
+
+impl Predict for LinearSvc {
+    fn predict(&self, point : &[f32]) -> ReturnType {
+        assert_predict_input(self, point);
+        self.predict_sparse(&to_sparse(point))
+    }
+}
+
+impl Estimator for LinearSvc {}
+
+impl Persist for LinearSvc {
+    const MODEL_TYPE: &'static str = "LinearSvc";
+
+    fn validate(&self) -> Result<(), PersistError> {
+        ensure(self.weights.len() == self.intercepts.len(), "the number of weight vectors and intercepts do not match")?;
+        ensure(self.weights.iter().all(|weights| weights.len() == self.n_features), "weight vector of the wrong length")?;
+        let n_classes = self.classes.as_ref().map_or(0, |classes| classes.len());
+        ensure(self.weights.is_empty() || self.weights.len() == if n_classes == 2 { 1 } else { n_classes }, "the number of machines does not match the classes")
+    }
+}
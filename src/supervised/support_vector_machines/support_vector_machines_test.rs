@@ -0,0 +1,145 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::{data_type::DataType, return_type::ReturnType, data_frame::DataFrame},
+    trait_definition::{MLalgo, Predict, MLError}, evaluation::accuracy::{score, r2_score},
+    feature_extraction::tokenisation::Tokens, persistence::persistence::Persist};
+use super::support_vector_machines::{Svc, Svr, LinearSvc, Kernel, MultiClass, LinearLoss};
+
+
+///inside(0) or outside(1) of the circle of radius 0.6, not linearly separable.
+fn circle_data(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut rows = vec![];
+    let mut targets = vec![];
+    while rows.len() < samples {
+        let row = vec![rng.f32() * 2.0 - 1.0, rng.f32() * 2.0 - 1.0];
+        let radius = (row[0] * row[0] + row[1] * row[1]).sqrt();
+        //a gap around the boundary keeps the classes separable.
+        if (radius - 0.6).abs() < 0.08 {
+            continue;
+        }
+        targets.push((radius > 0.6) as u8);
+        rows.push(row);
+    }
+    (Matrix::from(rows), DataType::Category(targets))
+}
+
+///three blobs at the corners of a triangle.
+fn three_blobs(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let centres = [(0.0, 0.0), (4.0, 0.0), (2.0, 4.0)];
+    let mut rows = vec![];
+    let mut targets = vec![];
+    for sample in 0..samples {
+        let (x, y) = centres[sample % 3];
+        rows.push(vec![x + rng.f32() * 2.0 - 1.0, y + rng.f32() * 2.0 - 1.0]);
+        targets.push(["a", "b", "c"][sample % 3].to_owned());
+    }
+    (Matrix::from(rows), DataType::Strings(targets))
+}
+
+#[test]
+fn rbf_separates_the_circle_where_linear_can_not() {
+    let (X_train, y_train) = circle_data(1, 300);
+    let (X_test, y_test) = circle_data(2, 100);
+    let mut rbf = Svc::new(Kernel::Rbf { gamma: Some(5.0) }, 10.0);
+    rbf.fit(&X_train, &y_train).unwrap();
+    assert!(score(&rbf, &X_test, &y_test) > 0.95);
+    assert!(rbf.n_support_vectors() < 300);
+    assert_eq!(rbf.predict(&[0.0, 0.05]), ReturnType::Category(0));
+
+    let mut linear = Svc::new(Kernel::Linear, 1.0);
+    linear.fit(&X_train, &y_train).unwrap();
+    assert!(score(&linear, &X_test, &y_test) < 0.8);
+
+    let mut polynomial = Svc::new(Kernel::Polynomial { degree: 2, gamma: Some(1.0), coef0: 1.0 }, 10.0);
+    polynomial.set_cache_size(0);
+    polynomial.fit(&X_train, &y_train).unwrap();
+    assert!(score(&polynomial, &X_test, &y_test) > 0.95);
+}
+
+#[test]
+fn multiclass_one_vs_one_and_one_vs_rest() {
+    let (X_train, y_train) = three_blobs(3, 150);
+    let (X_test, y_test) = three_blobs(4, 60);
+    for (multiclass, machines) in [(MultiClass::OneVsOne, 3), (MultiClass::OneVsRest, 3)] {
+        let mut model = Svc::new(Kernel::default(), 1.0);
+        model.set_multiclass(multiclass);
+        model.fit(&X_train, &y_train).unwrap();
+        assert_eq!(model.machines().len(), machines);
+        assert_eq!(score(&model, &X_test, &y_test), 1.0, "{:?}", multiclass);
+    }
+    let mut sigmoid = Svc::new(Kernel::Sigmoid { gamma: Some(0.05), coef0: 0.0 }, 1.0);
+    sigmoid.fit(&X_train, &y_train).unwrap();
+    let restored = Svc::from_json(&sigmoid.to_json().unwrap()).unwrap();
+    assert_eq!(restored.predict(&[2.0, 4.0]), sigmoid.predict(&[2.0, 4.0]));
+}
+
+#[test]
+fn svr_fits_inside_the_tube() {
+    let rows: Vec<Vec<f32>> = (0..100).map(|i| vec![i as f32 / 100.0 * 6.0]).collect();
+    let targets: Vec<f32> = rows.iter().map(|row| row[0].sin()).collect();
+    let (X, y) = (Matrix::from(rows), DataType::Floats(targets.clone()));
+    let mut model = Svr::new(Kernel::Rbf { gamma: Some(1.0) }, 10.0, 0.05);
+    model.fit(&X, &y).unwrap();
+    assert!(r2_score(&model, &X, &y) > 0.98);
+    //points inside the tube are not support vectors.
+    assert!(model.machine().unwrap().support_vectors.len() < 60);
+    assert!(matches!(model.fit(&X, &DataType::Category(vec![0; 100])), Err(MLError::InvalidTarget(_))));
+}
+
+#[test]
+fn linear_svc_on_sparse_token_counts() {
+    let sentences = ["great movie loved it", "loved the great acting", "what a great film", "i loved this movie",
+        "terrible movie hated it", "hated the awful acting", "what an awful film", "i hated this movie"];
+    let frame = DataFrame {
+        data: vec![DataType::Strings(sentences.iter().map(|sentence| sentence.to_string()).collect())],
+        headers: vec!["review".to_owned()],
+        number_of_features: 1,
+        number_of_samples: sentences.len() as u32,
+        max_vector: vec![],
+        min_vector: vec![],
+        normalized: false,
+    };
+    let mut tokens = Tokens::new(sentences.len());
+    tokens.tokenise(&frame, 0, 1, None);
+    let (counts, dimension) = tokens.sparse_counts();
+    assert_eq!(dimension, tokens.index_map_token.len());
+    let y = DataType::Strings((0..8).map(|i| if i < 4 { "positive".to_owned() } else { "negative".to_owned() }).collect());
+
+    for loss in [LinearLoss::Hinge, LinearLoss::SquaredHinge] {
+        let mut model = LinearSvc::new(1.0);
+        model.set_loss(loss);
+        model.fit_sparse(&counts, dimension, &y).unwrap();
+        for (point, label) in counts.iter().zip(0..8) {
+            assert_eq!(model.predict_sparse(point), ReturnType::Strings(if label < 4 { "positive" } else { "negative" }.to_owned()));
+        }
+        let hated = tokens.token_map_index["hated"].0;
+        let loved = tokens.token_map_index["loved"].0;
+        assert!(model.weights()[0][hated] != model.weights()[0][loved]);
+    }
+}
+
+#[test]
+fn linear_svc_dense_multiclass_and_errors() {
+    let (X_train, y_train) = three_blobs(5, 150);
+    let mut model = LinearSvc::new(1.0);
+    model.fit(&X_train, &y_train).unwrap();
+    assert_eq!(model.weights().len(), 3);
+    assert!(score(&model, &X_train, &y_train) > 0.95);
+    assert_eq!(model.try_predict(&[0.0]), Err(MLError::FeatureMismatch { expected: 2, found: 1 }));
+
+    let mut model = Svc::new(Kernel::Rbf { gamma: Some(-1.0) }, 1.0);
+    assert!(matches!(model.fit(&X_train, &y_train), Err(MLError::InvalidParameter(_))));
+    let mut model = Svc::new(Kernel::Linear, 0.0);
+    assert!(matches!(model.fit(&X_train, &y_train), Err(MLError::InvalidParameter(_))));
+
+    //a class whose samples all weigh 0 leaves its machines with one side only.
+    let weights: Vec<f32> = (0..150).map(|sample| if sample % 3 == 2 { 0.0 } else { 1.0 }).collect();
+    for multiclass in [MultiClass::OneVsOne, MultiClass::OneVsRest] {
+        let mut model = Svc::new(Kernel::Linear, 1.0);
+        model.set_multiclass(multiclass);
+        assert!(matches!(model.fit_weighted(&X_train, &y_train, &weights), Err(MLError::InvalidParameter(_))));
+        assert!(!model.is_fitted());
+    }
+}
@@ -0,0 +1,17 @@
+#![cfg(test)]
+
+use crate::{n_dimen::matrix::Matrix, data_frame::data_type::DataType};
+
+
+///three overlapping blobs, 0.8 spread around the centres.
+pub(crate) fn blobs(seed : u64, samples : usize) -> (Matrix, DataType) {
+    let rng = fastrand::Rng::with_seed(seed);
+    let centres = [(0.0, 0.0), (2.0, 0.0), (1.0, 2.0)];
+    let (mut rows, mut targets) = (vec![], vec![]);
+    for sample in 0..samples {
+        let class = sample % 3;
+        rows.push(vec![centres[class].0 + (rng.f32() - 0.5) * 1.6, centres[class].1 + (rng.f32() - 0.5) * 1.6]);
+        targets.push(class as u8);
+    }
+    (Matrix::from(rows), DataType::Category(targets))
+}
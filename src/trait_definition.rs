@@ -1,14 +1,349 @@
 //!Traits which will be used across the files.
 
-use crate::data_frame::data_type::DataType;
+use std::{collections::BTreeMap, fmt};
+use serde::{Deserialize, Serialize};
+use crate::data_frame::data_type::{DataType, length};
 use crate::data_frame::return_type::ReturnType;
+use crate::n_dimen::matrix::Matrix;
+
+
+///The errors an estimator can return while fitting, predicting or changing its parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MLError {
+    ///predict or transform was called before fit.
+    NotFitted,
+    ///the number of features is not what the model was fitted on.
+    FeatureMismatch { expected: usize, found: usize },
+    ///the number of samples in X and y do not match.
+    SampleMismatch { samples: usize, targets: usize },
+    ///there is nothing to fit on.
+    EmptyInput,
+    ///the model cannot be trained on this kind of target.
+    InvalidTarget(String),
+    ///an unknown hyperparameter, or a known one with a bad value.
+    InvalidParameter(String),
+}
+
+impl fmt::Display for MLError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MLError::NotFitted => write!(f, "the model is not fitted yet, call fit before using it"),
+            MLError::FeatureMismatch { expected, found } => write!(f, "the model was fitted on {} features, but {} were given", expected, found),
+            MLError::SampleMismatch { samples, targets } => write!(f, "X has {} samples but y has {} targets", samples, targets),
+            MLError::EmptyInput => write!(f, "cannot fit on an empty data set"),
+            MLError::InvalidTarget(reason) => write!(f, "invalid target : {}", reason),
+            MLError::InvalidParameter(reason) => write!(f, "invalid parameter : {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MLError {}
+
+
+///The value of a single hyperparameter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParamValue {
+    Float(f32),
+    Int(i64),
+    Bool(bool),
+    Text(String),
+}
+
+impl ParamValue {
+    pub fn as_f32(&self, name : &str) -> Result<f32, MLError> {
+        match self {
+            ParamValue::Float(value) => Ok(*value),
+            ParamValue::Int(value) => Ok(*value as f32),
+            _ => Err(MLError::InvalidParameter(format!("'{}' needs a number, got {:?}", name, self))),
+        }
+    }
+
+    pub fn as_usize(&self, name : &str) -> Result<usize, MLError> {
+        match self {
+            ParamValue::Int(value) if *value >= 0 => Ok(*value as usize),
+            _ => Err(MLError::InvalidParameter(format!("'{}' needs a non-negative integer, got {:?}", name, self))),
+        }
+    }
+
+    pub fn as_bool(&self, name : &str) -> Result<bool, MLError> {
+        match self {
+            ParamValue::Bool(value) => Ok(*value),
+            _ => Err(MLError::InvalidParameter(format!("'{}' needs a bool, got {:?}", name, self))),
+        }
+    }
+
+    pub fn as_str(&self, name : &str) -> Result<&str, MLError> {
+        match self {
+            ParamValue::Text(value) => Ok(value),
+            _ => Err(MLError::InvalidParameter(format!("'{}' needs a string, got {:?}", name, self))),
+        }
+    }
+}
+
+///hyperparameters by name, ordered so they print the same way every time.
+pub type Params = BTreeMap<String, ParamValue>;
 
 
 ///all the structs that are for creating and training.
 pub trait MLalgo {
-    fn fit(&mut self, X_train : &Vec<Vec<f32>> , y_train : &DataType);
+    fn fit(&mut self, X_train : &Matrix , y_train : &DataType) -> Result<(), MLError>;
+
+    ///true once the model has been fitted(or loaded) and can predict.
+    fn is_fitted(&self) -> bool;
+
+    ///number of features the model was fitted on, `None` before fitting.
+    fn n_features_in(&self) -> Option<usize>;
+
+    ///true for the models whose `fit_weighted` takes sample weights.
+    fn supports_sample_weights(&self) -> bool {
+        false
+    }
+
+    ///fits with a weight for every sample, a weight of 0 removes the sample.
+    ///Models that do not take weights(see `supports_sample_weights`) return an error.
+    fn fit_weighted(&mut self, _X_train : &Matrix, _y_train : &DataType, _sample_weights : &[f32]) -> Result<(), MLError> {
+        Err(MLError::InvalidParameter("this model does not take sample weights".to_owned()))
+    }
+
+    ///fits with the sample weights that give every class the weight in `class_weight`, see `class_sample_weights`.
+    fn fit_class_weighted(&mut self, X_train : &Matrix, y_train : &DataType, class_weight : &ClassWeight) -> Result<(), MLError> {
+        let sample_weights = class_sample_weights(y_train, class_weight)?;
+        self.fit_weighted(X_train, y_train, &sample_weights)
+    }
+
+    ///the hyperparameters of the model, not the learned values.
+    fn get_params(&self) -> Params {
+        Params::new()
+    }
+
+    ///changes the given hyperparameters, the ones not in `params` are left as they are.
+    ///an unknown name is an error, so typos do not silently do nothing.
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        match params.keys().next() {
+            Some(name) => Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            None => Ok(()),
+        }
+    }
+}
+
+pub trait Predict : MLalgo {
+    ///predicts a single point, panics if the model is not fitted or the point has the wrong number of features.
+    fn predict(&self, point : &[f32]) -> ReturnType;
+
+    ///same as `predict` but returns the error instead of panicking.
+    fn try_predict(&self, point : &[f32]) -> Result<ReturnType, MLError> {
+        check_predict_input(self, point)?;
+        Ok(self.predict(point))
+    }
+}
+
+///a model the ensembles and wrappers can hold behind a `Box<dyn Estimator>` and copy for every fold or member.
+///the models holding a `Box<dyn Estimator>` can not be saved with `Persist`, save the boxed models one by one instead.
+pub trait Estimator : Predict + CloneEstimator + Send + Sync {
+    ///class probabilities in the order of the sorted classes seen in `fit`, `None` for regressors and models without probabilities.
+    fn probabilities(&self, _point : &[f32]) -> Option<Vec<f32>> {
+        None
+    }
 }
 
-pub trait Predict {
-    fn predict(&self, point : &Vec<f32>) -> ReturnType;
-}
\ No newline at end of file
+///the `clone` half of `Estimator`, implemented for every `Estimator` that is `Clone`.
+pub trait CloneEstimator {
+    fn clone_box(&self) -> Box<dyn Estimator>;
+}
+
+impl<T : Estimator + Clone + 'static> CloneEstimator for T {
+    fn clone_box(&self) -> Box<dyn Estimator> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Estimator> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+///preprocessing steps, learn something from the data in `fit` and apply it in `transform`.
+pub trait Transformer {
+    fn fit(&mut self, X : &Matrix) -> Result<(), MLError>;
+
+    fn transform(&self, X : &Matrix) -> Result<Matrix, MLError>;
+
+    fn fit_transform(&mut self, X : &Matrix) -> Result<Matrix, MLError> {
+        self.fit(X)?;
+        self.transform(X)
+    }
+}
+
+///unsupervised models that learn what normal data looks like and flag the points that do not fit in, see `anomaly_detection`.
+///`fit` also sets the threshold on the anomaly score, from the share of outliers expected in the training set(the contamination).
+pub trait OutlierDetector {
+    fn fit(&mut self, X : &Matrix) -> Result<(), MLError>;
+
+    fn is_fitted(&self) -> bool;
+
+    ///number of features the detector was fitted on, `None` before fitting.
+    fn n_features_in(&self) -> Option<usize>;
+
+    ///how abnormal the point is, higher is more of an outlier. Panics if the detector is not fitted or the point has the wrong number of features.
+    fn score(&self, point : &[f32]) -> f32;
+
+    ///the score above which a point is an outlier, `None` before fitting.
+    fn threshold(&self) -> Option<f32>;
+
+    ///`score` minus the threshold, positive for the outliers.
+    fn decision_function(&self, point : &[f32]) -> f32 {
+        self.score(point) - self.threshold().unwrap_or_else(|| panic!("{}", MLError::NotFitted))
+    }
+
+    fn is_outlier(&self, point : &[f32]) -> bool {
+        self.decision_function(point) > 0.0
+    }
+
+    ///the score of every row.
+    fn scores(&self, X : &Matrix) -> Vec<f32> {
+        X.iter_rows().map(|point| self.score(point)).collect()
+    }
+
+    ///true for the rows that are outliers, false for the inliers.
+    fn predict(&self, X : &Matrix) -> Vec<bool> {
+        X.iter_rows().map(|point| self.is_outlier(point)).collect()
+    }
+
+    fn fit_predict(&mut self, X : &Matrix) -> Result<Vec<bool>, MLError> {
+        self.fit(X)?;
+        Ok(self.predict(X))
+    }
+
+    ///the hyperparameters of the detector, same as `MLalgo::get_params`.
+    fn get_params(&self) -> Params {
+        Params::new()
+    }
+
+    ///changes the given hyperparameters, same as `MLalgo::set_params`.
+    fn set_params(&mut self, params : &Params) -> Result<(), MLError> {
+        match params.keys().next() {
+            Some(name) => Err(MLError::InvalidParameter(format!("unknown parameter '{}'", name))),
+            None => Ok(()),
+        }
+    }
+}
+
+///checks that the model is fitted and the point has as many features as the model was fitted on.
+pub fn check_predict_input<M : MLalgo + ?Sized>(model : &M, point : &[f32]) -> Result<(), MLError> {
+    if !model.is_fitted() {
+        return Err(MLError::NotFitted);
+    }
+    match model.n_features_in() {
+        Some(expected) if expected != point.len() => Err(MLError::FeatureMismatch { expected, found: point.len() }),
+        _ => Ok(()),
+    }
+}
+
+///panicking version of `check_predict_input`, to be called at the start of `predict`.
+pub fn assert_predict_input<M : MLalgo + ?Sized>(model : &M, point : &[f32]) {
+    if let Err(error) = check_predict_input(model, point) {
+        panic!("{}", error);
+    }
+}
+
+///`assert_predict_input` for the models that are not `MLalgo`(multi-output models, outlier detectors), `None` is not fitted.
+pub fn assert_point(n_features : Option<usize>, point : &[f32]) {
+    match n_features {
+        None => panic!("{}", MLError::NotFitted),
+        Some(expected) if expected != point.len() => panic!("{}", MLError::FeatureMismatch { expected, found: point.len() }),
+        _ => {},
+    }
+}
+
+///checks that there is something to fit on and every sample has a target.
+pub fn check_fit_input(X_train : &Matrix, y_train : &DataType) -> Result<(), MLError> {
+    if X_train.rows() == 0 || X_train.cols() == 0 {
+        return Err(MLError::EmptyInput);
+    }
+    if X_train.rows() != y_train.len() {
+        return Err(MLError::SampleMismatch { samples: X_train.rows(), targets: y_train.len() });
+    }
+    Ok(())
+}
+
+///How much every class counts while fitting, for imbalanced targets.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum ClassWeight {
+    ///every sample counts the same.
+    #[default]
+    Uniform,
+    ///weights inversely proportional to the class frequencies, `n_samples / (n_classes * count(class))`.
+    Balanced,
+    ///one weight per class, in the sorted order of the classes.
+    Custom(Vec<f32>),
+}
+
+impl ClassWeight {
+    ///the weight of each class, `counts` has the number of samples of each class.
+    pub fn weights(&self, counts : &[usize]) -> Result<Vec<f32>, MLError> {
+        let total: usize = counts.iter().sum();
+        match self {
+            ClassWeight::Uniform => Ok(vec![1.0; counts.len()]),
+            ClassWeight::Balanced => Ok(counts.iter().map(|count| total as f32 / (counts.len() * count.max(&1)) as f32).collect()),
+            ClassWeight::Custom(weights) if weights.len() != counts.len() =>
+                Err(MLError::InvalidParameter(format!("{} class weights were given for {} classes", weights.len(), counts.len()))),
+            ClassWeight::Custom(weights) if weights.iter().any(|weight| !(*weight >= 0.0)) =>
+                Err(MLError::InvalidParameter("class weights need to be >= 0".to_owned())),
+            ClassWeight::Custom(weights) => Ok(weights.clone()),
+        }
+    }
+}
+
+///the weight of every sample from the weight of its class, only for category and string targets.
+pub fn class_sample_weights(y_train : &DataType, class_weight : &ClassWeight) -> Result<Vec<f32>, MLError> {
+    let (classes, labels) = y_train.unique_classes().ok_or_else(|| MLError::InvalidTarget("class weights need category or string targets".to_owned()))?;
+    let mut counts = vec![0_usize; classes.len()];
+    labels.iter().for_each(|label| counts[*label] += 1);
+    let weights = class_weight.weights(&counts)?;
+    Ok(labels.iter().map(|label| weights[*label]).collect())
+}
+
+///one weight >= 0 for every sample.
+pub fn check_sample_weights(sample_weights : &[f32], samples : usize) -> Result<(), MLError> {
+    if sample_weights.len() != samples {
+        return Err(MLError::SampleMismatch { samples, targets: sample_weights.len() });
+    }
+    if sample_weights.iter().any(|weight| !(*weight >= 0.0 && weight.is_finite())) {
+        return Err(MLError::InvalidParameter("sample weights need to be finite and >= 0".to_owned()));
+    }
+    Ok(())
+}
+
+///the class list for a `partial_fit` call and the class index of every sample of the batch.
+///The first call needs the full list of classes(the batch may not have all of them), later calls can leave it out but can not change it.
+pub fn partial_fit_classes(fitted : Option<&DataType>, declared : Option<&DataType>, y_batch : &DataType) -> Result<(DataType, Vec<usize>), MLError> {
+    let same = |first : &DataType, second : &DataType| first.len() == second.len() && (0..first.len()).all(|index| first.value_at(index) == second.value_at(index));
+    let classes = match (fitted, declared) {
+        (None, None) => return Err(MLError::InvalidParameter("the first call to partial_fit needs the full list of classes".to_owned())),
+        (None, Some(declared)) => {
+            match declared.unique_classes() {
+                Some((unique, _)) if unique.len() == declared.len() => declared.clone(),
+                Some(_) => return Err(MLError::InvalidParameter("the declared classes have duplicates".to_owned())),
+                None => return Err(MLError::InvalidTarget("the classes need to be categories or strings".to_owned())),
+            }
+        },
+        (Some(fitted), Some(declared)) if !same(fitted, declared) =>
+            return Err(MLError::InvalidParameter("the classes can not change between calls to partial_fit".to_owned())),
+        (Some(fitted), _) => fitted.clone(),
+    };
+    let labels = (0..y_batch.len()).map(|sample| {
+        let value = y_batch.value_at(sample);
+        (0..classes.len()).find(|class| classes.value_at(*class) == value)
+            .ok_or_else(|| MLError::InvalidTarget(format!("{:?} is not one of the declared classes", value)))
+    }).collect::<Result<Vec<usize>, MLError>>()?;
+    Ok((classes, labels))
+}
+
+///a batch for `partial_fit` needs the same features as the batches before it.
+pub fn check_batch_features(expected : Option<usize>, X_batch : &Matrix) -> Result<(), MLError> {
+    match expected {
+        Some(expected) if expected != X_batch.cols() => Err(MLError::FeatureMismatch { expected, found: X_batch.cols() }),
+        _ => Ok(()),
+    }
+}